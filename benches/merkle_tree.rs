@@ -2,20 +2,27 @@
 
 use ark_ff::Field;
 use ark_ff_optimized::fp64::Fp;
+use ark_std::rand::Rng;
+use ark_std::UniformRand;
 use criterion::criterion_group;
 use criterion::criterion_main;
 use criterion::BenchmarkId;
 use criterion::Criterion;
+use ministark::hash::Blake3HashFn;
 use ministark::hash::ElementHashFn;
 use ministark::hash::Sha256HashFn;
 use ministark::merkle::MatrixMerkleTree;
 use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::merkle::MerkleTreeDomain;
+use ministark::merkle::WideMatrixMerkleTree;
 use ministark::utils::GpuAllocator;
 use ministark::Matrix;
 use ministark_gpu::GpuField;
 
 const BENCHMARK_TREE_DEPTH: [usize; 4] = [14, 15, 16, 17];
 
+const BENCHMARK_NUM_QUERIES: [usize; 3] = [32, 128, 512];
+
 fn build_merkle_tree_bench<F: GpuField + Field, H: ElementHashFn<F>>(
     c: &mut Criterion,
     name: &str,
@@ -34,14 +41,49 @@ fn build_merkle_tree_bench<F: GpuField + Field, H: ElementHashFn<F>>(
         ]);
 
         group.bench_with_input(BenchmarkId::new("from_matrix", n), &n, |b, _| {
-            b.iter(|| MatrixMerkleTreeImpl::<H>::from_matrix(&matrix))
+            b.iter(|| MatrixMerkleTreeImpl::<H>::from_matrix(&matrix, MerkleTreeDomain::BaseTrace))
         });
     }
 }
 
+fn verify_merkle_tree_bench<T: MatrixMerkleTree<Fp>>(c: &mut Criterion, name: &str) {
+    let mut rng = ark_std::test_rng();
+    let mut group = c.benchmark_group(name);
+    let n = 1 << 16;
+    let leaves: Vec<Fp> = (0..n).map(|_| Fp::rand(&mut rng)).collect();
+    let matrix = Matrix::new(vec![leaves.to_vec_in(GpuAllocator)]);
+    let tree = T::from_matrix(&matrix, MerkleTreeDomain::BaseTrace);
+    let root = tree.root();
+
+    for num_queries in BENCHMARK_NUM_QUERIES {
+        let indices: Vec<usize> = (0..num_queries).map(|_| rng.gen_range(0..n)).collect();
+        let proof = tree.prove(&indices).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("verify", num_queries),
+            &num_queries,
+            |b, _| {
+                b.iter(|| {
+                    T::verify(&root, MerkleTreeDomain::BaseTrace, proof.clone(), &indices).unwrap()
+                })
+            },
+        );
+    }
+}
+
 fn build_merkle_tree_benches(c: &mut Criterion) {
     build_merkle_tree_bench::<Fp, Sha256HashFn>(c, "Sha256");
+    build_merkle_tree_bench::<Fp, Blake3HashFn>(c, "Blake3");
+}
+
+fn verify_merkle_tree_benches(c: &mut Criterion) {
+    verify_merkle_tree_bench::<MatrixMerkleTreeImpl<Sha256HashFn>>(c, "Sha256 binary");
+    verify_merkle_tree_bench::<WideMatrixMerkleTree<Sha256HashFn, 4>>(c, "Sha256 4-ary");
+    verify_merkle_tree_bench::<WideMatrixMerkleTree<Sha256HashFn, 8>>(c, "Sha256 8-ary");
 }
 
-criterion_group!(benches, build_merkle_tree_benches);
+criterion_group!(
+    benches,
+    build_merkle_tree_benches,
+    verify_merkle_tree_benches
+);
 criterion_main!(benches);
@@ -0,0 +1,59 @@
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use ministark::bench;
+use ministark::hash::Blake3HashFn;
+use ministark::hash::HashFn;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
+
+const BENCHMARK_TRACE_LEN_LOG2: [usize; 3] = [14, 16, 18];
+
+const NUM_COLUMNS: usize = 8;
+
+type Digest = <Blake3HashFn as HashFn>::Digest;
+
+type MerkleTree = MatrixMerkleTreeImpl<Blake3HashFn>;
+
+fn commit_phase_benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("commit_phase");
+    group.sample_size(10);
+    let options = ProofOptions::new(32, 4, 0, 4, 16);
+
+    for d in BENCHMARK_TRACE_LEN_LOG2 {
+        let n = 1 << d;
+        group.bench_with_input(BenchmarkId::new("base_trace", n), &n, |b, &n| {
+            b.iter_custom(|iters| {
+                (0..iters)
+                    .map(|_| bench::commit_phase::<Fp, MerkleTree>(n, NUM_COLUMNS, options))
+                    .sum()
+            });
+        });
+    }
+}
+
+fn fri_prove_benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fri_prove");
+    group.sample_size(10);
+
+    for folding_factor in [2, 4, 8, 16] {
+        let options = ProofOptions::new(32, 4, 0, folding_factor, 16);
+        for d in BENCHMARK_TRACE_LEN_LOG2 {
+            let n = 1 << d;
+            let id = BenchmarkId::new(format!("folding_factor_{folding_factor}"), n);
+            group.bench_with_input(id, &n, |b, &n| {
+                b.iter_custom(|iters| {
+                    (0..iters)
+                        .map(|_| bench::fri_prove::<Fq3, Digest, MerkleTree>(n, options))
+                        .sum()
+                });
+            });
+        }
+    }
+}
+
+criterion_group!(benches, commit_phase_benches, fri_prove_benches);
+criterion_main!(benches);
@@ -41,6 +41,7 @@ impl AirConfig for BrainfuckAirConfig {
             source_code,
             input,
             output,
+            ..
         } = execution_info;
 
         let (input_eval_arg, input_eval_offset) =
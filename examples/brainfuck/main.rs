@@ -3,11 +3,13 @@
 use air::BrainfuckAirConfig;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use ministark::hash::Digest;
 use ministark::hash::HashFn;
 use ministark::hash::Sha256HashFn;
 use ministark::merkle::MatrixMerkleTreeImpl;
 use ministark::random::PublicCoin;
 use ministark::random::PublicCoinImpl;
+use ministark::stark::PublicOutputs;
 use ministark::stark::Stark;
 use ministark::utils::SerdeOutput;
 use ministark::Proof;
@@ -16,6 +18,7 @@ use ministark::Trace;
 use ministark_gpu::fields::p18446744069414584321::ark::Fp;
 use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
 use sha2::Sha256;
+use std::fmt::Write as _;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
@@ -23,6 +26,7 @@ use std::path::PathBuf;
 use std::time::Instant;
 use structopt::StructOpt;
 use trace::BrainfuckTrace;
+use vm::program_hash;
 use vm::simulate;
 
 mod air;
@@ -49,16 +53,34 @@ enum BrainfuckOptions {
         input: String,
         #[structopt(long)]
         output: String,
+        /// Hex encoded hash of the program that must have been run
+        #[structopt(long)]
+        program_hash: Option<String>,
     },
 }
 
 #[derive(CanonicalSerialize, CanonicalDeserialize, Clone)]
 pub struct BrainfuckClaim {
     pub source_code: String,
+    /// Commitment to the program (see [`program_hash`]). It's part of the
+    /// public inputs so the verifier learns which program was run.
+    pub program_hash: SerdeOutput<Sha256>,
     pub input: Vec<u8>,
     pub output: Vec<u8>,
 }
 
+impl BrainfuckClaim {
+    pub fn new(source_code: String, input: Vec<u8>, output: Vec<u8>) -> Self {
+        let program_hash = program_hash(&source_code);
+        Self {
+            source_code,
+            program_hash,
+            input,
+            output,
+        }
+    }
+}
+
 impl Stark for BrainfuckClaim {
     type Fp = Fp;
     type Fq = Fq3;
@@ -86,6 +108,14 @@ impl Stark for BrainfuckClaim {
     }
 }
 
+impl PublicOutputs for BrainfuckClaim {
+    type Outputs = Vec<u8>;
+
+    fn public_outputs(&self) -> Vec<u8> {
+        self.output.clone()
+    }
+}
+
 const SECURITY_LEVEL: u32 = 96;
 
 /// Proof options for 96 bit security level
@@ -113,7 +143,8 @@ fn main() {
             proof,
             input,
             output,
-        } => verify(src, input, output, proof),
+            program_hash,
+        } => verify(src, input, output, program_hash, proof),
     }
 }
 
@@ -134,11 +165,8 @@ fn prove(source_code_path: PathBuf, input: String, output_path: PathBuf) {
         String::from_utf8(output.clone()).unwrap()
     );
 
-    let claim = BrainfuckClaim {
-        source_code,
-        input: input.into_bytes(),
-        output,
-    };
+    let claim = BrainfuckClaim::new(source_code, input.into_bytes(), output);
+    println!("Program hash: {}", hex(&claim.program_hash));
 
     let now = Instant::now();
     let proof = pollster::block_on(claim.prove(OPTIONS, trace)).unwrap();
@@ -157,7 +185,13 @@ fn prove(source_code_path: PathBuf, input: String, output_path: PathBuf) {
     println!("Proof written to {}", output_path.as_path().display());
 }
 
-fn verify(source_code_path: PathBuf, input: String, output: String, proof_path: PathBuf) {
+fn verify(
+    source_code_path: PathBuf,
+    input: String,
+    output: String,
+    expected_program_hash: Option<String>,
+    proof_path: PathBuf,
+) {
     let source_code = fs::read_to_string(source_code_path).unwrap();
     let proof_bytes = fs::read(proof_path).unwrap();
     let (execution_info, proof): (BrainfuckClaim, Proof<BrainfuckClaim>) =
@@ -165,10 +199,29 @@ fn verify(source_code_path: PathBuf, input: String, output: String, proof_path:
     assert_eq!(input.as_bytes(), execution_info.input);
     assert_eq!(output.as_bytes(), execution_info.output);
     assert_eq!(source_code, execution_info.source_code);
+    // the code determines the instruction table so it must match the commitment
+    assert_eq!(program_hash(&source_code), execution_info.program_hash);
+    let program_hash = hex(&execution_info.program_hash);
+    if let Some(expected_program_hash) = expected_program_hash {
+        assert_eq!(expected_program_hash, program_hash, "unexpected program");
+    }
+    println!("Program hash: {program_hash}");
 
     let now = Instant::now();
-    execution_info
-        .verify(proof, SECURITY_LEVEL)
+    let program_output = execution_info
+        .verify_and_extract(proof, SECURITY_LEVEL)
         .expect("verification failed");
     println!("Proof verified in: {:?}", now.elapsed());
+    println!(
+        "Program output: \"{}\"",
+        String::from_utf8_lossy(&program_output)
+    );
+}
+
+fn hex(digest: &impl Digest) -> String {
+    let mut hex = String::new();
+    for byte in digest.as_bytes() {
+        write!(hex, "{byte:02x}").unwrap();
+    }
+    hex
 }
@@ -83,13 +83,41 @@ impl Trace for BrainfuckTrace {
         let instr_initial = Fq3::rand(&mut rng);
         let mem_initial = Fq3::rand(&mut rng);
 
-        let processor_matrix =
-            gen_processor_ext_matrix(instr_initial, mem_initial, challenges, processor_base_trace);
-        let memory_matrix = gen_memory_ext_matrix(mem_initial, challenges, memory_base_trace);
-        let instruction_matrix =
-            gen_instruction_ext_matrix(instr_initial, challenges, instruction_base_trace);
-        let input_matrix = gen_input_ext_matrix(challenges, input_base_trace);
-        let output_matrix = gen_output_ext_matrix(challenges, output_base_trace);
+        // each table is a sequence of running products and sums but the tables
+        // are independent of each other
+        let ((processor_matrix, memory_matrix), (instruction_matrix, io_matrices)) = join(
+            || {
+                join(
+                    || {
+                        gen_processor_ext_matrix(
+                            instr_initial,
+                            mem_initial,
+                            challenges,
+                            processor_base_trace,
+                        )
+                    },
+                    || gen_memory_ext_matrix(mem_initial, challenges, memory_base_trace),
+                )
+            },
+            || {
+                join(
+                    || {
+                        gen_instruction_ext_matrix(
+                            instr_initial,
+                            challenges,
+                            instruction_base_trace,
+                        )
+                    },
+                    || {
+                        join(
+                            || gen_input_ext_matrix(challenges, input_base_trace),
+                            || gen_output_ext_matrix(challenges, output_base_trace),
+                        )
+                    },
+                )
+            },
+        );
+        let (input_matrix, output_matrix) = io_matrices;
 
         Some(Matrix::join(vec![
             processor_matrix,
@@ -299,3 +327,17 @@ pub fn into_columns<F: Field, const N: usize>(rows: Vec<[F; N]>) -> Vec<GpuVec<F
     }
     cols
 }
+
+/// Runs `a` and `b` on rayon workers if the parallel feature is enabled
+pub fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    #[cfg(feature = "parallel")]
+    return rayon::join(a, b);
+    #[cfg(not(feature = "parallel"))]
+    return (a(), b());
+}
@@ -5,11 +5,19 @@ use crate::tables::MemoryBaseColumn;
 use crate::tables::OutputBaseColumn;
 use crate::tables::ProcessorBaseColumn;
 use crate::trace::into_columns;
+use crate::trace::join;
 use crate::BrainfuckTrace;
 use ark_ff::Field;
 use ark_ff::One;
 use ark_ff::Zero;
+use ministark::domain::ceil_power_of_two;
+use ministark::hash::ElementHashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::utils::SerdeOutput;
 use ministark::Matrix;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use sha2::Sha256;
 
 type Fp = <BrainfuckTrace as ministark::Trace>::Fp;
 
@@ -88,6 +96,18 @@ pub fn compile(source: &str) -> Vec<usize> {
     program
 }
 
+/// Returns the commitment to a program. This is the hash of the rows of the
+/// instruction table that hold the program code i.e. `(ip, instr, next_instr)`
+/// for every address.
+pub fn program_hash(source: &str) -> SerdeOutput<Sha256> {
+    let program = compile(source);
+    let rows = (0..program.len()).flat_map(|ip| {
+        let next_instr = program.get(ip + 1).map_or(0, |&x| x as u64);
+        [ip as u64, program[ip] as u64, next_instr].map(Fp::from)
+    });
+    Sha256HashFn::hash_elements(rows)
+}
+
 /// Registers of the brainfuck VM
 #[derive(Default)]
 struct Register {
@@ -239,10 +259,15 @@ pub fn simulate(
         row
     });
 
-    // sort instructions by address
-    instruction_rows.sort_by_key(|row| row[0]);
-
-    let mut memory_rows = derive_memory_rows(&processor_rows);
+    // the tables only depend on the processor and instruction rows from here on
+    let (mut instruction_rows, mut memory_rows) = join(
+        || {
+            // sort instructions by address
+            sort_by_key(&mut instruction_rows, |row| row[0]);
+            instruction_rows
+        },
+        || derive_memory_rows(&processor_rows),
+    );
 
     let padding_len = {
         let max_length = [
@@ -258,17 +283,37 @@ pub fn simulate(
         ceil_power_of_two(max_length)
     };
 
-    pad_processor_rows(&mut processor_rows, padding_len);
-    pad_memory_rows(&mut memory_rows, padding_len);
-    pad_instruction_rows(&mut instruction_rows, padding_len);
-    pad_input_rows(&mut input_rows, padding_len);
-    pad_output_rows(&mut output_rows, padding_len);
-
-    let processor_base_trace = Matrix::new(into_columns(processor_rows));
-    let memory_base_trace = Matrix::new(into_columns(memory_rows));
-    let instruction_base_trace = Matrix::new(into_columns(instruction_rows));
-    let input_base_trace = Matrix::new(into_columns(input_rows));
-    let output_base_trace = Matrix::new(into_columns(output_rows));
+    let ((processor_base_trace, memory_base_trace), (instruction_base_trace, io_base_traces)) =
+        join(
+            || {
+                join(
+                    || {
+                        pad_processor_rows(&mut processor_rows, padding_len);
+                        Matrix::new(into_columns(processor_rows))
+                    },
+                    || {
+                        pad_memory_rows(&mut memory_rows, padding_len);
+                        Matrix::new(into_columns(memory_rows))
+                    },
+                )
+            },
+            || {
+                join(
+                    || {
+                        pad_instruction_rows(&mut instruction_rows, padding_len);
+                        Matrix::new(into_columns(instruction_rows))
+                    },
+                    || {
+                        pad_input_rows(&mut input_rows, padding_len);
+                        pad_output_rows(&mut output_rows, padding_len);
+                        let input_base_trace = Matrix::new(into_columns(input_rows));
+                        let output_base_trace = Matrix::new(into_columns(output_rows));
+                        (input_base_trace, output_base_trace)
+                    },
+                )
+            },
+        );
+    let (input_base_trace, output_base_trace) = io_base_traces;
 
     BrainfuckTrace::new(
         processor_base_trace,
@@ -339,8 +384,7 @@ fn derive_memory_rows(
     processor_rows: &[[Fp; ProcessorBaseColumn::NUM_TRACE_COLUMNS]],
 ) -> Vec<[Fp; MemoryBaseColumn::NUM_TRACE_COLUMNS]> {
     use MemoryBaseColumn::*;
-    let mut memory_rows = processor_rows
-        .iter()
+    let mut memory_rows = ark_std::cfg_iter!(processor_rows)
         .filter_map(|row| {
             if row[ProcessorBaseColumn::CurrInstr as usize].is_zero() {
                 None
@@ -355,36 +399,37 @@ fn derive_memory_rows(
         })
         .collect::<Vec<_>>();
 
-    memory_rows.sort_by_key(|row| (row[Mp as usize], row[Cycle as usize]));
+    sort_by_key(&mut memory_rows, |row| {
+        (row[Mp as usize], row[Cycle as usize])
+    });
 
     // insert dummy rows for smooth clk jumps
-    let mut i = 0;
-    while i < memory_rows.len() - 1 {
-        let curr = &memory_rows[i];
-        let next = &memory_rows[i + 1];
-
-        if curr[Mp as usize] == next[Mp as usize]
-            && curr[Cycle as usize] + Fp::one() != next[Cycle as usize]
-        {
-            let mut dummy_row = [Fp::zero(); MemoryBaseColumn::NUM_TRACE_COLUMNS];
-            dummy_row[Cycle as usize] = curr[Cycle as usize] + Fp::one();
-            dummy_row[Mp as usize] = curr[Mp as usize];
-            dummy_row[MemVal as usize] = curr[MemVal as usize];
-            dummy_row[Dummy as usize] = Fp::one();
-            memory_rows.insert(i + 1, dummy_row)
+    let mut rows = Vec::with_capacity(memory_rows.len());
+    for window in memory_rows.windows(2) {
+        let (curr, next) = (&window[0], &window[1]);
+        rows.push(*curr);
+        if curr[Mp as usize] == next[Mp as usize] {
+            let mut cycle = curr[Cycle as usize] + Fp::one();
+            while cycle != next[Cycle as usize] {
+                let mut dummy_row = [Fp::zero(); MemoryBaseColumn::NUM_TRACE_COLUMNS];
+                dummy_row[Cycle as usize] = cycle;
+                dummy_row[Mp as usize] = curr[Mp as usize];
+                dummy_row[MemVal as usize] = curr[MemVal as usize];
+                dummy_row[Dummy as usize] = Fp::one();
+                rows.push(dummy_row);
+                cycle += Fp::one();
+            }
         }
-
-        i += 1;
     }
+    rows.extend(memory_rows.last());
 
-    memory_rows
+    rows
 }
 
-/// Rounds the input value up the the nearest power of two
-fn ceil_power_of_two(value: usize) -> usize {
-    if value.is_power_of_two() {
-        value
-    } else {
-        value.next_power_of_two()
-    }
+/// Sorts the rows (in parallel if the parallel feature is enabled)
+fn sort_by_key<T: Send, K: Ord>(rows: &mut [T], key: impl Fn(&T) -> K + Sync) {
+    #[cfg(feature = "parallel")]
+    rows.par_sort_by_key(key);
+    #[cfg(not(feature = "parallel"))]
+    rows.sort_by_key(key);
 }
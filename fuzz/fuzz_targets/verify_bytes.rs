@@ -0,0 +1,75 @@
+//! Checks [`verify_bytes`] never panics on malformed proofs. Inputs are
+//! mutations of a valid proof so the fuzzer reaches the FRI and merkle checks
+//! without a corpus: every 5 byte chunk of the input XORs the proof byte at
+//! position `u32::from_le_bytes(chunk[..4])` (modulo the proof length) with
+//! `chunk[4]` and the remaining bytes truncate the proof to
+//! `remainder[0] / 256` of its length.
+//!
+//! ```text
+//! cargo +nightly fuzz run verify_bytes
+//! ```
+#![no_main]
+#![feature(allocator_api)]
+
+#[path = "../../tests/common/mod.rs"]
+mod common;
+
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use ark_serialize::SerializationError;
+use common::gen_trace;
+use common::FibClaim;
+use common::FibTrace;
+use libfuzzer_sys::fuzz_target;
+use ministark::ffi::verify_bytes;
+use ministark::ffi::FfiStark;
+use ministark::stark::Stark;
+use ministark::verifier::FoldingPolicy;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use std::sync::LazyLock;
+
+const OPTIONS: ProofOptions = ProofOptions::new(24, 4, 2, 2, 8);
+
+impl FfiStark for FibClaim {
+    fn from_public_inputs(bytes: &[u8]) -> Result<Self, SerializationError> {
+        Ok(Self::from_last_value(Fp::deserialize_compressed(bytes)?))
+    }
+
+    /// The witness is the number of rows of the trace
+    fn deserialize_witness(bytes: &[u8]) -> Result<FibTrace, SerializationError> {
+        Ok(gen_trace(u64::deserialize_compressed(bytes)? as usize))
+    }
+}
+
+/// Public inputs and the compressed serialization of a valid proof
+static PROOF: LazyLock<(Vec<u8>, Vec<u8>)> = LazyLock::new(|| {
+    let trace = gen_trace(256);
+    let claim: FibClaim = FibClaim::new(&trace);
+    let proof = pollster::block_on(claim.prove(OPTIONS, trace)).unwrap();
+    let mut public_inputs = Vec::new();
+    claim
+        .last_value
+        .serialize_compressed(&mut public_inputs)
+        .unwrap();
+    let mut proof_bytes = Vec::new();
+    proof.serialize_compressed(&mut proof_bytes).unwrap();
+    (public_inputs, proof_bytes)
+});
+
+fuzz_target!(|data: &[u8]| {
+    let (public_inputs, proof) = &*PROOF;
+    let mut proof = proof.clone();
+    let mutations = data.chunks_exact(5);
+    let truncation = mutations.remainder().first().copied();
+    for mutation in mutations {
+        let position = u32::from_le_bytes(mutation[..4].try_into().unwrap()) as usize;
+        let len = proof.len();
+        proof[position % len] ^= mutation[4];
+    }
+    if let Some(fraction) = truncation {
+        proof.truncate(proof.len() * usize::from(fraction) / 256);
+    }
+    let policy = FoldingPolicy::new().accept_options(&OPTIONS);
+    let _ = verify_bytes::<FibClaim>(&proof, public_inputs, &policy, 1);
+});
@@ -10,11 +10,11 @@ pub mod macros;
 pub mod fields;
 pub mod plan;
 pub mod prelude;
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub mod stage;
 pub mod utils;
 
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub use metal;
 
 /// A trait to be implemented if the field can be used for FFTs on the GPU.
@@ -167,6 +167,51 @@ FillBuff(device FieldT *dst [[ buffer(0) ]],
     dst[global_tid] = value;
 }
 
+// dst[i] = coeffs[i*f] + alpha * coeffs[i*f + 1] + ... + alpha^(f-1) * coeffs[i*f + f - 1]
+// evaluated with Horner's method so powers of alpha aren't needed
+template<typename FieldT> kernel void
+FoldCoeffs(device FieldT *dst [[ buffer(0) ]],
+        constant FieldT *coeffs [[ buffer(1) ]],
+        constant FieldT &alpha [[ buffer(2) ]],
+        constant unsigned &folding_factor [[ buffer(3) ]],
+        unsigned global_tid [[ thread_position_in_grid ]]) {
+    unsigned offset = global_tid * folding_factor;
+    FieldT acc = coeffs[offset + folding_factor - 1];
+    for (unsigned k = folding_factor - 1; k > 0; k--) {
+        acc = acc * alpha + coeffs[offset + k - 1];
+    }
+    dst[global_tid] = acc;
+}
+
+// evals[i] *= inv_vanishing[i % period]
+// the vanishing polynomial of a subgroup only takes `period` distinct values
+// over a coset of a larger group so the inverses are computed on the host
+template<typename LHSFieldT, typename RHSFieldT = LHSFieldT> kernel void
+DivideByVanishing(device LHSFieldT *evals [[ buffer(0) ]],
+        constant RHSFieldT *inv_vanishing [[ buffer(1) ]],
+        constant unsigned &period [[ buffer(2) ]],
+        unsigned i [[ thread_position_in_grid ]]) {
+    LHSFieldT eval = evals[i];
+    RHSFieldT inv = inv_vanishing[i % period];
+    evals[i] = eval * inv;
+}
+
+// Permutes `vals` into bit-reversed order in place. Unlike the FFT's bit
+// reversal the size is passed at runtime so buffers of any size can be
+// permuted without planning an FFT.
+template<typename FieldT> kernel void
+BitReversePermute(device FieldT *vals [[ buffer(0) ]],
+        constant unsigned &log_n [[ buffer(1) ]],
+        unsigned i [[ thread_position_in_grid ]]) {
+    unsigned ri = reverse_bits(i) >> (sizeof(i) * 8 - log_n);
+
+    if (i < ri) {
+        FieldT tmp = vals[i];
+        vals[i] = vals[ri];
+        vals[ri] = tmp;
+    }
+}
+
 // ===========================================================
 // Evaluation for Fp=18446744069414584321
 template [[ host_name("add_assign_LHS_p18446744069414584321_fp_RHS_p18446744069414584321_fp") ]] kernel void
@@ -268,6 +313,24 @@ FillBuff<p18446744069414584321::Fp>(
         device p18446744069414584321::Fp*,
         constant p18446744069414584321::Fp&,
         unsigned);
+template [[ host_name("fold_coeffs_p18446744069414584321_fp") ]] kernel void
+FoldCoeffs<p18446744069414584321::Fp>(
+        device p18446744069414584321::Fp*,
+        constant p18446744069414584321::Fp*,
+        constant p18446744069414584321::Fp&,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("divide_by_vanishing_LHS_p18446744069414584321_fp_RHS_p18446744069414584321_fp") ]] kernel void
+DivideByVanishing<p18446744069414584321::Fp>(
+        device p18446744069414584321::Fp*,
+        constant p18446744069414584321::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("bit_reverse_permute_p18446744069414584321_fp") ]] kernel void
+BitReversePermute<p18446744069414584321::Fp>(
+        device p18446744069414584321::Fp*,
+        constant unsigned&,
+        unsigned);
 // ===========================================================
 // Evaluation for cubic extension of Fp=18446744069414584321
 template [[ host_name("add_assign_LHS_p18446744069414584321_fq3_RHS_p18446744069414584321_fq3") ]] kernel void
@@ -414,6 +477,24 @@ ExpInto<p18446744069414584321::Fq3>(
         constant p18446744069414584321::Fq3*,
         constant unsigned&,
         unsigned);
+template [[ host_name("fold_coeffs_p18446744069414584321_fq3") ]] kernel void
+FoldCoeffs<p18446744069414584321::Fq3>(
+        device p18446744069414584321::Fq3*,
+        constant p18446744069414584321::Fq3*,
+        constant p18446744069414584321::Fq3&,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("divide_by_vanishing_LHS_p18446744069414584321_fq3_RHS_p18446744069414584321_fp") ]] kernel void
+DivideByVanishing<p18446744069414584321::Fq3, p18446744069414584321::Fp>(
+        device p18446744069414584321::Fq3*,
+        constant p18446744069414584321::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("bit_reverse_permute_p18446744069414584321_fq3") ]] kernel void
+BitReversePermute<p18446744069414584321::Fq3>(
+        device p18446744069414584321::Fq3*,
+        constant unsigned&,
+        unsigned);
 // ===========================================================
 // Evaluation for Fp=3618502788666131213697322783095070105623107215331596699973092056135872020481
 template [[ host_name("add_assign_LHS_p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp_RHS_p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp") ]] kernel void
@@ -510,6 +591,24 @@ FillBuff<p3618502788666131213697322783095070105623107215331596699973092056135872
         device p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
         constant p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp&,
         unsigned);
+template [[ host_name("fold_coeffs_p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp") ]] kernel void
+FoldCoeffs<p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp>(
+        device p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        constant p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        constant p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp&,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("divide_by_vanishing_LHS_p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp_RHS_p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp") ]] kernel void
+DivideByVanishing<p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp>(
+        device p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        constant p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("bit_reverse_permute_p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp") ]] kernel void
+BitReversePermute<p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp>(
+        device p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        constant unsigned&,
+        unsigned);
 // ===========================================================
 
 #endif /* evaluation_shaders_h */
@@ -0,0 +1,199 @@
+#ifndef scan_shaders_h
+#define scan_shaders_h
+
+#include <metal_stdlib>
+#include "felt_u256.h.metal"
+#include "felt_u64.h.metal"
+using namespace metal;
+
+struct ScanAdd {
+    template<typename T> static T apply(T a, T b) {
+        return a + b;
+    }
+};
+
+struct ScanMul {
+    template<typename T> static T apply(T a, T b) {
+        return a * b;
+    }
+};
+
+// Inclusive scan of each block of 2 * threads_per_threadgroup values (Blelloch).
+// The total of each block is written to `block_totals` so the blocks can be
+// combined by scanning the totals and applying them with ScanApplyBlockPrefix.
+template<typename FieldT, typename OpT> kernel void
+ScanBlocks(device FieldT *vals [[ buffer(0) ]],
+        device FieldT *block_totals [[ buffer(1) ]],
+        constant FieldT &identity [[ buffer(2) ]],
+        constant unsigned &n [[ buffer(3) ]],
+        threadgroup FieldT *shared [[ threadgroup(0) ]],
+        unsigned tid [[ thread_index_in_threadgroup ]],
+        unsigned group [[ threadgroup_position_in_grid ]],
+        unsigned threads [[ threads_per_threadgroup ]]) {
+    unsigned block_size = 2 * threads;
+    unsigned offset = group * block_size;
+    unsigned i0 = offset + tid;
+    unsigned i1 = offset + tid + threads;
+    FieldT v0 = i0 < n ? vals[i0] : identity;
+    FieldT v1 = i1 < n ? vals[i1] : identity;
+    shared[tid] = v0;
+    shared[tid + threads] = v1;
+
+    // up-sweep
+    unsigned stride = 1;
+    for (unsigned d = threads; d > 0; d >>= 1) {
+        threadgroup_barrier(mem_flags::mem_threadgroup);
+        if (tid < d) {
+            unsigned a = stride * (2 * tid + 1) - 1;
+            unsigned b = stride * (2 * tid + 2) - 1;
+            shared[b] = OpT::apply(shared[a], shared[b]);
+        }
+        stride <<= 1;
+    }
+
+    if (tid == 0) {
+        block_totals[group] = shared[block_size - 1];
+        shared[block_size - 1] = identity;
+    }
+
+    // down-sweep
+    for (unsigned d = 1; d < block_size; d <<= 1) {
+        stride >>= 1;
+        threadgroup_barrier(mem_flags::mem_threadgroup);
+        if (tid < d) {
+            unsigned a = stride * (2 * tid + 1) - 1;
+            unsigned b = stride * (2 * tid + 2) - 1;
+            FieldT t = shared[a];
+            shared[a] = shared[b];
+            shared[b] = OpT::apply(t, shared[b]);
+        }
+    }
+    threadgroup_barrier(mem_flags::mem_threadgroup);
+
+    // exclusive to inclusive
+    if (i0 < n) {
+        vals[i0] = OpT::apply(shared[tid], v0);
+    }
+    if (i1 < n) {
+        vals[i1] = OpT::apply(shared[tid + threads], v1);
+    }
+}
+
+// Combines each value with the inclusive scan of the totals of the preceding
+// blocks
+template<typename FieldT, typename OpT> kernel void
+ScanApplyBlockPrefix(device FieldT *vals [[ buffer(0) ]],
+        constant FieldT *block_prefixes [[ buffer(1) ]],
+        constant unsigned &block_size [[ buffer(2) ]],
+        unsigned i [[ thread_position_in_grid ]]) {
+    unsigned block = i / block_size;
+    if (block > 0) {
+        vals[i] = OpT::apply(block_prefixes[block - 1], vals[i]);
+    }
+}
+
+// ===========================================================
+// Scans for Fp=18446744069414584321
+template [[ host_name("scan_blocks_sum_p18446744069414584321_fp") ]] kernel void
+ScanBlocks<p18446744069414584321::Fp, ScanAdd>(
+        device p18446744069414584321::Fp*,
+        device p18446744069414584321::Fp*,
+        constant p18446744069414584321::Fp&,
+        constant unsigned&,
+        threadgroup p18446744069414584321::Fp*,
+        unsigned,
+        unsigned,
+        unsigned);
+template [[ host_name("scan_blocks_product_p18446744069414584321_fp") ]] kernel void
+ScanBlocks<p18446744069414584321::Fp, ScanMul>(
+        device p18446744069414584321::Fp*,
+        device p18446744069414584321::Fp*,
+        constant p18446744069414584321::Fp&,
+        constant unsigned&,
+        threadgroup p18446744069414584321::Fp*,
+        unsigned,
+        unsigned,
+        unsigned);
+template [[ host_name("scan_apply_sum_p18446744069414584321_fp") ]] kernel void
+ScanApplyBlockPrefix<p18446744069414584321::Fp, ScanAdd>(
+        device p18446744069414584321::Fp*,
+        constant p18446744069414584321::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("scan_apply_product_p18446744069414584321_fp") ]] kernel void
+ScanApplyBlockPrefix<p18446744069414584321::Fp, ScanMul>(
+        device p18446744069414584321::Fp*,
+        constant p18446744069414584321::Fp*,
+        constant unsigned&,
+        unsigned);
+// ===========================================================
+// Scans for cubic extension of Fp=18446744069414584321
+template [[ host_name("scan_blocks_sum_p18446744069414584321_fq3") ]] kernel void
+ScanBlocks<p18446744069414584321::Fq3, ScanAdd>(
+        device p18446744069414584321::Fq3*,
+        device p18446744069414584321::Fq3*,
+        constant p18446744069414584321::Fq3&,
+        constant unsigned&,
+        threadgroup p18446744069414584321::Fq3*,
+        unsigned,
+        unsigned,
+        unsigned);
+template [[ host_name("scan_blocks_product_p18446744069414584321_fq3") ]] kernel void
+ScanBlocks<p18446744069414584321::Fq3, ScanMul>(
+        device p18446744069414584321::Fq3*,
+        device p18446744069414584321::Fq3*,
+        constant p18446744069414584321::Fq3&,
+        constant unsigned&,
+        threadgroup p18446744069414584321::Fq3*,
+        unsigned,
+        unsigned,
+        unsigned);
+template [[ host_name("scan_apply_sum_p18446744069414584321_fq3") ]] kernel void
+ScanApplyBlockPrefix<p18446744069414584321::Fq3, ScanAdd>(
+        device p18446744069414584321::Fq3*,
+        constant p18446744069414584321::Fq3*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("scan_apply_product_p18446744069414584321_fq3") ]] kernel void
+ScanApplyBlockPrefix<p18446744069414584321::Fq3, ScanMul>(
+        device p18446744069414584321::Fq3*,
+        constant p18446744069414584321::Fq3*,
+        constant unsigned&,
+        unsigned);
+// ===========================================================
+// Scans for Fp=3618502788666131213697322783095070105623107215331596699973092056135872020481
+template [[ host_name("scan_blocks_sum_p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp") ]] kernel void
+ScanBlocks<p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp, ScanAdd>(
+        device p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        device p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        constant p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp&,
+        constant unsigned&,
+        threadgroup p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        unsigned,
+        unsigned,
+        unsigned);
+template [[ host_name("scan_blocks_product_p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp") ]] kernel void
+ScanBlocks<p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp, ScanMul>(
+        device p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        device p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        constant p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp&,
+        constant unsigned&,
+        threadgroup p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        unsigned,
+        unsigned,
+        unsigned);
+template [[ host_name("scan_apply_sum_p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp") ]] kernel void
+ScanApplyBlockPrefix<p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp, ScanAdd>(
+        device p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        constant p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        constant unsigned&,
+        unsigned);
+template [[ host_name("scan_apply_product_p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp") ]] kernel void
+ScanApplyBlockPrefix<p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp, ScanMul>(
+        device p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        constant p3618502788666131213697322783095070105623107215331596699973092056135872020481::Fp*,
+        constant unsigned&,
+        unsigned);
+// ===========================================================
+
+#endif /* scan_shaders_h */
@@ -33,4 +33,5 @@ constant unsigned THREADGROUP_FFT_SIZE [[ function_constant(2) ]];
 
 #include "fft_shaders.h.metal"
 #include "evaluation_shaders.h.metal"
-#include "hash_shaders.h.metal"
\ No newline at end of file
+#include "hash_shaders.h.metal"
+#include "scan_shaders.h.metal"
\ No newline at end of file
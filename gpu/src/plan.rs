@@ -1,16 +1,31 @@
-#![cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#![cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
+use crate::stage::AddAssignConstStage;
+use crate::stage::AddAssignStage;
 #[cfg(feature = "arkworks")]
 use crate::stage::BitReverseGpuStage;
+use crate::stage::BitReversePermuteStage;
+#[cfg(feature = "arkworks")]
+use crate::stage::DivideByVanishingStage;
+use crate::stage::ExpInPlaceStage;
 #[cfg(feature = "arkworks")]
 use crate::stage::FftGpuStage;
 #[cfg(feature = "arkworks")]
 use crate::stage::FftVariant;
+#[cfg(feature = "arkworks")]
+use crate::stage::FoldCoeffsStage;
+use crate::stage::InverseInPlaceStage;
+use crate::stage::MulAssignConstStage;
+use crate::stage::MulAssignStage;
 use crate::stage::Rpo256AbsorbColumnsStage;
 use crate::stage::Rpo256AbsorbRowsStage;
 use crate::stage::Rpo256GenMerkleNodesFirstRowStage;
 use crate::stage::Rpo256GenMerkleNodesRowStage;
 #[cfg(feature = "arkworks")]
 use crate::stage::ScaleAndNormalizeGpuStage;
+#[cfg(feature = "arkworks")]
+use crate::stage::ScanOp;
+#[cfg(feature = "arkworks")]
+use crate::stage::ScanStage;
 use crate::utils::buffer_mut_no_copy;
 use crate::utils::buffer_no_copy;
 use crate::utils::is_page_aligned;
@@ -24,6 +39,8 @@ use ark_ff::One;
 use ark_poly::EvaluationDomain;
 #[cfg(feature = "arkworks")]
 use ark_poly::Radix2EvaluationDomain;
+#[cfg(feature = "arkworks")]
+use core::marker::PhantomData;
 use metal::CommandBufferRef;
 use once_cell::sync::Lazy;
 
@@ -232,12 +249,22 @@ where
     }
 }
 
+/// Order of the evaluations output by a [`GpuFft`] or input to a [`GpuIfft`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EvaluationOrder {
+    #[default]
+    Natural,
+    /// Evaluation `i` is at index `bit_reverse_index(n, i)`
+    BitReversed,
+}
+
 #[cfg(feature = "arkworks")]
 pub struct GpuFft<'a, F: GpuField + ark_ff::Field>
 where
     F::FftField: ark_ff::FftField,
 {
     encoder: FftEncoder<'a, F>,
+    output_order: EvaluationOrder,
 }
 
 #[cfg(feature = "arkworks")]
@@ -248,7 +275,24 @@ where
     pub const MIN_SIZE: usize = 2048;
 
     fn new(encoder: FftEncoder<'a, F>) -> Self {
-        GpuFft { encoder }
+        GpuFft {
+            encoder,
+            output_order: EvaluationOrder::Natural,
+        }
+    }
+
+    /// Sets the order of the output evaluations. The butterflies produce
+    /// bit-reversed evaluations so [`EvaluationOrder::BitReversed`] skips the
+    /// final permutation.
+    pub fn with_output_order(mut self, output_order: EvaluationOrder) -> Self {
+        self.output_order = output_order;
+        self
+    }
+
+    fn encode_output_permutation(&self, input_buffer: &mut metal::Buffer) {
+        if self.output_order == EvaluationOrder::Natural {
+            self.encoder.encode_bit_reverse_stage(input_buffer);
+        }
     }
 
     pub fn encode(&mut self, buffer: &mut [F]) {
@@ -259,7 +303,33 @@ where
             crate::utils::buffer_mut_no_copy(encoder.command_queue.device(), buffer);
         encoder.encode_scale_stage(&mut input_buffer);
         encoder.encode_butterfly_stages(&mut input_buffer);
-        encoder.encode_bit_reverse_stage(&mut input_buffer);
+        self.encode_output_permutation(&mut input_buffer);
+    }
+
+    /// Encodes folding `coeffs` into `buffer` followed by the FFT of `buffer`.
+    /// Every `folding_factor` consecutive coefficients are combined with powers
+    /// of `alpha` (FRI's degree respecting projection). Both steps run in the
+    /// same command buffer so the folded coefficients never visit the host.
+    pub fn encode_fold(&mut self, buffer: &mut [F], coeffs: &[F], alpha: F, folding_factor: usize) {
+        assert!(is_page_aligned(buffer));
+        assert!(is_page_aligned(coeffs));
+        let encoder = &self.encoder;
+        assert_eq!(encoder.n, buffer.len());
+        assert_eq!(encoder.n * folding_factor, coeffs.len());
+        let device = encoder.command_queue.device();
+        let mut input_buffer = crate::utils::buffer_mut_no_copy(device, buffer);
+        let coeffs_buffer = buffer_no_copy(device, coeffs);
+        let fold_stage = FoldCoeffsStage::<F>::new(&get_planner().library, encoder.n);
+        fold_stage.encode(
+            encoder.command_buffer,
+            &input_buffer,
+            &coeffs_buffer,
+            alpha,
+            folding_factor,
+        );
+        encoder.encode_scale_stage(&mut input_buffer);
+        encoder.encode_butterfly_stages(&mut input_buffer);
+        self.encode_output_permutation(&mut input_buffer);
     }
 
     pub fn execute(self) {
@@ -284,6 +354,7 @@ where
     F::FftField: ark_ff::FftField,
 {
     encoder: FftEncoder<'a, F>,
+    input_order: EvaluationOrder,
 }
 
 #[cfg(feature = "arkworks")]
@@ -294,7 +365,17 @@ where
     pub const MIN_SIZE: usize = 2048;
 
     fn new(encoder: FftEncoder<'a, F>) -> Self {
-        GpuIfft { encoder }
+        GpuIfft {
+            encoder,
+            input_order: EvaluationOrder::Natural,
+        }
+    }
+
+    /// Sets the order of the input evaluations. Bit-reversed inputs are
+    /// permuted on the GPU before the butterflies.
+    pub fn with_input_order(mut self, input_order: EvaluationOrder) -> Self {
+        self.input_order = input_order;
+        self
     }
 
     pub fn encode(&mut self, input: &mut [F]) {
@@ -303,6 +384,9 @@ where
         assert_eq!(encoder.n, input.len());
         let mut input_buffer =
             crate::utils::buffer_mut_no_copy(encoder.command_queue.device(), input);
+        if self.input_order == EvaluationOrder::BitReversed {
+            encoder.encode_bit_reverse_stage(&mut input_buffer);
+        }
         encoder.encode_butterfly_stages(&mut input_buffer);
         encoder.encode_bit_reverse_stage(&mut input_buffer);
         encoder.encode_scale_stage(&mut input_buffer);
@@ -324,8 +408,408 @@ where
     }
 }
 
+/// FRI's degree respecting projection of bit-reversed evaluations.
+///
+/// Interpolates the evaluations, folds every `folding_factor` consecutive
+/// coefficients with powers of alpha and evaluates the folded coefficients
+/// over the folded domain. All three steps are encoded into one command buffer
+/// so the codeword stays on the device between them and the host only waits
+/// once for the folded evaluations. The evaluations are overwritten with the
+/// interpolated coefficients.
+#[cfg(feature = "arkworks")]
+pub struct GpuDrp<'a, F: GpuField + ark_ff::Field>
+where
+    F::FftField: ark_ff::FftField,
+{
+    ifft: FftEncoder<'a, F>,
+    fft: FftEncoder<'a, F>,
+    folding_factor: usize,
+}
+
+#[cfg(feature = "arkworks")]
+impl<'a, F: GpuField + ark_ff::Field> GpuDrp<'a, F>
+where
+    F::FftField: ark_ff::FftField,
+{
+    /// Encodes folding the bit-reversed evaluations `evals` into `dst` with
+    /// challenge `alpha`. The folded evaluations are bit-reversed.
+    pub fn encode(&mut self, dst: &mut [F], evals: &mut [F], alpha: F) {
+        assert!(is_page_aligned(dst));
+        assert!(is_page_aligned(evals));
+        assert_eq!(self.ifft.n, evals.len());
+        assert_eq!(self.fft.n, dst.len());
+        let device = self.ifft.command_queue.device();
+        let mut evals_buffer = buffer_mut_no_copy(device, evals);
+        let mut dst_buffer = buffer_mut_no_copy(device, dst);
+
+        // interpolate bit-reversed evaluations. The scale stage also multiplies
+        // the coefficients by the folding factor (see `Planner::plan_drp`)
+        self.ifft.encode_bit_reverse_stage(&mut evals_buffer);
+        self.ifft.encode_butterfly_stages(&mut evals_buffer);
+        self.ifft.encode_bit_reverse_stage(&mut evals_buffer);
+        self.ifft.encode_scale_stage(&mut evals_buffer);
+
+        let fold_stage = FoldCoeffsStage::<F>::new(&get_planner().library, self.fft.n);
+        fold_stage.encode(
+            self.ifft.command_buffer,
+            &dst_buffer,
+            &evals_buffer,
+            alpha,
+            self.folding_factor,
+        );
+
+        // evaluate the folded coefficients in bit-reversed order
+        self.fft.encode_scale_stage(&mut dst_buffer);
+        self.fft.encode_butterfly_stages(&mut dst_buffer);
+    }
+
+    pub fn execute(self) {
+        self.ifft.execute()
+    }
+}
+
+/// FFT over domains that are too large to transform on the GPU in one pass.
+///
+/// Uses the four-step FFT. A column of size `n = n1 * n2` is viewed as a
+/// row-major `n1 x n2` matrix and is transformed with `n2` FFTs of size `n1`,
+/// a twiddle multiplication and `n1` FFTs of size `n2`. The smaller FFTs and
+/// the twiddles are evaluated on the GPU in passes over at most
+/// `max_pass_size` elements. Transposes between steps are done on the CPU.
+#[cfg(feature = "arkworks")]
+pub struct GpuChunkedFft<F: GpuField + ark_ff::Field>
+where
+    F::FftField: ark_ff::FftField,
+{
+    direction: FftDirection,
+    domain: Radix2EvaluationDomain<F::FftField>,
+    max_pass_size: usize,
+    _phantom: PhantomData<F>,
+}
+
+#[cfg(feature = "arkworks")]
+impl<F: GpuField + ark_ff::Field> GpuChunkedFft<F>
+where
+    F::FftField: ark_ff::FftField,
+{
+    fn new(
+        direction: FftDirection,
+        domain: Radix2EvaluationDomain<F::FftField>,
+        max_pass_size: usize,
+    ) -> Self {
+        let (n1, n2) = Self::dimensions(domain.size());
+        assert!(n1 >= GpuFft::<F>::MIN_SIZE, "domain is too small");
+        assert!(n2 <= max_pass_size, "domain is too large");
+        GpuChunkedFft {
+            direction,
+            domain,
+            max_pass_size,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of rows and columns `(n1, n2)` the values are split
+    /// into
+    fn dimensions(n: usize) -> (usize, usize) {
+        let n1 = 1 << (n.ilog2() / 2);
+        (n1, n / n1)
+    }
+
+    /// Transforms `values` in place. The FFT evaluates coefficients over the
+    /// domain and the IFFT interpolates evaluations over the domain.
+    pub fn transform(&self, values: &mut [F]) {
+        assert!(is_page_aligned(values));
+        let n = self.domain.size();
+        assert_eq!(n, values.len());
+        let (n1, n2) = Self::dimensions(n);
+        let (root, offset) = match self.direction {
+            FftDirection::Forward => (self.domain.group_gen, self.domain.offset),
+            FftDirection::Inverse => (self.domain.group_gen_inv, self.domain.offset_inv),
+        };
+
+        // FFTs over a coset are FFTs of the coefficients scaled by powers of the
+        // offset. IFFTs scale the interpolated coefficients instead.
+        if self.direction == FftDirection::Forward && !offset.is_one() {
+            self.scale_rows(values, n2, |i| (offset, offset.pow([(i * n2) as u64])));
+        }
+
+        let mut scratch = unsafe { page_aligned_uninit_vector(n) };
+        // 1. FFTs of size n1 down the columns
+        crate::utils::transpose(values, &mut scratch, n1, n2);
+        self.sub_transforms(&mut scratch, n1);
+        crate::utils::transpose(&scratch, values, n2, n1);
+        // 2. multiply row i and column j with root^(i * j)
+        self.scale_rows(values, n2, |i| (root.pow([i as u64]), F::FftField::one()));
+        // 3. FFTs of size n2 along the rows
+        self.sub_transforms(values, n2);
+        // 4. the result is read column by column
+        crate::utils::transpose(values, &mut scratch, n1, n2);
+        values.copy_from_slice(&scratch);
+
+        if self.direction == FftDirection::Inverse && !offset.is_one() {
+            self.scale_rows(values, n2, |i| (offset, offset.pow([(i * n2) as u64])));
+        }
+    }
+
+    /// Transforms consecutive chunks of `values` of size `m` in place
+    fn sub_transforms(&self, values: &mut [F], m: usize) {
+        let sub_domain = Radix2EvaluationDomain::new(m).unwrap();
+        let chunks_per_pass = core::cmp::max(self.max_pass_size / m, 1);
+        for pass in values.chunks_mut(m * chunks_per_pass) {
+            match self.direction {
+                FftDirection::Forward => {
+                    let mut fft = GpuFft::from(sub_domain);
+                    for chunk in pass.chunks_mut(m) {
+                        fft.encode(chunk);
+                    }
+                    fft.execute();
+                }
+                FftDirection::Inverse => {
+                    // sub IFFTs normalize by 1/m so the result is normalized by 1/n
+                    let mut ifft = GpuIfft::from(sub_domain);
+                    for chunk in pass.chunks_mut(m) {
+                        ifft.encode(chunk);
+                    }
+                    ifft.execute();
+                }
+            }
+        }
+    }
+
+    /// Multiplies the `j`-th element of row `i` by `norm * base^j` where
+    /// `(base, norm) = factors(i)`
+    fn scale_rows(
+        &self,
+        values: &mut [F],
+        row_len: usize,
+        factors: impl Fn(usize) -> (F::FftField, F::FftField),
+    ) {
+        let planner = get_planner();
+        let device = planner.command_queue.device();
+        let rows_per_pass = core::cmp::max(self.max_pass_size / row_len, 1);
+        let mut scale_factors = unsafe { page_aligned_uninit_vector(row_len * rows_per_pass) };
+        for (pass_offset, pass) in values.chunks_mut(row_len * rows_per_pass).enumerate() {
+            let pass_factors = &mut scale_factors[0..pass.len()];
+            for (i, row_factors) in pass_factors.chunks_mut(row_len).enumerate() {
+                let (base, norm) = factors(pass_offset * rows_per_pass + i);
+                let mut factor = norm;
+                for row_factor in row_factors {
+                    *row_factor = factor;
+                    factor *= base;
+                }
+            }
+
+            let mul_assign_stage =
+                MulAssignStage::<F, F::FftField>::new(&planner.library, pass.len());
+            let command_buffer = planner.command_queue.new_command_buffer();
+            let values_buffer = buffer_mut_no_copy(device, pass);
+            let factors_buffer = buffer_no_copy(device, &*pass_factors);
+            mul_assign_stage.encode(command_buffer, &values_buffer, &factors_buffer, 0);
+            command_buffer.commit();
+            command_buffer.wait_until_completed();
+        }
+    }
+}
+
 static PLANNER: Lazy<Planner> = Lazy::new(Planner::default);
 
+/// Divides evaluations over `eval_domain` (in natural order) by the vanishing
+/// polynomial of `vanish_domain` on the GPU. Since the vanishing polynomial
+/// only takes `eval_domain.size() / vanish_domain.size()` distinct values over
+/// `eval_domain` these are evaluated and inverted on the host and the GPU
+/// only performs the multiplication.
+#[cfg(feature = "arkworks")]
+pub fn divide_by_vanishing<F: GpuField + ark_ff::Field>(
+    evals: &mut [F],
+    vanish_domain: Radix2EvaluationDomain<F::FftField>,
+    eval_domain: Radix2EvaluationDomain<F::FftField>,
+) where
+    F::FftField: ark_ff::FftField,
+{
+    assert!(is_page_aligned(evals));
+    assert_eq!(evals.len(), eval_domain.size());
+    let n = vanish_domain.size();
+    let period = eval_domain.size() / n;
+    assert!(
+        period > 1,
+        "evaluation domain must be larger than the vanishing domain"
+    );
+
+    // x^n for x in the evaluation domain is offset^n * (g^n)^i
+    let scaled_eval_offset = eval_domain.coset_offset().pow([n as u64]);
+    let scaled_eval_generator = eval_domain.group_gen().pow([n as u64]);
+    let scaled_vanish_offset = vanish_domain.coset_offset_pow_size();
+    let mut inv_vanishing = unsafe { page_aligned_uninit_vector(period) };
+    let mut acc = scaled_eval_offset;
+    for v in &mut inv_vanishing {
+        *v = acc - scaled_vanish_offset;
+        acc *= scaled_eval_generator;
+    }
+    ark_ff::batch_inversion(&mut inv_vanishing);
+    assert!(
+        inv_vanishing.iter().all(|v| !v.is_zero()),
+        "evaluation domain intersects the vanishing domain"
+    );
+
+    let planner = get_planner();
+    let device = planner.command_queue.device();
+    let evals_buffer = buffer_mut_no_copy(device, evals);
+    let inv_vanishing_buffer = buffer_no_copy(device, &inv_vanishing);
+    let stage = DivideByVanishingStage::<F, F::FftField>::new(&planner.library, evals.len());
+    let command_buffer = planner.command_queue.new_command_buffer();
+    #[cfg(debug_assertions)]
+    command_buffer.set_label("divide by vanishing polynomial");
+    stage.encode(command_buffer, &evals_buffer, &inv_vanishing_buffer, period);
+    command_buffer.commit();
+    command_buffer.wait_until_completed();
+}
+
+/// Replaces `values` with their inclusive prefix sums on the GPU
+#[cfg(feature = "arkworks")]
+pub fn scan_sum<F: GpuField + ark_ff::Field>(values: &mut [F]) {
+    scan(values, ScanOp::Sum, F::zero());
+}
+
+/// Replaces `values` with their inclusive prefix products on the GPU
+#[cfg(feature = "arkworks")]
+pub fn scan_product<F: GpuField + ark_ff::Field>(values: &mut [F]) {
+    scan(values, ScanOp::Product, F::one());
+}
+
+/// Blelloch scan of `values`. Blocks of values are scanned by threadgroups
+/// and the block totals are scanned recursively until they fit in a single
+/// block. The scanned totals are then applied to the levels below.
+#[cfg(feature = "arkworks")]
+fn scan<F: GpuField + ark_ff::Field>(values: &mut [F], op: ScanOp, identity: F) {
+    assert!(is_page_aligned(values));
+    if values.is_empty() {
+        return;
+    }
+
+    let planner = get_planner();
+    let device = planner.command_queue.device();
+    let stage = ScanStage::<F>::new(&planner.library, op);
+    let block_size = stage.block_size();
+    let mut lens = alloc::vec![values.len()];
+    let mut buffers = alloc::vec![buffer_mut_no_copy(device, values)];
+    // owns the memory of the no copy buffers of the block totals
+    let mut totals = Vec::new();
+    loop {
+        let len = lens.last().unwrap().div_ceil(block_size);
+        let mut level = unsafe { page_aligned_uninit_vector::<F>(len) };
+        buffers.push(buffer_mut_no_copy(device, &mut level));
+        totals.push(level);
+        if len == 1 {
+            break;
+        }
+        lens.push(len);
+    }
+
+    let command_buffer = planner.command_queue.new_command_buffer();
+    #[cfg(debug_assertions)]
+    command_buffer.set_label("scan");
+    for (i, &len) in lens.iter().enumerate() {
+        stage.encode_blocks(command_buffer, &buffers[i], len, &buffers[i + 1], &identity);
+    }
+    for (i, &len) in lens.iter().enumerate().rev().skip(1) {
+        stage.encode_apply(command_buffer, &buffers[i], len, &buffers[i + 1]);
+    }
+    command_buffer.commit();
+    command_buffer.wait_until_completed();
+}
+
+/// Adds `rhs` to `lhs` element-wise on the GPU
+pub fn add_assign<F: GpuField>(lhs: &mut [F], rhs: &[F]) {
+    assert_eq!(lhs.len(), rhs.len());
+    let planner = get_planner();
+    let device = planner.command_queue.device();
+    let lhs_buffer = buffer_mut_no_copy(device, lhs);
+    let rhs_buffer = buffer_no_copy(device, rhs);
+    let stage = AddAssignStage::<F>::new(&planner.library, lhs.len());
+    let command_buffer = planner.command_queue.new_command_buffer();
+    #[cfg(debug_assertions)]
+    command_buffer.set_label("add assign");
+    stage.encode(command_buffer, &lhs_buffer, &rhs_buffer, 0);
+    command_buffer.commit();
+    command_buffer.wait_until_completed();
+}
+
+/// Multiplies `lhs` by `rhs` element-wise on the GPU
+pub fn mul_assign<F: GpuField>(lhs: &mut [F], rhs: &[F]) {
+    assert_eq!(lhs.len(), rhs.len());
+    let planner = get_planner();
+    let device = planner.command_queue.device();
+    let lhs_buffer = buffer_mut_no_copy(device, lhs);
+    let rhs_buffer = buffer_no_copy(device, rhs);
+    let stage = MulAssignStage::<F>::new(&planner.library, lhs.len());
+    let command_buffer = planner.command_queue.new_command_buffer();
+    #[cfg(debug_assertions)]
+    command_buffer.set_label("mul assign");
+    stage.encode(command_buffer, &lhs_buffer, &rhs_buffer, 0);
+    command_buffer.commit();
+    command_buffer.wait_until_completed();
+}
+
+/// Replaces each value `v` with `mul * v + add` on the GPU
+pub fn affine_assign<F: GpuField>(values: &mut [F], mul: F, add: F) {
+    let planner = get_planner();
+    let device = planner.command_queue.device();
+    let buffer = buffer_mut_no_copy(device, values);
+    let mul_stage = MulAssignConstStage::<F>::new(&planner.library, values.len());
+    let add_stage = AddAssignConstStage::<F>::new(&planner.library, values.len());
+    let command_buffer = planner.command_queue.new_command_buffer();
+    #[cfg(debug_assertions)]
+    command_buffer.set_label("affine assign");
+    mul_stage.encode(command_buffer, &buffer, mul);
+    add_stage.encode(command_buffer, &buffer, &add);
+    command_buffer.commit();
+    command_buffer.wait_until_completed();
+}
+
+/// Raises each value to the power `exponent` on the GPU
+pub fn exp_in_place<F: GpuField>(values: &mut [F], exponent: usize) {
+    let planner = get_planner();
+    let device = planner.command_queue.device();
+    let buffer = buffer_mut_no_copy(device, values);
+    let stage = ExpInPlaceStage::<F>::new(&planner.library, values.len());
+    let command_buffer = planner.command_queue.new_command_buffer();
+    #[cfg(debug_assertions)]
+    command_buffer.set_label("exp in place");
+    stage.encode(command_buffer, &buffer, exponent);
+    command_buffer.commit();
+    command_buffer.wait_until_completed();
+}
+
+/// Inverts each value on the GPU. Zeros are left as zero.
+pub fn inverse_in_place<F: GpuField>(values: &mut [F]) {
+    let planner = get_planner();
+    let device = planner.command_queue.device();
+    let buffer = buffer_mut_no_copy(device, values);
+    let stage = InverseInPlaceStage::<F>::new(&planner.library, values.len());
+    let command_buffer = planner.command_queue.new_command_buffer();
+    #[cfg(debug_assertions)]
+    command_buffer.set_label("inverse in place");
+    stage.encode(command_buffer, &buffer);
+    command_buffer.commit();
+    command_buffer.wait_until_completed();
+}
+
+/// Permutes `values` into bit-reversed order on the GPU. The permutation is
+/// an involution so this also converts bit-reversed values back into natural
+/// order.
+pub fn bit_reverse<F: GpuField>(values: &mut [F]) {
+    assert!(is_page_aligned(values));
+    let planner = get_planner();
+    let device = planner.command_queue.device();
+    let buffer = buffer_mut_no_copy(device, values);
+    let stage = BitReversePermuteStage::<F>::new(&planner.library, values.len());
+    let command_buffer = planner.command_queue.new_command_buffer();
+    #[cfg(debug_assertions)]
+    command_buffer.set_label("bit reverse");
+    stage.encode(command_buffer, &buffer);
+    command_buffer.commit();
+    command_buffer.wait_until_completed();
+}
+
 pub fn get_planner() -> &'static Planner {
     &PLANNER
 }
@@ -373,6 +857,75 @@ impl Planner {
         GpuIfft::new(self.create_fft_encoder(FftDirection::Inverse, domain))
     }
 
+    /// Plans folding evaluations over `domain` by `folding_factor` (see
+    /// [`GpuDrp`])
+    #[cfg(feature = "arkworks")]
+    pub fn plan_drp<F: GpuField + ark_ff::Field>(
+        &self,
+        domain: Radix2EvaluationDomain<F::FftField>,
+        folding_factor: usize,
+    ) -> GpuDrp<F>
+    where
+        F::FftField: ark_ff::FftField,
+    {
+        let n = domain.size();
+        let drp_offset = ark_ff::Field::pow(&domain.offset, [folding_factor as u64]);
+        let drp_domain = Radix2EvaluationDomain::new_coset(n / folding_factor, drp_offset).unwrap();
+        assert!(drp_domain.size() >= GpuFft::<F>::MIN_SIZE);
+        let mut ifft = self.create_fft_encoder(FftDirection::Inverse, domain);
+        let mut fft = self.create_fft_encoder(FftDirection::Forward, drp_domain);
+        // coefficients are scaled by the folding factor while normalizing
+        let scale = F::FftField::from(folding_factor as u64);
+        ifft.scale_and_normalize_stage = Some(ScaleAndNormalizeGpuStage::new(
+            &self.library,
+            &self.command_queue,
+            n,
+            domain.offset_inv,
+            domain.size_inv * scale,
+        ));
+        // both transforms are encoded into the command buffer of the IFFT
+        fft.command_buffer = ifft.command_buffer;
+        GpuDrp {
+            ifft,
+            fft,
+            folding_factor,
+        }
+    }
+
+    /// Returns the largest FFT that can be planned as a single GPU pass. The
+    /// values and twiddles both need to fit in the device's working set.
+    /// Larger domains can be transformed with [`Planner::plan_chunked_fft`].
+    pub fn max_fft_size<F: GpuField>(&self) -> usize {
+        let device = self.command_queue.device();
+        let working_set_size = device.recommended_max_working_set_size() as usize;
+        let max_size = working_set_size / (2 * core::mem::size_of::<F>());
+        1 << max_size.ilog2()
+    }
+
+    #[cfg(feature = "arkworks")]
+    pub fn plan_chunked_fft<F: GpuField + ark_ff::Field>(
+        &self,
+        domain: Radix2EvaluationDomain<F::FftField>,
+        max_pass_size: usize,
+    ) -> GpuChunkedFft<F>
+    where
+        F::FftField: ark_ff::FftField,
+    {
+        GpuChunkedFft::new(FftDirection::Forward, domain, max_pass_size)
+    }
+
+    #[cfg(feature = "arkworks")]
+    pub fn plan_chunked_ifft<F: GpuField + ark_ff::Field>(
+        &self,
+        domain: Radix2EvaluationDomain<F::FftField>,
+        max_pass_size: usize,
+    ) -> GpuChunkedFft<F>
+    where
+        F::FftField: ark_ff::FftField,
+    {
+        GpuChunkedFft::new(FftDirection::Inverse, domain, max_pass_size)
+    }
+
     // TODO: move to FftEncoder struct
     #[cfg(feature = "arkworks")]
     fn create_fft_encoder<F: GpuField + ark_ff::Field>(
@@ -1,19 +1,45 @@
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub use crate::plan::get_planner;
-#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "arkworks"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
+pub use crate::plan::EvaluationOrder;
+#[cfg(all(
+    target_arch = "aarch64",
+    target_os = "macos",
+    feature = "metal",
+    feature = "arkworks"
+))]
+pub use crate::plan::GpuChunkedFft;
+#[cfg(all(
+    target_arch = "aarch64",
+    target_os = "macos",
+    feature = "metal",
+    feature = "arkworks"
+))]
+pub use crate::plan::GpuDrp;
+#[cfg(all(
+    target_arch = "aarch64",
+    target_os = "macos",
+    feature = "metal",
+    feature = "arkworks"
+))]
 pub use crate::plan::GpuFft;
-#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "arkworks"))]
+#[cfg(all(
+    target_arch = "aarch64",
+    target_os = "macos",
+    feature = "metal",
+    feature = "arkworks"
+))]
 pub use crate::plan::GpuIfft;
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub use crate::stage::AddAssignStage;
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub use crate::stage::FillBuffStage;
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub use crate::stage::MulPowStage;
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub use crate::utils::buffer_mut_no_copy;
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub use crate::utils::buffer_no_copy;
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub use crate::utils::page_aligned_uninit_vector;
 pub use crate::GpuField;
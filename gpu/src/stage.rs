@@ -1,4 +1,4 @@
-#![cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#![cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 use super::GpuField;
 use crate::plan::get_planner;
 use crate::prelude::buffer_mut_no_copy;
@@ -578,6 +578,285 @@ impl<LhsF: GpuField + GpuAdd<RhsF>, RhsF: GpuField> AddIntoConstStage<LhsF, RhsF
     }
 }
 
+/// Folds every `folding_factor` consecutive coefficients into a single
+/// coefficient i.e. `dst[i] = Σ_k alpha^k * coeffs[i * folding_factor + k]`
+pub struct FoldCoeffsStage<F> {
+    pipeline: metal::ComputePipelineState,
+    threadgroup_dim: metal::MTLSize,
+    grid_dim: metal::MTLSize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: GpuField> FoldCoeffsStage<F> {
+    /// `n` is the number of folded coefficients
+    pub fn new(library: &metal::LibraryRef, n: usize) -> Self {
+        // Create the compute pipeline
+        let func = library
+            .get_function(&alloc::format!("fold_coeffs_{}", F::field_name()), None)
+            .unwrap();
+        let pipeline = library
+            .device()
+            .new_compute_pipeline_state_with_function(&func)
+            .unwrap();
+
+        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
+        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
+
+        FoldCoeffsStage {
+            threadgroup_dim,
+            pipeline,
+            grid_dim,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn encode(
+        &self,
+        command_buffer: &metal::CommandBufferRef,
+        dst_buffer: &metal::BufferRef,
+        coeffs_buffer: &metal::BufferRef,
+        alpha: F,
+        folding_factor: usize,
+    ) {
+        let command_encoder = command_buffer.new_compute_command_encoder();
+        command_encoder.set_compute_pipeline_state(&self.pipeline);
+        command_encoder.set_buffer(0, Some(dst_buffer), 0);
+        command_encoder.set_buffer(1, Some(coeffs_buffer), 0);
+        command_encoder.set_bytes(2, size_of::<F>().try_into().unwrap(), void_ptr(&alpha));
+        let folding_factor = folding_factor as u32;
+        command_encoder.set_bytes(
+            3,
+            size_of::<u32>().try_into().unwrap(),
+            void_ptr(&folding_factor),
+        );
+        command_encoder.dispatch_threads(self.grid_dim, self.threadgroup_dim);
+        command_encoder.memory_barrier_with_resources(&[dst_buffer, coeffs_buffer]);
+        command_encoder.end_encoding()
+    }
+}
+
+/// Divides evaluations by a periodic table of values i.e.
+/// `evals[i] *= inv_vanishing[i % period]`. Used to divide constraint
+/// evaluations over an LDE domain by the vanishing polynomial of the trace
+/// domain (see [`crate::plan::divide_by_vanishing`]).
+pub struct DivideByVanishingStage<LhsF, RhsF = LhsF> {
+    pipeline: metal::ComputePipelineState,
+    threadgroup_dim: metal::MTLSize,
+    grid_dim: metal::MTLSize,
+    _phantom: PhantomData<(LhsF, RhsF)>,
+}
+
+impl<LhsF: GpuField + GpuMul<RhsF>, RhsF: GpuField> DivideByVanishingStage<LhsF, RhsF> {
+    pub fn new(library: &metal::LibraryRef, n: usize) -> Self {
+        // Create the compute pipeline
+        let kernel_name = alloc::format!(
+            "divide_by_vanishing_LHS_{}_RHS_{}",
+            LhsF::field_name(),
+            RhsF::field_name()
+        );
+        let func = library.get_function(&kernel_name, None).unwrap();
+        let pipeline = library
+            .device()
+            .new_compute_pipeline_state_with_function(&func)
+            .unwrap();
+
+        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
+        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
+
+        DivideByVanishingStage {
+            pipeline,
+            threadgroup_dim,
+            grid_dim,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn encode(
+        &self,
+        command_buffer: &metal::CommandBufferRef,
+        evals: &metal::BufferRef,
+        inv_vanishing: &metal::BufferRef,
+        period: usize,
+    ) {
+        let command_encoder = command_buffer
+            .compute_command_encoder_with_dispatch_type(metal::MTLDispatchType::Concurrent);
+        command_encoder.set_compute_pipeline_state(&self.pipeline);
+        command_encoder.set_buffer(0, Some(evals), 0);
+        command_encoder.set_buffer(1, Some(inv_vanishing), 0);
+        let period = period as u32;
+        command_encoder.set_bytes(2, size_of::<u32>().try_into().unwrap(), void_ptr(&period));
+        command_encoder.dispatch_threads(self.grid_dim, self.threadgroup_dim);
+        command_encoder.memory_barrier_with_resources(&[evals, inv_vanishing]);
+        command_encoder.end_encoding()
+    }
+}
+
+/// Permutes a buffer of any power of two size into bit-reversed order in
+/// place (see [`crate::plan::bit_reverse`])
+pub struct BitReversePermuteStage<F> {
+    pipeline: metal::ComputePipelineState,
+    threadgroup_dim: metal::MTLSize,
+    grid_dim: metal::MTLSize,
+    log_n: u32,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: GpuField> BitReversePermuteStage<F> {
+    pub fn new(library: &metal::LibraryRef, n: usize) -> Self {
+        assert!(n.is_power_of_two());
+        assert!((2..=1 << 32).contains(&n));
+
+        // Create the compute pipeline
+        let kernel_name = alloc::format!("bit_reverse_permute_{}", F::field_name());
+        let func = library.get_function(&kernel_name, None).unwrap();
+        let pipeline = library
+            .device()
+            .new_compute_pipeline_state_with_function(&func)
+            .unwrap();
+
+        let max_threadgroup_threads = pipeline.max_total_threads_per_threadgroup();
+        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
+
+        BitReversePermuteStage {
+            pipeline,
+            threadgroup_dim,
+            grid_dim,
+            log_n: n.ilog2(),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn encode(&self, command_buffer: &metal::CommandBufferRef, buffer: &metal::BufferRef) {
+        let command_encoder = command_buffer.new_compute_command_encoder();
+        command_encoder.set_compute_pipeline_state(&self.pipeline);
+        command_encoder.set_buffer(0, Some(buffer), 0);
+        command_encoder.set_bytes(
+            1,
+            size_of::<u32>().try_into().unwrap(),
+            void_ptr(&self.log_n),
+        );
+        command_encoder.dispatch_threads(self.grid_dim, self.threadgroup_dim);
+        command_encoder.memory_barrier_with_resources(&[buffer]);
+        command_encoder.end_encoding()
+    }
+}
+
+/// Associative operation of a [`ScanStage`]
+#[derive(Clone, Copy, Debug)]
+pub enum ScanOp {
+    Sum,
+    Product,
+}
+
+/// Inclusive scan of a buffer (see [`crate::plan::scan_sum`] and
+/// [`crate::plan::scan_product`]). Each threadgroup scans a block of
+/// [`ScanStage::block_size`] values in threadgroup memory and writes the block
+/// total. The totals are then scanned and applied to the blocks they precede.
+pub struct ScanStage<F> {
+    blocks_pipeline: metal::ComputePipelineState,
+    apply_pipeline: metal::ComputePipelineState,
+    threadgroup_threads: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: GpuField> ScanStage<F> {
+    // Threadgroup memory available on all Apple GPUs
+    const MAX_THREADGROUP_MEMORY: usize = 16384;
+
+    pub fn new(library: &metal::LibraryRef, op: ScanOp) -> Self {
+        let op_name = match op {
+            ScanOp::Sum => "sum",
+            ScanOp::Product => "product",
+        };
+        let pipeline = |kernel| {
+            let kernel_name = alloc::format!("scan_{kernel}_{op_name}_{}", F::field_name());
+            let func = library.get_function(&kernel_name, None).unwrap();
+            library
+                .device()
+                .new_compute_pipeline_state_with_function(&func)
+                .unwrap()
+        };
+        let blocks_pipeline = pipeline("blocks");
+        let apply_pipeline = pipeline("apply");
+
+        // each thread scans two values
+        let max_threads = Self::MAX_THREADGROUP_MEMORY / (2 * size_of::<F>());
+        let max_threadgroup_threads = blocks_pipeline.max_total_threads_per_threadgroup() as usize;
+        let threadgroup_threads = 1 << max_threads.min(max_threadgroup_threads).ilog2();
+
+        ScanStage {
+            blocks_pipeline,
+            apply_pipeline,
+            threadgroup_threads,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Number of values scanned by each threadgroup
+    pub const fn block_size(&self) -> usize {
+        2 * self.threadgroup_threads
+    }
+
+    /// Scans each block of the first `n` values of `values` and writes the
+    /// block totals to `totals`
+    pub fn encode_blocks(
+        &self,
+        command_buffer: &metal::CommandBufferRef,
+        values: &metal::BufferRef,
+        n: usize,
+        totals: &metal::BufferRef,
+        identity: &F,
+    ) {
+        let command_encoder = command_buffer.new_compute_command_encoder();
+        command_encoder.set_compute_pipeline_state(&self.blocks_pipeline);
+        let num_bytes = (self.block_size() * size_of::<F>()).try_into().unwrap();
+        command_encoder.set_threadgroup_memory_length(0, num_bytes);
+        command_encoder.set_buffer(0, Some(values), 0);
+        command_encoder.set_buffer(1, Some(totals), 0);
+        command_encoder.set_bytes(2, size_of::<F>().try_into().unwrap(), void_ptr(identity));
+        let n_u32 = u32::try_from(n).unwrap();
+        command_encoder.set_bytes(3, size_of::<u32>().try_into().unwrap(), void_ptr(&n_u32));
+        let num_blocks = n.div_ceil(self.block_size());
+        let grid_dim = metal::MTLSize::new(num_blocks.try_into().unwrap(), 1, 1);
+        let threadgroup_dim =
+            metal::MTLSize::new(self.threadgroup_threads.try_into().unwrap(), 1, 1);
+        command_encoder.dispatch_thread_groups(grid_dim, threadgroup_dim);
+        command_encoder.memory_barrier_with_resources(&[values, totals]);
+        command_encoder.end_encoding()
+    }
+
+    /// Combines the first `n` values of `values` with the scanned totals of
+    /// the preceding blocks
+    pub fn encode_apply(
+        &self,
+        command_buffer: &metal::CommandBufferRef,
+        values: &metal::BufferRef,
+        n: usize,
+        prefixes: &metal::BufferRef,
+    ) {
+        let command_encoder = command_buffer
+            .compute_command_encoder_with_dispatch_type(metal::MTLDispatchType::Concurrent);
+        command_encoder.set_compute_pipeline_state(&self.apply_pipeline);
+        command_encoder.set_buffer(0, Some(values), 0);
+        command_encoder.set_buffer(1, Some(prefixes), 0);
+        let block_size = u32::try_from(self.block_size()).unwrap();
+        command_encoder.set_bytes(
+            2,
+            size_of::<u32>().try_into().unwrap(),
+            void_ptr(&block_size),
+        );
+        let max_threadgroup_threads = self.apply_pipeline.max_total_threads_per_threadgroup();
+        let threadgroup_dim = metal::MTLSize::new(max_threadgroup_threads, 1, 1);
+        let grid_dim = metal::MTLSize::new(n.try_into().unwrap(), 1, 1);
+        command_encoder.dispatch_threads(grid_dim, threadgroup_dim);
+        command_encoder.memory_barrier_with_resources(&[values]);
+        command_encoder.end_encoding()
+    }
+}
+
 pub struct ConvertIntoStage<LhsF, RhsF = LhsF> {
     pipeline: metal::ComputePipelineState,
     threadgroup_dim: metal::MTLSize,
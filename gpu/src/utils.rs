@@ -6,6 +6,40 @@ pub fn bit_reverse_index(n: usize, i: usize) -> usize {
     i.reverse_bits() >> (usize::BITS - n.ilog2())
 }
 
+/// Transposes a row-major `num_rows x num_cols` matrix from `src` into `dst`
+pub fn transpose<T: Copy + Send + Sync>(
+    src: &[T],
+    dst: &mut [T],
+    num_rows: usize,
+    num_cols: usize,
+) {
+    // number of columns of `src` transposed at a time
+    const BLOCK_SIZE: usize = 16;
+    assert_eq!(src.len(), num_rows * num_cols);
+    assert_eq!(dst.len(), src.len());
+    if src.is_empty() {
+        return;
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    let blocks = dst.chunks_mut(BLOCK_SIZE * num_rows);
+    #[cfg(feature = "parallel")]
+    let blocks = dst.par_chunks_mut(BLOCK_SIZE * num_rows);
+
+    blocks.enumerate().for_each(|(block_offset, block)| {
+        let col_offset = block_offset * BLOCK_SIZE;
+        for row_offset in (0..num_rows).step_by(BLOCK_SIZE) {
+            let rows = row_offset..core::cmp::min(row_offset + BLOCK_SIZE, num_rows);
+            for (i, dst_row) in block.chunks_mut(num_rows).enumerate() {
+                let col = col_offset + i;
+                for row in rows.clone() {
+                    dst_row[row] = src[row * num_cols + col];
+                }
+            }
+        }
+    });
+}
+
 /// Fills a slice with twiddle factors
 /// TODO: Generate of the GPU <https://kieber-emmons.medium.com/9e60b974d62> or cache
 /// inverse twiddles are normalized by `1 / n`.
@@ -79,7 +113,7 @@ pub fn bit_reverse<T: Send>(v: &mut [T]) {
 
 // Copies a cpu buffer to a gpu buffer
 // Never use on unified memory architechture devices (M1, M2 etc.)
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub fn copy_to_private_buffer<T: Sized>(
     command_queue: &metal::CommandQueue,
     v: &[T],
@@ -102,7 +136,7 @@ pub fn copy_to_private_buffer<T: Sized>(
 
 /// WARNING: keep the original data around or it will be freed.
 // TODO: see buffer_mut_no_copy comments
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub fn buffer_no_copy<T: Sized>(device: &metal::DeviceRef, v: &[T]) -> metal::Buffer {
     assert!(is_page_aligned(v));
     let byte_len = core::mem::size_of_val(v);
@@ -119,7 +153,7 @@ pub fn buffer_no_copy<T: Sized>(device: &metal::DeviceRef, v: &[T]) -> metal::Bu
 // the page size (as per doc requirements). Seems to work in practice (on M1 at least) if only the
 // pointer is aligned. Passing a slice if handy because passing a vec with any allocator requires
 // nightly allocator_api feature. https://developer.apple.com/documentation/metal/mtldevice/1433382-makebuffer
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub fn buffer_mut_no_copy<T: Sized>(device: &metal::DeviceRef, v: &mut [T]) -> metal::Buffer {
     assert!(is_page_aligned(v));
     // TODO: once allocator_api stabilized check capacity is aligned to page size
@@ -135,7 +169,12 @@ pub fn buffer_mut_no_copy<T: Sized>(device: &metal::DeviceRef, v: &mut [T]) -> m
 
 // adapted form arkworks
 /// Multiply the `i`-th element of `coeffs` with `g^i`.
-#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "arkworks"))]
+#[cfg(all(
+    target_arch = "aarch64",
+    target_os = "macos",
+    feature = "metal",
+    feature = "arkworks"
+))]
 pub(crate) fn distribute_powers<F: crate::GpuField + ark_ff::Field>(coeffs: &mut [F], g: F) {
     let n = coeffs.len();
     #[cfg(not(feature = "parallel"))]
@@ -156,7 +195,7 @@ pub(crate) fn distribute_powers<F: crate::GpuField + ark_ff::Field>(coeffs: &mut
 }
 
 /// Returns the max FFT size each threadgroup can compute
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub fn threadgroup_fft_size<F: crate::GpuField>(
     max_threadgroup_mem_length: usize,
     max_threads_per_threadgroup: usize,
@@ -179,17 +218,17 @@ pub fn threadgroup_fft_size<F: crate::GpuField>(
 }
 
 // Converts a reference to a void pointer
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub(crate) fn void_ptr<T>(v: &T) -> *const core::ffi::c_void {
     v as *const T as *const core::ffi::c_void
 }
 
 #[repr(C, align(16384))]
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 struct Page([u8; 16384]);
 
 /// Checks a slice is page aligned on
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub fn is_page_aligned<T>(v: &[T]) -> bool {
     v.as_ptr().align_offset(core::mem::align_of::<Page>()) == 0
 }
@@ -202,9 +241,9 @@ pub fn is_page_aligned<T>(v: &[T]) -> bool {
 /// # Safety
 /// Using values from the returned vector before initializing them will lead to
 /// undefined behavior.
-// #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+// #[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 #[allow(clippy::uninit_vec)]
-#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 pub unsafe fn page_aligned_uninit_vector<T>(length: usize) -> alloc::vec::Vec<T> {
     #[repr(C, align(16384))]
     struct Page([u8; 16384]);
@@ -235,6 +274,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn transpose_works() {
+        let src = (0..3 * 20).collect::<alloc::vec::Vec<_>>();
+        let mut dst = alloc::vec![0; src.len()];
+
+        transpose(&src, &mut dst, 3, 20);
+
+        for row in 0..3 {
+            for col in 0..20 {
+                assert_eq!(src[row * 20 + col], dst[col * 3 + row]);
+            }
+        }
+    }
+
     #[test]
     #[should_panic]
     fn bit_reversal_fails_for_non_power_of_two() {
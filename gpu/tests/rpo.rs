@@ -1,4 +1,4 @@
-#![cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#![cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 #![feature(allocator_api, array_windows)]
 // TODO: test GPU RPO against CPU rpo. these aren't tests.
 
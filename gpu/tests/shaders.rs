@@ -1,4 +1,4 @@
-#![cfg(all(target_arch = "aarch64", target_os = "macos"))]
+#![cfg(all(target_arch = "aarch64", target_os = "macos", feature = "metal"))]
 #![feature(allocator_api)]
 
 use core::iter::zip;
@@ -115,3 +115,39 @@ fn ifft() {
         }
     }
 }
+
+#[test]
+fn fft_with_bit_reversed_order() {
+    let domain = Radix2EvaluationDomain::new_coset(4096, Fp::GENERATOR).unwrap();
+    let n = domain.size();
+    let poly = DensePolynomial::<Fp>::rand(n - 1, &mut ark_std::test_rng());
+    let mut expected = domain.fft(&poly.coeffs);
+    ministark_gpu::utils::bit_reverse(&mut expected);
+
+    let mut evals = unsafe { page_aligned_uninit_vector(n) };
+    evals.copy_from_slice(&poly.coeffs);
+    let mut fft = GpuFft::from(domain).with_output_order(EvaluationOrder::BitReversed);
+    fft.encode(&mut evals);
+    fft.execute();
+    assert_eq!(expected, evals);
+
+    let mut ifft = GpuIfft::from(domain).with_input_order(EvaluationOrder::BitReversed);
+    ifft.encode(&mut evals);
+    ifft.execute();
+    assert_eq!(poly.coeffs, evals);
+}
+
+#[test]
+fn bit_reverse_any_size() {
+    for n in [2, 1024, 4096] {
+        let values = (0..n).map(|i| Fq3::from(i as u64)).collect::<Vec<Fq3>>();
+        let mut expected = values.clone();
+        ministark_gpu::utils::bit_reverse(&mut expected);
+
+        let mut actual = unsafe { page_aligned_uninit_vector(n) };
+        actual.copy_from_slice(&values);
+        ministark_gpu::plan::bit_reverse(&mut actual);
+
+        assert_eq!(expected, actual, "size {n}");
+    }
+}
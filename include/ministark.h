@@ -0,0 +1,63 @@
+#ifndef MINISTARK_H
+#define MINISTARK_H
+
+/* Generated with cbindgen. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Result of an FFI call
+ */
+typedef enum MinistarkStatus {
+  MinistarkStatus_Ok = 0,
+  /**
+   * A pointer argument was null or the proof options were invalid
+   */
+  MinistarkStatus_InvalidArgument = 1,
+  /**
+   * The public inputs, witness or proof couldn't be deserialized
+   */
+  MinistarkStatus_DeserializationFailed = 2,
+  MinistarkStatus_ProvingFailed = 3,
+  MinistarkStatus_VerificationFailed = 4,
+  /**
+   * The prover or verifier panicked. The panic is caught before it crosses
+   * the ABI.
+   */
+  MinistarkStatus_Panic = 5,
+} MinistarkStatus;
+
+/**
+ * Byte buffer allocated by this crate
+ */
+typedef struct MinistarkBuffer {
+  uint8_t *data;
+  size_t len;
+  size_t capacity;
+} MinistarkBuffer;
+
+/**
+ * C representation of [`ProofOptions`]
+ */
+typedef struct MinistarkProofOptions {
+  uint8_t num_queries;
+  uint8_t lde_blowup_factor;
+  uint8_t grinding_factor;
+  uint8_t fri_folding_factor;
+  uint8_t fri_max_remainder_coeffs;
+} MinistarkProofOptions;
+
+/**
+ * Releases a buffer returned by this crate. Null buffers are ignored.
+ *
+ * # Safety
+ *
+ * `buffer` must have been returned by this crate and not freed before.
+ */
+void ministark_buffer_free(struct MinistarkBuffer buffer);
+
+#endif /* MINISTARK_H */
@@ -0,0 +1,173 @@
+//! Nondeterministic advice for trace generation
+//!
+//! Trace builders often fill columns with values the AIR only checks rather
+//! than computes e.g. the inverse of a value for an `is_zero` gadget or the
+//! bits of a range checked value. [`HintRegistry`] hands out this advice and
+//! records every value in order. The recording can be serialized and replayed
+//! so trace generation is reproducible and tests can replay tampered advice to
+//! check the AIR rejects it.
+//!
+//! Not to be confused with [`crate::hints::Hints`] which are values the
+//! verifier passes to the constraints.
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+
+/// Advice handed out by a [`HintRegistry`] in the order it was requested.
+/// Each entry is labeled with the kind of advice.
+#[derive(Debug, Clone, Default, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct HintRecording<F: Field> {
+    entries: Vec<(String, Vec<F>)>,
+}
+
+impl<F: Field> HintRecording<F> {
+    pub const fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the values of entry `i`. Used to tamper with advice in tests.
+    pub fn values_mut(&mut self, i: usize) -> &mut Vec<F> {
+        &mut self.entries[i].1
+    }
+}
+
+/// Source of advice for trace generation (see the [module docs](self))
+#[derive(Debug, Clone, Default)]
+pub struct HintRegistry<F: Field> {
+    recording: HintRecording<F>,
+    /// Next entry to replay. [`None`] when recording.
+    cursor: Option<usize>,
+}
+
+impl<F: Field> HintRegistry<F> {
+    /// Returns a registry that computes advice and records it
+    pub fn new() -> Self {
+        Self {
+            recording: HintRecording::default(),
+            cursor: None,
+        }
+    }
+
+    /// Returns a registry that hands out the advice in `recording` instead of
+    /// computing it
+    pub const fn replay(recording: HintRecording<F>) -> Self {
+        Self {
+            recording,
+            cursor: Some(0),
+        }
+    }
+
+    pub const fn is_replaying(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    /// Returns advice of the given kind. The advice is computed with `compute`
+    /// when recording and read from the recording when replaying.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a replay runs out of advice or the recorded advice is of a
+    /// different kind i.e. trace generation took a different path than when
+    /// it was recorded
+    pub fn advise(&mut self, kind: &str, compute: impl FnOnce() -> Vec<F>) -> Vec<F> {
+        let Some(cursor) = &mut self.cursor else {
+            let values = compute();
+            let entry = (kind.to_string(), values.clone());
+            self.recording.entries.push(entry);
+            return values;
+        };
+        let Some((recorded_kind, values)) = self.recording.entries.get(*cursor) else {
+            panic!("no recorded advice left for {kind} (entry {cursor})");
+        };
+        assert_eq!(
+            recorded_kind, kind,
+            "advice {cursor} was recorded as {recorded_kind} but replayed as {kind}"
+        );
+        *cursor += 1;
+        values.clone()
+    }
+
+    /// Like [`Self::advise`] for a single value
+    pub fn advise_one(&mut self, kind: &str, compute: impl FnOnce() -> F) -> F {
+        let values = self.advise(kind, || vec![compute()]);
+        assert_eq!(values.len(), 1, "expected a single value of {kind}");
+        values[0]
+    }
+
+    /// Returns the inverse of `x` or zero if `x` is zero
+    pub fn inverse(&mut self, x: F) -> F {
+        self.advise_one("inverse", || x.inverse().unwrap_or_else(F::zero))
+    }
+
+    /// Returns the `num_bits` least significant bits of `x` (least significant
+    /// first)
+    pub fn bits(&mut self, x: u64, num_bits: usize) -> Vec<F> {
+        self.advise("bits", || {
+            (0..num_bits)
+                .map(|i| F::from(i < 64 && (x >> i) & 1 == 1))
+                .collect()
+        })
+    }
+
+    /// Returns the number of recorded entries that haven't been replayed yet
+    pub fn remaining(&self) -> usize {
+        self.cursor
+            .map_or(0, |cursor| self.recording.len().saturating_sub(cursor))
+    }
+
+    pub fn into_recording(self) -> HintRecording<F> {
+        self.recording
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HintRegistry;
+    use alloc::vec::Vec;
+    use ark_ff::Field;
+    use ark_ff::One;
+    use ark_ff::Zero;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    /// Trace generation that needs advice
+    fn gen_row(hints: &mut HintRegistry<Fp>, x: u64) -> Vec<Fp> {
+        let mut row = hints.bits(x, 4);
+        row.push(hints.inverse(Fp::from(x)));
+        row
+    }
+
+    #[test]
+    fn replay_returns_recorded_advice() {
+        let mut hints = HintRegistry::new();
+        let rows = [5, 0].map(|x| gen_row(&mut hints, x));
+        assert_eq!(rows[0][..4], [Fp::one(), Fp::zero(), Fp::one(), Fp::zero()]);
+        assert_eq!(rows[0][4], Fp::from(5u8).inverse().unwrap());
+        assert!(rows[1][4].is_zero());
+
+        let mut recording = hints.into_recording();
+        let mut hints = HintRegistry::replay(recording.clone());
+        assert_eq!([5, 0].map(|x| gen_row(&mut hints, x)), rows);
+        assert_eq!(hints.remaining(), 0);
+
+        // advice is replayed even if it's wrong
+        recording.values_mut(1)[0] = Fp::from(3u8);
+        let mut hints = HintRegistry::replay(recording);
+        assert_eq!(gen_row(&mut hints, 5)[4], Fp::from(3u8));
+    }
+
+    #[test]
+    #[should_panic = "recorded as bits but replayed as inverse"]
+    fn replay_panics_on_divergence() {
+        let mut hints = HintRegistry::<Fp>::new();
+        gen_row(&mut hints, 5);
+        let mut hints = HintRegistry::replay(hints.into_recording());
+        hints.inverse(Fp::one());
+    }
+}
@@ -3,16 +3,27 @@ use crate::constraints::AlgebraicItem;
 use crate::constraints::CompositionConstraint;
 use crate::constraints::CompositionItem;
 use crate::constraints::Constraint;
+use crate::domain::lde_domain_size;
 use crate::expression::Expr;
+use crate::hash::write_canonical_element;
+use crate::hash::Blake3HashFn;
+use crate::hash::Digest;
+use crate::hash::HashFn;
 use crate::hints::Hints;
+use crate::random::PublicCoin;
+use crate::random::ReseedTag;
 use crate::utils::FieldVariant;
 use crate::utils::GpuVec;
 use crate::Matrix;
 use crate::ProofOptions;
 use crate::StarkExtensionOf;
+use alloc::collections::BTreeMap;
 use alloc::collections::BTreeSet;
+use alloc::string::String;
 use alloc::vec::Vec;
 use ark_ff::FftField;
+use ark_ff::Field;
+use ark_ff::PrimeField;
 use ark_poly::EvaluationDomain;
 use ark_poly::Radix2EvaluationDomain;
 use ark_serialize::CanonicalDeserialize;
@@ -24,6 +35,16 @@ use std::time::Instant;
 pub trait AirConfig: Send + Sync + Sized + 'static {
     const NUM_BASE_COLUMNS: usize;
     const NUM_EXTENSION_COLUMNS: usize = 0;
+    /// Base columns that are committed to separately, each with its own vector
+    /// commitment, rather than as part of the base trace matrix. Openings of
+    /// these columns can be checked without the rest of the trace which is
+    /// useful when a verifier only needs a few columns e.g. public memory.
+    const SEPARATELY_COMMITTED_BASE_COLUMNS: &'static [usize] = &[];
+    /// Base columns that are fixed when the AIR is set up e.g. an instruction
+    /// ROM or a lookup table. They're committed to once with
+    /// [`crate::setup::VerifyingKey::setup`] and openings are checked
+    /// against the key rather than a commitment sent with every proof.
+    const PREPROCESSED_BASE_COLUMNS: &'static [usize] = &[];
 
     type Fp: GpuFftField<FftField = Self::Fp> + FftField;
     type Fq: StarkExtensionOf<Self::Fp>;
@@ -45,40 +66,35 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
 
     /// Combines multiple constraints into a single constraint (the composition
     /// constraint). Constraints are composed with verifiers randomness.
-    /// This verifier randomness is expressed symbolically.
+    /// This verifier randomness is expressed symbolically. Each constraint `c`
+    /// of degree `d` is adjusted to the composition degree `D` with its own
+    /// pair of coefficients i.e. `c * (alpha * x^(D - d) + beta)`. Constraints
+    /// that need no adjustment are only multiplied by `alpha`. Constraints with
+    /// the same adjustment are summed before multiplying by `x^(D - d)`. `D` is
+    /// set by the highest degree constraint so lower degree constraints never
+    /// increase the composition blowup.
     /// <https://medium.com/starkware/starkdex-deep-dive-the-stark-core-engine-497942d0f0ab>
     fn composition_constraint(
         trace_len: usize,
         constraints: &[Constraint<FieldVariant<Self::Fp, Self::Fq>>],
     ) -> CompositionConstraint<FieldVariant<Self::Fp, Self::Fq>> {
-        let ce_blowup_factor = constraints
-            .iter()
-            .map(|c| c.blowup_factor(trace_len))
-            .max()
-            .unwrap();
-        let composition_degree = trace_len * ce_blowup_factor - 1;
-        let trace_degree = trace_len - 1;
-        let x = Expr::Leaf(CompositionItem::Item(AlgebraicItem::X));
-        let mut composition_coeff = (0..).map(|i| Expr::Leaf(CompositionItem::CompositionCoeff(i)));
-        let expr = constraints
-            .iter()
-            .map(|constraint| {
-                let (numerator_degree, denominator_degree) = constraint.degree(trace_degree);
-                let evaluation_degree = numerator_degree - denominator_degree;
-                assert!(evaluation_degree <= composition_degree);
-                let degree_adjustment = composition_degree - evaluation_degree;
-                // TODO: if degree_adjustment is 0 then we only need one challenge
-                let constraint = constraint.map_leaves(&mut |&leaf| CompositionItem::Item(leaf));
-                let alpha = composition_coeff.next().unwrap();
-                let beta = composition_coeff.next().unwrap();
-                &constraint * (x.clone().pow(degree_adjustment) * alpha + beta)
-            })
-            .sum::<Expr<CompositionItem<FieldVariant<Self::Fp, Self::Fq>>>>();
-        // TODO: remove log and timing
-        let now = Instant::now();
-        let expr = expr.reuse_shared_nodes();
-        println!("Reuse took: {:?}", now.elapsed());
-        CompositionConstraint::new(expr)
+        compose::<Self>(trace_len, constraints, true)
+    }
+
+    /// Returns the composition constraint for proofs generated with `options`.
+    /// Uses [`Self::composition_constraint`] unless degree adjustment is
+    /// disabled in which case every constraint is only multiplied by `alpha`
+    /// (see [`ProofOptions::with_degree_adjusted_composition`]).
+    fn composition_constraint_with(
+        trace_len: usize,
+        constraints: &[Constraint<FieldVariant<Self::Fp, Self::Fq>>],
+        options: ProofOptions,
+    ) -> CompositionConstraint<FieldVariant<Self::Fp, Self::Fq>> {
+        if options.degree_adjusted_composition {
+            Self::composition_constraint(trace_len, constraints)
+        } else {
+            compose::<Self>(trace_len, constraints, false)
+        }
     }
 
     // TODO: maybe move this into a constraint evaluator
@@ -128,10 +144,153 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
     }
 }
 
+/// Composes `constraints` as described in
+/// [`AirConfig::composition_constraint`]. Constraints are only multiplied by
+/// `alpha` if not `degree_adjusted`.
+fn compose<C: AirConfig>(
+    trace_len: usize,
+    constraints: &[Constraint<FieldVariant<C::Fp, C::Fq>>],
+    degree_adjusted: bool,
+) -> CompositionConstraint<FieldVariant<C::Fp, C::Fq>> {
+    let ce_blowup_factor = constraints
+        .iter()
+        .map(|c| c.blowup_factor(trace_len))
+        .max()
+        .unwrap();
+    let composition_degree = trace_len * ce_blowup_factor - 1;
+    let trace_degree = trace_len - 1;
+    let x = Expr::Leaf(CompositionItem::Item(AlgebraicItem::X));
+    let mut composition_coeff = (0..).map(|i| Expr::Leaf(CompositionItem::CompositionCoeff(i)));
+
+    // constraints are grouped by their degree adjustment so each power of x is
+    // only multiplied in once per group
+    let mut groups = BTreeMap::<usize, (Vec<_>, Vec<_>)>::new();
+    for constraint in constraints {
+        let (numerator_degree, denominator_degree) = constraint.degree(trace_degree);
+        let evaluation_degree = numerator_degree - denominator_degree;
+        assert!(evaluation_degree <= composition_degree);
+        let degree_adjustment = composition_degree - evaluation_degree;
+        let constraint = constraint.map_leaves(&mut |&leaf| CompositionItem::Item(leaf));
+        let (adjusted_terms, terms) = groups.entry(degree_adjustment).or_default();
+        let alpha = composition_coeff.next().unwrap();
+        if degree_adjustment == 0 || !degree_adjusted {
+            // `alpha * x^0 + beta` is no more random than `alpha`
+            terms.push(&constraint * alpha);
+        } else {
+            let beta = composition_coeff.next().unwrap();
+            adjusted_terms.push(&constraint * alpha);
+            terms.push(&constraint * beta);
+        }
+    }
+
+    let expr = groups
+        .into_iter()
+        .map(|(degree_adjustment, (adjusted_terms, terms))| {
+            let terms = terms.into_iter().sum::<Expr<_>>();
+            if adjusted_terms.is_empty() {
+                return terms;
+            }
+            let adjusted_terms = adjusted_terms.into_iter().sum::<Expr<_>>();
+            x.clone().pow(degree_adjustment) * adjusted_terms + terms
+        })
+        .sum::<Expr<CompositionItem<FieldVariant<C::Fp, C::Fq>>>>();
+    // TODO: remove log and timing
+    let now = Instant::now();
+    let expr = expr.reuse_shared_nodes();
+    println!("Reuse took: {:?}", now.elapsed());
+    CompositionConstraint::new(expr)
+}
+
 pub fn trace_domain<A: AirConfig>(trace_len: usize) -> Radix2EvaluationDomain<A::Fp> {
     Radix2EvaluationDomain::new(trace_len).unwrap()
 }
 
+/// Returns a hash of the columns and constraints of an AIR along with the
+/// trace length and proof options (see [`Air::fingerprint`])
+pub(crate) fn air_fingerprint<C: AirConfig>(
+    trace_len: usize,
+    options: ProofOptions,
+    constraints: &[Constraint<FieldVariant<C::Fp, C::Fq>>],
+) -> [u8; 32] {
+    let mut bytes = b"ministark-air".to_vec();
+    let write_int = |bytes: &mut Vec<u8>, n: usize| bytes.extend((n as u64).to_le_bytes());
+    write_int(&mut bytes, C::NUM_BASE_COLUMNS);
+    write_int(&mut bytes, C::NUM_EXTENSION_COLUMNS);
+    for columns in [
+        C::SEPARATELY_COMMITTED_BASE_COLUMNS,
+        C::PREPROCESSED_BASE_COLUMNS,
+    ] {
+        write_int(&mut bytes, columns.len());
+        for &i in columns {
+            write_int(&mut bytes, i);
+        }
+    }
+    write_int(&mut bytes, trace_len);
+    bytes.extend(options.fingerprint());
+    write_int(&mut bytes, constraints.len());
+    for constraint in constraints {
+        // nodes are visited bottom-up so the tags and arities identify the tree
+        constraint.traverse(&mut |node| match node {
+            Expr::Leaf(item) => {
+                bytes.push(0);
+                write_item(item, &mut bytes);
+            }
+            Expr::Neg(_) => bytes.push(1),
+            Expr::Add(..) => bytes.push(2),
+            Expr::Mul(..) => bytes.push(3),
+            Expr::Div(..) => bytes.push(4),
+            &Expr::Pow(_, exponent) => {
+                bytes.push(5);
+                write_int(&mut bytes, exponent);
+            }
+        });
+    }
+    Blake3HashFn::hash_chunks([&*bytes]).as_bytes()
+}
+
+fn write_item<Fp: Field, Fq: Field>(
+    item: &AlgebraicItem<FieldVariant<Fp, Fq>>,
+    bytes: &mut Vec<u8>,
+) {
+    use AlgebraicItem::*;
+    let write_value = |value: &FieldVariant<Fp, Fq>, bytes: &mut Vec<u8>| match value {
+        FieldVariant::Fp(v) => {
+            bytes.push(0);
+            write_canonical_element(v, bytes);
+        }
+        FieldVariant::Fq(v) => {
+            bytes.push(1);
+            write_canonical_element(v, bytes);
+        }
+    };
+    match item {
+        X => bytes.push(0),
+        Constant(v) => {
+            bytes.push(1);
+            write_value(v, bytes);
+        }
+        Challenge(i) => {
+            bytes.push(2);
+            bytes.extend((*i as u64).to_le_bytes());
+        }
+        Hint(i) => {
+            bytes.push(3);
+            bytes.extend((*i as u64).to_le_bytes());
+        }
+        Periodic(col) => {
+            bytes.push(4);
+            bytes.extend((col.interval_size() as u64).to_le_bytes());
+            bytes.extend((col.coeffs().len() as u64).to_le_bytes());
+            col.coeffs().iter().for_each(|v| write_value(v, bytes));
+        }
+        &Trace(column, offset) => {
+            bytes.push(5);
+            bytes.extend((column as u64).to_le_bytes());
+            bytes.extend((offset as i64).to_le_bytes());
+        }
+    }
+}
+
 pub struct Air<AC: AirConfig> {
     constraints: Vec<Constraint<FieldVariant<AC::Fp, AC::Fq>>>,
     composition_constraint: CompositionConstraint<FieldVariant<AC::Fp, AC::Fq>>,
@@ -144,9 +303,76 @@ pub struct Air<AC: AirConfig> {
 impl<C: AirConfig> Air<C> {
     pub fn new(trace_len: usize, public_inputs: C::PublicInputs, options: ProofOptions) -> Self {
         let constraints = C::constraints(trace_len);
-        let composition_constraint = C::composition_constraint(trace_len, &constraints);
+        Self::with_constraints(trace_len, public_inputs, options, constraints)
+    }
+
+    /// Like [`Self::new`] but returns [`None`] instead of panicking if the
+    /// trace length or LDE blowup factor isn't supported. The verifier reads
+    /// both from the proof.
+    pub fn try_new(
+        trace_len: usize,
+        public_inputs: C::PublicInputs,
+        options: ProofOptions,
+    ) -> Option<Self> {
+        let lde_blowup_factor = usize::from(options.lde_blowup_factor);
+        lde_domain_size::<C::Fp>(trace_len, lde_blowup_factor)?;
+        let air = Self::build(trace_len, public_inputs, options, C::constraints(trace_len));
+        (air.ce_blowup_factor <= lde_blowup_factor).then_some(air)
+    }
+
+    /// Builds an AIR from a custom set of constraints rather than the
+    /// constraints of the config. Used for testing dishonest provers.
+    pub(crate) fn with_constraints(
+        trace_len: usize,
+        public_inputs: C::PublicInputs,
+        options: ProofOptions,
+        constraints: Vec<Constraint<FieldVariant<C::Fp, C::Fq>>>,
+    ) -> Self {
+        assert!(trace_len.is_power_of_two());
+        let lde_blowup_factor = usize::from(options.lde_blowup_factor);
+        assert!(
+            lde_domain_size::<C::Fp>(trace_len, lde_blowup_factor).is_some(),
+            "LDE domain of {trace_len} * {lde_blowup_factor} rows exceeds the 2-adicity of the field"
+        );
+        let air = Self::build(trace_len, public_inputs, options, constraints);
+        assert!(air.ce_blowup_factor <= lde_blowup_factor);
+        air
+    }
+
+    fn build(
+        trace_len: usize,
+        public_inputs: C::PublicInputs,
+        options: ProofOptions,
+        constraints: Vec<Constraint<FieldVariant<C::Fp, C::Fq>>>,
+    ) -> Self {
+        let composition_constraint =
+            C::composition_constraint_with(trace_len, &constraints, options);
+        Self::from_parts(
+            trace_len,
+            public_inputs,
+            options,
+            constraints,
+            composition_constraint,
+        )
+    }
+
+    /// Builds an AIR from constraints and a composition constraint that were
+    /// derived earlier (see [`crate::setup::ProvingKey`])
+    pub(crate) fn from_parts(
+        trace_len: usize,
+        public_inputs: C::PublicInputs,
+        options: ProofOptions,
+        constraints: Vec<Constraint<FieldVariant<C::Fp, C::Fq>>>,
+        composition_constraint: CompositionConstraint<FieldVariant<C::Fp, C::Fq>>,
+    ) -> Self {
         let ce_blowup_factor = composition_constraint.blowup_factor(trace_len);
-        assert!(ce_blowup_factor <= options.lde_blowup_factor.into());
+        let separate_columns = C::SEPARATELY_COMMITTED_BASE_COLUMNS;
+        let preprocessed_columns = C::PREPROCESSED_BASE_COLUMNS;
+        let columns = [separate_columns, preprocessed_columns].concat();
+        // at least one column has to remain in the base trace matrix
+        assert!(columns.len() < C::NUM_BASE_COLUMNS);
+        assert!(columns.iter().all(|&i| i < C::NUM_BASE_COLUMNS));
+        assert_eq!(columns.len(), columns.iter().collect::<BTreeSet<_>>().len());
 
         Self {
             constraints,
@@ -226,18 +452,63 @@ impl<C: AirConfig> Air<C> {
         Radix2EvaluationDomain::new_coset(trace_len * blowup_factor, offset).unwrap()
     }
 
+    /// Absorbs the parameters of the evaluation domain into the public coin.
+    /// The prover and verifier do this before anything else is absorbed so a
+    /// proof can't be replayed against a verifier configured with a different
+    /// domain size, coset offset or field.
+    pub fn reseed_with_domain_parameters(&self, public_coin: &mut impl PublicCoin<Field = C::Fq>) {
+        // the field is identified by the base field modulus and extension degree
+        for &limb in <C::Fp as Field>::BasePrimeField::MODULUS.as_ref() {
+            public_coin.reseed_with_int(ReseedTag::DomainParameters, limb);
+        }
+        public_coin.reseed_with_int(ReseedTag::DomainParameters, C::Fq::extension_degree());
+        let lde_domain = self.lde_domain();
+        public_coin.reseed_with_int(ReseedTag::DomainParameters, lde_domain.size() as u64);
+        let offset = C::Fq::from(lde_domain.offset);
+        public_coin.reseed_with_field_elements(ReseedTag::DomainParameters, &[offset]);
+    }
+
     /// Low degree extension domain
     #[inline]
     pub const fn lde_blowup_factor(&self) -> usize {
         self.options.lde_blowup_factor as usize
     }
 
+    pub fn constraints(&self) -> &[Constraint<FieldVariant<C::Fp, C::Fq>>] {
+        &self.constraints
+    }
+
+    /// Returns a fingerprint of the AIR: its columns, constraints, trace
+    /// length and proof options. Public inputs aren't part of the fingerprint.
+    /// Proofs carry the fingerprint of the AIR they were generated for so a
+    /// verifier with a different version of the AIR or different parameters
+    /// rejects them before checking any constraints.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        air_fingerprint::<C>(self.trace_len, self.options, &self.constraints)
+    }
+
+    /// Returns a JSON description of the columns, constraints and degrees of
+    /// the AIR for external tooling (see [`crate::air_spec`])
+    pub fn to_spec(&self) -> String {
+        crate::air_spec::air_spec(self)
+    }
+
     pub const fn composition_constraint(
         &self,
     ) -> &CompositionConstraint<FieldVariant<C::Fp, C::Fq>> {
         &self.composition_constraint
     }
 
+    /// Returns the base columns that are committed to as part of the base
+    /// trace matrix i.e. all columns that aren't committed to separately or
+    /// preprocessed
+    pub fn matrix_committed_base_columns(&self) -> Vec<usize> {
+        (0..C::NUM_BASE_COLUMNS)
+            .filter(|i| !C::SEPARATELY_COMMITTED_BASE_COLUMNS.contains(i))
+            .filter(|i| !C::PREPROCESSED_BASE_COLUMNS.contains(i))
+            .collect()
+    }
+
     pub fn trace_arguments(&self) -> BTreeSet<(usize, isize)> {
         self.constraints
             .iter()
@@ -245,3 +516,79 @@ impl<C: AirConfig> Air<C> {
             .fold(BTreeSet::new(), |a, b| &a | &b)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::AirConfig;
+    use crate::constraints::AlgebraicItem;
+    use crate::constraints::CompositionConstraint;
+    use crate::constraints::Constraint;
+    use crate::expression::Expr;
+    use crate::utils::FieldVariant;
+    use crate::Air;
+    use crate::ProofOptions;
+    use alloc::vec::Vec;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    struct MixedDegreeAir;
+
+    impl AirConfig for MixedDegreeAir {
+        const NUM_BASE_COLUMNS: usize = 2;
+
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraints(_trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            let mut constraints = vec![Constraint::new(
+                Expr::from(Trace(0, 0)) * Trace(0, 0) * Trace(0, 0) - Trace(1, 0),
+            )];
+            constraints
+                .extend((0..8).map(|_| Constraint::new(Expr::from(Trace(1, 1)) - Trace(0, 0))));
+            constraints
+        }
+    }
+
+    #[test]
+    fn composition_blowup_follows_highest_degree_constraint() {
+        let options = ProofOptions::new(24, 4, 0, 2, 8);
+
+        let air = Air::<MixedDegreeAir>::new(16, (), options);
+
+        assert_eq!(air.ce_blowup_factor(), 4);
+        assert_eq!(air.composition_degree(), 63);
+        // every linear constraint is adjusted by the same power of x
+        assert_eq!(air.num_composition_constraint_coeffs(), 18);
+        let mut num_powers_of_x = 0;
+        air.composition_constraint().traverse(&mut |node| {
+            if let Expr::Pow(_, 48) = node {
+                num_powers_of_x += 1;
+            }
+        });
+        assert_eq!(num_powers_of_x, 1);
+    }
+
+    #[test]
+    fn composition_without_degree_adjustment_has_one_coeff_per_constraint() {
+        let options = ProofOptions::new(24, 4, 0, 2, 8).with_degree_adjusted_composition(false);
+        let constraints = MixedDegreeAir::constraints(16);
+
+        let air = Air::<MixedDegreeAir>::new(16, (), options);
+        let adjusted = MixedDegreeAir::composition_constraint(16, &constraints);
+
+        assert_eq!(air.num_composition_constraint_coeffs(), constraints.len());
+        let num_powers_of_x = |composition_constraint: &CompositionConstraint<_>| {
+            let mut num_powers_of_x = 0;
+            composition_constraint.traverse(&mut |node| {
+                if let Expr::Pow(_, 48) = node {
+                    num_powers_of_x += 1;
+                }
+            });
+            num_powers_of_x
+        };
+        assert_eq!(num_powers_of_x(air.composition_constraint()), 0);
+        // the default composition is unchanged by the options
+        assert_eq!(num_powers_of_x(&adjusted), 1);
+    }
+}
@@ -0,0 +1,309 @@
+//! Multi-AIR proofs
+//!
+//! [`AirComposer`] merges two independent AIRs, each with its own columns and
+//! constraints, into a single AIR so both statements are proven with one trace
+//! commitment and one FRI. More AIRs are merged by nesting composers e.g.
+//! `AirComposer<A, AirComposer<B, C>>`. The columns of the composed trace are
+//! laid out as
+//!
+//! | `A` base | `B` base | `A` extension | `B` extension |
+//!
+//! and the columns, challenges and hints referenced by the constraints of each
+//! sub-AIR are remapped to match. The public inputs of the composed AIR are the
+//! pair of public inputs of the sub-AIRs. [`AirComposer::compose_traces`]
+//! builds the composed trace from the traces of the sub-AIRs.
+//!
+//! Sub-AIRs must have the same trace length and domain offset. Only the first
+//! sub-AIR can have separately committed or preprocessed columns since their
+//! indices aren't remapped.
+use crate::air::AirConfig;
+use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::expression::Expr;
+use crate::hints::Hints;
+use crate::utils::FieldVariant;
+use crate::Matrix;
+use crate::StarkExtensionOf;
+use crate::Trace;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use core::marker::PhantomData;
+use ministark_gpu::GpuFftField;
+
+/// AIR of the statements of `A` and `B` (see the [module docs](self))
+pub struct AirComposer<A, B>(PhantomData<(A, B)>);
+
+impl<A: AirConfig, B: AirConfig<Fp = A::Fp, Fq = A::Fq>> AirComposer<A, B> {
+    /// Returns the index of the first challenge of `B` i.e. the number of
+    /// challenges of `A`
+    pub fn challenge_offset(trace_len: usize) -> usize {
+        num_challenges(&A::constraints(trace_len))
+    }
+
+    /// Returns the index of the first hint of `B` i.e. the number of hints of
+    /// `A`
+    pub fn hint_offset(trace_len: usize) -> usize {
+        num_hints(&A::constraints(trace_len))
+    }
+
+    /// Returns the trace of the composed AIR
+    ///
+    /// # Panics
+    ///
+    /// Panics if the traces have different lengths or don't have the number of
+    /// base columns of their AIR
+    pub fn compose_traces<TA, TB>(a: TA, b: TB) -> ComposedTrace<TA, TB>
+    where
+        TA: Trace<Fp = A::Fp, Fq = A::Fq>,
+        TB: Trace<Fp = A::Fp, Fq = A::Fq>,
+    {
+        let trace_len = a.len();
+        assert_eq!(trace_len, b.len(), "sub-traces must have the same length");
+        assert_eq!(a.base_columns().num_cols(), A::NUM_BASE_COLUMNS);
+        assert_eq!(b.base_columns().num_cols(), B::NUM_BASE_COLUMNS);
+        let base_columns = Matrix::join(vec![a.base_columns().clone(), b.base_columns().clone()]);
+        ComposedTrace {
+            a,
+            b,
+            base_columns,
+            challenge_offset: Self::challenge_offset(trace_len),
+        }
+    }
+}
+
+impl<A: AirConfig, B: AirConfig<Fp = A::Fp, Fq = A::Fq>> AirConfig for AirComposer<A, B> {
+    const NUM_BASE_COLUMNS: usize = A::NUM_BASE_COLUMNS + B::NUM_BASE_COLUMNS;
+    const NUM_EXTENSION_COLUMNS: usize = A::NUM_EXTENSION_COLUMNS + B::NUM_EXTENSION_COLUMNS;
+    const SEPARATELY_COMMITTED_BASE_COLUMNS: &'static [usize] =
+        A::SEPARATELY_COMMITTED_BASE_COLUMNS;
+    const PREPROCESSED_BASE_COLUMNS: &'static [usize] = A::PREPROCESSED_BASE_COLUMNS;
+
+    type Fp = A::Fp;
+    type Fq = A::Fq;
+    type PublicInputs = (A::PublicInputs, B::PublicInputs);
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Self::Fp, Self::Fq>>> {
+        assert!(
+            B::SEPARATELY_COMMITTED_BASE_COLUMNS.is_empty()
+                && B::PREPROCESSED_BASE_COLUMNS.is_empty(),
+            "only the first sub-AIR can have separately committed or preprocessed columns"
+        );
+        assert!(
+            A::domain_offset() == B::domain_offset(),
+            "sub-AIRs must have the same domain offset"
+        );
+        let a_constraints = A::constraints(trace_len);
+        let b_constraints = B::constraints(trace_len);
+        let challenge_offset = num_challenges(&a_constraints);
+        let hint_offset = num_hints(&a_constraints);
+        // extension columns of `A` move past the base columns of `B`
+        let a_column = |column| {
+            if column < A::NUM_BASE_COLUMNS {
+                column
+            } else {
+                column + B::NUM_BASE_COLUMNS
+            }
+        };
+        let b_column = |column| {
+            if column < B::NUM_BASE_COLUMNS {
+                column + A::NUM_BASE_COLUMNS
+            } else {
+                column + A::NUM_BASE_COLUMNS + A::NUM_EXTENSION_COLUMNS
+            }
+        };
+        let a_constraints = a_constraints.iter().map(|c| remap(c, a_column, 0, 0));
+        let b_constraints = b_constraints
+            .iter()
+            .map(|c| remap(c, b_column, challenge_offset, hint_offset));
+        a_constraints.chain(b_constraints).collect()
+    }
+
+    fn gen_hints(
+        trace_len: usize,
+        (a_inputs, b_inputs): &Self::PublicInputs,
+        challenges: &Challenges<Self::Fq>,
+    ) -> Hints<Self::Fq> {
+        let a_constraints = A::constraints(trace_len);
+        let challenge_offset = num_challenges(&a_constraints);
+        let hint_offset = num_hints(&a_constraints);
+        let (a_challenges, b_challenges) = challenges.split_at(challenge_offset);
+        let a_hints = A::gen_hints(trace_len, a_inputs, &Challenges::new(a_challenges.to_vec()));
+        let b_hints = B::gen_hints(trace_len, b_inputs, &Challenges::new(b_challenges.to_vec()));
+        // unreferenced hints of `A` would shift the hints of `B`
+        let hints = a_hints.iter().take(hint_offset).chain(b_hints.iter());
+        Hints::new(hints.copied().enumerate().collect())
+    }
+
+    fn domain_offset() -> Self::Fp {
+        A::domain_offset()
+    }
+}
+
+/// Trace of an [`AirComposer`]
+pub struct ComposedTrace<TA: Trace, TB> {
+    a: TA,
+    b: TB,
+    base_columns: Matrix<TA::Fp>,
+    challenge_offset: usize,
+}
+
+impl<TA: Trace, TB: Trace<Fp = TA::Fp, Fq = TA::Fq>> ComposedTrace<TA, TB> {
+    pub fn into_parts(self) -> (TA, TB) {
+        (self.a, self.b)
+    }
+}
+
+impl<TA: Trace, TB: Trace<Fp = TA::Fp, Fq = TA::Fq>> Trace for ComposedTrace<TA, TB> {
+    type Fp = TA::Fp;
+    type Fq = TA::Fq;
+
+    fn base_columns(&self) -> &Matrix<Self::Fp> {
+        &self.base_columns
+    }
+
+    fn build_extension_columns(
+        &self,
+        challenges: &Challenges<Self::Fq>,
+    ) -> Option<Matrix<Self::Fq>> {
+        let (a_challenges, b_challenges) = challenges.split_at(self.challenge_offset);
+        let a_columns = self
+            .a
+            .build_extension_columns(&Challenges::new(a_challenges.to_vec()));
+        let b_columns = self
+            .b
+            .build_extension_columns(&Challenges::new(b_challenges.to_vec()));
+        let columns = a_columns.into_iter().chain(b_columns).collect::<Vec<_>>();
+        (!columns.is_empty()).then(|| Matrix::join(columns))
+    }
+}
+
+fn remap<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    constraint: &Constraint<FieldVariant<Fp, Fq>>,
+    column: impl Fn(usize) -> usize,
+    challenge_offset: usize,
+    hint_offset: usize,
+) -> Constraint<FieldVariant<Fp, Fq>> {
+    use AlgebraicItem::*;
+    Constraint::new(constraint.map_leaves(&mut |&leaf| match leaf {
+        Trace(i, offset) => Trace(column(i), offset),
+        Challenge(i) => Challenge(i + challenge_offset),
+        Hint(i) => Hint(i + hint_offset),
+        leaf => leaf,
+    }))
+}
+
+fn num_challenges<T>(constraints: &[Constraint<T>]) -> usize {
+    max_index(constraints, |item| match item {
+        &AlgebraicItem::Challenge(i) => Some(i),
+        _ => None,
+    })
+}
+
+fn num_hints<T>(constraints: &[Constraint<T>]) -> usize {
+    max_index(constraints, |item| match item {
+        &AlgebraicItem::Hint(i) => Some(i),
+        _ => None,
+    })
+}
+
+fn max_index<T>(
+    constraints: &[Constraint<T>],
+    index: impl Fn(&AlgebraicItem<T>) -> Option<usize>,
+) -> usize {
+    let mut n = 0;
+    for constraint in constraints {
+        constraint.traverse(&mut |node| {
+            if let Expr::Leaf(item) = node { if let Some(i) = index(item) {
+                n = n.max(i + 1);
+            }}
+        });
+    }
+    n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AirComposer;
+    use crate::air::AirConfig;
+    use crate::constraints::AlgebraicItem;
+    use crate::constraints::Constraint;
+    use crate::expression::Expr;
+    use crate::utils::FieldVariant;
+    use alloc::collections::BTreeSet;
+    use alloc::vec::Vec;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    struct AirA;
+
+    impl AirConfig for AirA {
+        const NUM_BASE_COLUMNS: usize = 2;
+        const NUM_EXTENSION_COLUMNS: usize = 1;
+
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraints(_trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            vec![
+                Constraint::new(Expr::from(Trace(0, 0)) * Challenge(0) - Trace(2, 0)),
+                Constraint::new(Expr::from(Trace(1, 1)) - Hint(0)),
+            ]
+        }
+    }
+
+    struct AirB;
+
+    impl AirConfig for AirB {
+        const NUM_BASE_COLUMNS: usize = 1;
+        const NUM_EXTENSION_COLUMNS: usize = 1;
+
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraints(_trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            vec![Constraint::new(
+                Expr::from(Trace(0, -1)) - Challenge(0) - Trace(1, 0) - Hint(0),
+            )]
+        }
+    }
+
+    fn leaves(
+        constraint: &Constraint<FieldVariant<Fp, Fp>>,
+    ) -> BTreeSet<AlgebraicItem<FieldVariant<Fp, Fp>>> {
+        let mut leaves = BTreeSet::new();
+        constraint.traverse(&mut |node| {
+            if let Expr::Leaf(item) = node {
+                leaves.insert(*item);
+            }
+        });
+        leaves
+    }
+
+    #[test]
+    fn composed_constraints_are_remapped() {
+        use AlgebraicItem::*;
+        type Composed = AirComposer<AirA, AirB>;
+        assert_eq!(Composed::NUM_BASE_COLUMNS, 3);
+        assert_eq!(Composed::NUM_EXTENSION_COLUMNS, 2);
+
+        let constraints = Composed::constraints(8);
+
+        assert_eq!(constraints.len(), 3);
+        assert_eq!(
+            leaves(&constraints[0]),
+            BTreeSet::from([Trace(0, 0), Challenge(0), Trace(3, 0)])
+        );
+        assert_eq!(
+            leaves(&constraints[1]),
+            BTreeSet::from([Trace(1, 1), Hint(0)])
+        );
+        assert_eq!(
+            leaves(&constraints[2]),
+            BTreeSet::from([Trace(2, -1), Challenge(1), Trace(4, 0), Hint(1)])
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! Machine readable description of an AIR
+//!
+//! [`Air::to_spec`] exports the columns, constraints, degrees and periodic
+//! columns of an AIR as JSON so tooling outside of Rust (visualizers, audits,
+//! verifiers generated for other languages) doesn't have to parse Rust code.
+//! The format only changes along with [`SPEC_VERSION`]:
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "fingerprint": "<hex of Air::fingerprint>",
+//!   "trace_len": 1024,
+//!   "ce_blowup_factor": 2,
+//!   "composition_degree": 2047,
+//!   "columns": {
+//!     "base": 3,
+//!     "extension": 1,
+//!     "separately_committed": [],
+//!     "preprocessed": []
+//!   },
+//!   "num_challenges": 1,
+//!   "num_hints": 1,
+//!   "periodic_columns": [{ "interval_size": 8, "coeffs": [<element>, ..] }],
+//!   "constraints": [{ "numerator_degree": 2046, "denominator_degree": 1023, "expr": <expr> }]
+//! }
+//! ```
+//!
+//! An `<expr>` is an object with an `op` of `neg`, `add`, `mul` or `div` and
+//! its operands in `args`, `pow` with its base in `args` and an `exponent`, or
+//! a leaf:
+//! * `x` - the domain element
+//! * `constant` - the field element `value`
+//! * `challenge`/`hint` - the verifier challenge or hint at `index`
+//! * `periodic` - the periodic column at `index` of `periodic_columns`
+//! * `trace` - the trace `column` at row offset `offset`
+//!
+//! An `<element>` is `{ "field": "fp" | "fq", "coeffs": ["<decimal>", ..] }`
+//! holding the canonical base prime field coefficients. Assertions e.g.
+//! boundary constraints are constraints that reference hints. Hint values are
+//! derived from the public inputs and challenges so they aren't exported.
+use crate::air::AirConfig;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::constraints::PeriodicColumn;
+use crate::expression::Expr;
+use crate::utils::FieldVariant;
+use crate::Air;
+use alloc::string::String;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_ff::PrimeField;
+use core::fmt::Write;
+
+/// Version of the format written by [`Air::to_spec`]
+pub const SPEC_VERSION: u32 = 1;
+
+type Item<C> = AlgebraicItem<FieldVariant<<C as AirConfig>::Fp, <C as AirConfig>::Fq>>;
+
+/// Returns the JSON description of `air` (see the [module docs](self))
+pub(crate) fn air_spec<C: AirConfig>(air: &Air<C>) -> String {
+    let constraints = air.constraints();
+    let mut periodic_columns = Vec::new();
+    let mut num_hints = 0;
+    for constraint in constraints {
+        constraint.traverse(&mut |node| match node {
+            Expr::Leaf(AlgebraicItem::Periodic(col)) if !periodic_columns.contains(col) => {
+                periodic_columns.push(*col);
+            }
+            &Expr::Leaf(AlgebraicItem::Hint(i)) => num_hints = num_hints.max(i + 1),
+            _ => {}
+        });
+    }
+
+    let mut out = String::new();
+    let fingerprint = air.fingerprint().map(|byte| format!("{byte:02x}")).concat();
+    write!(out, r#"{{"version":{SPEC_VERSION},"fingerprint":"{fingerprint}","#).unwrap();
+    write!(
+        out,
+        r#""trace_len":{},"ce_blowup_factor":{},"composition_degree":{},"#,
+        air.trace_len(),
+        air.ce_blowup_factor(),
+        air.composition_degree()
+    )
+    .unwrap();
+    write!(
+        out,
+        r#""columns":{{"base":{},"extension":{},"separately_committed":{:?},"preprocessed":{:?}}},"#,
+        C::NUM_BASE_COLUMNS,
+        C::NUM_EXTENSION_COLUMNS,
+        C::SEPARATELY_COMMITTED_BASE_COLUMNS,
+        C::PREPROCESSED_BASE_COLUMNS,
+    )
+    .unwrap();
+    write!(
+        out,
+        r#""num_challenges":{},"num_hints":{num_hints},"#,
+        air.num_challenges()
+    )
+    .unwrap();
+
+    out.push_str(r#""periodic_columns":["#);
+    for (i, col) in periodic_columns.iter().enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+        write_periodic_column(&mut out, col);
+    }
+    out.push_str(r#"],"constraints":["#);
+    let trace_degree = air.trace_len() - 1;
+    for (i, constraint) in constraints.iter().enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+        write_constraint::<C>(&mut out, constraint, trace_degree, &periodic_columns);
+    }
+    out.push_str("]}");
+    out
+}
+
+fn write_constraint<C: AirConfig>(
+    out: &mut String,
+    constraint: &Constraint<FieldVariant<C::Fp, C::Fq>>,
+    trace_degree: usize,
+    periodic_columns: &[PeriodicColumn<'static, FieldVariant<C::Fp, C::Fq>>],
+) {
+    let (numerator_degree, denominator_degree) = constraint.degree(trace_degree);
+    write!(
+        out,
+        r#"{{"numerator_degree":{numerator_degree},"denominator_degree":{denominator_degree},"expr":"#
+    )
+    .unwrap();
+    write_expr::<C>(out, constraint, periodic_columns);
+    out.push('}');
+}
+
+fn write_expr<C: AirConfig>(
+    out: &mut String,
+    expr: &Expr<Item<C>>,
+    periodic_columns: &[PeriodicColumn<'static, FieldVariant<C::Fp, C::Fq>>],
+) {
+    let (op, args) = match expr {
+        Expr::Leaf(item) => return write_item::<C>(out, item, periodic_columns),
+        Expr::Neg(a) => ("neg", [Some(a), None]),
+        Expr::Add(a, b) => ("add", [Some(a), Some(b)]),
+        Expr::Mul(a, b) => ("mul", [Some(a), Some(b)]),
+        Expr::Div(a, b) => ("div", [Some(a), Some(b)]),
+        Expr::Pow(a, _) => ("pow", [Some(a), None]),
+    };
+    write!(out, r#"{{"op":"{op}","args":["#).unwrap();
+    for (i, arg) in args.into_iter().flatten().enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+        write_expr::<C>(out, &arg.read().unwrap(), periodic_columns);
+    }
+    out.push(']');
+    if let Expr::Pow(_, exponent) = expr {
+        write!(out, r#","exponent":{exponent}"#).unwrap();
+    }
+    out.push('}');
+}
+
+fn write_item<C: AirConfig>(
+    out: &mut String,
+    item: &Item<C>,
+    periodic_columns: &[PeriodicColumn<'static, FieldVariant<C::Fp, C::Fq>>],
+) {
+    use AlgebraicItem::*;
+    match item {
+        X => out.push_str(r#"{"op":"x"}"#),
+        Constant(value) => {
+            out.push_str(r#"{"op":"constant","value":"#);
+            write_element(out, value);
+            out.push('}');
+        }
+        Challenge(i) => write!(out, r#"{{"op":"challenge","index":{i}}}"#).unwrap(),
+        Hint(i) => write!(out, r#"{{"op":"hint","index":{i}}}"#).unwrap(),
+        Periodic(col) => {
+            let i = periodic_columns.iter().position(|c| c == col).unwrap();
+            write!(out, r#"{{"op":"periodic","index":{i}}}"#).unwrap();
+        }
+        Trace(column, offset) => write!(
+            out,
+            r#"{{"op":"trace","column":{column},"offset":{offset}}}"#
+        )
+        .unwrap(),
+    }
+}
+
+fn write_periodic_column<Fp: Field, Fq: Field>(
+    out: &mut String,
+    col: &PeriodicColumn<'static, FieldVariant<Fp, Fq>>,
+) {
+    write!(out, r#"{{"interval_size":{},"coeffs":["#, col.interval_size()).unwrap();
+    for (i, coeff) in col.coeffs().iter().enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+        write_element(out, coeff);
+    }
+    out.push_str("]}");
+}
+
+fn write_element<Fp: Field, Fq: Field>(out: &mut String, value: &FieldVariant<Fp, Fq>) {
+    let (field, coeffs) = match value {
+        FieldVariant::Fp(v) => ("fp", coefficients(v)),
+        FieldVariant::Fq(v) => ("fq", coefficients(v)),
+    };
+    write!(out, r#"{{"field":"{field}","coeffs":{coeffs:?}}}"#).unwrap();
+}
+
+fn coefficients<F: Field>(element: &F) -> Vec<String> {
+    element
+        .to_base_prime_field_elements()
+        .map(|coeff| coeff.into_bigint().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::air::AirConfig;
+    use crate::constraints::AlgebraicItem;
+    use crate::constraints::Constraint;
+    use crate::constraints::PeriodicColumn;
+    use crate::expression::Expr;
+    use crate::utils::FieldVariant;
+    use crate::Air;
+    use crate::ProofOptions;
+    use alloc::vec::Vec;
+    use ark_ff::Field;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    static PERIODIC_COEFFS: [FieldVariant<Fp, Fp>; 1] = [FieldVariant::Fp(Fp::ONE)];
+
+    struct TestAir;
+
+    impl AirConfig for TestAir {
+        const NUM_BASE_COLUMNS: usize = 2;
+
+        type Fp = Fp;
+        type Fq = Fp;
+        type PublicInputs = ();
+
+        fn constraints(_trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+            use AlgebraicItem::*;
+            let periodic = Periodic(PeriodicColumn::new(&PERIODIC_COEFFS, 4));
+            vec![
+                Constraint::new(Expr::from(Trace(1, 1)) - Trace(0, 0) * periodic),
+                Constraint::new(Expr::from(Trace(0, 0)) - Hint(0)),
+            ]
+        }
+    }
+
+    #[test]
+    fn spec_describes_columns_and_constraints() {
+        let air = Air::<TestAir>::new(8, (), ProofOptions::new(24, 4, 0, 2, 8));
+
+        let spec = air.to_spec();
+
+        assert!(spec.starts_with(r#"{"version":1,"fingerprint":""#));
+        assert!(spec.contains(
+            r#""columns":{"base":2,"extension":0,"separately_committed":[],"preprocessed":[]}"#
+        ));
+        assert!(spec.contains(r#""num_challenges":0,"num_hints":1"#));
+        assert!(spec.contains(
+            r#""periodic_columns":[{"interval_size":4,"coeffs":[{"field":"fp","coeffs":["1"]}]}]"#
+        ));
+        assert!(spec.contains(r#"{"op":"trace","column":1,"offset":1}"#));
+        assert!(spec.contains(r#"{"op":"periodic","index":0}"#));
+        assert!(spec.contains(r#"{"op":"hint","index":0}"#));
+        assert!(spec.ends_with("}]}"));
+    }
+}
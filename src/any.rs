@@ -0,0 +1,172 @@
+//! Proofs over a field chosen at runtime
+//!
+//! A [`Proof`] is generic over its [`Stark`] and therefore over its field. A
+//! service that accepts proofs over several fields can use [`ProofAny`]
+//! instead which is tagged with its field (see [`FieldTag`]) when serialized.
+//! The statement is wrapped in a [`StarkAny`] and proofs are dispatched to the
+//! verifier of the matching field:
+//!
+//! ```ignore
+//! let proof = ProofAny::<GoldilocksClaim, Stark252Claim>::deserialize_compressed(bytes)?;
+//! let claim = StarkAny::Goldilocks(GoldilocksClaim(public_inputs));
+//! claim.verify(proof, 96)?;
+//! ```
+use crate::stark::Stark;
+use crate::verifier::VerificationError;
+use crate::Proof;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use ark_serialize::Compress;
+use ark_serialize::SerializationError;
+use ark_serialize::Valid;
+use ark_serialize::Validate;
+use ministark_gpu::fields::p18446744069414584321;
+use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481;
+use snafu::Snafu;
+
+type GoldilocksFp = p18446744069414584321::ark::Fp;
+
+type Stark252Fp =
+    p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
+
+/// Field of a [`ProofAny`]. Serialized as a single byte before the proof.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldTag {
+    /// Prime field of order `2^64 - 2^32 + 1`
+    Goldilocks = 0,
+    /// Prime field of order `2^251 + 17 * 2^192 + 1`
+    Stark252 = 1,
+}
+
+impl FieldTag {
+    const fn from_u8(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(Self::Goldilocks),
+            1 => Some(Self::Stark252),
+            _ => None,
+        }
+    }
+}
+
+/// Proof over one of the supported fields. `G` and `S` are the statements
+/// over the Goldilocks and Stark252 fields.
+pub enum ProofAny<G: Stark<Fp = GoldilocksFp>, S: Stark<Fp = Stark252Fp>> {
+    Goldilocks(Proof<G>),
+    Stark252(Proof<S>),
+}
+
+impl<G: Stark<Fp = GoldilocksFp>, S: Stark<Fp = Stark252Fp>> ProofAny<G, S> {
+    pub const fn field(&self) -> FieldTag {
+        match self {
+            Self::Goldilocks(_) => FieldTag::Goldilocks,
+            Self::Stark252(_) => FieldTag::Stark252,
+        }
+    }
+}
+
+impl<G: Stark<Fp = GoldilocksFp>, S: Stark<Fp = Stark252Fp>> Clone for ProofAny<G, S> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Goldilocks(proof) => Self::Goldilocks(proof.clone()),
+            Self::Stark252(proof) => Self::Stark252(proof.clone()),
+        }
+    }
+}
+
+impl<G: Stark<Fp = GoldilocksFp>, S: Stark<Fp = Stark252Fp>> CanonicalSerialize for ProofAny<G, S> {
+    fn serialize_with_mode<W: ark_serialize::Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        (self.field() as u8).serialize_with_mode(&mut writer, compress)?;
+        match self {
+            Self::Goldilocks(proof) => proof.serialize_with_mode(writer, compress),
+            Self::Stark252(proof) => proof.serialize_with_mode(writer, compress),
+        }
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        1 + match self {
+            Self::Goldilocks(proof) => proof.serialized_size(compress),
+            Self::Stark252(proof) => proof.serialized_size(compress),
+        }
+    }
+}
+
+impl<G: Stark<Fp = GoldilocksFp>, S: Stark<Fp = Stark252Fp>> Valid for ProofAny<G, S> {
+    #[inline]
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl<G: Stark<Fp = GoldilocksFp>, S: Stark<Fp = Stark252Fp>> CanonicalDeserialize
+    for ProofAny<G, S>
+{
+    fn deserialize_with_mode<R: ark_serialize::Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let tag = u8::deserialize_with_mode(&mut reader, compress, validate)?;
+        Ok(match FieldTag::from_u8(tag) {
+            Some(FieldTag::Goldilocks) => {
+                Self::Goldilocks(<_>::deserialize_with_mode(reader, compress, validate)?)
+            }
+            Some(FieldTag::Stark252) => {
+                Self::Stark252(<_>::deserialize_with_mode(reader, compress, validate)?)
+            }
+            None => return Err(SerializationError::InvalidData),
+        })
+    }
+}
+
+/// Statement over one of the supported fields (see [`ProofAny`])
+#[derive(Debug, Clone)]
+pub enum StarkAny<G: Stark<Fp = GoldilocksFp>, S: Stark<Fp = Stark252Fp>> {
+    Goldilocks(G),
+    Stark252(S),
+}
+
+impl<G: Stark<Fp = GoldilocksFp>, S: Stark<Fp = Stark252Fp>> StarkAny<G, S> {
+    pub const fn field(&self) -> FieldTag {
+        match self {
+            Self::Goldilocks(_) => FieldTag::Goldilocks,
+            Self::Stark252(_) => FieldTag::Stark252,
+        }
+    }
+
+    /// Verifies a proof with the verifier of the statement's field
+    pub fn verify(&self, proof: ProofAny<G, S>, required_security_bits: u32) -> Result<(), Error> {
+        match (self, proof) {
+            (Self::Goldilocks(stark), ProofAny::Goldilocks(proof)) => {
+                stark.verify(proof, required_security_bits)?;
+            }
+            (Self::Stark252(stark), ProofAny::Stark252(proof)) => {
+                stark.verify(proof, required_security_bits)?;
+            }
+            (stark, proof) => {
+                return Err(Error::FieldMismatch {
+                    statement: stark.field(),
+                    proof: proof.field(),
+                })
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Errors that are returned when verifying a [`ProofAny`]
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("statement is over the {statement:?} field but the proof is over {proof:?}"))]
+    FieldMismatch {
+        statement: FieldTag,
+        proof: FieldTag,
+    },
+    #[snafu(context(false))]
+    #[snafu(display("{source}"))]
+    Verification { source: VerificationError },
+}
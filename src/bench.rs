@@ -0,0 +1,165 @@
+//! Benchmark entry points
+//!
+//! Each function times one phase of proof generation (or verification) on
+//! random inputs so downstream users can run comparable benchmarks on their own
+//! hardware and pick folding factors, GPU thresholds etc. empirically. The
+//! returned duration excludes generating the inputs which makes the functions a
+//! good fit for criterion's `iter_custom`:
+//!
+//! ```ignore
+//! group.bench_function("commit", |b| {
+//!     b.iter_custom(|iters| {
+//!         (0..iters)
+//!             .map(|_| bench::commit_phase::<Fp, MerkleTree>(n, 8, options))
+//!             .sum()
+//!     })
+//! });
+//! ```
+//!
+//! The harness in `benches/phases.rs` runs all phases.
+use crate::fri::FriProver;
+use crate::fri::ProverChannel;
+use crate::hash::Digest;
+use crate::merkle::MatrixMerkleTree;
+use crate::merkle::MerkleTreeDomain;
+use crate::random::DrawTag;
+use crate::stark::Stark;
+use crate::utils::GpuAllocator;
+use crate::verifier::VerificationError;
+use crate::Matrix;
+use crate::Proof;
+use crate::ProofOptions;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_poly::domain::DomainCoeff;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use core::marker::PhantomData;
+use ministark_gpu::utils::bit_reverse;
+use ministark_gpu::GpuFftField;
+use ministark_gpu::GpuField;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Times committing to a trace of `size` random rows and `num_columns`
+/// columns i.e. interpolating the columns, evaluating them over the LDE domain
+/// and building the Merkle tree
+pub fn commit_phase<F, M>(size: usize, num_columns: usize, options: ProofOptions) -> Duration
+where
+    F: GpuFftField<FftField = F> + FftField,
+    M: MatrixMerkleTree<F>,
+{
+    let mut rng = StdRng::seed_from_u64(0);
+    let trace_domain = Radix2EvaluationDomain::<F>::new(size).unwrap();
+    let lde_domain_size = size * usize::from(options.lde_blowup_factor);
+    let lde_domain = Radix2EvaluationDomain::new_coset(lde_domain_size, F::GENERATOR).unwrap();
+    let columns = (0..num_columns)
+        .map(|_| {
+            let mut column = Vec::with_capacity_in(size, GpuAllocator);
+            column.extend((0..size).map(|_| F::rand(&mut rng)));
+            column
+        })
+        .collect();
+    let trace = Matrix::new(columns);
+
+    let now = Instant::now();
+    let polys = trace.interpolate(trace_domain);
+    let lde = polys.bit_reversed_evaluate(lde_domain);
+    let tree = M::from_matrix(&lde, MerkleTreeDomain::BaseTrace);
+    let elapsed = now.elapsed();
+    drop(tree);
+    elapsed
+}
+
+/// Times generating a FRI proof for a polynomial of degree `size - 1`
+/// including querying the layers at `options.num_queries` random positions
+pub fn fri_prove<F, D, M>(size: usize, options: ProofOptions) -> Duration
+where
+    F: GpuField + Field + DomainCoeff<F::FftField>,
+    F::FftField: FftField,
+    D: Digest,
+    M: MatrixMerkleTree<F, Root = D>,
+{
+    let mut rng = StdRng::seed_from_u64(0);
+    let fri_options = options.into_fri_options();
+    let lde_domain_size = size * usize::from(options.lde_blowup_factor);
+    let domain_offset = fri_options.domain_offset::<F>();
+    let lde_domain = Radix2EvaluationDomain::new_coset(lde_domain_size, domain_offset).unwrap();
+    let coeffs = (0..size).map(|_| F::rand(&mut rng)).collect::<Vec<F>>();
+    let mut evaluations = lde_domain.fft(&coeffs);
+    bit_reverse(&mut evaluations);
+    let evaluations = evaluations.to_vec_in(GpuAllocator);
+    let mut positions = (0..options.num_queries)
+        .map(|_| rng.gen_range(0..lde_domain_size))
+        .collect::<Vec<usize>>();
+    positions.sort_unstable();
+    positions.dedup();
+    let mut channel = BenchChannel::<F, D> {
+        rng,
+        _phantom: PhantomData,
+    };
+
+    let now = Instant::now();
+    let mut prover = FriProver::<F, D, M>::new(fri_options);
+    prover
+        .build_layers(&mut channel, evaluations, size - 1)
+        .unwrap();
+    let proof = prover.into_proof(&positions).unwrap();
+    let elapsed = now.elapsed();
+    drop(proof);
+    elapsed
+}
+
+/// Times verifying `proof`
+pub fn verify<S: Stark>(
+    stark: &S,
+    proof: &Proof<S>,
+    required_security_bits: u32,
+) -> Result<Duration, VerificationError> {
+    let proof = proof.clone();
+    let now = Instant::now();
+    stark.verify(proof, required_security_bits)?;
+    Ok(now.elapsed())
+}
+
+/// Channel that draws FRI challenges from a seeded RNG rather than a public
+/// coin. Only the cost of the FRI prover is measured.
+struct BenchChannel<F, D> {
+    rng: StdRng,
+    _phantom: PhantomData<(F, D)>,
+}
+
+impl<F: GpuField + Field, D: Digest> ProverChannel for BenchChannel<F, D> {
+    type Digest = D;
+    type Field = F;
+
+    fn commit_fri_layer(&mut self, _layer_cap: Vec<D>) {}
+
+    fn commit_remainder(&mut self, _remainder_coeffs: &[F]) {}
+
+    fn draw_fri_alpha(&mut self) -> F {
+        F::rand(&mut self.rng)
+    }
+
+    fn commit_ood_eval(&mut self, _ood_eval: F) {}
+
+    fn draw_challenge(&mut self, _tag: DrawTag) -> F {
+        F::rand(&mut self.rng)
+    }
+
+    fn draw_positions(
+        &mut self,
+        _tag: DrawTag,
+        max_n: usize,
+        domain_size: usize,
+    ) -> BTreeSet<usize> {
+        (0..max_n)
+            .map(|_| self.rng.gen_range(0..domain_size))
+            .collect()
+    }
+}
@@ -0,0 +1,207 @@
+//! Bitwise operations
+//!
+//! XOR, AND and OR of `u32` values checked with the byte table (see
+//! [`crate::byte_table`]). Operands and the result are held as four byte
+//! columns each (least significant first) and the chip looks up
+//! `(op, a_i, b_i, c_i)` for every byte. The lookups also check the columns
+//! hold bytes so the chip has no constraints of its own. Bytes are packed into
+//! larger values with [`pack_bytes`] e.g. to relate them to the limbs of a
+//! [`crate::u32_chip::U32`].
+//!
+//! The operation is an expression so VM AIRs can use a single chip for all
+//! their bitwise instructions by looking up the opcode column.
+use crate::byte_table::ByteLookup;
+use crate::byte_table::ByteOp;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::ExecutionTraceColumn;
+use crate::expression::Expr;
+use crate::utils::FieldVariant;
+use crate::Matrix;
+use alloc::vec::Vec;
+use ark_ff::Field;
+
+type E<Fp, Fq> = Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>;
+
+/// Execution trace columns of the bitwise chip
+#[derive(Debug, Clone, Copy)]
+pub struct BitwiseColumns<C> {
+    pub a: [C; 4],
+    pub b: [C; 4],
+    /// Bytes of `a ∘ b`
+    pub c: [C; 4],
+}
+
+impl<C: ExecutionTraceColumn> BitwiseColumns<C> {
+    /// Returns the byte table lookups of the chip in the rows where `selector`
+    /// is 1. `op` is the operation e.g. [`ByteOp::constant`] or a column
+    /// holding the operation of each row.
+    pub fn lookups<Fp: Field, Fq: Field>(
+        &self,
+        selector: impl Into<E<Fp, Fq>>,
+        op: impl Into<E<Fp, Fq>>,
+    ) -> Vec<ByteLookup<FieldVariant<Fp, Fq>>> {
+        let (selector, op) = (selector.into(), op.into());
+        (0..4)
+            .map(|i| {
+                let (a, b, c) = (self.a[i].curr(), self.b[i].curr(), self.c[i].curr());
+                ByteLookup::with_op(selector.clone(), op.clone(), a, b, c)
+            })
+            .collect()
+    }
+
+    /// Writes `a`, `b` and `a ∘ b` to row `row` and returns `a ∘ b`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `op` is [`ByteOp::Range`]
+    pub fn assign<F: Field>(
+        &self,
+        trace: &mut Matrix<F>,
+        row: usize,
+        op: ByteOp,
+        a: u32,
+        b: u32,
+    ) -> u32 {
+        let c = match op {
+            ByteOp::Xor => a ^ b,
+            ByteOp::And => a & b,
+            ByteOp::Or => a | b,
+            ByteOp::Range => panic!("range isn't a bitwise operation"),
+        };
+        for (columns, value) in [(&self.a, a), (&self.b, b), (&self.c, c)] {
+            for (column, byte) in columns.iter().zip(value.to_le_bytes()) {
+                trace.0[column.index()][row] = F::from(byte);
+            }
+        }
+        c
+    }
+}
+
+/// Returns the value of byte columns (least significant first)
+pub fn pack_bytes<Fp: Field, Fq: Field>(bytes: &[impl ExecutionTraceColumn]) -> E<Fp, Fq> {
+    bytes
+        .iter()
+        .rev()
+        .map(ExecutionTraceColumn::curr)
+        .reduce(|acc, byte| {
+            acc * AlgebraicItem::Constant(FieldVariant::Fp(Fp::from(256u32))) + byte
+        })
+        .expect("no bytes to pack")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pack_bytes;
+    use super::BitwiseColumns;
+    use crate::byte_table::byte_table_constraint;
+    use crate::byte_table::ByteChallenges;
+    use crate::byte_table::ByteOp;
+    use crate::byte_table::ByteTable;
+    use crate::byte_table::ByteTableColumns;
+    use crate::constraints::AlgebraicItem;
+    use crate::constraints::Constraint;
+    use crate::utils::FieldVariant;
+    use crate::utils::GpuAllocator;
+    use crate::Matrix;
+    use alloc::vec::Vec;
+    use ark_ff::UniformRand;
+    use ark_ff::Zero;
+    use ark_poly::EvaluationDomain;
+    use ark_poly::Radix2EvaluationDomain;
+    use ark_std::rand::Rng;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    const TABLE: ByteTableColumns<usize> = ByteTableColumns {
+        op: 0,
+        a: 1,
+        b: 2,
+        c: 3,
+        multiplicity: 4,
+        sum: 19,
+    };
+
+    const OP: usize = 5;
+
+    const CHIP: BitwiseColumns<usize> = BitwiseColumns {
+        a: [6, 7, 8, 9],
+        b: [10, 11, 12, 13],
+        c: [14, 15, 16, 17],
+    };
+
+    const NUM_BASE_COLUMNS: usize = 18;
+
+    #[test]
+    fn bitwise_ops_are_looked_up() {
+        let mut rng = ark_std::test_rng();
+        let ops = [ByteOp::Xor, ByteOp::And];
+        let table = ByteTable::new(&ops);
+        let trace_len = 1 << 17;
+        let columns = (0..NUM_BASE_COLUMNS)
+            .map(|_| vec![Fp::zero(); trace_len].to_vec_in(GpuAllocator))
+            .collect::<Vec<_>>();
+        let mut trace = Matrix::new(columns);
+        let table_columns = table.columns::<Fp>(trace_len);
+        for (i, column) in table_columns.0.into_iter().enumerate() {
+            trace.0[i] = column;
+        }
+        // chip is active in every row with the operation in the opcode column
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::from(1u8)));
+        let lookups = CHIP.lookups(one, AlgebraicItem::Trace(OP, 0));
+        let mut results = Vec::new();
+        for row in 0..trace_len {
+            let op = ops[row % 2];
+            let (a, b) = rng.gen();
+            trace.0[OP][row] = Fp::from(op as u8);
+            results.push(CHIP.assign(&mut trace, row, op, a, b));
+        }
+        trace.0[TABLE.multiplicity] = table.multiplicity_column(&trace, &lookups);
+
+        let challenges = [Fp::rand(&mut rng), Fp::rand(&mut rng)];
+        let byte_challenges = ByteChallenges {
+            z: challenges[0],
+            alpha: challenges[1],
+        };
+        let challenge_indices = ByteChallenges { z: 0, alpha: 1 };
+        let constraint = byte_table_constraint(&TABLE, &lookups, &challenge_indices, trace_len);
+        let domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+        let check = |trace: &Matrix<Fp>| {
+            let sum = ByteTable::sum_column(trace, &TABLE, &lookups, &byte_challenges);
+            // first rows and the wrap around
+            let mut rows = (0..16).chain(trace_len - 16..trace_len);
+            rows.all(|i| {
+                use AlgebraicItem::*;
+                let eval = constraint.check(&mut |leaf| match *leaf {
+                    X => FieldVariant::Fp(domain.element(i)),
+                    Constant(v) => v,
+                    Challenge(j) => FieldVariant::Fp(challenges[j]),
+                    Trace(column, offset) => {
+                        let row = (i + trace_len).wrapping_add_signed(offset) % trace_len;
+                        let value = if column == TABLE.sum {
+                            sum[row]
+                        } else {
+                            trace.0[column][row]
+                        };
+                        FieldVariant::Fp(value)
+                    }
+                    Hint(_) | Periodic(_) => unreachable!(),
+                });
+                eval.is_some()
+            })
+        };
+        assert!(check(&trace));
+
+        // result bytes pack into the result
+        let packed =
+            Constraint::new(pack_bytes::<Fp, Fp>(&CHIP.c)).check(&mut |leaf| match *leaf {
+                AlgebraicItem::Constant(v) => v,
+                AlgebraicItem::Trace(column, 0) => FieldVariant::Fp(trace.0[column][7]),
+                _ => unreachable!(),
+            });
+        assert_eq!(packed, Some(FieldVariant::Fp(Fp::from(results[7]))));
+
+        // XOR claimed as AND with the old multiplicities
+        let (a, b) = (0b1100, 0b1010);
+        CHIP.assign(&mut trace, 0, ByteOp::And, a, b);
+        assert!(!check(&trace));
+    }
+}
@@ -0,0 +1,487 @@
+//! Byte lookup table
+//!
+//! A table of 8-bit operations shared by every chip of an AIR. Chips check
+//! byte values (range checks, XOR for Keccak, bytes that are packed into
+//! larger limbs etc.) by looking up `(op, a, b, c)` tuples in the table rather
+//! than each carrying columns of its own. The table is made of preprocessed
+//! base columns so it's committed to once (see
+//! [`crate::air::AirConfig::PREPROCESSED_BASE_COLUMNS`]) and a single `LogUp`
+//! style argument checks the lookups of every chip:
+//!
+//! ```text
+//! Σ_rows Σ_lookups selector / (z - fingerprint(lookup)) = Σ_rows multiplicity / (z - fingerprint(table row))
+//! ```
+//!
+//! where the multiplicity column counts the lookups of each table row. The
+//! running sum of the difference is kept in an extension column and the
+//! constraint returned by [`byte_table_constraint`] checks it in every row
+//! including the wrap around from the last row to the first. The running sum
+//! is cyclic so it only satisfies the constraint if the sums are equal.
+//!
+//! Lookups are expressions over the base columns of the AIR (see
+//! [`ByteLookup`]). The same lookups are used to build the constraint, the
+//! multiplicity column and the running sum so they can't get out of sync.
+//! Each lookup in a row adds one to the degree of the constraint.
+use crate::constraints::rows_constraint;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::constraints::ExecutionTraceColumn;
+use crate::constraints::Exemptions;
+use crate::constraints::VerifierChallenge;
+use crate::expression::Expr;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::utils::Scan;
+use crate::Matrix;
+use crate::StarkExtensionOf;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ministark_gpu::GpuFftField;
+
+type E<Fp, Fq> = Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>;
+
+/// Number of columns returned by [`ByteTable::columns`]. The columns are the
+/// operation, the two inputs and the output (in that order).
+pub const NUM_BYTE_TABLE_COLUMNS: usize = 4;
+
+/// An operation in the byte table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ByteOp {
+    /// `a` is a byte. Has a row for every byte with `b` and `c` set to zero.
+    Range = 0,
+    /// `c = a ^ b`
+    Xor = 1,
+    /// `c = a & b`
+    And = 2,
+    /// `c = a | b`
+    Or = 3,
+}
+
+impl ByteOp {
+    /// Returns the output of the operation. [`ByteOp::Range`] has no output and
+    /// returns zero.
+    pub const fn eval(self, a: u8, b: u8) -> u8 {
+        match self {
+            Self::Range => 0,
+            Self::Xor => a ^ b,
+            Self::And => a & b,
+            Self::Or => a | b,
+        }
+    }
+
+    /// Returns the operation as a constraint constant. This is the value of
+    /// the operation column of its rows in the table.
+    pub fn constant<Fp: Field, Fq>(self) -> AlgebraicItem<FieldVariant<Fp, Fq>> {
+        AlgebraicItem::Constant(FieldVariant::Fp(Fp::from(self as u8)))
+    }
+
+    /// Returns the number of rows of the operation in the table
+    pub const fn num_rows(self) -> usize {
+        match self {
+            Self::Range => 1 << 8,
+            Self::Xor | Self::And | Self::Or => 1 << 16,
+        }
+    }
+
+    fn rows(self) -> impl Iterator<Item = [u8; 3]> {
+        let max_b = if self == Self::Range { 0 } else { u8::MAX };
+        (0..=u8::MAX).flat_map(move |a| (0..=max_b).map(move |b| [a, b, self.eval(a, b)]))
+    }
+}
+
+/// Execution trace columns of the byte table
+#[derive(Debug, Clone, Copy)]
+pub struct ByteTableColumns<C> {
+    pub op: C,
+    pub a: C,
+    pub b: C,
+    pub c: C,
+    /// Number of lookups of each row of the table
+    pub multiplicity: C,
+    /// Extension column with the running sum of the lookups
+    pub sum: C,
+}
+
+/// Verifier challenges used to fingerprint lookups
+#[derive(Debug, Clone, Copy)]
+pub struct ByteChallenges<T> {
+    pub z: T,
+    pub alpha: T,
+}
+
+/// A lookup of `(op, a, b, c)` in the byte table in the rows where `selector`
+/// is 1. The expressions can only reference base columns and constants.
+#[derive(Clone)]
+pub struct ByteLookup<T: 'static> {
+    pub selector: Expr<AlgebraicItem<T>>,
+    /// The operation. Usually a constant (see [`ByteOp::constant`]) but can
+    /// be a column e.g. the opcode of a VM instruction.
+    pub op: Expr<AlgebraicItem<T>>,
+    pub a: Expr<AlgebraicItem<T>>,
+    pub b: Expr<AlgebraicItem<T>>,
+    pub c: Expr<AlgebraicItem<T>>,
+}
+
+impl<Fp: Field, Fq: Field> ByteLookup<FieldVariant<Fp, Fq>> {
+    pub fn new(
+        selector: impl Into<E<Fp, Fq>>,
+        op: ByteOp,
+        a: impl Into<E<Fp, Fq>>,
+        b: impl Into<E<Fp, Fq>>,
+        c: impl Into<E<Fp, Fq>>,
+    ) -> Self {
+        Self::with_op(selector, op.constant(), a, b, c)
+    }
+
+    /// Returns a lookup of the operation `op` evaluates to in each row
+    pub fn with_op(
+        selector: impl Into<E<Fp, Fq>>,
+        op: impl Into<E<Fp, Fq>>,
+        a: impl Into<E<Fp, Fq>>,
+        b: impl Into<E<Fp, Fq>>,
+        c: impl Into<E<Fp, Fq>>,
+    ) -> Self {
+        Self {
+            selector: selector.into(),
+            op: op.into(),
+            a: a.into(),
+            b: b.into(),
+            c: c.into(),
+        }
+    }
+
+    /// Returns a lookup that checks `a` is a byte
+    pub fn range(selector: impl Into<E<Fp, Fq>>, a: impl Into<E<Fp, Fq>>) -> Self {
+        let zero = || AlgebraicItem::Constant(FieldVariant::Fp(Fp::zero()));
+        Self::new(selector, ByteOp::Range, a, zero(), zero())
+    }
+}
+
+impl<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>> ByteLookup<FieldVariant<Fp, Fq>> {
+    /// Returns the selector and looked up row in row `i` of `trace` or
+    /// [`None`] if the selector isn't set
+    fn eval(&self, trace: &Matrix<Fp>, i: usize) -> Option<(Fp, [Fp; NUM_BYTE_TABLE_COLUMNS])> {
+        let selector = eval_base(&self.selector, trace, i);
+        (!selector.is_zero()).then(|| {
+            let row = [&self.op, &self.a, &self.b, &self.c].map(|e| eval_base(e, trace, i));
+            (selector, row)
+        })
+    }
+}
+
+/// The rows of the byte table (see the [module docs](self))
+#[derive(Debug, Clone)]
+pub struct ByteTable {
+    /// Operations in the order they appear in the table
+    ops: Vec<ByteOp>,
+}
+
+impl ByteTable {
+    /// Returns a table with the rows of each operation. AIRs should only
+    /// include the operations their chips use since binary operations have
+    /// `2^16` rows each.
+    pub fn new(ops: &[ByteOp]) -> Self {
+        let mut ops = ops.to_vec();
+        ops.sort_unstable();
+        ops.dedup();
+        Self { ops }
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.ops.iter().map(|op| op.num_rows()).sum()
+    }
+
+    fn rows<F: Field>(&self) -> impl Iterator<Item = [F; NUM_BYTE_TABLE_COLUMNS]> + '_ {
+        self.ops.iter().flat_map(|&op| {
+            op.rows()
+                .map(move |[a, b, c]| [F::from(op as u8), F::from(a), F::from(b), F::from(c)])
+        })
+    }
+
+    /// Returns the columns of the table (see [`NUM_BYTE_TABLE_COLUMNS`] for
+    /// the order) padded to `trace_len` rows with copies of the first row
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table has more than `trace_len` rows
+    pub fn columns<F: Field>(&self, trace_len: usize) -> Matrix<F> {
+        assert!(
+            self.num_rows() <= trace_len,
+            "byte table has {} rows",
+            self.num_rows()
+        );
+        let mut columns = (0..NUM_BYTE_TABLE_COLUMNS)
+            .map(|_| Vec::with_capacity_in(trace_len, GpuAllocator))
+            .collect::<Vec<GpuVec<F>>>();
+        let first = self.rows().next();
+        let padding = core::iter::repeat(first.unwrap_or_default());
+        for row in self.rows().chain(padding).take(trace_len) {
+            for (column, value) in columns.iter_mut().zip(row) {
+                column.push(value);
+            }
+        }
+        Matrix::new(columns)
+    }
+
+    /// Returns the multiplicity column for the lookups in `trace` which
+    /// holds the base columns of the AIR
+    ///
+    /// # Panics
+    ///
+    /// Panics if a lookup isn't in the table
+    pub fn multiplicity_column<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        trace: &Matrix<Fp>,
+        lookups: &[ByteLookup<FieldVariant<Fp, Fq>>],
+    ) -> GpuVec<Fp> {
+        let table_rows = self
+            .rows()
+            .enumerate()
+            .map(|(i, row)| (row, i))
+            .collect::<BTreeMap<[Fp; NUM_BYTE_TABLE_COLUMNS], usize>>();
+        let mut column = Vec::with_capacity_in(trace.num_rows(), GpuAllocator);
+        column.resize(trace.num_rows(), Fp::zero());
+        for i in 0..trace.num_rows() {
+            for (selector, row) in lookups.iter().filter_map(|l| l.eval(trace, i)) {
+                let Some(&table_row) = table_rows.get(&row) else {
+                    panic!("lookup {row:?} in row {i} isn't in the byte table");
+                };
+                column[table_row] += selector;
+            }
+        }
+        column
+    }
+
+    /// Returns the running sum column for the lookups in `trace` which holds
+    /// the base columns of the AIR, the table and the multiplicity column. Row
+    /// `i` holds the sum over rows `0..i` so the first row is zero. The
+    /// challenges must be the ones the constraint is built with.
+    pub fn sum_column<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        trace: &Matrix<Fp>,
+        columns: &ByteTableColumns<impl ExecutionTraceColumn>,
+        lookups: &[ByteLookup<FieldVariant<Fp, Fq>>],
+        challenges: &ByteChallenges<Fq>,
+    ) -> GpuVec<Fq> {
+        let ByteChallenges { z, alpha } = *challenges;
+        let fingerprint = |[op, a, b, c]: [Fp; 4]| {
+            let (op, a, b, c) = (Fq::from(op), Fq::from(a), Fq::from(b), Fq::from(c));
+            z - (op + alpha * (a + alpha * (b + alpha * c)))
+        };
+        // the sum is exclusive so row `i` holds the terms of rows `0..i`
+        let mut column = Vec::with_capacity_in(trace.num_rows(), GpuAllocator);
+        column.push(Fq::zero());
+        for i in 0..trace.num_rows() - 1 {
+            let mut term = Fq::zero();
+            for (selector, row) in lookups.iter().filter_map(|l| l.eval(trace, i)) {
+                term += Fq::from(selector) * fingerprint(row).inverse().unwrap();
+            }
+            let value = |column: usize| trace.0[column][i];
+            let multiplicity = value(columns.multiplicity.index());
+            if !multiplicity.is_zero() {
+                let row =
+                    [&columns.op, &columns.a, &columns.b, &columns.c].map(|c| value(c.index()));
+                term -= Fq::from(multiplicity) * fingerprint(row).inverse().unwrap();
+            }
+            column.push(term);
+        }
+        column.scan_sum();
+        column
+    }
+}
+
+/// Evaluates an expression over base columns in row `row`
+fn eval_base<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    expr: &E<Fp, Fq>,
+    trace: &Matrix<Fp>,
+    row: usize,
+) -> Fp {
+    let n = trace.num_rows();
+    let eval = expr.eval(&mut |leaf| match *leaf {
+        AlgebraicItem::Constant(v) => v,
+        AlgebraicItem::Trace(column, offset) => {
+            let row = (row + n).wrapping_add_signed(offset) % n;
+            FieldVariant::Fp(trace.0[column][row])
+        }
+        _ => panic!("lookups can only reference base columns and constants"),
+    });
+    match eval {
+        FieldVariant::Fp(v) => v,
+        FieldVariant::Fq(_) => panic!("lookups can only reference base columns and constants"),
+    }
+}
+
+/// Returns the constraint of the byte table for a trace of `trace_len` rows
+/// with the lookups of every chip
+pub fn byte_table_constraint<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    columns: &ByteTableColumns<impl ExecutionTraceColumn>,
+    lookups: &[ByteLookup<FieldVariant<Fp, Fq>>],
+    challenges: &ByteChallenges<impl VerifierChallenge>,
+    trace_len: usize,
+) -> Constraint<FieldVariant<Fp, Fq>> {
+    let alpha = challenges.alpha.challenge::<FieldVariant<Fp, Fq>>();
+    let fingerprint = |op: &E<Fp, Fq>, a: &E<Fp, Fq>, b: &E<Fp, Fq>, c: &E<Fp, Fq>| {
+        challenges.z.challenge() - (op + &alpha * (a + &alpha * (b + &alpha * c)))
+    };
+    let table = fingerprint(
+        &columns.op.curr(),
+        &columns.a.curr(),
+        &columns.b.curr(),
+        &columns.c.curr(),
+    );
+    let queries = lookups
+        .iter()
+        .map(|l| fingerprint(&l.op, &l.a, &l.b, &l.c))
+        .collect::<Vec<_>>();
+    let product_except = |skip: Option<usize>| {
+        let factors = queries.iter().enumerate().filter(|&(i, _)| Some(i) != skip);
+        factors.map(|(_, q)| q.clone()).product::<E<Fp, Fq>>()
+    };
+
+    // sum_next - sum = Σ selector / query - multiplicity / table with the
+    // denominators cleared
+    let lhs = (columns.sum.next() - columns.sum.curr()) * &table * product_except(None);
+    let rhs = lookups
+        .iter()
+        .enumerate()
+        .map(|(i, l)| &l.selector * &table * product_except(Some(i)))
+        .sum::<E<Fp, Fq>>()
+        - columns.multiplicity.curr() * product_except(None);
+    Constraint::new(rows_constraint(
+        lhs - rhs,
+        trace_len,
+        1,
+        0,
+        &Exemptions::new(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::byte_table_constraint;
+    use super::ByteChallenges;
+    use super::ByteLookup;
+    use super::ByteOp;
+    use super::ByteTable;
+    use super::ByteTableColumns;
+    use crate::constraints::AlgebraicItem;
+    use crate::constraints::ExecutionTraceColumn;
+    use crate::utils::FieldVariant;
+    use crate::utils::GpuAllocator;
+    use crate::Matrix;
+    use alloc::vec::Vec;
+    use ark_ff::UniformRand;
+    use ark_poly::EvaluationDomain;
+    use ark_poly::Radix2EvaluationDomain;
+    use ark_std::rand::Rng;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    type Lookup = ByteLookup<FieldVariant<Fp, Fp>>;
+
+    /// The table is followed by the columns of a chip and the multiplicity
+    const COLUMNS: ByteTableColumns<usize> = ByteTableColumns {
+        op: 0,
+        a: 1,
+        b: 2,
+        c: 3,
+        multiplicity: 8,
+        sum: 9,
+    };
+
+    const CHALLENGES: ByteChallenges<usize> = ByteChallenges { z: 0, alpha: 1 };
+
+    fn gen_trace(
+        table: &ByteTable,
+        trace_len: usize,
+        lookups: &[Lookup],
+        chip: [Vec<Fp>; 4],
+    ) -> Matrix<Fp> {
+        let mut columns = table.columns(trace_len).0;
+        columns.extend(chip.map(|column| column.to_vec_in(GpuAllocator)));
+        let mut trace = Matrix::new(columns);
+        let multiplicity = table.multiplicity_column(&trace, lookups);
+        trace.0.push(multiplicity);
+        trace
+    }
+
+    /// Checks the constraint in `rows` with the running sum built from `trace`
+    fn check_rows(
+        trace: &Matrix<Fp>,
+        lookups: &[Lookup],
+        challenges: &[Fp],
+        rows: impl Iterator<Item = usize>,
+    ) -> bool {
+        let n = trace.num_rows();
+        let domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+        let byte_challenges = ByteChallenges {
+            z: challenges[0],
+            alpha: challenges[1],
+        };
+        let sum = ByteTable::sum_column(trace, &COLUMNS, lookups, &byte_challenges);
+        let constraint = byte_table_constraint(&COLUMNS, lookups, &CHALLENGES, n);
+        rows.into_iter().all(|i| {
+            use AlgebraicItem::*;
+            let eval = constraint.check(&mut |leaf| match *leaf {
+                X => FieldVariant::Fp(domain.element(i)),
+                Constant(v) => v,
+                Challenge(j) => FieldVariant::Fp(challenges[j]),
+                Trace(column, offset) => {
+                    let row = (i + n).wrapping_add_signed(offset) % n;
+                    let value = if column == COLUMNS.sum.index() {
+                        sum[row]
+                    } else {
+                        trace.0[column][row]
+                    };
+                    FieldVariant::Fp(value)
+                }
+                Hint(_) | Periodic(_) => unreachable!(),
+            });
+            eval.is_some()
+        })
+    }
+
+    #[test]
+    fn range_and_xor_lookups_are_checked() {
+        let mut rng = ark_std::test_rng();
+        let challenges = [Fp::rand(&mut rng), Fp::rand(&mut rng)];
+        let table = ByteTable::new(&[ByteOp::Xor, ByteOp::Range]);
+        let trace_len = 1 << 17;
+        // chip has `out = lhs ^ rhs` in the rows where its selector is set and
+        // range checks `out` in every row
+        let (selector, lhs, rhs, out) = (4, 5, 6, 7);
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::from(1u8)));
+        let lookups = [
+            Lookup::new(
+                selector.curr(),
+                ByteOp::Xor,
+                lhs.curr(),
+                rhs.curr(),
+                out.curr(),
+            ),
+            Lookup::range(one, out.curr()),
+        ];
+        let mut chip: [Vec<Fp>; 4] = Default::default();
+        for i in 0..trace_len {
+            let (x, y) = rng.gen::<(u8, u8)>();
+            let active = i % 3 == 0;
+            let xor = if active { x ^ y } else { rng.gen() };
+            for (column, value) in chip.iter_mut().zip([u8::from(active), x, y, xor]) {
+                column.push(Fp::from(value));
+            }
+        }
+        let mut trace = gen_trace(&table, trace_len, &lookups, chip);
+        // first rows, the end of the table and the wrap around
+        let rows = || {
+            (0..64)
+                .chain(table.num_rows() - 8..table.num_rows() + 8)
+                .chain(trace_len - 8..trace_len)
+        };
+        assert!(check_rows(&trace, &lookups, &challenges, rows()));
+
+        // wrong XOR output with the old multiplicities
+        trace.0[out][0] += Fp::from(1u8);
+        assert!(!check_rows(&trace, &lookups, &challenges, rows()));
+    }
+}
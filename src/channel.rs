@@ -2,7 +2,10 @@ use crate::challenges::Challenges;
 use crate::fri;
 use crate::fri::FriProof;
 use crate::hints::Hints;
+use crate::prover::ProverError;
+use crate::random::DrawTag;
 use crate::random::PublicCoin;
+use crate::random::ReseedTag;
 use crate::stark::Stark;
 use crate::trace::Queries;
 use crate::Air;
@@ -16,10 +19,12 @@ use std::collections::BTreeSet;
 pub struct ProverChannel<'a, S: Stark> {
     air: &'a Air<S::AirConfig>,
     pub public_coin: S::PublicCoin,
-    base_trace_commitment: S::Digest,
-    extension_trace_commitment: Option<S::Digest>,
-    composition_trace_commitment: S::Digest,
-    fri_layer_commitments: Vec<S::Digest>,
+    nonce: Option<[u8; 32]>,
+    base_trace_commitment: Vec<S::Digest>,
+    base_column_commitments: Vec<Vec<S::Digest>>,
+    extension_trace_commitment: Option<Vec<S::Digest>>,
+    composition_trace_commitment: Vec<S::Digest>,
+    fri_layer_commitments: Vec<Vec<S::Digest>>,
     fri_remainder_coeffs: Vec<S::Fq>,
     execution_trace_ood_evals: Vec<S::Fq>,
     composition_trace_ood_evals: Vec<S::Fq>,
@@ -32,9 +37,11 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
         ProverChannel {
             air,
             public_coin,
+            nonce: None,
             extension_trace_commitment: None,
-            base_trace_commitment: S::Digest::default(),
-            composition_trace_commitment: S::Digest::default(),
+            base_trace_commitment: Vec::new(),
+            base_column_commitments: Vec::new(),
+            composition_trace_commitment: Vec::new(),
             execution_trace_ood_evals: Vec::new(),
             composition_trace_ood_evals: Vec::new(),
             fri_layer_commitments: Vec::new(),
@@ -43,23 +50,52 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
         }
     }
 
-    pub fn commit_base_trace(&mut self, commitment: S::Digest) {
-        self.public_coin.reseed_with_digest(&commitment);
-        self.base_trace_commitment = commitment;
+    /// Absorbs the per-proof nonce. Must be called before anything else is
+    /// committed.
+    pub fn commit_nonce(&mut self, nonce: [u8; 32]) {
+        self.public_coin.reseed_with_nonce(&nonce);
+        self.nonce = Some(nonce);
     }
 
-    pub fn commit_extension_trace(&mut self, commitment: S::Digest) {
-        self.public_coin.reseed_with_digest(&commitment);
-        self.extension_trace_commitment = Some(commitment);
+    /// Absorbs the commitment to the preprocessed columns (see
+    /// [`crate::setup::VerifyingKey`]). The verifier takes the
+    /// commitment from its key so it isn't part of the proof. Must be called
+    /// before [`Self::commit_base_trace`].
+    pub fn commit_preprocessed_columns(&mut self, cap: &[S::Digest]) {
+        self.public_coin
+            .reseed_with_cap(ReseedTag::Preprocessed, cap);
+    }
+
+    /// Commits to the merkle cap of the base trace (see
+    /// [`crate::ProofOptions::with_merkle_cap_height`])
+    pub fn commit_base_trace(&mut self, cap: Vec<S::Digest>) {
+        self.public_coin.reseed_with_cap(ReseedTag::BaseTrace, &cap);
+        self.base_trace_commitment = cap;
+    }
+
+    /// Commits to the separately committed base columns. Must be called right
+    /// after [`Self::commit_base_trace`].
+    pub fn commit_base_columns(&mut self, caps: Vec<Vec<S::Digest>>) {
+        for cap in &caps {
+            self.public_coin.reseed_with_cap(ReseedTag::BaseColumn, cap);
+        }
+        self.base_column_commitments = caps;
     }
 
-    pub fn commit_composition_trace(&mut self, commitment: S::Digest) {
-        self.public_coin.reseed_with_digest(&commitment);
-        self.composition_trace_commitment = commitment;
+    pub fn commit_extension_trace(&mut self, cap: Vec<S::Digest>) {
+        self.public_coin
+            .reseed_with_cap(ReseedTag::ExtensionTrace, &cap);
+        self.extension_trace_commitment = Some(cap);
+    }
+
+    pub fn commit_composition_trace(&mut self, cap: Vec<S::Digest>) {
+        self.public_coin
+            .reseed_with_cap(ReseedTag::CompositionTrace, &cap);
+        self.composition_trace_commitment = cap;
     }
 
     pub fn get_ood_point(&mut self) -> S::Fq {
-        self.public_coin.draw()
+        self.public_coin.draw_fq(DrawTag::OodPoint)
     }
 
     pub fn send_ood_evals(
@@ -68,28 +104,31 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
         composition_trace_oods: Vec<S::Fq>,
     ) {
         let ood_evals = [execution_trace_oods.clone(), composition_trace_oods.clone()].concat();
-        self.public_coin.reseed_with_field_elements(&ood_evals);
+        self.public_coin
+            .reseed_with_field_elements(ReseedTag::OodEvals, &ood_evals);
         self.execution_trace_ood_evals = execution_trace_oods;
         self.composition_trace_ood_evals = composition_trace_oods;
     }
 
-    pub fn grind_fri_commitments(&mut self) {
+    pub fn grind_fri_commitments(&mut self) -> Result<(), ProverError> {
         let grinding_factor = self.air.options().grinding_factor;
         if grinding_factor == 0 {
             // skip if there is no grinding required
-            return;
+            return Ok(());
         }
 
         let nonce = self
             .public_coin
             .grind_proof_of_work(grinding_factor)
-            .expect("nonce not found");
+            .ok_or(ProverError::ProofOfWorkNotFound { grinding_factor })?;
         assert!(self
             .public_coin
             .verify_proof_of_work(grinding_factor, nonce));
 
         self.pow_nonce = nonce;
-        self.public_coin.reseed_with_int(self.pow_nonce);
+        self.public_coin
+            .reseed_with_int(ReseedTag::ProofOfWork, self.pow_nonce);
+        Ok(())
     }
 
     pub fn get_fri_query_positions(&mut self) -> BTreeSet<usize> {
@@ -99,6 +138,20 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
         self.public_coin.draw_queries(num_queries, lde_domain_size)
     }
 
+    /// Returns the query positions of the FRI layers after the first (see
+    /// [`fri::draw_layer_positions`])
+    pub fn get_fri_layer_query_positions(&mut self, num_layers: usize) -> Vec<Vec<usize>> {
+        let options = self.air.options();
+        let lde_domain_size = self.air.trace_len() * self.air.lde_blowup_factor();
+        fri::draw_layer_positions(
+            &mut self.public_coin,
+            &options.into_fri_options(),
+            lde_domain_size,
+            num_layers,
+            options.num_queries.into(),
+        )
+    }
+
     pub fn build_proof(
         self,
         trace_queries: Queries<S>,
@@ -107,7 +160,10 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
         Proof {
             options: self.air.options(),
             trace_len: self.air.trace_len(),
+            nonce: self.nonce,
+            air_fingerprint: self.air.fingerprint(),
             base_trace_commitment: self.base_trace_commitment,
+            base_column_commitments: self.base_column_commitments,
             extension_trace_commitment: self.extension_trace_commitment,
             composition_trace_commitment: self.composition_trace_commitment,
             execution_trace_ood_evals: self.execution_trace_ood_evals,
@@ -125,19 +181,38 @@ impl<'a, S: Stark> fri::ProverChannel for ProverChannel<'a, S> {
     type Digest = S::Digest;
     type Field = S::Fq;
 
-    fn commit_fri_layer(&mut self, commitment: S::Digest) {
-        self.public_coin.reseed_with_digest(&commitment);
-        self.fri_layer_commitments.push(commitment);
+    fn commit_fri_layer(&mut self, layer_cap: Vec<S::Digest>) {
+        self.public_coin
+            .reseed_with_cap(ReseedTag::FriLayer, &layer_cap);
+        self.fri_layer_commitments.push(layer_cap);
     }
 
     fn commit_remainder(&mut self, remainder_coeffs: &[Self::Field]) {
         self.public_coin
-            .reseed_with_field_element_vector(remainder_coeffs);
+            .reseed_with_field_element_vector(ReseedTag::FriRemainder, remainder_coeffs);
         self.fri_remainder_coeffs = remainder_coeffs.to_vec();
     }
 
     fn draw_fri_alpha(&mut self) -> S::Fq {
-        self.public_coin.draw()
+        self.public_coin.draw_fq(DrawTag::FriAlpha)
+    }
+
+    fn commit_ood_eval(&mut self, ood_eval: S::Fq) {
+        self.public_coin
+            .reseed_with_field_elements(ReseedTag::StirOodEval, &[ood_eval]);
+    }
+
+    fn draw_challenge(&mut self, tag: DrawTag) -> S::Fq {
+        self.public_coin.draw_fq(tag)
+    }
+
+    fn draw_positions(
+        &mut self,
+        tag: DrawTag,
+        max_n: usize,
+        domain_size: usize,
+    ) -> BTreeSet<usize> {
+        self.public_coin.draw_positions(tag, max_n, domain_size)
     }
 }
 
@@ -0,0 +1,132 @@
+//! Comparison gadgets
+//!
+//! [`LessThan`] compares two columns holding values in `[0, 2^16)` e.g. limbs
+//! of [`crate::u32_chip::U32`] values. The comparison subtracts the values and
+//! the borrow column is set if the subtraction wraps around. Like the u32 chip
+//! the constraints only imply the comparison if the difference is range
+//! checked (see [`LessThan::range_checked`]). `u32` values are compared with
+//! the borrow of a [`crate::u32_chip::U32Sub`] (see
+//! [`crate::u32_chip::U32Sub::lt`]).
+//!
+//! The result of a comparison is usually used with
+//! [`crate::constraints::select`] or [`crate::constraints::when`]. Greater than
+//! and less equal are less than and greater equal with the operands swapped.
+use crate::constraints::assert_boolean;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::ExecutionTraceColumn;
+use crate::expression::Expr;
+use crate::utils::FieldVariant;
+use crate::Matrix;
+use crate::StarkExtensionOf;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_ff::One;
+use ministark_gpu::GpuFftField;
+
+/// `a - b = diff - borrow * 2^16` so `borrow` is set iff `a < b`
+#[derive(Debug, Clone, Copy)]
+pub struct LessThan<C> {
+    pub a: C,
+    pub b: C,
+    pub diff: C,
+    pub borrow: C,
+}
+
+impl<C: ExecutionTraceColumn> LessThan<C> {
+    pub fn constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let base = AlgebraicItem::Constant(FieldVariant::Fp(Fp::from(1u32 << 16)));
+        vec![
+            self.a.curr() - self.b.curr() - self.diff.curr() + self.borrow.curr() * base,
+            assert_boolean(self.borrow.curr()),
+        ]
+    }
+
+    /// Columns that need a 16-bit range check. The operands must also be in
+    /// `[0, 2^16)` but are usually range checked by the component that
+    /// constrains them so they aren't included.
+    pub fn range_checked(&self) -> Vec<&C> {
+        vec![&self.diff]
+    }
+
+    /// Returns an expression that's 1 where `a < b` and 0 otherwise
+    pub fn lt<T>(&self) -> Expr<AlgebraicItem<T>> {
+        self.borrow.curr()
+    }
+
+    /// Returns an expression that's 1 where `a >= b` and 0 otherwise
+    pub fn ge<T: One>(&self) -> Expr<AlgebraicItem<T>> {
+        Expr::from(AlgebraicItem::Constant(T::one())) - self.borrow.curr()
+    }
+
+    /// Writes `a`, `b`, their difference and the comparison to row `row` and
+    /// returns `a < b`
+    pub fn assign<F: Field>(&self, trace: &mut Matrix<F>, row: usize, a: u16, b: u16) -> bool {
+        let (diff, borrow) = a.overflowing_sub(b);
+        trace.0[self.a.index()][row] = F::from(a);
+        trace.0[self.b.index()][row] = F::from(b);
+        trace.0[self.diff.index()][row] = F::from(diff);
+        trace.0[self.borrow.index()][row] = F::from(borrow);
+        borrow
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LessThan;
+    use crate::constraints::select;
+    use crate::constraints::AlgebraicItem;
+    use crate::constraints::Constraint;
+    use crate::constraints::ExecutionTraceColumn;
+    use crate::expression::Expr;
+    use crate::utils::FieldVariant;
+    use crate::utils::GpuAllocator;
+    use crate::Matrix;
+    use ark_ff::PrimeField;
+    use ark_ff::Zero;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    const LT: LessThan<usize> = LessThan {
+        a: 0,
+        b: 1,
+        diff: 2,
+        borrow: 3,
+    };
+
+    fn eval(expr: Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>, trace: &Matrix<Fp>) -> Fp {
+        let eval = Constraint::new(expr).check(&mut |leaf| match *leaf {
+            AlgebraicItem::Constant(v) => v,
+            AlgebraicItem::Trace(column, 0) => FieldVariant::Fp(trace.0[column][0]),
+            _ => unreachable!(),
+        });
+        eval.unwrap().as_fq()
+    }
+
+    #[test]
+    fn less_than_selects_the_minimum() {
+        let columns = (0..4)
+            .map(|_| [Fp::zero()].to_vec_in(GpuAllocator))
+            .collect();
+        let mut trace = Matrix::new(columns);
+        for (a, b) in [(3, 7), (7, 3), (5, 5), (0, u16::MAX), (u16::MAX, 0)] {
+            assert_eq!(LT.assign(&mut trace, 0, a, b), a < b);
+            for constraint in LT.constraints::<Fp, Fp>() {
+                assert!(eval(constraint, &trace).is_zero());
+            }
+            let min = select(LT.lt(), LT.a.curr(), LT.b.curr());
+            assert_eq!(eval(min, &trace), Fp::from(a.min(b)));
+            assert_eq!(eval(LT.ge(), &trace), Fp::from(a >= b));
+        }
+
+        // claims 7 < 3 with a difference that's out of range
+        LT.assign(&mut trace, 0, 7, 3);
+        trace.0[LT.borrow][0] = Fp::from(1u8);
+        trace.0[LT.diff][0] = Fp::from(4u32 + (1 << 16));
+        for constraint in LT.constraints::<Fp, Fp>() {
+            assert!(eval(constraint, &trace).is_zero());
+        }
+        assert!(trace.0[*LT.range_checked()[0]][0].into_bigint().0[0] >= 1 << 16);
+    }
+}
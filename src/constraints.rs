@@ -1,8 +1,14 @@
+use crate::domain;
 use crate::expression::Expr;
-use crate::utils;
+use crate::utils::FieldVariant;
 use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
 use ark_ff::One;
 use ark_ff::Zero;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
 use core::iter::Product;
 use core::iter::Sum;
 use core::ops::Add;
@@ -141,6 +147,18 @@ impl<'a, T> PeriodicColumn<'a, T> {
     }
 }
 
+impl<Fp: Field, Fq: Field + From<Fp>> PeriodicColumn<'_, FieldVariant<Fp, Fq>> {
+    /// Evaluates the periodic column at `x` e.g. the out of domain point. The
+    /// column's polynomial is in `x^(trace_len / interval_size)` so it's
+    /// evaluated with Horner's method at that point.
+    pub fn evaluate_at(&self, trace_len: usize, x: Fq) -> Fq {
+        let point = x.pow([(trace_len / self.interval_size) as u64]);
+        self.coeffs
+            .iter()
+            .rfold(Fq::zero(), |acc, coeff| acc * point + coeff.as_fq())
+    }
+}
+
 #[derive(Clone)]
 pub struct Constraint<T: 'static>(Expr<AlgebraicItem<T>>);
 
@@ -303,6 +321,7 @@ impl<T: Zero> Sum<Self> for Expr<CompositionItem<T>> {
     }
 }
 
+#[derive(Clone)]
 pub struct CompositionConstraint<T: 'static>(Expr<CompositionItem<T>>);
 
 impl<T: Clone + Copy + Zero + Ord + Hash> CompositionConstraint<T> {
@@ -343,7 +362,7 @@ const fn blowup_factor(
     trace_degree: usize,
 ) -> usize {
     let degree = numerator_degree.saturating_sub(denominator_degree);
-    utils::ceil_power_of_two(degree) / trace_degree
+    domain::ceil_power_of_two(degree) / trace_degree
 }
 
 pub trait Hint {
@@ -393,6 +412,9 @@ pub trait ExecutionTraceColumn {
         self.offset(1)
     }
 
+    /// Create a constraint element for the row `offset` rows away. Offsets
+    /// can be any distance in either direction. The prover opens every
+    /// referenced row at `z * g^offset` for the out-of-domain point `z`.
     fn offset<T>(&self, offset: isize) -> Expr<AlgebraicItem<T>> {
         AlgebraicItem::Trace(self.index(), offset).into()
     }
@@ -404,6 +426,215 @@ impl ExecutionTraceColumn for usize {
     }
 }
 
+/// Rows of the trace in which a constraint doesn't have to hold (see
+/// [`rows_constraint`])
+#[derive(Clone, Debug, Default)]
+pub struct Exemptions {
+    last_rows: usize,
+    rows: Vec<usize>,
+    periodic: Vec<(/* =interval */ usize, /* =first_row */ usize)>,
+}
+
+impl Exemptions {
+    pub const fn new() -> Self {
+        Self {
+            last_rows: 0,
+            rows: Vec::new(),
+            periodic: Vec::new(),
+        }
+    }
+
+    /// Exempts the last `n` rows e.g. for transitions that reference `n` rows
+    /// ahead
+    pub const fn last_rows(mut self, n: usize) -> Self {
+        self.last_rows = n;
+        self
+    }
+
+    /// Exempts a single row
+    pub fn row(mut self, row: usize) -> Self {
+        self.rows.push(row);
+        self
+    }
+
+    /// Exempts rows `first_row + i * interval` e.g. the last row of every
+    /// `interval` row cycle of a hash. Costs a single factor of degree
+    /// `n / interval` rather than a factor for every exempt row.
+    pub fn every(mut self, interval: usize, first_row: usize) -> Self {
+        self.periodic.push((interval, first_row));
+        self
+    }
+
+    /// Returns the product of `x - g^e` over the exempt rows `e`. Rows that
+    /// are exempt more than once only increase the degree.
+    fn zerofier<Fp: FftField, Fq: Field>(
+        &self,
+        trace_domain: Radix2EvaluationDomain<Fp>,
+    ) -> Option<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        use AlgebraicItem::*;
+        let trace_len = trace_domain.size();
+        assert!(self.last_rows <= trace_len);
+        let rows = (trace_len - self.last_rows..trace_len).chain(self.rows.iter().copied());
+        let row_factors = rows.map(|row| {
+            let x = Constant(FieldVariant::Fp(trace_domain.element(row)));
+            X - x
+        });
+        let periodic_factors = self
+            .periodic
+            .iter()
+            .map(|&(interval, first_row)| coset_zerofier(trace_domain, interval, first_row));
+        row_factors.chain(periodic_factors).reduce(|a, b| a * b)
+    }
+}
+
+/// Returns the vanishing polynomial of rows `first_row + i * interval` i.e.
+/// `x^(n / interval) - g^(first_row * n / interval)`
+fn coset_zerofier<Fp: FftField, Fq: Field>(
+    trace_domain: Radix2EvaluationDomain<Fp>,
+    interval: usize,
+    first_row: usize,
+) -> Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> {
+    use AlgebraicItem::*;
+    let trace_len = trace_domain.size();
+    assert!(interval != 0 && trace_len.is_multiple_of(interval));
+    let num_rows = trace_len / interval;
+    let shift = trace_domain.element(first_row).pow([num_rows as u64]);
+    X.pow(num_rows) - Constant(FieldVariant::Fp(shift))
+}
+
+/// Returns `constraint` restricted to rows `first_row + i * interval` of the
+/// trace except for the exempt rows `e_0, e_1, ...` i.e.
+///
+/// ```text
+/// constraint * (x - g^e_0) * (x - g^e_1) * ... / (x^(n / interval) - g^(first_row * n / interval))
+/// ```
+///
+/// where `g` generates the trace domain of size `n`. Constraints that reference
+/// `k` rows ahead (e.g. `column.offset(8)` for a hash with 8 rounds per row
+/// group) wrap around to the start of the trace in the last `k` rows which
+/// must be exempt (see [`transition_constraint`]). The prover and verifier
+/// evaluate the same expression so they always agree on the zerofier.
+///
+/// # Panics
+///
+/// Panics if `interval` doesn't divide the trace length
+pub fn rows_constraint<Fp: FftField, Fq: Field>(
+    constraint: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    trace_len: usize,
+    interval: usize,
+    first_row: usize,
+    exemptions: &Exemptions,
+) -> Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> {
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+    // exemptions are multiplied in rather than divided out of the vanishing
+    // polynomial so the constraint can be checked row by row
+    let constraint = match exemptions.zerofier(trace_domain) {
+        Some(zerofier) => constraint * zerofier,
+        None => constraint,
+    };
+    constraint / coset_zerofier(trace_domain, interval, first_row)
+}
+
+/// Returns a transition constraint that references up to `max_offset` rows
+/// ahead restricted to every row except the last `max_offset` rows and the
+/// rows in `exemptions` (see [`rows_constraint`])
+pub fn transition_constraint<Fp: FftField, Fq: Field>(
+    constraint: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    trace_len: usize,
+    max_offset: usize,
+    exemptions: Exemptions,
+) -> Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> {
+    let exemptions = exemptions.last_rows(max_offset);
+    rows_constraint(constraint, trace_len, 1, 0, &exemptions)
+}
+
+/// Rows in which a constraint has to hold. Built from selectors i.e.
+/// expressions that are 0 or 1 in every row (see [`when`] and
+/// [`assert_boolean`]).
+///
+/// ```text
+/// when(is_add.curr()).assert_eq(c.curr(), a.curr() + b.curr())
+/// ```
+///
+/// Constraints are multiplied by their selectors so they have a higher degree
+/// than the expressions they assert. Degrees are adjusted to the composition
+/// degree along with all other constraints (see
+/// [`crate::air::AirConfig::composition_constraint`]).
+#[derive(Clone)]
+pub struct When<T: 'static>(Expr<AlgebraicItem<T>>);
+
+/// Returns a condition that holds in the rows where `selector` is 1
+pub fn when<T>(selector: impl Into<Expr<AlgebraicItem<T>>>) -> When<T> {
+    When(selector.into())
+}
+
+/// Returns a condition that holds in the rows where `selector` is 0
+pub fn when_not<T: One>(selector: impl Into<Expr<AlgebraicItem<T>>>) -> When<T> {
+    When(Expr::from(AlgebraicItem::Constant(T::one())) - selector.into())
+}
+
+/// Returns an expression that's zero in the rows where `expr` is 0 or 1. Used
+/// to constrain selector columns.
+pub fn assert_boolean<T: Clone + One>(
+    expr: impl Into<Expr<AlgebraicItem<T>>>,
+) -> Expr<AlgebraicItem<T>> {
+    let expr = expr.into();
+    &expr * (&expr - AlgebraicItem::Constant(T::one()))
+}
+
+/// Returns an expression that's `a` in the rows where `cond` is 1 and `b` in
+/// the rows where `cond` is 0. `cond` should be constrained to be boolean.
+pub fn select<T: Clone>(
+    cond: impl Into<Expr<AlgebraicItem<T>>>,
+    a: impl Into<Expr<AlgebraicItem<T>>>,
+    b: impl Into<Expr<AlgebraicItem<T>>>,
+) -> Expr<AlgebraicItem<T>> {
+    let b = b.into();
+    cond.into() * (a.into() - &b) + b
+}
+
+impl<T: Clone> When<T> {
+    /// Returns a condition that holds where this condition holds and
+    /// `selector` is 1
+    pub fn when(self, selector: impl Into<Expr<AlgebraicItem<T>>>) -> Self {
+        Self(self.0 * selector.into())
+    }
+
+    /// Returns a condition that holds where this condition holds and
+    /// `selector` is 0
+    pub fn when_not(self, selector: impl Into<Expr<AlgebraicItem<T>>>) -> Self
+    where
+        T: One,
+    {
+        self.when(when_not(selector).0)
+    }
+
+    /// Returns an expression that's zero in the rows where `expr` is zero or
+    /// the condition doesn't hold
+    pub fn assert_zero(&self, expr: impl Into<Expr<AlgebraicItem<T>>>) -> Expr<AlgebraicItem<T>> {
+        &self.0 * expr.into()
+    }
+
+    /// Returns an expression that's zero in the rows where `a` equals `b` or
+    /// the condition doesn't hold
+    pub fn assert_eq(
+        &self,
+        a: impl Into<Expr<AlgebraicItem<T>>>,
+        b: impl Into<Expr<AlgebraicItem<T>>>,
+    ) -> Expr<AlgebraicItem<T>> {
+        self.assert_zero(a.into() - b.into())
+    }
+
+    /// Returns an expression that's zero in the rows where `expr` is 0 or 1 or
+    /// the condition doesn't hold
+    pub fn assert_boolean(&self, expr: impl Into<Expr<AlgebraicItem<T>>>) -> Expr<AlgebraicItem<T>>
+    where
+        T: One,
+    {
+        self.assert_zero(assert_boolean(expr))
+    }
+}
+
 /// Degree of the form `(numerator_degree, denominator_degree)`
 struct Degree(pub usize, pub usize);
 
@@ -453,3 +684,140 @@ impl Pow<usize> for Degree {
         Self(n * rhs, d * rhs)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::assert_boolean;
+    use super::rows_constraint;
+    use super::select;
+    use super::transition_constraint;
+    use super::when;
+    use super::AlgebraicItem;
+    use super::Constraint;
+    use super::ExecutionTraceColumn;
+    use super::Exemptions;
+    use crate::expression::Expr;
+    use crate::utils::FieldVariant;
+    use ark_ff::Zero;
+    use ark_poly::EvaluationDomain;
+    use ark_poly::Radix2EvaluationDomain;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    type Item = AlgebraicItem<FieldVariant<Fp, Fp>>;
+
+    fn eval(constraint: &Constraint<FieldVariant<Fp, Fp>>, row: &[Fp]) -> FieldVariant<Fp, Fp> {
+        constraint
+            .check(&mut |leaf| match *leaf {
+                Item::Trace(column, 0) => FieldVariant::Fp(row[column]),
+                Item::Constant(v) => v,
+                _ => unreachable!(),
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn selected_constraints_only_hold_where_selected() {
+        let (selector, a, b) = (0, 1, 2);
+        let constraint = Constraint::new(
+            when(selector.curr())
+                .when_not(a.curr())
+                .assert_eq(b.curr(), Item::Constant(FieldVariant::Fp(Fp::from(7u8)))),
+        );
+        let is_boolean = Constraint::new(assert_boolean(selector.curr()));
+        let [zero, one, two, seven] = [0u8, 1, 2, 7].map(Fp::from);
+
+        assert!(eval(&constraint, &[one, zero, seven]).is_zero());
+        assert!(!eval(&constraint, &[one, zero, two]).is_zero());
+        // not selected
+        assert!(eval(&constraint, &[zero, zero, two]).is_zero());
+        assert!(eval(&constraint, &[one, one, two]).is_zero());
+        assert!(eval(&is_boolean, &[one, zero, zero]).is_zero());
+        assert!(!eval(&is_boolean, &[two, zero, zero]).is_zero());
+        // each selector adds the trace degree
+        assert_eq!(constraint.degree(7), (21, 0));
+
+        let selected = Constraint::new(select(selector.curr(), a.curr(), b.curr()));
+        assert_eq!(eval(&selected, &[one, two, seven]), FieldVariant::Fp(two));
+        assert_eq!(
+            eval(&selected, &[zero, two, seven]),
+            FieldVariant::Fp(seven)
+        );
+    }
+
+    /// Checks `constraint` in every row of a single column trace
+    fn holds_in_all_rows(constraint: &Expr<Item>, column: &[Fp]) -> bool {
+        let n = column.len();
+        let domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+        let constraint = Constraint::new(constraint.clone());
+        (0..n).all(|i| {
+            let eval = constraint.check(&mut |leaf| match *leaf {
+                Item::X => FieldVariant::Fp(domain.element(i)),
+                Item::Constant(v) => v,
+                Item::Trace(0, offset) => {
+                    let row = (i + n).wrapping_add_signed(offset) % n;
+                    FieldVariant::Fp(column[row])
+                }
+                _ => unreachable!(),
+            });
+            eval.is_some()
+        })
+    }
+
+    #[test]
+    fn constraints_hold_in_selected_rows() {
+        let n = 32;
+        let one = Item::Constant(FieldVariant::Fp(Fp::from(1u8)));
+        let five = Item::Constant(FieldVariant::Fp(Fp::from(5u8)));
+        // every value is one more than the value 8 rows before except where
+        // the column wraps around
+        let column = (0..n).map(|i| Fp::from(i as u64 / 8)).collect::<Vec<Fp>>();
+        let transition = 0.offset(8) - 0.curr() - one;
+
+        assert!(holds_in_all_rows(
+            &transition_constraint(transition.clone(), n, 8, Exemptions::new()),
+            &column
+        ));
+        assert!(!holds_in_all_rows(
+            &transition_constraint(transition, n, 7, Exemptions::new()),
+            &column
+        ));
+
+        // rows 3, 11 and 19 but not 27
+        let mut column = vec![Fp::zero(); n];
+        for row in [3, 11, 19] {
+            column[row] = Fp::from(5u8);
+        }
+        let periodic = 0.curr() - five;
+        assert!(holds_in_all_rows(
+            &rows_constraint(periodic.clone(), n, 8, 3, &Exemptions::new().row(27)),
+            &column
+        ));
+        assert!(!holds_in_all_rows(
+            &rows_constraint(periodic, n, 8, 3, &Exemptions::new()),
+            &column
+        ));
+    }
+
+    #[test]
+    fn periodic_exemptions() {
+        let n = 32;
+        let one = Item::Constant(FieldVariant::Fp(Fp::from(1u8)));
+        // counter that resets every 8 rows so the transition doesn't hold in
+        // rows 7, 15, 23 and 31
+        let column = (0..n).map(|i| Fp::from(i as u64 % 8)).collect::<Vec<Fp>>();
+        let transition = 0.next() - 0.curr() - one;
+
+        assert!(holds_in_all_rows(
+            &transition_constraint(transition.clone(), n, 0, Exemptions::new().every(8, 7)),
+            &column
+        ));
+        assert!(!holds_in_all_rows(
+            &transition_constraint(transition.clone(), n, 1, Exemptions::new()),
+            &column
+        ));
+        assert!(!holds_in_all_rows(
+            &transition_constraint(transition, n, 1, Exemptions::new().every(8, 6)),
+            &column
+        ));
+    }
+}
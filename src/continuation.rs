@@ -0,0 +1,273 @@
+//! Continuations
+//!
+//! A long VM execution can be proven as a chain of segments. Each segment is
+//! a separate [`Stark`] statement whose public inputs include the state of the
+//! VM (registers, memory commitment etc.) at the start and end of the segment.
+//! The segment's AIR must constrain the first and last rows of its trace to
+//! these states with boundary constraints. The chain is valid if every segment
+//! proof is valid and the final state of each segment is the initial state of
+//! the next. [`verify_chain`] checks both.
+//!
+//! [`MachineState`] is a snapshot of a VM that can be used as the state. Its
+//! registers are asserted in the first or last row of a segment with
+//! [`state_boundary_constraints`] and its memory is committed to with a Merkle
+//! tree. Snapshots can also be serialized to pause trace generation and resume
+//! it later.
+use crate::channel::VerifierChannelArtifacts;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::constraints::ExecutionTraceColumn;
+use crate::constraints::Hint;
+use crate::hash::Digest;
+use crate::merkle::MatrixMerkleTree;
+use crate::merkle::MerkleTreeDomain;
+use crate::prover::ProverError;
+use crate::stark::Stark;
+use crate::utils::FieldVariant;
+use crate::verifier::VerificationError;
+use crate::Proof;
+use crate::ProofOptions;
+use crate::StarkExtensionOf;
+use alloc::string::String;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use core::fmt::Debug;
+use ministark_gpu::GpuFftField;
+use ministark_gpu::GpuField;
+use snafu::Snafu;
+
+/// A segment of a longer execution
+pub trait Segment: Stark {
+    /// State of the VM between segments e.g. its registers and a commitment to
+    /// its memory
+    type State: Debug + PartialEq;
+
+    /// State of the VM at the start of the segment. Must be constrained to the
+    /// first row of the trace by the AIR.
+    fn initial_state(&self) -> Self::State;
+
+    /// State of the VM at the end of the segment. Must be constrained to the
+    /// last row of the trace by the AIR.
+    fn final_state(&self) -> Self::State;
+}
+
+/// Snapshot of a VM i.e. its registers and memory
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MachineState<F: Field> {
+    pub registers: Vec<F>,
+    pub memory: Vec<F>,
+}
+
+/// Commitment to a [`MachineState`]. Compact enough to be part of the public
+/// inputs of a segment.
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct StateCommitment<F: Field, D: Digest> {
+    pub registers: Vec<F>,
+    pub memory_root: D,
+}
+
+impl<F: GpuField + Field> MachineState<F> {
+    pub const fn new(registers: Vec<F>, memory: Vec<F>) -> Self {
+        Self { registers, memory }
+    }
+
+    /// Returns a Merkle tree with a leaf for every memory cell. Memory is
+    /// padded with zeros to a power of two cells (and at least two) i.e.
+    /// unused cells are treated as zero initialized.
+    pub fn memory_tree<M: MatrixMerkleTree<F>>(&self) -> M {
+        let num_cells = self.memory.len().next_power_of_two().max(2);
+        let mut cells = self.memory.clone();
+        cells.resize(num_cells, F::zero());
+        // cells are hashed straight into leaves rather than copied into a matrix
+        let rows = cells.chunks(1).collect::<Vec<_>>();
+        M::from_rows(&rows, MerkleTreeDomain::MachineMemory).unwrap()
+    }
+
+    pub fn commit<M: MatrixMerkleTree<F>>(&self) -> StateCommitment<F, M::Root> {
+        StateCommitment {
+            registers: self.registers.clone(),
+            memory_root: self.memory_tree::<M>().root(),
+        }
+    }
+
+    /// Returns hints holding the register values starting at index
+    /// `first_hint`. Used with [`state_boundary_constraints`].
+    pub fn register_hints<Fq: Field + From<F>>(&self, first_hint: usize) -> Vec<(usize, Fq)> {
+        register_hints(&self.registers, first_hint)
+    }
+}
+
+impl<F: Field, D: Digest> StateCommitment<F, D> {
+    /// See [`MachineState::register_hints`]
+    pub fn register_hints<Fq: Field + From<F>>(&self, first_hint: usize) -> Vec<(usize, Fq)> {
+        register_hints(&self.registers, first_hint)
+    }
+}
+
+fn register_hints<F: Field, Fq: Field + From<F>>(
+    registers: &[F],
+    first_hint: usize,
+) -> Vec<(usize, Fq)> {
+    let values = registers.iter().map(|&register| Fq::from(register));
+    (first_hint..).zip(values).collect()
+}
+
+/// Row of a trace that a [`MachineState`] is asserted in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateRow {
+    First,
+    Last,
+}
+
+/// Returns constraints asserting that register `i` of a state is in
+/// `register_columns[i]` in the given row. The register values are read from
+/// hints starting at `first_hint` (see [`MachineState::register_hints`]) since
+/// constraints can't depend on the public inputs.
+pub fn state_boundary_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    register_columns: &[impl ExecutionTraceColumn],
+    first_hint: usize,
+    row: StateRow,
+    trace_len: usize,
+) -> Vec<Constraint<FieldVariant<Fp, Fq>>> {
+    use AlgebraicItem::*;
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+    let x = match row {
+        StateRow::First => trace_domain.element(0),
+        StateRow::Last => trace_domain.group_gen_inv(),
+    };
+    let x = Constant(FieldVariant::Fp(x));
+    register_columns
+        .iter()
+        .zip(first_hint..)
+        .map(|(column, hint)| {
+            let register = hint.hint();
+            Constraint::from((column.curr() - register) / (X - x))
+        })
+        .collect()
+}
+
+/// Proves each segment of a chain. Segments are proven one after the other.
+// witnesses aren't required to be `Send`
+#[allow(clippy::future_not_send)]
+pub async fn prove_chain<S: Segment>(
+    segments: Vec<(S, S::Witness)>,
+    options: ProofOptions,
+) -> Result<Vec<Proof<S>>, ProverError> {
+    let mut proofs = Vec::with_capacity(segments.len());
+    for (segment, witness) in segments {
+        proofs.push(segment.prove(options, witness).await?);
+    }
+    Ok(proofs)
+}
+
+/// Verifies the proofs of a chain of segments and that the segments link up
+/// i.e. the final state of each segment is the initial state of the next.
+/// Returns the verifier artifacts of each segment.
+pub fn verify_chain<S: Segment>(
+    segments: &[S],
+    proofs: Vec<Proof<S>>,
+    required_security_bits: u32,
+) -> Result<Vec<VerifierChannelArtifacts<S::Fq>>, ChainError> {
+    if segments.is_empty() {
+        return Err(ChainError::EmptyChain);
+    }
+    if segments.len() != proofs.len() {
+        return Err(ChainError::NumProofsMismatch {
+            expected: segments.len(),
+            actual: proofs.len(),
+        });
+    }
+
+    // check the links first since it's much cheaper than verifying proofs
+    for (i, [segment, next]) in segments.array_windows().enumerate() {
+        let final_state = segment.final_state();
+        let initial_state = next.initial_state();
+        if final_state != initial_state {
+            return Err(ChainError::BrokenLink {
+                segment: i,
+                final_state: format!("{final_state:?}"),
+                initial_state: format!("{initial_state:?}"),
+            });
+        }
+    }
+
+    segments
+        .iter()
+        .zip(proofs)
+        .enumerate()
+        .map(|(i, (segment, proof))| {
+            segment
+                .verify(proof, required_security_bits)
+                .map_err(|source| ChainError::InvalidSegment { segment: i, source })
+        })
+        .collect()
+}
+
+/// Errors that are returned when verifying a chain of segment proofs
+#[derive(Debug, Snafu)]
+pub enum ChainError {
+    #[snafu(display("chain has no segments"))]
+    EmptyChain,
+    #[snafu(display("chain has {expected} segments but {actual} proofs"))]
+    NumProofsMismatch { expected: usize, actual: usize },
+    #[snafu(display(
+        "final state {final_state} of segment {segment} isn't the initial state {initial_state} of the next segment"
+    ))]
+    BrokenLink {
+        segment: usize,
+        final_state: String,
+        initial_state: String,
+    },
+    #[snafu(display("proof of segment {segment} is invalid: {source}"))]
+    InvalidSegment {
+        segment: usize,
+        source: VerificationError,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MachineState;
+    use crate::hash::Sha256HashFn;
+    use crate::merkle::MatrixMerkleTreeImpl;
+    use ark_serialize::CanonicalDeserialize;
+    use ark_serialize::CanonicalSerialize;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+
+    #[test]
+    fn machine_state_roundtrip() {
+        let state = MachineState::new(vec![Fp::from(7u8), Fp::from(3u8)], vec![Fp::from(1u8)]);
+
+        let mut bytes = Vec::new();
+        state.serialize_compressed(&mut bytes).unwrap();
+
+        assert_eq!(
+            state,
+            MachineState::deserialize_compressed(&*bytes).unwrap()
+        );
+    }
+
+    #[test]
+    fn memory_root_pads_with_zeros() {
+        let registers = vec![Fp::from(7u8)];
+        let memory = vec![Fp::from(1u8), Fp::from(2u8), Fp::from(3u8)];
+        let mut padded_memory = memory.clone();
+        padded_memory.resize(4, Fp::from(0u8));
+        let mut other_memory = memory.clone();
+        other_memory[1] = Fp::from(5u8);
+
+        let commitment = MachineState::new(registers.clone(), memory).commit::<MerkleTree>();
+        let padded = MachineState::new(registers.clone(), padded_memory).commit::<MerkleTree>();
+        let other = MachineState::new(registers, other_memory).commit::<MerkleTree>();
+
+        assert_eq!(commitment.memory_root, padded.memory_root);
+        assert_ne!(commitment.memory_root, other.memory_root);
+    }
+}
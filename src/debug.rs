@@ -1,10 +1,38 @@
 //! Tools for debugging issues that may arrive with AIR or STARK
 //! TODO:
 
+use crate::air::AirConfig;
 use crate::challenges::Challenges;
 use crate::hints::Hints;
+use crate::prover::prove_trace;
+use crate::prover::ProverError;
 use crate::stark::Stark;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::Air;
 use crate::Matrix;
+use crate::Proof;
+use crate::ProofOptions;
+use crate::StarkExtensionOf;
+use crate::Trace;
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_ff::PrimeField;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ministark_gpu::GpuFftField;
+use snafu::Snafu;
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
 
 /// Checks AIR constraints are valid
 pub const fn default_validate_constraints<S: Stark>(
@@ -126,3 +154,479 @@ pub const fn default_validate_constraints<S: Stark>(
     // }
     // ```
 }
+
+/// Prover that deliberately deviates from the protocol
+///
+/// Intended for testing that the constraints of an AIR reject invalid
+/// witnesses end-to-end. Every proof generated by this prover should fail
+/// verification. A proof that verifies indicates an under-constrained AIR.
+pub struct DishonestProver<'a, S: Stark> {
+    stark: &'a S,
+    base_trace_tampering: Vec<(usize, usize, S::Fp)>,
+    skipped_constraints: BTreeSet<usize>,
+}
+
+impl<'a, S: Stark> DishonestProver<'a, S> {
+    pub const fn new(stark: &'a S) -> Self {
+        Self {
+            stark,
+            base_trace_tampering: Vec::new(),
+            skipped_constraints: BTreeSet::new(),
+        }
+    }
+
+    /// Overwrites a cell of the base trace before it is committed to. The
+    /// extension trace is still built from the untampered base trace.
+    #[must_use]
+    pub fn tamper_base_trace(mut self, row: usize, col: usize, value: S::Fp) -> Self {
+        self.base_trace_tampering.push((row, col, value));
+        self
+    }
+
+    /// Leaves the constraint at `index` (of [`AirConfig::constraints`]) out of
+    /// the composition polynomial. The verifier still checks it.
+    #[must_use]
+    pub fn skip_constraint(mut self, index: usize) -> Self {
+        self.skipped_constraints.insert(index);
+        self
+    }
+
+    /// Generates a dishonest proof
+    ///
+    /// # Panics
+    ///
+    /// Panics if a tampered cell or skipped constraint is out of bounds.
+    pub fn prove(
+        &self,
+        options: ProofOptions,
+        witness: S::Witness,
+    ) -> Result<Proof<S>, ProverError> {
+        let trace = self.stark.generate_trace(witness);
+        let trace_len = trace.len();
+
+        let mut base_columns = trace.base_columns().clone();
+        for &(row, col, value) in &self.base_trace_tampering {
+            base_columns.0[col][row] = value;
+        }
+
+        let mut constraints = S::AirConfig::constraints(trace_len);
+        for &index in self.skipped_constraints.iter().rev() {
+            constraints.remove(index);
+        }
+
+        let public_inputs = self.stark.get_public_inputs();
+        let air = Air::with_constraints(trace_len, public_inputs, options, constraints);
+        let trace = TamperedTrace {
+            trace,
+            base_columns,
+        };
+        prove_trace(self.stark, &air, trace)
+    }
+}
+
+struct TamperedTrace<T: Trace> {
+    trace: T,
+    base_columns: Matrix<T::Fp>,
+}
+
+impl<T: Trace> Trace for TamperedTrace<T> {
+    type Fp = T::Fp;
+    type Fq = T::Fq;
+
+    fn base_columns(&self) -> &Matrix<T::Fp> {
+        &self.base_columns
+    }
+
+    fn build_extension_columns(&self, challenges: &Challenges<T::Fq>) -> Option<Matrix<T::Fq>> {
+        self.trace.build_extension_columns(challenges)
+    }
+}
+
+/// Execution trace columns that can be exported to (and imported from) CSV
+///
+/// Intended for inspecting large traces in a spreadsheet or dataframe library
+/// when debugging constraint failures. Each row of the CSV is a row of the
+/// trace. Base columns are named `base_{i}` and extension columns are named
+/// `extension_{i}`. Extension field elements take up one CSV column per base
+/// field coefficient i.e. `extension_{i}_{j}` for the `j`-th coefficient.
+/// Field elements are written as canonical decimal integers.
+pub struct TraceTable<Fp, Fq> {
+    pub base_columns: Matrix<Fp>,
+    pub extension_columns: Option<Matrix<Fq>>,
+}
+
+impl<Fp: Field, Fq: Field> TraceTable<Fp, Fq> {
+    pub const fn new(base_columns: Matrix<Fp>, extension_columns: Option<Matrix<Fq>>) -> Self {
+        Self {
+            base_columns,
+            extension_columns,
+        }
+    }
+
+    /// Collects the base columns and (if any) the extension columns of a trace
+    pub fn from_trace<T: Trace<Fp = Fp, Fq = Fq>>(trace: &T, challenges: &Challenges<Fq>) -> Self {
+        Self::new(
+            trace.base_columns().clone(),
+            trace.build_extension_columns(challenges),
+        )
+    }
+
+    /// Returns the number of rows in the table
+    pub fn num_rows(&self) -> usize {
+        self.base_columns.num_rows()
+    }
+
+    /// Writes the table as CSV to the file at `path`
+    pub fn to_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_csv(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Reads a table from the CSV file at `path`
+    pub fn from_csv(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read_csv(BufReader::new(File::open(path)?))
+    }
+
+    pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        let base_degree = usize::try_from(Fp::extension_degree()).unwrap();
+        let extension_degree = usize::try_from(Fq::extension_degree()).unwrap();
+        let num_base_columns = self.base_columns.num_cols();
+        let num_extension_columns = self.extension_columns.as_ref().map_or(0, Matrix::num_cols);
+
+        let mut header = Vec::new();
+        header.extend(column_names("base", num_base_columns, base_degree));
+        header.extend(column_names(
+            "extension",
+            num_extension_columns,
+            extension_degree,
+        ));
+        writeln!(writer, "{}", header.join(","))?;
+
+        for row in 0..self.num_rows() {
+            let mut cells = Vec::new();
+            for column in &self.base_columns.0 {
+                cells.extend(coefficients(&column[row]));
+            }
+            for column in self.extension_columns.iter().flat_map(|m| &m.0) {
+                cells.extend(coefficients(&column[row]));
+            }
+            writeln!(writer, "{}", cells.join(","))?;
+        }
+        Ok(())
+    }
+
+    pub fn read_csv(csv: impl BufRead) -> io::Result<Self> {
+        let base_degree = usize::try_from(Fp::extension_degree()).unwrap();
+        let extension_degree = usize::try_from(Fq::extension_degree()).unwrap();
+        let mut lines = csv.lines();
+        let header_line = lines
+            .next()
+            .ok_or_else(|| invalid_data("missing CSV header"))??;
+        let header = header_line.split(',').collect::<Vec<&str>>();
+        let num_base_cells = header.iter().filter(|n| n.starts_with("base_")).count();
+        let num_extension_cells = header.len() - num_base_cells;
+        if num_base_cells % base_degree != 0 || num_extension_cells % extension_degree != 0 {
+            return Err(invalid_data("CSV header doesn't match the trace fields"));
+        }
+        let num_base_columns = num_base_cells / base_degree;
+        let num_extension_columns = num_extension_cells / extension_degree;
+
+        let mut base_rows = Vec::new();
+        let mut extension_rows = Vec::new();
+        for line in lines {
+            let line = line?;
+            let cells = line.split(',').collect::<Vec<&str>>();
+            if cells.len() != header.len() {
+                return Err(invalid_data("CSV row has the wrong number of cells"));
+            }
+            let (base_cells, extension_cells) = cells.split_at(num_base_cells);
+            base_rows.push(parse_elements(base_cells, base_degree)?);
+            extension_rows.push(parse_elements(extension_cells, extension_degree)?);
+        }
+
+        let base_columns = if num_base_columns == 0 {
+            Matrix::new(Vec::new())
+        } else {
+            Matrix::from_rows(base_rows)
+        };
+        let extension_columns =
+            (num_extension_columns != 0).then(|| Matrix::from_rows(extension_rows));
+        Ok(Self::new(base_columns, extension_columns))
+    }
+}
+
+impl<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>> TraceTable<Fp, Fq> {
+    /// Returns the low degree extension of the table over `lde_domain` in
+    /// natural order i.e. row `i` is the evaluation at the `i`-th element of
+    /// the domain. Use [`Air::lde_domain`] to get the domain used by the
+    /// prover.
+    pub fn lde(&self, lde_domain: Radix2EvaluationDomain<Fp>) -> Self {
+        let trace_domain = Radix2EvaluationDomain::new(self.num_rows()).unwrap();
+        let base_columns = self
+            .base_columns
+            .interpolate(trace_domain)
+            .into_evaluations(lde_domain);
+        let extension_columns = self
+            .extension_columns
+            .as_ref()
+            .map(|m| m.interpolate(trace_domain).into_evaluations(lde_domain));
+        Self::new(base_columns, extension_columns)
+    }
+}
+
+/// Builds trace columns from cell writes of several components e.g. the
+/// chips of a composed AIR
+///
+/// With conflict detection on (the default in debug builds) every cell
+/// remembers the component that wrote it. A component writing a different
+/// value to a cell written by another component is reported with both
+/// component names rather than silently overwriting the value. Writing the
+/// same value twice is allowed. Cells that are never written are zero.
+pub struct TraceBuilder<F: Field> {
+    columns: Vec<GpuVec<F>>,
+    writers: Option<Vec<Vec<Option<&'static str>>>>,
+}
+
+impl<F: Field> TraceBuilder<F> {
+    pub fn new(num_rows: usize, num_cols: usize) -> Self {
+        let columns = (0..num_cols)
+            .map(|_| {
+                let mut column = Vec::new_in(GpuAllocator);
+                column.resize(num_rows, F::zero());
+                column
+            })
+            .collect();
+        Self {
+            columns,
+            writers: None,
+        }
+        .with_conflict_detection(cfg!(debug_assertions))
+    }
+
+    /// Enables or disables tracking which component wrote each cell
+    pub fn with_conflict_detection(mut self, conflict_detection: bool) -> Self {
+        self.writers = conflict_detection.then(|| {
+            let num_rows = self.num_rows();
+            vec![vec![None; num_rows]; self.columns.len()]
+        });
+        self
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.columns.first().map_or(0, Vec::len)
+    }
+
+    /// Writes `value` to the cell at `row` and `col` on behalf of `component`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cell is out of bounds or conflict detection is
+    /// on and another component wrote a different value to the cell
+    pub fn set(
+        &mut self,
+        component: &'static str,
+        row: usize,
+        col: usize,
+        value: F,
+    ) -> Result<(), TraceBuilderError> {
+        let num_rows = self.num_rows();
+        let num_cols = self.columns.len();
+        let cell = (self.columns.get_mut(col))
+            .and_then(|column| column.get_mut(row))
+            .ok_or(TraceBuilderError::OutOfBounds {
+                row,
+                col,
+                num_rows,
+                num_cols,
+            })?;
+        if let Some(writers) = &mut self.writers {
+            let writer = &mut writers[col][row];
+            // equal writes keep the original writer so a later conflicting
+            // write is still reported against it
+            match *writer {
+                Some(previous) if previous != component && *cell != value => {
+                    return Err(TraceBuilderError::CellConflict {
+                        row,
+                        col,
+                        component,
+                        value: value.to_string(),
+                        previous_component: previous,
+                        previous_value: cell.to_string(),
+                    });
+                }
+                Some(_) => {}
+                None => *writer = Some(component),
+            }
+        }
+        *cell = value;
+        Ok(())
+    }
+
+    /// Writes `values` to column `col` starting at `first_row` (see
+    /// [`Self::set`])
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first write that fails
+    pub fn set_column(
+        &mut self,
+        component: &'static str,
+        col: usize,
+        first_row: usize,
+        values: &[F],
+    ) -> Result<(), TraceBuilderError> {
+        for (i, &value) in values.iter().enumerate() {
+            self.set(component, first_row + i, col, value)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the component that wrote the cell at `row` and `col`. Always
+    /// [`None`] if conflict detection is off.
+    pub fn writer(&self, row: usize, col: usize) -> Option<&'static str> {
+        *self.writers.as_ref()?.get(col)?.get(row)?
+    }
+
+    pub fn build(self) -> Matrix<F> {
+        Matrix::new(self.columns)
+    }
+}
+
+/// Errors returned while writing cells of a [`TraceBuilder`]
+#[derive(Debug, Snafu, Clone, PartialEq, Eq)]
+pub enum TraceBuilderError {
+    #[snafu(display(
+        "cell at row {row} column {col} is outside the trace of {num_rows} rows and {num_cols} \
+         columns"
+    ))]
+    OutOfBounds {
+        row: usize,
+        col: usize,
+        num_rows: usize,
+        num_cols: usize,
+    },
+    #[snafu(display(
+        "{component} wrote {value} to row {row} column {col} but {previous_component} already \
+         wrote {previous_value}"
+    ))]
+    CellConflict {
+        row: usize,
+        col: usize,
+        component: &'static str,
+        value: String,
+        previous_component: &'static str,
+        previous_value: String,
+    },
+}
+
+fn column_names(prefix: &str, num_columns: usize, degree: usize) -> Vec<String> {
+    (0..num_columns)
+        .flat_map(|i| {
+            (0..degree).map(move |j| {
+                if degree == 1 {
+                    format!("{prefix}_{i}")
+                } else {
+                    format!("{prefix}_{i}_{j}")
+                }
+            })
+        })
+        .collect()
+}
+
+fn coefficients<F: Field>(element: &F) -> impl Iterator<Item = String> + '_ {
+    element
+        .to_base_prime_field_elements()
+        .map(|coeff| coeff.into_bigint().to_string())
+}
+
+fn parse_elements<F: Field>(cells: &[&str], degree: usize) -> io::Result<Vec<F>> {
+    cells
+        .chunks(degree)
+        .map(|chunk| {
+            let coeffs = chunk
+                .iter()
+                .map(|cell| {
+                    cell.trim()
+                        .parse::<F::BasePrimeField>()
+                        .map_err(|_| invalid_data("CSV cell is not a field element"))
+                })
+                .collect::<io::Result<Vec<F::BasePrimeField>>>()?;
+            F::from_base_prime_field_elems(&coeffs)
+                .ok_or_else(|| invalid_data("CSV cell is not a field element"))
+        })
+        .collect()
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TraceBuilder;
+    use super::TraceBuilderError;
+    use super::TraceTable;
+    use crate::utils::tests::gen_fib_matrix;
+    use crate::Matrix;
+    use ark_ff::UniformRand;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
+
+    #[test]
+    fn csv_roundtrip() {
+        let mut rng = ark_std::test_rng();
+        let base_columns = gen_fib_matrix::<Fp>(16);
+        let extension_rows = (0..16)
+            .map(|_| vec![Fq3::rand(&mut rng), Fq3::rand(&mut rng)])
+            .collect();
+        let table = TraceTable::new(base_columns, Some(Matrix::from_rows(extension_rows)));
+        let mut csv = Vec::new();
+
+        table.write_csv(&mut csv).unwrap();
+        let imported = TraceTable::<Fp, Fq3>::read_csv(&*csv).unwrap();
+
+        assert!(String::from_utf8(csv)
+            .unwrap()
+            .starts_with("base_0,base_1,extension_0_0,extension_0_1,extension_0_2,extension_1_0"));
+        assert_eq!(table.base_columns.0, imported.base_columns.0);
+        assert_eq!(
+            table.extension_columns.unwrap().0,
+            imported.extension_columns.unwrap().0
+        );
+    }
+
+    #[test]
+    fn conflicting_cell_writes_are_reported() {
+        let mut builder = TraceBuilder::<Fp>::new(4, 2).with_conflict_detection(true);
+        builder.set("cpu", 1, 0, Fp::from(7u8)).unwrap();
+        builder.set("memory", 1, 0, Fp::from(7u8)).unwrap();
+
+        let err = builder.set("memory", 1, 0, Fp::from(8u8)).unwrap_err();
+
+        assert_eq!(
+            TraceBuilderError::CellConflict {
+                row: 1,
+                col: 0,
+                component: "memory",
+                value: "8".into(),
+                previous_component: "cpu",
+                previous_value: "7".into(),
+            },
+            err
+        );
+        assert_eq!(Some("cpu"), builder.writer(1, 0));
+        assert_eq!(Fp::from(7u8), builder.build().0[0][1]);
+    }
+
+    #[test]
+    fn writes_without_conflict_detection_overwrite() {
+        let mut builder = TraceBuilder::<Fp>::new(4, 2).with_conflict_detection(false);
+        builder.set_column("cpu", 1, 0, &[Fp::from(1u8); 4]).unwrap();
+        builder.set("memory", 3, 1, Fp::from(2u8)).unwrap();
+
+        assert!(builder.set("memory", 4, 1, Fp::from(2u8)).is_err());
+        assert_eq!(None, builder.writer(3, 1));
+        assert_eq!(Fp::from(2u8), builder.build().0[1][3]);
+    }
+}
@@ -0,0 +1,75 @@
+//! Domain sizing
+//!
+//! All domains (trace, constraint evaluation, LDE and FRI layers) are radix-2.
+//! The prover, verifier and AIR size them with these functions so they agree
+//! on edge cases such as tiny traces and domains that exceed the 2-adicity of
+//! the field.
+use ark_ff::FftField;
+
+/// Rounds the input value up to the nearest power of two. Zero is rounded up
+/// to one.
+pub const fn ceil_power_of_two(value: usize) -> usize {
+    if value.is_power_of_two() {
+        value
+    } else {
+        value.next_power_of_two()
+    }
+}
+
+/// Returns the size of the smallest domain that can hold the coefficients of a
+/// polynomial of degree `degree` i.e. the smallest power of two greater than
+/// `degree`
+pub const fn coeffs_domain_size(degree: usize) -> usize {
+    ceil_power_of_two(degree + 1)
+}
+
+/// Returns the size of the largest power of two evaluation domain of the
+/// field. The LDE domain is limited by the field's 2-adicity.
+pub const fn max_radix2_domain_size<F: FftField>() -> usize {
+    if F::TWO_ADICITY as usize >= usize::BITS as usize {
+        usize::MAX
+    } else {
+        1 << F::TWO_ADICITY
+    }
+}
+
+/// Returns the size of the LDE domain of a trace. Returns [`None`] if the
+/// trace length isn't a power of two or the LDE domain doesn't fit in the
+/// field (see [`max_radix2_domain_size`]).
+pub fn lde_domain_size<F: FftField>(trace_len: usize, lde_blowup_factor: usize) -> Option<usize> {
+    if !trace_len.is_power_of_two() || !lde_blowup_factor.is_power_of_two() {
+        return None;
+    }
+    trace_len
+        .checked_mul(lde_blowup_factor)
+        .filter(|&size| size <= max_radix2_domain_size::<F>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ceil_power_of_two;
+    use super::coeffs_domain_size;
+    use super::lde_domain_size;
+    use super::max_radix2_domain_size;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    #[test]
+    fn sizes_at_small_values() {
+        assert_eq!(
+            [1, 1, 2, 4, 4, 8],
+            [0, 1, 2, 3, 4, 5].map(ceil_power_of_two)
+        );
+        assert_eq!([1, 2, 4, 4, 8], [0, 1, 2, 3, 4].map(coeffs_domain_size));
+    }
+
+    #[test]
+    fn lde_domain_size_is_bounded_by_two_adicity() {
+        let max_size = max_radix2_domain_size::<Fp>();
+
+        assert_eq!(Some(32), lde_domain_size::<Fp>(8, 4));
+        assert_eq!(Some(max_size), lde_domain_size::<Fp>(max_size / 2, 2));
+        assert_eq!(None, lde_domain_size::<Fp>(max_size, 2));
+        assert_eq!(None, lde_domain_size::<Fp>(usize::MAX / 2 + 1, 4));
+        assert_eq!(None, lde_domain_size::<Fp>(12, 4));
+    }
+}
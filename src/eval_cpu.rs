@@ -332,7 +332,7 @@ impl<
                     Evals(Box::new(FieldVariant::Fq(lhs)))
                 }
                 (FieldVariant::Fq(lhs), FieldVariant::Fq(rhs)) => {
-                    let (mut lhs, rhs) = if lhs.is_owned() {
+                    let (mut lhs, rhs) = if matches!(lhs, std::borrow::Cow::Owned(_)) {
                         (lhs, rhs)
                     } else {
                         (rhs, lhs)
@@ -341,7 +341,7 @@ impl<
                     Evals(Box::new(FieldVariant::Fq(lhs)))
                 }
                 (FieldVariant::Fp(lhs), FieldVariant::Fp(rhs)) => {
-                    let (mut lhs, rhs) = if lhs.is_owned() {
+                    let (mut lhs, rhs) = if matches!(lhs, std::borrow::Cow::Owned(_)) {
                         (lhs, rhs)
                     } else {
                         (rhs, lhs)
@@ -405,7 +405,7 @@ impl<
                     Evals(Box::new(FieldVariant::Fq(lhs)))
                 }
                 (FieldVariant::Fq(lhs), FieldVariant::Fq(rhs)) => {
-                    let (mut lhs, rhs) = if lhs.is_owned() {
+                    let (mut lhs, rhs) = if matches!(lhs, std::borrow::Cow::Owned(_)) {
                         (lhs, rhs)
                     } else {
                         (rhs, lhs)
@@ -414,7 +414,7 @@ impl<
                     Evals(Box::new(FieldVariant::Fq(lhs)))
                 }
                 (FieldVariant::Fp(lhs), FieldVariant::Fp(rhs)) => {
-                    let (mut lhs, rhs) = if lhs.is_owned() {
+                    let (mut lhs, rhs) = if matches!(lhs, std::borrow::Cow::Owned(_)) {
                         (lhs, rhs)
                     } else {
                         (rhs, lhs)
@@ -0,0 +1,306 @@
+//! C ABI for proving and verifying
+//!
+//! Functions exported over a C ABI can't be generic so the prover and verifier
+//! for a concrete [`Stark`] are exported with [`export_ffi!`] from the crate
+//! that defines it (built as a `cdylib` or `staticlib`). Statements, witnesses
+//! and proofs cross the boundary as byte buffers:
+//!
+//! ```ignore
+//! impl FfiStark for FibClaim {
+//!     fn from_public_inputs(bytes: &[u8]) -> Result<Self, SerializationError> {
+//!         Ok(FibClaim(Fp::deserialize_compressed(bytes)?))
+//!     }
+//!
+//!     fn deserialize_witness(bytes: &[u8]) -> Result<FibWitness, SerializationError> {
+//!         FibWitness::deserialize_compressed(bytes)
+//!     }
+//! }
+//!
+//! ministark::export_ffi!(FibClaim, prove = fib_prove, verify = fib_verify);
+//! ```
+//!
+//! The C declarations of the shared types are in `include/ministark.h`
+//! (generated with `cbindgen --config cbindgen.toml`). Every buffer returned
+//! by this crate must be released with [`ministark_buffer_free`].
+//!
+//! Other languages use the exported functions through their C FFI e.g.
+//! Python's `ctypes` or `cffi`. There are no native Python bindings since AIRs
+//! are defined at compile time by [`crate::air::AirConfig`] so an AIR can't be
+//! prototyped from Python without first adding a runtime AIR.
+use crate::channel::VerifierChannelArtifacts;
+use crate::stark::Stark;
+use crate::utils::block_on;
+use crate::verifier::verify_with_policy;
+use crate::verifier::FoldingPolicy;
+use crate::verifier::VerificationError;
+use crate::Proof;
+use crate::ProofOptions;
+use alloc::vec::Vec;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use ark_serialize::SerializationError;
+use core::panic::AssertUnwindSafe;
+use std::panic::catch_unwind;
+
+/// A [`Stark`] that can be built from serialized public inputs and
+/// witnesses
+pub trait FfiStark: Stark {
+    /// Builds the STARK for the statement encoded in `bytes`
+    fn from_public_inputs(bytes: &[u8]) -> Result<Self, SerializationError>;
+
+    fn deserialize_witness(bytes: &[u8]) -> Result<Self::Witness, SerializationError>;
+}
+
+/// Result of an FFI call
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinistarkStatus {
+    Ok = 0,
+    /// A pointer argument was null or the proof options were invalid
+    InvalidArgument = 1,
+    /// The public inputs, witness or proof couldn't be deserialized
+    DeserializationFailed = 2,
+    ProvingFailed = 3,
+    VerificationFailed = 4,
+    /// The prover or verifier panicked. The panic was caught before crossing
+    /// the ABI.
+    Panic = 5,
+}
+
+/// Byte buffer allocated by this crate
+#[repr(C)]
+#[derive(Debug)]
+pub struct MinistarkBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+    pub capacity: usize,
+}
+
+impl MinistarkBuffer {
+    const fn empty() -> Self {
+        Self {
+            data: core::ptr::null_mut(),
+            len: 0,
+            capacity: 0,
+        }
+    }
+
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        let (data, len, capacity) = bytes.into_raw_parts();
+        Self {
+            data,
+            len,
+            capacity,
+        }
+    }
+}
+
+/// C representation of [`ProofOptions`]
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MinistarkProofOptions {
+    pub num_queries: u8,
+    pub lde_blowup_factor: u8,
+    pub grinding_factor: u8,
+    pub fri_folding_factor: u8,
+    pub fri_max_remainder_coeffs: u8,
+}
+
+impl MinistarkProofOptions {
+    fn to_options(self) -> Option<ProofOptions> {
+        let valid = (ProofOptions::MIN_NUM_QUERIES..=ProofOptions::MAX_NUM_QUERIES)
+            .contains(&self.num_queries)
+            && (ProofOptions::MIN_BLOWUP_FACTOR..=ProofOptions::MAX_BLOWUP_FACTOR)
+                .contains(&self.lde_blowup_factor)
+            && self.lde_blowup_factor.is_power_of_two()
+            && self.grinding_factor <= ProofOptions::MAX_GRINDING_FACTOR
+            && [2, 4, 8, 16].contains(&self.fri_folding_factor)
+            && self.fri_max_remainder_coeffs.is_power_of_two();
+        valid.then(|| {
+            ProofOptions::new(
+                self.num_queries,
+                self.lde_blowup_factor,
+                self.grinding_factor,
+                self.fri_folding_factor,
+                self.fri_max_remainder_coeffs,
+            )
+        })
+    }
+}
+
+/// Releases a buffer returned by this crate. Null buffers are ignored.
+///
+/// # Safety
+///
+/// `buffer` must have been returned by this crate and not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn ministark_buffer_free(buffer: MinistarkBuffer) {
+    if !buffer.data.is_null() {
+        drop(Vec::from_raw_parts(
+            buffer.data,
+            buffer.len,
+            buffer.capacity,
+        ));
+    }
+}
+
+/// Generates a proof and writes its compressed serialization to `proof`.
+/// Used by the functions exported with [`export_ffi!`].
+///
+/// # Safety
+///
+/// `public_inputs` and `witness` must be valid for reads of their lengths and
+/// `proof` must be valid for writes.
+pub unsafe fn prove<S: FfiStark>(
+    public_inputs: *const u8,
+    public_inputs_len: usize,
+    witness: *const u8,
+    witness_len: usize,
+    options: MinistarkProofOptions,
+    proof: *mut MinistarkBuffer,
+) -> MinistarkStatus {
+    if public_inputs.is_null() || witness.is_null() || proof.is_null() {
+        return MinistarkStatus::InvalidArgument;
+    }
+    proof.write(MinistarkBuffer::empty());
+    let public_inputs = core::slice::from_raw_parts(public_inputs, public_inputs_len);
+    let witness = core::slice::from_raw_parts(witness, witness_len);
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let Some(options) = options.to_options() else {
+            return Err(MinistarkStatus::InvalidArgument);
+        };
+        let stark = S::from_public_inputs(public_inputs)
+            .map_err(|_| MinistarkStatus::DeserializationFailed)?;
+        let witness =
+            S::deserialize_witness(witness).map_err(|_| MinistarkStatus::DeserializationFailed)?;
+        let proof =
+            block_on(stark.prove(options, witness)).map_err(|_| MinistarkStatus::ProvingFailed)?;
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        Ok(bytes)
+    }));
+
+    match result {
+        Ok(Ok(bytes)) => {
+            proof.write(MinistarkBuffer::from_vec(bytes));
+            MinistarkStatus::Ok
+        }
+        Ok(Err(status)) => status,
+        Err(_) => MinistarkStatus::Panic,
+    }
+}
+
+/// Verifies the compressed serialization of a proof. Used by the functions
+/// exported with [`export_ffi!`].
+///
+/// # Safety
+///
+/// `public_inputs` and `proof` must be valid for reads of their lengths.
+pub unsafe fn verify<S: FfiStark>(
+    public_inputs: *const u8,
+    public_inputs_len: usize,
+    proof: *const u8,
+    proof_len: usize,
+    required_security_bits: u32,
+) -> MinistarkStatus {
+    if public_inputs.is_null() || proof.is_null() {
+        return MinistarkStatus::InvalidArgument;
+    }
+    let public_inputs = core::slice::from_raw_parts(public_inputs, public_inputs_len);
+    let proof = core::slice::from_raw_parts(proof, proof_len);
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let stark = S::from_public_inputs(public_inputs)
+            .map_err(|_| MinistarkStatus::DeserializationFailed)?;
+        let proof = Proof::<S>::deserialize_compressed(proof)
+            .map_err(|_| MinistarkStatus::DeserializationFailed)?;
+        stark
+            .verify(proof, required_security_bits)
+            .map_err(|_| MinistarkStatus::VerificationFailed)?;
+        Ok(())
+    }));
+
+    match result {
+        Ok(Ok(())) => MinistarkStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => MinistarkStatus::Panic,
+    }
+}
+
+/// Verifies the compressed serialization of a proof of the statement encoded
+/// in `public_input_bytes`. The proof's folding schedule has to be accepted by
+/// `policy` and its security level has to be at least
+/// `required_security_bits`.
+///
+/// Malformed bytes are reported as errors rather than panics which makes this
+/// safe to call on untrusted input e.g. from a fuzzer.
+pub fn verify_bytes<S: FfiStark>(
+    proof_bytes: &[u8],
+    public_input_bytes: &[u8],
+    policy: &FoldingPolicy,
+    required_security_bits: u32,
+) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+    let stark = S::from_public_inputs(public_input_bytes)
+        .map_err(|error| VerificationError::InvalidEncoding { error })?;
+    let proof = Proof::<S>::deserialize_compressed(proof_bytes)
+        .map_err(|error| VerificationError::InvalidEncoding { error })?;
+    verify_with_policy(&stark, proof, policy, required_security_bits)
+}
+
+/// Exports `extern "C"` prove and verify functions for an [`FfiStark`]
+///
+/// ```c
+/// MinistarkStatus prove(const uint8_t *public_inputs, size_t public_inputs_len,
+///                       const uint8_t *witness, size_t witness_len,
+///                       MinistarkProofOptions options, MinistarkBuffer *proof);
+/// MinistarkStatus verify(const uint8_t *public_inputs, size_t public_inputs_len,
+///                        const uint8_t *proof, size_t proof_len,
+///                        uint32_t required_security_bits);
+/// ```
+#[macro_export]
+macro_rules! export_ffi {
+    ($stark:ty,prove = $prove:ident,verify = $verify:ident) => {
+        /// # Safety
+        ///
+        /// See [`ministark::ffi::prove`]
+        #[no_mangle]
+        pub unsafe extern "C" fn $prove(
+            public_inputs: *const u8,
+            public_inputs_len: usize,
+            witness: *const u8,
+            witness_len: usize,
+            options: $crate::ffi::MinistarkProofOptions,
+            proof: *mut $crate::ffi::MinistarkBuffer,
+        ) -> $crate::ffi::MinistarkStatus {
+            $crate::ffi::prove::<$stark>(
+                public_inputs,
+                public_inputs_len,
+                witness,
+                witness_len,
+                options,
+                proof,
+            )
+        }
+
+        /// # Safety
+        ///
+        /// See [`ministark::ffi::verify`]
+        #[no_mangle]
+        pub unsafe extern "C" fn $verify(
+            public_inputs: *const u8,
+            public_inputs_len: usize,
+            proof: *const u8,
+            proof_len: usize,
+            required_security_bits: u32,
+        ) -> $crate::ffi::MinistarkStatus {
+            $crate::ffi::verify::<$stark>(
+                public_inputs,
+                public_inputs_len,
+                proof,
+                proof_len,
+                required_security_bits,
+            )
+        }
+    };
+}
@@ -0,0 +1,116 @@
+//! Shared fixtures for unit tests
+//!
+//! Low-degree instances over the 64-bit field
+//! `p = 18446744069414584321` and a prover channel that drives the public
+//! coin the same way the verifier does. [`run_ldt`] runs an instance through
+//! the low-degree test selected by the options so the same instance can be
+//! checked with FRI, STIR and the direct test.
+use crate::fri::FriOptions;
+use crate::fri::ProverChannel;
+use crate::fri::VerificationError;
+use crate::hash::HashFn;
+use crate::hash::Sha256HashFn;
+use crate::ldt::LdtProver;
+use crate::ldt::LdtVerifier;
+use crate::ldt::LowDegreeTest;
+use crate::ldt::LowDegreeTestVerifier;
+use crate::merkle::MatrixMerkleTreeImpl;
+use crate::random::DrawTag;
+use crate::random::PublicCoin;
+use crate::random::PublicCoinImpl;
+use crate::random::ReseedTag;
+use crate::rs_code;
+use crate::utils::GpuAllocator;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::DenseUVPolynomial;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+pub type TestCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+
+pub type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+
+pub struct TestChannel(pub TestCoin);
+
+impl ProverChannel for TestChannel {
+    type Digest = <Sha256HashFn as HashFn>::Digest;
+    type Field = Fp;
+
+    fn commit_fri_layer(&mut self, layer_cap: Vec<Self::Digest>) {
+        self.0.reseed_with_cap(ReseedTag::FriLayer, &layer_cap);
+    }
+
+    fn commit_remainder(&mut self, remainder_coeffs: &[Fp]) {
+        self.0
+            .reseed_with_field_element_vector(ReseedTag::FriRemainder, remainder_coeffs);
+    }
+
+    fn draw_fri_alpha(&mut self) -> Fp {
+        self.0.draw_fq(DrawTag::FriAlpha)
+    }
+
+    fn commit_ood_eval(&mut self, ood_eval: Fp) {
+        self.0
+            .reseed_with_field_elements(ReseedTag::StirOodEval, &[ood_eval]);
+    }
+
+    fn draw_challenge(&mut self, tag: DrawTag) -> Fp {
+        self.0.draw_fq(tag)
+    }
+
+    fn draw_positions(
+        &mut self,
+        tag: DrawTag,
+        max_n: usize,
+        domain_size: usize,
+    ) -> BTreeSet<usize> {
+        self.0.draw_positions(tag, max_n, domain_size)
+    }
+}
+
+/// Returns a random polynomial of degree `degree` generated from `seed` and
+/// its codeword over the coset `GENERATOR * <ω>` with blowup factor
+/// `blowup_factor` (see [`rs_code::encode`])
+pub fn random_codeword(
+    degree: usize,
+    blowup_factor: usize,
+    seed: u64,
+) -> (DensePolynomial<Fp>, Vec<Fp>) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let poly = DensePolynomial::rand(degree, &mut rng);
+    let codeword = rs_code::encode(&poly.coeffs, blowup_factor, Fp::GENERATOR);
+    (poly, codeword)
+}
+
+/// Proves that `codeword` has degree at most `degree` with the low-degree
+/// test selected by `options` and verifies the proof with `evaluations` as
+/// the values at `positions` against the degree bound `max_poly_degree`
+///
+/// # Panics
+///
+/// Panics if the prover rejects the codeword
+pub fn run_ldt(
+    options: FriOptions,
+    codeword: &[Fp],
+    degree: usize,
+    max_poly_degree: usize,
+    positions: &[usize],
+    evaluations: &[Fp],
+) -> Result<(), VerificationError> {
+    let seed = Sha256HashFn::hash([]);
+    let mut prover = LdtProver::<Fp, _, MerkleTree>::new(options, codeword.len());
+    let mut channel = TestChannel(TestCoin::new(seed.clone()));
+    prover
+        .commit(&mut channel, codeword.to_vec_in(GpuAllocator), degree)
+        .unwrap();
+    let proof = prover.into_proof(positions, &[]).unwrap();
+
+    let mut public_coin = TestCoin::new(seed);
+    let mut verifier = LdtVerifier::new(&mut public_coin, options, proof, max_poly_degree)?;
+    let claim = verifier.verify_head(positions, evaluations)?;
+    verifier.verify_tail_layers(claim, [])
+}
@@ -1,11 +1,29 @@
+use crate::domain::coeffs_domain_size;
 use crate::hash::Digest;
 use crate::merkle;
 use crate::merkle::MatrixMerkleTree;
 use crate::merkle::MerkleTree;
+use crate::merkle::MerkleTreeDomain;
+use crate::positions::derive_layer_positions;
+// re-exported for compatibility. See [`crate::positions`]
+pub use crate::positions::fold_positions;
+pub use crate::positions::get_query_values;
+use crate::positions::leaf_rows;
+use crate::positions::leaf_size;
+use crate::positions::LayerPositions;
+use crate::random::DrawTag;
 use crate::random::PublicCoin;
+use crate::random::ReseedTag;
+use crate::utils::fft_bit_reversed_columns;
+#[cfg(feature = "gpu")]
+use crate::utils::gpu_min_domain_size;
+use crate::utils::horner_evaluate;
 use crate::utils::GpuAllocator;
 use crate::utils::GpuVec;
 use crate::Matrix;
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::string::ToString;
 use alloc::vec::Vec;
 use ark_ff::FftField;
 use ark_ff::Field;
@@ -29,8 +47,13 @@ use std::marker::PhantomData;
 #[derive(Clone, Copy)]
 pub struct FriOptions {
     folding_factor: usize,
+    folds_per_layer: usize,
+    independent_queries: bool,
+    values_per_leaf: usize,
+    cap_height: u32,
     max_remainder_coeffs: usize,
     blowup_factor: usize,
+    stir_queries: Option<usize>,
 }
 
 impl FriOptions {
@@ -41,25 +64,191 @@ impl FriOptions {
     ) -> Self {
         Self {
             folding_factor,
+            folds_per_layer: 1,
+            independent_queries: false,
+            values_per_leaf: 1,
+            cap_height: 0,
             max_remainder_coeffs,
             blowup_factor,
+            stir_queries: None,
         }
     }
 
-    pub const fn num_layers(&self, mut domain_size: usize) -> usize {
+    /// Folds `folds_per_layer` times between layer commitments. Only every
+    /// `folds_per_layer`-th layer is committed to which reduces the number of
+    /// merkle trees the prover builds and the verifier absorbs. Queries
+    /// decommit rows of [`Self::layer_folding_factor`] evaluations instead and
+    /// the verifier folds each row `folds_per_layer` times.
+    pub const fn with_folds_per_layer(mut self, folds_per_layer: usize) -> Self {
+        self.folds_per_layer = folds_per_layer;
+        self
+    }
+
+    pub const fn folds_per_layer(&self) -> usize {
+        self.folds_per_layer
+    }
+
+    /// Samples the query positions of every layer after the first
+    /// independently (see [`draw_layer_positions`]) instead of folding them
+    /// down from the positions of the first layer. Each layer is opened at the
+    /// positions folded from the previous layer (to check the fold) and at its
+    /// own positions (which are folded into the next layer).
+    pub const fn with_independent_queries(mut self, independent_queries: bool) -> Self {
+        self.independent_queries = independent_queries;
+        self
+    }
+
+    pub const fn independent_queries(&self) -> bool {
+        self.independent_queries
+    }
+
+    /// Commits to `values_per_leaf` consecutive evaluations of a layer in each
+    /// merkle leaf. Leaves always hold the [`Self::layer_folding_factor`]
+    /// evaluations that fold together so smaller values have no effect.
+    /// Larger leaves make the opened values larger but the paths shorter.
+    pub const fn with_values_per_leaf(mut self, values_per_leaf: usize) -> Self {
+        self.values_per_leaf = values_per_leaf;
+        self
+    }
+
+    pub const fn values_per_leaf(&self) -> usize {
+        self.values_per_leaf
+    }
+
+    /// Commits to each layer with the merkle cap of height `cap_height` instead
+    /// of the root (see [`MerkleTree::cap`]). The cap is sent in place of the
+    /// root and every path is `cap_height` nodes shorter.
+    pub const fn with_cap_height(mut self, cap_height: u32) -> Self {
+        self.cap_height = cap_height;
+        self
+    }
+
+    pub const fn cap_height(&self) -> u32 {
+        self.cap_height
+    }
+
+    pub const fn blowup_factor(&self) -> usize {
+        self.blowup_factor
+    }
+
+    pub const fn folding_factor(&self) -> usize {
+        self.folding_factor
+    }
+
+    pub const fn max_remainder_coeffs(&self) -> usize {
+        self.max_remainder_coeffs
+    }
+
+    /// Tests codewords with the experimental STIR protocol (see
+    /// [`crate::stir`]) instead of FRI. The first round is queried
+    /// `num_queries` times and later rounds fewer times. Each round folds by
+    /// [`Self::layer_folding_factor`] with a single alpha.
+    pub const fn with_stir(mut self, num_queries: usize) -> Self {
+        self.stir_queries = Some(num_queries);
+        self
+    }
+
+    /// Returns the number of queries of the first STIR round if codewords are
+    /// tested with STIR (see [`Self::with_stir`])
+    pub const fn stir_queries(&self) -> Option<usize> {
+        self.stir_queries
+    }
+
+    /// Returns the number of rows of [`Self::layer_folding_factor`]
+    /// evaluations in each leaf of a layer over a domain of size `domain_size`
+    pub const fn rows_per_leaf(&self, domain_size: usize) -> usize {
+        let layer_folding_factor = self.layer_folding_factor();
+        let num_rows = domain_size / layer_folding_factor;
+        leaf_size(self.values_per_leaf / layer_folding_factor, num_rows)
+    }
+
+    /// Returns the number of evaluations of a layer that fold into a single
+    /// evaluation of the next committed layer
+    pub const fn layer_folding_factor(&self) -> usize {
+        let mut layer_folding_factor = 1;
+        let mut i = 0;
+        while i < self.folds_per_layer {
+            layer_folding_factor = self.folding_factor.saturating_mul(layer_folding_factor);
+            i += 1;
+        }
+        layer_folding_factor
+    }
+
+    /// Returns the domain sizes of the committed layers of a codeword over a
+    /// domain of size `domain_size`. The first layer is the codeword itself and
+    /// each layer is [`Self::layer_folding_factor`] times smaller than the one
+    /// before. Layers are committed until the domain is small enough to send
+    /// the remainder (see [`Self::remainder_size`]).
+    pub fn layer_sizes(&self, domain_size: usize) -> impl Iterator<Item = usize> {
+        let max_remainder_size = self.max_remainder_size();
+        self.folded_sizes(domain_size)
+            .take_while(move |&size| size > max_remainder_size)
+    }
+
+    /// Returns the number of committed layers of a codeword over a domain of
+    /// size `domain_size` (see [`Self::layer_sizes`])
+    pub fn num_layers(&self, domain_size: usize) -> usize {
+        self.layer_sizes(domain_size).count()
+    }
+
+    /// Returns the number of layers needed to fold a polynomial of degree
+    /// `degree` into a remainder with at most `max_remainder_coeffs`
+    /// coefficients. This is at most [`Self::num_layers`] for a domain that is
+    /// `blowup_factor` times larger than the degree bound and can be smaller
+    /// if the polynomial's degree is below the bound.
+    pub const fn num_layers_for_degree(&self, mut degree: usize) -> usize {
         let mut num_layers = 0;
-        while domain_size > self.max_remainder_coeffs * self.blowup_factor {
-            domain_size /= self.folding_factor;
+        while degree >= self.max_remainder_coeffs {
+            degree /= self.layer_folding_factor();
             num_layers += 1;
         }
         num_layers
     }
 
-    pub const fn remainder_size(&self, mut domain_size: usize) -> usize {
-        while domain_size > self.max_remainder_coeffs * self.blowup_factor {
-            domain_size /= self.folding_factor;
-        }
-        domain_size
+    /// Returns the domain size of the remainder of a codeword over a domain of
+    /// size `domain_size` after folding all [`Self::layer_sizes`]
+    pub fn remainder_size(&self, domain_size: usize) -> usize {
+        let max_remainder_size = self.max_remainder_size();
+        self.folded_sizes(domain_size)
+            .find(|&size| size <= max_remainder_size)
+            .unwrap()
+    }
+
+    /// Returns the maximum degree of the remainder of a codeword over a domain
+    /// of size `domain_size` after folding all [`Self::layer_sizes`]
+    pub fn final_remainder_degree(&self, domain_size: usize) -> usize {
+        max_remainder_degree(self, self.remainder_size(domain_size))
+    }
+
+    /// Returns the sizes a domain of size `domain_size` shrinks to when it's
+    /// folded between commitments. An invalid folding factor of 1 is treated
+    /// as 2 so the sizes always reach 0.
+    fn folded_sizes(&self, domain_size: usize) -> impl Iterator<Item = usize> {
+        let layer_folding_factor = self.layer_folding_factor().max(2);
+        core::iter::successors(Some(domain_size), move |size| {
+            Some(size / layer_folding_factor)
+        })
+    }
+
+    /// Returns the offsets of the domains of the first `num_layers` committed
+    /// layers followed by the offset of the remainder's domain. Layers are
+    /// folded as if the codeword was evaluated over a subgroup (offset one).
+    /// The offset of each domain is the offset of the previous domain raised
+    /// to [`Self::layer_folding_factor`] (see [`apply_drp`]).
+    pub fn layer_offsets<F: GpuField>(&self, num_layers: usize) -> Vec<F::FftField>
+    where
+        F::FftField: FftField,
+    {
+        let layer_folding_factor = self.layer_folding_factor() as u64;
+        core::iter::successors(Some(F::FftField::ONE), |offset| {
+            Some(offset.pow([layer_folding_factor]))
+        })
+        .take(num_layers + 1)
+        .collect()
+    }
+
+    const fn max_remainder_size(&self) -> usize {
+        self.max_remainder_coeffs * self.blowup_factor
     }
 
     pub const fn domain_offset<F: GpuField>(&self) -> F::FftField
@@ -72,58 +261,133 @@ impl FriOptions {
     }
 }
 
+/// FRI proof
+///
+/// The merkle caps of all layers (see [`FriOptions::with_cap_height`]) are
+/// stored separately from the layer queries. The verifier absorbs the caps in
+/// order and verifies the query paths of each layer against them. STIR proofs
+/// (see [`crate::stir`]) also carry the out of domain evaluation of every
+/// round. FRI proofs have none. FRI proofs carry the domain offset of every
+/// layer and the remainder (see [`FriOptions::layer_offsets`]) so a verifier
+/// with a different folding convention fails explicitly. STIR proofs have
+/// none.
 #[derive(CanonicalSerialize, CanonicalDeserialize, Clone)]
 pub struct FriProof<F: Field, D: Digest, M: MatrixMerkleTree<F>> {
-    pub layers: Vec<LayerProof<F, D, M>>,
+    pub layer_caps: Vec<Vec<D>>,
+    pub layers: Vec<LayerProof<F, M>>,
     pub remainder_coeffs: Vec<F>,
+    pub ood_evals: Vec<F>,
+    pub domain_offsets: Vec<F>,
 }
 
 impl<F: GpuField + Field, D: Digest, M: MatrixMerkleTree<F, Root = D>> FriProof<F, D, M>
 where
     F::FftField: FftField,
 {
-    pub fn new(layers: Vec<LayerProof<F, D, M>>, remainder_coeffs: Vec<F>) -> Self {
+    pub const fn new(
+        layer_caps: Vec<Vec<D>>,
+        layers: Vec<LayerProof<F, M>>,
+        remainder_coeffs: Vec<F>,
+    ) -> Self {
         Self {
+            layer_caps,
             layers,
             remainder_coeffs,
+            ood_evals: Vec::new(),
+            domain_offsets: Vec::new(),
         }
     }
+
+    /// Splits off the queries of all layers after the first `num_layers`
+    /// layers. This caps the size of the proof. The caps and remainder stay
+    /// in the proof since the verifier needs them to rebuild the transcript.
+    /// The tail can be verified separately (see [`FriVerifier::verify_tail`]).
+    pub fn split_tail(&mut self, num_layers: usize) -> FriTailProof<F, M> {
+        let num_layers = num_layers.min(self.layers.len());
+        FriTailProof {
+            layers: self.layers.split_off(num_layers),
+        }
+    }
+
+    /// Re-attaches a tail split off with [`Self::split_tail`]
+    pub fn join_tail(&mut self, tail: FriTailProof<F, M>) {
+        self.layers.extend(tail.layers);
+    }
 }
 
-struct FriLayer<F: GpuField, M: MerkleTree> {
-    merkle_tree: M,
-    evaluations: Matrix<F>,
+/// Queries of the last FRI layers split off a proof with
+/// [`FriProof::split_tail`]
+#[derive(CanonicalSerialize, CanonicalDeserialize, Clone)]
+pub struct FriTailProof<F: Field, M: MatrixMerkleTree<F>> {
+    pub layers: Vec<LayerProof<F, M>>,
 }
 
+impl<F: Field, M: MatrixMerkleTree<F>> Default for FriTailProof<F, M> {
+    fn default() -> Self {
+        Self { layers: Vec::new() }
+    }
+}
+
+/// Evaluations of a FRI layer at the folded query positions. Verifying the
+/// head of a proof (see [`FriVerifier::verify_head`]) returns the claim for
+/// the first layer that's not part of the head.
+#[derive(Debug, Clone)]
+pub struct FriTailClaim<F: GpuField> {
+    /// Index of the layer the evaluations belong to. Equals the number of
+    /// layers if they belong to the remainder.
+    pub layer: usize,
+    pub positions: Vec<usize>,
+    pub evaluations: Vec<F>,
+    pub(crate) domain_size: usize,
+    pub(crate) domain_generator: F::FftField,
+}
+
+pub(crate) struct FriLayer<F: GpuField, M: MerkleTree> {
+    pub(crate) merkle_tree: M,
+    pub(crate) evaluations: Matrix<F>,
+    pub(crate) rows_per_leaf: usize,
+}
+
+/// Rows of a committed layer opened at the query positions and their merkle
+/// proof. The prover decommits a layer into this type and the verifier checks
+/// it against the layer's commitment with [`Self::verify`] so both directions
+/// share one data structure.
 #[derive(CanonicalSerialize, CanonicalDeserialize, Clone)]
-pub struct LayerProof<F: Field, D: Digest, M: MatrixMerkleTree<F>> {
+pub struct LayerProof<F: Field, M: MatrixMerkleTree<F>> {
     pub flattenend_rows: Vec<F>,
     pub merkle_proof: M::Proof,
-    pub commitment: D,
 }
 
-impl<F: GpuField + Field, D: Digest, M: MatrixMerkleTree<F, Root = D>> LayerProof<F, D, M>
+impl<F: GpuField + Field, M: MatrixMerkleTree<F>> LayerProof<F, M>
 where
     F::FftField: FftField,
 {
-    pub fn new<const N: usize>(rows: Vec<[F; N]>, merkle_proof: M::Proof, commitment: D) -> Self {
+    pub fn new<const N: usize>(rows: Vec<[F; N]>, merkle_proof: M::Proof) -> Self {
         Self {
             flattenend_rows: rows.into_iter().flatten().collect(),
             merkle_proof,
-            commitment,
         }
     }
 
-    pub fn verify<const N: usize>(self, positions: &[usize]) -> Result<(), merkle::Error> {
-        let commitment = &self.commitment;
+    /// Verifies the queried rows against the merkle cap of the layer. If
+    /// leaves hold several rows (see [`FriOptions::with_values_per_leaf`]) `N`
+    /// is the number of values per leaf and `positions` are leaf positions.
+    pub fn verify<const N: usize>(
+        self,
+        cap: &[M::Root],
+        domain: MerkleTreeDomain,
+        positions: &[usize],
+    ) -> Result<(), merkle::Error> {
         let (rows, _remainder) = &self.flattenend_rows.as_chunks::<N>();
-        M::verify_rows(commitment, positions, rows, self.merkle_proof)
+        M::verify_rows_against_cap(cap, domain, positions, rows, self.merkle_proof)
     }
 }
 
 pub struct FriProver<F: GpuField, D: Digest, M: MerkleTree> {
     options: FriOptions,
     layers: Vec<FriLayer<F, M>>,
+    // offsets of the layer domains and the remainder domain
+    domain_offsets: Vec<F::FftField>,
     remainder_coeffs: Vec<F>,
     _phantom: PhantomData<D>,
 }
@@ -140,26 +404,47 @@ where
         Self {
             options,
             layers: Vec::new(),
+            domain_offsets: Vec::new(),
             remainder_coeffs: Vec::new(),
             _phantom: PhantomData,
         }
     }
 
-    pub fn into_proof(self, positions: &[usize]) -> FriProof<F, D, M> {
-        let folding_factor = self.options.folding_factor;
-        // let (last_layer, initial_layers) = self.layers.split_last().unwrap();
+    pub const fn num_layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn into_proof(self, positions: &[usize]) -> Result<FriProof<F, D, M>, ProverError> {
+        self.into_proof_with_layer_positions(positions, &[])
+    }
+
+    /// Queries the first layer at `positions` and layer `i + 1` at
+    /// `layer_positions[i]` if layers are sampled independently (see
+    /// [`FriOptions::with_independent_queries`])
+    pub fn into_proof_with_layer_positions(
+        self,
+        positions: &[usize],
+        layer_positions: &[Vec<usize>],
+    ) -> Result<FriProof<F, D, M>, ProverError> {
+        let folding_factor = self.options.layer_folding_factor();
+        let cap_height = self.options.cap_height;
         let mut proof_layers = Vec::new();
         let mut positions = positions.to_vec();
-        for layer in &self.layers {
-            positions = fold_positions(&positions, folding_factor);
-            proof_layers.push(match folding_factor {
-                2 => query_layer::<F, D, M, 2>(layer, &positions),
-                4 => query_layer::<F, D, M, 4>(layer, &positions),
-                6 => query_layer::<F, D, M, 6>(layer, &positions),
-                8 => query_layer::<F, D, M, 8>(layer, &positions),
-                16 => query_layer::<F, D, M, 16>(layer, &positions),
-                _ => unimplemented!("folding factor {folding_factor} is not supported"),
-            });
+        for (i, layer) in self.layers.iter().enumerate() {
+            let independent_positions = independent_positions(&self.options, i, layer_positions)
+                .expect("positions of every layer are given");
+            let LayerPositions { rows, folded } =
+                derive_layer_positions(&positions, independent_positions, folding_factor);
+            let layer_proof = match folding_factor {
+                2 => query_layer::<F, M, 2>(layer, &rows, cap_height),
+                4 => query_layer::<F, M, 4>(layer, &rows, cap_height),
+                8 => query_layer::<F, M, 8>(layer, &rows, cap_height),
+                16 => query_layer::<F, M, 16>(layer, &rows, cap_height),
+                _ => return Err(unsupported_folding(&self.options)),
+            };
+            proof_layers
+                .push(layer_proof.map_err(|source| ProverError::LayerQuery { layer: i, source })?);
+            positions = folded;
         }
 
         // // layers store interlaved evaluations so they need to be un-interleaved
@@ -173,34 +458,82 @@ where
         // j];     }
         // }
 
-        FriProof::new(proof_layers, self.remainder_coeffs)
+        let layer_caps = self.layers.iter().map(|l| l.merkle_tree.cap(cap_height));
+        let domain_offsets = self.domain_offsets.into_iter().map(lift::<F>).collect();
+        Ok(FriProof {
+            domain_offsets,
+            ..FriProof::new(layer_caps.collect(), proof_layers, self.remainder_coeffs)
+        })
     }
 
+    /// Commits to the FRI layers of the bit-reversed `evaluations` of a
+    /// polynomial of degree `degree`. Folding stops as soon as the polynomial
+    /// has fewer than `max_remainder_coeffs` coefficients. This way low degree
+    /// polynomials (common for small test circuits) skip some or all of the
+    /// layers and are sent to the verifier directly.
+    ///
+    /// Only the first layer is interpolated. Layers are folded in coefficient
+    /// form and the next layer is evaluated straight into its rows (see
+    /// [`fft_bit_reversed_columns`]).
     pub fn build_layers(
         &mut self,
         channel: &mut impl ProverChannel<Field = F, Digest = D>,
-        mut evaluations: GpuVec<F>,
-    ) {
-        assert!(self.layers.is_empty());
-        for _ in 0..self.options.num_layers(evaluations.len()) {
-            evaluations = match self.options.folding_factor {
-                2 => self.build_layer::<2>(channel, evaluations),
-                4 => self.build_layer::<4>(channel, evaluations),
-                8 => self.build_layer::<8>(channel, evaluations),
-                16 => self.build_layer::<16>(channel, evaluations),
-                folding_factor => unreachable!("folding factor {folding_factor} not supported"),
+        evaluations: GpuVec<F>,
+        degree: usize,
+    ) -> Result<(), ProverError> {
+        if !self.layers.is_empty() {
+            return Err(ProverError::AlreadyCommitted);
+        }
+        let domain_size = evaluations.len();
+        check_codeword(&self.options, domain_size, degree)?;
+        let num_layers = self.options.num_layers_for_degree(degree);
+        debug_assert!(num_layers <= self.options.num_layers(domain_size));
+        // debug builds check every layer is a codeword of the folded degree so
+        // folding bugs are caught at the first bad layer
+        #[cfg(debug_assertions)]
+        let mut max_layer_degree = codeword_degree(&evaluations);
+        self.domain_offsets = self.options.layer_offsets::<F>(num_layers);
+        let mut layer = LayerInput::Evaluations(evaluations);
+        for i in 0..num_layers {
+            let offset = self.domain_offsets[i];
+            let coeffs = match self.options.layer_folding_factor() {
+                2 => self.build_layer::<2>(channel, layer, offset)?,
+                4 => self.build_layer::<4>(channel, layer, offset)?,
+                8 => self.build_layer::<8>(channel, layer, offset)?,
+                16 => self.build_layer::<16>(channel, layer, offset)?,
+                _ => return Err(unsupported_folding(&self.options)),
+            };
+            #[cfg(debug_assertions)]
+            {
+                max_layer_degree /= self.options.layer_folding_factor();
+                let layer_degree = coeffs.iter().rposition(|c| !c.is_zero()).unwrap_or(0);
+                assert!(
+                    layer_degree <= max_layer_degree,
+                    "folding layer {i} gave a codeword of degree {layer_degree} but expected at \
+                     most degree {max_layer_degree}"
+                );
             }
+            layer = LayerInput::Coeffs(coeffs);
         }
-        self.set_remainder(channel, evaluations);
+        let coeffs = match layer {
+            LayerInput::Evaluations(evaluations) => {
+                let domain = Radix2EvaluationDomain::new_coset(domain_size, self.domain_offsets[0])
+                    .ok_or(ProverError::InvalidDomainSize { size: domain_size })?;
+                ifft_bit_reversed(evaluations, domain).to_vec()
+            }
+            LayerInput::Coeffs(coeffs) => coeffs,
+        };
+        self.set_remainder(channel, &coeffs)
     }
 
-    /// Builds a single layer of the FRI protocol
-    /// Returns the evaluations for the next layer.
+    /// Builds a single layer of the FRI protocol over the domain with offset
+    /// `domain_offset`. Returns the coefficients of the next layer.
     fn build_layer<const N: usize>(
         &mut self,
         channel: &mut impl ProverChannel<Field = F, Digest = D>,
-        evaluations: GpuVec<F>,
-    ) -> GpuVec<F> {
+        layer: LayerInput<F>,
+        domain_offset: F::FftField,
+    ) -> Result<Vec<F>, ProverError> {
         // Each layer requires decommitting to `folding_factor` many evaluations e.g.
         // `folding_factor = 2` decommits to an evaluation for LHS_i and RHS_i
         // (0 ≤ i < n/2) which requires two merkle paths if the evaluations are
@@ -209,64 +542,239 @@ where
         // single merkle path for their decommitment.
 
         // TODO: update docs with bit reversed evals
-        let (cosets, remainder) = evaluations.as_chunks::<N>();
-        assert!(remainder.is_empty());
+        // rows hold the `N = folding_factor^folds_per_layer` evaluations that fold
+        // into one evaluation of the next committed layer
+        let domain_size = layer.len();
+        if !domain_size.is_multiple_of(N) {
+            return Err(ProverError::CodewordNotDivisible {
+                size: domain_size,
+                folding_factor: N,
+            });
+        }
+        let domain = Radix2EvaluationDomain::new_coset(domain_size, domain_offset)
+            .ok_or(ProverError::InvalidDomainSize { size: domain_size })?;
+        let (matrix, evaluations, coeffs) = match layer {
+            LayerInput::Evaluations(evaluations) => {
+                let (rows, _) = evaluations.as_chunks::<N>();
+                (Matrix::from_arrays(rows), Some(evaluations), Vec::new())
+            }
+            LayerInput::Coeffs(coeffs) => (evaluate_rows::<F, N>(&coeffs, domain), None, coeffs),
+        };
+        let merkle_domain = MerkleTreeDomain::FriLayer(self.layers.len());
+        let folding_factor = self.options.folding_factor;
+        let rows_per_leaf = self.options.rows_per_leaf(domain_size);
 
-        let matrix = Matrix::from_arrays(cosets);
-        let merkle_tree = M::from_matrix(&matrix);
-        channel.commit_fri_layer(merkle_tree.root());
+        // The layer's cap must be committed to before alpha can be drawn but
+        // interpolating the evaluations doesn't depend on alpha. Hash the layer while
+        // interpolating so the merkle tree and FFTs overlap.
+        let build_tree = || M::from_matrix_chunked(&matrix, merkle_domain, rows_per_leaf);
+        let interpolate = || evaluations.map(|evaluations| ifft_bit_reversed(evaluations, domain));
+        #[cfg(feature = "parallel")]
+        let (merkle_tree, interpolated) = rayon::join(build_tree, interpolate);
+        #[cfg(not(feature = "parallel"))]
+        let (merkle_tree, interpolated) = (build_tree(), interpolate());
+        channel.commit_fri_layer(merkle_tree.cap(self.options.cap_height));
 
         self.layers.push(FriLayer {
             merkle_tree,
             evaluations: matrix,
+            rows_per_leaf,
         });
 
-        // return the next evaluations
-        apply_drp(
-            evaluations,
-            F::FftField::ONE,
-            channel.draw_fri_alpha(),
-            self.options.folding_factor,
-        )
+        // return the next coefficients. Layers in between commitments are folded
+        // without being committed to (see `FriOptions::with_folds_per_layer`)
+        let coeffs = interpolated.as_deref().unwrap_or(&coeffs);
+        let mut coeffs = fold_coeffs(coeffs, channel.draw_fri_alpha(), folding_factor);
+        for _ in 1..self.options.folds_per_layer {
+            coeffs = fold_coeffs(&coeffs, channel.draw_fri_alpha(), folding_factor);
+        }
+        Ok(coeffs)
     }
 
+    /// Sends the coefficients of the last layer. `coeffs` has as many
+    /// coefficients as the layer's domain size.
     fn set_remainder(
         &mut self,
         channel: &mut impl ProverChannel<Field = F, Digest = D>,
-        mut evaluations: GpuVec<F>,
-    ) {
-        let domain_size = evaluations.len();
-        assert!(domain_size.is_power_of_two());
-        assert!(domain_size <= self.options.max_remainder_coeffs * self.options.blowup_factor);
-        let domain = Radix2EvaluationDomain::new(domain_size).unwrap();
-        bit_reverse(&mut evaluations);
-        let coeffs = domain.ifft(&evaluations);
-        let max_degree = domain_size / self.options.blowup_factor - 1;
+        coeffs: &[F],
+    ) -> Result<(), ProverError> {
+        let max_degree = max_remainder_degree(&self.options, coeffs.len());
         let (remainder_coeffs, zero_coeffs) = coeffs.split_at(max_degree + 1);
-        assert!(zero_coeffs.iter().all(F::is_zero));
+        if !zero_coeffs.iter().all(F::is_zero) {
+            return Err(ProverError::NotLowDegree { max_degree });
+        }
         channel.commit_remainder(remainder_coeffs);
         self.remainder_coeffs = remainder_coeffs.to_vec();
+        Ok(())
     }
 }
 
+/// Input of a FRI layer
+enum LayerInput<F: GpuField> {
+    /// Bit-reversed evaluations of the first layer
+    Evaluations(GpuVec<F>),
+    /// Coefficients of a folded layer
+    Coeffs(Vec<F>),
+}
+
+impl<F: GpuField> LayerInput<F> {
+    const fn len(&self) -> usize {
+        match self {
+            Self::Evaluations(evaluations) => evaluations.len(),
+            Self::Coeffs(coeffs) => coeffs.len(),
+        }
+    }
+}
+
+/// Returns the rows of `N` consecutive bit-reversed evaluations of a
+/// polynomial over `domain`
+fn evaluate_rows<F: GpuField + Field + DomainCoeff<F::FftField>, const N: usize>(
+    coeffs: &[F],
+    domain: Radix2EvaluationDomain<F::FftField>,
+) -> Matrix<F>
+where
+    F::FftField: FftField,
+{
+    #[cfg(feature = "gpu")]
+    if domain.size() >= gpu_min_domain_size() {
+        let evaluations = fft_bit_reversed(coeffs.to_vec_in(GpuAllocator), domain);
+        return Matrix::from_arrays(evaluations.as_chunks::<N>().0);
+    }
+
+    Matrix::new(fft_bit_reversed_columns(domain, coeffs, N))
+}
+
+/// Errors that are returned while generating a FRI proof
+#[derive(Debug, Snafu)]
+pub enum ProverError {
+    #[snafu(display("layers have already been committed to"))]
+    AlreadyCommitted,
+    #[snafu(display(
+        "folding {folds_per_layer} times by {folding_factor} between layers is not supported"
+    ))]
+    FoldingNotSupported {
+        folding_factor: usize,
+        folds_per_layer: usize,
+    },
+    #[snafu(display("{size} evaluations aren't a power of two the field supports"))]
+    InvalidDomainSize { size: usize },
+    #[snafu(display("degree {degree} isn't below the degree bound {degree_bound} of the domain"))]
+    DegreeTooHigh { degree: usize, degree_bound: usize },
+    #[snafu(display("evaluations aren't of a polynomial of degree at most {max_degree}"))]
+    NotLowDegree { max_degree: usize },
+    #[snafu(display("{size} evaluations can't be divided into rows of {folding_factor}"))]
+    CodewordNotDivisible { size: usize, folding_factor: usize },
+    #[snafu(display("queries of layer {layer} can't be opened: {source}"))]
+    LayerQuery { layer: usize, source: merkle::Error },
+}
+
+/// Returns [`ProverError::FoldingNotSupported`] for the folding of `options`
+pub(crate) const fn unsupported_folding(options: &FriOptions) -> ProverError {
+    ProverError::FoldingNotSupported {
+        folding_factor: options.folding_factor,
+        folds_per_layer: options.folds_per_layer,
+    }
+}
+
+/// Checks that `domain_size` evaluations can be tested for degree `degree`
+pub(crate) const fn check_codeword(
+    options: &FriOptions,
+    domain_size: usize,
+    degree: usize,
+) -> Result<(), ProverError> {
+    if !domain_size.is_power_of_two() {
+        return Err(ProverError::InvalidDomainSize { size: domain_size });
+    }
+    let degree_bound = domain_size / options.blowup_factor;
+    if degree >= degree_bound {
+        return Err(ProverError::DegreeTooHigh {
+            degree,
+            degree_bound,
+        });
+    }
+    Ok(())
+}
+
+/// Errors that are returned during verification of a FRI proof
 #[derive(Debug, Snafu)]
 pub enum VerificationError {
-    #[snafu(display("queries do not resolve to their commitment in layer {layer}"))]
-    LayerCommitmentInvalid { layer: usize },
-    #[snafu(display("degree respecting projection is invalid for layer {layer}"))]
-    InvalidDegreeRespectingProjection { layer: usize },
+    #[snafu(display("queries do not resolve to their commitment in layer {layer}: {source}"))]
+    MerklePathInvalid { layer: usize, source: merkle::Error },
+    #[snafu(display(
+        "degree respecting projection is invalid for layer {layer} at position {position}: \
+         expected {expected} but got {got}"
+    ))]
+    FoldMismatch {
+        layer: usize,
+        position: usize,
+        expected: String,
+        got: String,
+    },
     #[snafu(display("the number of query positions does not match the number of evaluations"))]
     NumPositionEvaluationMismatch,
-    #[snafu(display("remainder is invalid"))]
-    RemainderCommitmentInvalid,
-    #[snafu(display("remainder is not a degree {degree} polynomial"))]
-    RemainderDegreeMismatch { degree: usize },
+    #[snafu(display(
+        "remainder does not match the last layer at position {position}: expected \
+         {expected} but got {got}"
+    ))]
+    RemainderMismatch {
+        position: usize,
+        expected: String,
+        got: String,
+    },
+    #[snafu(display("remainder has degree {degree} but can be at most degree {max_degree}"))]
+    RemainderDegreeTooHigh { degree: usize, max_degree: usize },
+    #[snafu(display("expected {expected} layers but the proof contains {actual}"))]
+    NumLayersMismatch { expected: usize, actual: usize },
+    #[snafu(display("expected a cap of {expected} nodes for layer {layer} but got {actual}"))]
+    CapSizeMismatch {
+        layer: usize,
+        expected: usize,
+        actual: usize,
+    },
+    #[snafu(display(
+        "expected {expected} queries in layer {layer} but the proof contains {actual}"
+    ))]
+    NumLayerQueriesMismatch {
+        layer: usize,
+        expected: usize,
+        actual: usize,
+    },
+    #[snafu(display(
+        "folding {folds_per_layer} times by {folding_factor} between layers is not supported"
+    ))]
+    UnsupportedFolding {
+        folding_factor: usize,
+        folds_per_layer: usize,
+    },
+    #[snafu(display("{values_per_leaf} values per leaf isn't a power of two"))]
+    InvalidValuesPerLeaf { values_per_leaf: usize },
     #[snafu(display("{size} can't be divided by {folding_factor} (layer {layer})"))]
     CodewordTruncation {
         size: usize,
         folding_factor: usize,
         layer: usize,
     },
+    #[snafu(display(
+        "expected {expected} out of domain evaluations but the proof contains {actual}"
+    ))]
+    NumOodEvalsMismatch { expected: usize, actual: usize },
+    #[snafu(display("out of domain point of round {round} collides with a query"))]
+    OodPointCollision { round: usize },
+    #[snafu(display(
+        "codewords of degree {max_poly_degree} with blowup {blowup_factor} exceed the 2-adicity of the field"
+    ))]
+    DomainTooLarge {
+        max_poly_degree: usize,
+        blowup_factor: usize,
+    },
+    #[snafu(display("expected {expected} domain offsets but the proof contains {actual}"))]
+    NumDomainOffsetsMismatch { expected: usize, actual: usize },
+    #[snafu(display("domain offset of layer {layer} is {got} but expected {expected}"))]
+    DomainOffsetMismatch {
+        layer: usize,
+        expected: String,
+        got: String,
+    },
 }
 
 /// Fri verifier adapted from Winterfell to match Starkware's verifier
@@ -276,8 +784,15 @@ where
     F::FftField: FftField,
 {
     options: FriOptions,
-    layer_commitments: Vec<D>,
+    layer_commitments: Vec<Vec<D>>,
+    /// Alphas of every fold. Layer `i` is folded with the alphas at
+    /// `i * folds_per_layer..(i + 1) * folds_per_layer`.
     pub layer_alphas: Vec<F>,
+    /// Query positions of every layer after the first if layers are sampled
+    /// independently (see [`Self::draw_layer_positions`])
+    pub layer_positions: Vec<Vec<usize>>,
+    // offsets of the layer domains and the remainder domain
+    layer_offsets: Vec<F::FftField>,
     proof: FriProof<F, D, M>,
     domain: Radix2EvaluationDomain<F::FftField>,
 }
@@ -296,34 +811,99 @@ where
         proof: FriProof<F, D, M>,
         max_poly_degree: usize,
     ) -> Result<Self, VerificationError> {
-        let folding_factor = options.folding_factor;
+        let folding_factor = options.layer_folding_factor();
+        if !matches!(folding_factor, 2 | 4 | 8 | 16) {
+            return Err(VerificationError::UnsupportedFolding {
+                folding_factor: options.folding_factor,
+                folds_per_layer: options.folds_per_layer,
+            });
+        }
+        if !options.values_per_leaf.is_power_of_two() {
+            return Err(VerificationError::InvalidValuesPerLeaf {
+                values_per_leaf: options.values_per_leaf,
+            });
+        }
         let domain_offset = options.domain_offset::<F>();
-        let domain_size = max_poly_degree.next_power_of_two() * options.blowup_factor;
-        let domain = Radix2EvaluationDomain::new_coset(domain_size, domain_offset).unwrap();
+        let domain = coeffs_domain_size(max_poly_degree)
+            .checked_mul(options.blowup_factor)
+            .and_then(|domain_size| Radix2EvaluationDomain::new_coset(domain_size, domain_offset))
+            .ok_or(VerificationError::DomainTooLarge {
+                max_poly_degree,
+                blowup_factor: options.blowup_factor,
+            })?;
+        let domain_size = domain.size();
+
+        // the prover stops folding early if the polynomial's degree is low enough
+        let max_num_layers = options.num_layers(domain_size);
+        let num_layers = proof.layer_caps.len();
+        if num_layers > max_num_layers {
+            return Err(VerificationError::NumLayersMismatch {
+                expected: max_num_layers,
+                actual: num_layers,
+            });
+        }
+        // the queries of the last layers may have been split off the proof
+        if proof.layers.len() > num_layers {
+            return Err(VerificationError::NumLayersMismatch {
+                expected: num_layers,
+                actual: proof.layers.len(),
+            });
+        }
+
+        // the domain offsets are recomputed rather than trusted so a prover that
+        // folds with a different convention is rejected here instead of failing
+        // as an invalid fold
+        let layer_offsets = options.layer_offsets::<F>(num_layers);
+        if proof.domain_offsets.len() != layer_offsets.len() {
+            return Err(VerificationError::NumDomainOffsetsMismatch {
+                expected: layer_offsets.len(),
+                actual: proof.domain_offsets.len(),
+            });
+        }
+        for (layer, (&expected, got)) in zip(&layer_offsets, &proof.domain_offsets).enumerate() {
+            let expected = lift::<F>(expected);
+            if expected != *got {
+                return Err(VerificationError::DomainOffsetMismatch {
+                    layer,
+                    expected: expected.to_string(),
+                    got: got.to_string(),
+                });
+            }
+        }
 
         let mut layer_alphas = Vec::new();
         let mut layer_commitments = Vec::new();
-        let mut layer_codeword_len = domain_size;
-        for (i, layer) in proof.layers.iter().enumerate() {
-            // TODO: batch merkle tree proofs
-            // get the merkle root from the first merkle path
-            public_coin.reseed_with_digest(&layer.commitment);
-            let alpha = public_coin.draw();
-            layer_alphas.push(alpha);
-            layer_commitments.push(layer.commitment.clone());
-
-            if i != proof.layers.len() - 1 && layer_codeword_len % folding_factor != 0 {
+        let layer_sizes = options.layer_sizes(domain_size);
+        for (i, (layer_cap, layer_codeword_len)) in zip(&proof.layer_caps, layer_sizes).enumerate()
+        {
+            let num_rows = layer_codeword_len / folding_factor;
+            let num_leaves = num_rows / options.rows_per_leaf(layer_codeword_len);
+            let cap_size = M::cap_size(num_leaves, options.cap_height);
+            if layer_cap.len() != cap_size {
+                return Err(VerificationError::CapSizeMismatch {
+                    layer: i,
+                    expected: cap_size,
+                    actual: layer_cap.len(),
+                });
+            }
+
+            public_coin.reseed_with_cap(ReseedTag::FriLayer, layer_cap);
+            for _ in 0..options.folds_per_layer {
+                layer_alphas.push(public_coin.draw_fq(DrawTag::FriAlpha));
+            }
+            layer_commitments.push(layer_cap.clone());
+
+            if i != num_layers - 1 && layer_codeword_len % folding_factor != 0 {
                 return Err(VerificationError::CodewordTruncation {
                     size: layer_codeword_len,
                     folding_factor,
                     layer: i,
                 });
             }
-
-            layer_codeword_len /= folding_factor;
         }
 
-        public_coin.reseed_with_field_element_vector(&proof.remainder_coeffs);
+        public_coin
+            .reseed_with_field_element_vector(ReseedTag::FriRemainder, &proof.remainder_coeffs);
 
         // TODO: add back in
         // let remainder_root =
@@ -339,104 +919,231 @@ where
             options,
             layer_commitments,
             layer_alphas,
+            layer_positions: Vec::new(),
+            layer_offsets,
             proof,
             domain,
         })
     }
 
-    pub fn verify_generic<const N: usize>(
-        self,
-        positions: &[usize],
-        evaluations: &[F],
-    ) -> Result<(), VerificationError> {
-        let folding_domain = Radix2EvaluationDomain::new(N).unwrap();
-        let mut layers = self.proof.layers.into_iter();
-        let mut layer_alphas = self.layer_alphas.into_iter();
-        let mut layer_commitments = self.layer_commitments.into_iter();
-        let mut positions = positions.to_vec();
-        let mut evaluations = evaluations.to_vec();
-        let mut domain_size = self.domain.size();
-        let mut domain_generator = self.domain.group_gen();
+    /// Draws the query positions of every layer after the first if layers are
+    /// sampled independently. Must be called after the query positions of the
+    /// first layer are drawn.
+    pub fn draw_layer_positions(
+        &mut self,
+        public_coin: &mut impl PublicCoin<Field = F, Digest = D>,
+        num_queries: usize,
+    ) {
+        self.layer_positions = draw_layer_positions(
+            public_coin,
+            &self.options,
+            self.domain.size(),
+            self.layer_commitments.len(),
+            num_queries,
+        );
+    }
 
-        // verify all layers except remainder
-        for i in 0..self.options.num_layers(domain_size) {
-            let folded_positions = fold_positions(&positions, N);
-            let layer_alpha = layer_alphas.next().unwrap();
-            let layer_commitment = layer_commitments.next().unwrap();
+    /// Folds the claim through the given layers
+    fn verify_layers<const N: usize>(
+        &self,
+        layers: impl IntoIterator<Item = LayerProof<F, M>>,
+        claim: &mut FriTailClaim<F>,
+    ) -> Result<(), VerificationError> {
+        let num_layers = self.layer_commitments.len();
+        for layer in layers {
+            let i = claim.layer;
+            if i == num_layers {
+                return Err(VerificationError::NumLayersMismatch {
+                    expected: num_layers,
+                    actual: num_layers + 1,
+                });
+            }
+            let positions = &claim.positions;
+            let independent_positions =
+                independent_positions(&self.options, i, &self.layer_positions).ok_or(
+                    VerificationError::NumLayerQueriesMismatch {
+                        layer: i,
+                        expected: positions.len(),
+                        actual: 0,
+                    },
+                )?;
+            let LayerPositions {
+                rows: folded_positions,
+                folded: next_positions,
+            } = derive_layer_positions(positions, independent_positions, N);
+            let folds_per_layer = self.options.folds_per_layer;
+            let layer_alphas = &self.layer_alphas[i * folds_per_layer..(i + 1) * folds_per_layer];
+            let layer_commitment = &self.layer_commitments[i];
 
-            // TODO: change assert to error. Check remainder
-            let layer = layers.next().unwrap();
-            let (rows, _) = &layer.flattenend_rows.as_chunks::<N>();
-            assert_eq!(rows.len(), folded_positions.len());
+            // leaves can hold several rows (see `FriOptions::with_values_per_leaf`)
+            let rows_per_leaf = self.options.rows_per_leaf(claim.domain_size);
+            let leaf_positions = fold_positions(&folded_positions, rows_per_leaf);
+            let (leaf_rows, _) = &layer.flattenend_rows.as_chunks::<N>();
+            if leaf_rows.len() != leaf_positions.len() * rows_per_leaf {
+                return Err(VerificationError::NumLayerQueriesMismatch {
+                    layer: i,
+                    expected: leaf_positions.len() * rows_per_leaf,
+                    actual: leaf_rows.len(),
+                });
+            }
 
             // verify the layer values against the layer's commitment
-            M::verify_rows(
-                &layer_commitment,
-                &folded_positions,
-                rows,
+            M::verify_row_chunks(
+                layer_commitment,
+                MerkleTreeDomain::FriLayer(i),
+                &leaf_positions,
+                leaf_rows,
+                rows_per_leaf,
                 layer.merkle_proof,
             )
-            .map_err(|_| VerificationError::LayerCommitmentInvalid { layer: i })?;
-
-            let query_values = get_query_values(rows, &positions, &folded_positions);
-            // println!("evaluatinos: {:?}", evaluations);
-            // println!("query vals: {:?}", query_values);
-            assert_eq!(evaluations.len(), query_values.len());
-            for (i, (eval, query_val)) in zip(&evaluations, &query_values).enumerate() {
-                assert_eq!(eval, query_val, "mismatch at {i}");
-            }
+            .map_err(|source| VerificationError::MerklePathInvalid { layer: i, source })?;
+            let rows = &folded_positions
+                .iter()
+                .map(|position| {
+                    let leaf = leaf_positions
+                        .binary_search(&(position / rows_per_leaf))
+                        .ok()?;
+                    leaf_rows
+                        .get(leaf * rows_per_leaf + position % rows_per_leaf)
+                        .copied()
+                })
+                .collect::<Option<Vec<[F; N]>>>()
+                .ok_or(VerificationError::NumLayerQueriesMismatch {
+                    layer: i,
+                    expected: leaf_positions.len() * rows_per_leaf,
+                    actual: leaf_rows.len(),
+                })?;
 
-            if evaluations != query_values {
-                return Err(VerificationError::InvalidDegreeRespectingProjection { layer: i });
+            let query_values = get_query_values(rows, positions, &folded_positions);
+            if claim.evaluations.len() != query_values.len() {
+                return Err(VerificationError::NumPositionEvaluationMismatch);
+            }
+            for ((eval, query_val), &position) in
+                zip(zip(&claim.evaluations, &query_values), positions)
+            {
+                if eval != query_val {
+                    return Err(VerificationError::FoldMismatch {
+                        layer: i,
+                        position,
+                        expected: eval.to_string(),
+                        got: query_val.to_string(),
+                    });
+                }
             }
 
-            let polys = rows
+            // prepare for next layer
+            let domain_size = claim.domain_size;
+            let domain_generator = claim.domain_generator;
+            let domain_offset = self.layer_offsets[i];
+            claim.evaluations = next_positions
                 .iter()
-                .zip(&folded_positions)
-                .map(move |(chunk, position)| {
+                .map(|position| {
+                    let row = &rows[folded_positions.binary_search(position).ok()?];
                     let bit_rev_position = bit_reverse_index(domain_size / N, *position);
-                    // let offset = domain_offset.pow([N.pow(i as u32) as u64])
-                    //     * domain_generator.pow([bit_rev_position as u64]);
-                    let offset = domain_generator.pow([bit_rev_position as u64]);
-                    let domain = folding_domain.get_coset(offset).unwrap();
-                    let mut chunk = *chunk;
-                    bit_reverse(&mut chunk);
-                    let mut coeffs = domain.ifft(&chunk);
-                    for coeff in &mut coeffs {
-                        *coeff *= F::from(N as u64);
-                    }
-                    DensePolynomial::from_coefficients_vec(coeffs)
-                });
+                    let offset = domain_offset * domain_generator.pow([bit_rev_position as u64]);
+                    Some(fold_row(
+                        row,
+                        offset,
+                        layer_alphas,
+                        self.options.folding_factor,
+                    ))
+                })
+                .collect::<Option<Vec<F>>>()
+                .ok_or(VerificationError::NumPositionEvaluationMismatch)?;
+            claim.positions = next_positions;
+            claim.domain_generator = domain_generator.pow([N as u64]);
+            claim.domain_size /= N;
+            claim.layer += 1;
+        }
+        Ok(())
+    }
 
-            // prepare for next layer
-            evaluations = polys.map(|poly| poly.evaluate(&layer_alpha)).collect();
-            positions = folded_positions;
-            domain_generator = domain_generator.pow([N as u64]);
-            domain_size /= N;
+    fn verify_layers_dyn(
+        &self,
+        layers: impl IntoIterator<Item = LayerProof<F, M>>,
+        claim: &mut FriTailClaim<F>,
+    ) -> Result<(), VerificationError> {
+        match self.options.layer_folding_factor() {
+            2 => self.verify_layers::<2>(layers, claim),
+            4 => self.verify_layers::<4>(layers, claim),
+            8 => self.verify_layers::<8>(layers, claim),
+            16 => self.verify_layers::<16>(layers, claim),
+            _ => Err(VerificationError::UnsupportedFolding {
+                folding_factor: self.options.folding_factor,
+                folds_per_layer: self.options.folds_per_layer,
+            }),
         }
-        verify_remainder::<F>(
-            self.proof.remainder_coeffs,
-            &positions,
-            &evaluations,
-            domain_generator,
-            domain_size,
-            self.options.blowup_factor,
-        )
     }
 
-    pub fn verify(self, positions: &[usize], evaluations: &[F]) -> Result<(), VerificationError> {
+    /// Verifies the layers that are part of the proof and returns the claim
+    /// for the first layer that isn't. If the tail of the proof was split off
+    /// (see [`FriProof::split_tail`]) the claim has to be checked with
+    /// [`Self::verify_tail`] e.g. by a secondary verifier.
+    pub fn verify_head(
+        &mut self,
+        positions: &[usize],
+        evaluations: &[F],
+    ) -> Result<FriTailClaim<F>, VerificationError> {
         if positions.len() != evaluations.len() {
             return Err(VerificationError::NumPositionEvaluationMismatch);
         }
 
-        match self.options.folding_factor {
-            2 => self.verify_generic::<2>(positions, evaluations),
-            4 => self.verify_generic::<4>(positions, evaluations),
-            8 => self.verify_generic::<8>(positions, evaluations),
-            16 => self.verify_generic::<16>(positions, evaluations),
-            // TODO: move this to options
-            folding_factor => unreachable!("folding factor {folding_factor} not supported"),
+        let mut claim = FriTailClaim {
+            layer: 0,
+            positions: positions.to_vec(),
+            evaluations: evaluations.to_vec(),
+            domain_size: self.domain.size(),
+            domain_generator: self.domain.group_gen(),
+        };
+        let layers = core::mem::take(&mut self.proof.layers);
+        self.verify_layers_dyn(layers, &mut claim)?;
+        Ok(claim)
+    }
+
+    /// Verifies the remaining layers and remainder starting from a claim
+    /// returned by [`Self::verify_head`]
+    pub fn verify_tail(
+        &self,
+        claim: FriTailClaim<F>,
+        tail: FriTailProof<F, M>,
+    ) -> Result<(), VerificationError> {
+        self.verify_tail_layers(claim, tail.layers)
+    }
+
+    /// Like [`Self::verify_tail`] but takes the layers one at a time so they
+    /// can be decoded lazily (see [`crate::ProofView`])
+    pub fn verify_tail_layers(
+        &self,
+        mut claim: FriTailClaim<F>,
+        layers: impl IntoIterator<Item = LayerProof<F, M>>,
+    ) -> Result<(), VerificationError> {
+        self.verify_layers_dyn(layers, &mut claim)?;
+        let num_layers = self.layer_commitments.len();
+        if claim.layer != num_layers {
+            return Err(VerificationError::NumLayersMismatch {
+                expected: num_layers,
+                actual: claim.layer,
+            });
         }
+
+        verify_remainder::<F>(
+            self.proof.remainder_coeffs.clone(),
+            &claim.positions,
+            &claim.evaluations,
+            self.layer_offsets[num_layers],
+            claim.domain_generator,
+            claim.domain_size,
+            &self.options,
+        )
+    }
+
+    pub fn verify(
+        mut self,
+        positions: &[usize],
+        evaluations: &[F],
+    ) -> Result<(), VerificationError> {
+        let claim = self.verify_head(positions, evaluations)?;
+        self.verify_tail(claim, FriTailProof::default())
     }
 }
 
@@ -444,23 +1151,25 @@ fn verify_remainder<F: GpuField + Field + DomainCoeff<F::FftField>>(
     remainder_coeffs: Vec<F>,
     positions: &[usize],
     expected_evaluations: &[F],
+    domain_offset: F::FftField,
     domain_generator: F::FftField,
     domain_size: usize,
-    blowup_factor: usize,
+    options: &FriOptions,
 ) -> Result<(), VerificationError>
 where
     F::FftField: FftField,
 {
     let remainder_poly = DensePolynomial::from_coefficients_vec(remainder_coeffs);
-    let expected_degree = domain_size / blowup_factor - 1;
+    let expected_degree = max_remainder_degree(options, domain_size);
     if remainder_poly.degree() > expected_degree {
-        return Err(VerificationError::RemainderDegreeMismatch {
-            degree: expected_degree,
+        return Err(VerificationError::RemainderDegreeTooHigh {
+            degree: remainder_poly.degree(),
+            max_degree: expected_degree,
         });
     }
     let xs = positions
         .iter()
-        .map(|p| domain_generator.pow([bit_reverse_index(domain_size, *p) as u64]));
+        .map(|p| domain_offset * domain_generator.pow([bit_reverse_index(domain_size, *p) as u64]));
     for (i, x) in xs.enumerate() {
         // TODO: fix types and just call horner_evaluate
         let y = remainder_poly
@@ -470,24 +1179,102 @@ where
                 result + coeff
             });
         if expected_evaluations[i] != y {
-            return Err(VerificationError::RemainderCommitmentInvalid);
+            return Err(VerificationError::RemainderMismatch {
+                position: positions[i],
+                expected: expected_evaluations[i].to_string(),
+                got: y.to_string(),
+            });
         }
     }
     Ok(())
 }
 
+/// Returns the maximum degree of the remainder polynomial over a domain of
+/// size `domain_size`
+const fn max_remainder_degree(options: &FriOptions, domain_size: usize) -> usize {
+    let degree_bound = domain_size / options.blowup_factor;
+    if degree_bound < options.max_remainder_coeffs {
+        degree_bound.saturating_sub(1)
+    } else {
+        options.max_remainder_coeffs.saturating_sub(1)
+    }
+}
+
+/// Draws the query positions of every layer after the first for a proof with
+/// `num_layers` layers if layers are sampled independently (see
+/// [`FriOptions::with_independent_queries`]). The positions of layer `i + 1`
+/// are in the domain of layer `i + 1`. Returns no positions if the positions
+/// of all layers are folded from the positions of the first layer.
+pub fn draw_layer_positions(
+    public_coin: &mut impl PublicCoin,
+    options: &FriOptions,
+    domain_size: usize,
+    num_layers: usize,
+    num_queries: usize,
+) -> Vec<Vec<usize>> {
+    if !options.independent_queries {
+        return Vec::new();
+    }
+    options
+        .layer_sizes(domain_size)
+        .skip(1)
+        .take(num_layers.saturating_sub(1))
+        .map(|layer_domain_size| {
+            Vec::from_iter(public_coin.draw_queries(num_queries, layer_domain_size))
+        })
+        .collect()
+}
+
+/// Returns the independently sampled positions of layer `layer` if there are
+/// any (see [`derive_layer_positions`]). Returns `None` if layers are sampled
+/// independently but `layer_positions` is missing the positions of `layer`.
+fn independent_positions<'a>(
+    options: &FriOptions,
+    layer: usize,
+    layer_positions: &'a [Vec<usize>],
+) -> Option<Option<&'a [usize]>> {
+    if options.independent_queries && layer != 0 {
+        layer_positions
+            .get(layer - 1)
+            .map(|positions| Some(&**positions))
+    } else {
+        Some(None)
+    }
+}
+
 pub trait ProverChannel {
     type Digest: Digest;
     type Field: GpuField;
 
-    fn commit_fri_layer(&mut self, layer_root: Self::Digest);
+    /// Commits to the merkle cap of a layer (see
+    /// [`FriOptions::with_cap_height`])
+    fn commit_fri_layer(&mut self, layer_cap: Vec<Self::Digest>);
 
     fn commit_remainder(&mut self, remainder_coeffs: &[Self::Field]);
 
     fn draw_fri_alpha(&mut self) -> Self::Field;
+
+    /// Commits to the out of domain evaluation of a STIR round (see
+    /// [`crate::stir`])
+    fn commit_ood_eval(&mut self, ood_eval: Self::Field);
+
+    /// Draws a challenge for the purpose `tag`
+    fn draw_challenge(&mut self, tag: DrawTag) -> Self::Field;
+
+    /// Draws a maximum of `max_n` unique positions in the range `[0,
+    /// domain_size)` for the purpose `tag`
+    fn draw_positions(&mut self, tag: DrawTag, max_n: usize, domain_size: usize)
+        -> BTreeSet<usize>;
 }
 
 /// Performs a degree respecting projection (drp) on polynomial evaluations.
+///
+/// `evals` are the bit-reversed evaluations of a polynomial over the coset
+/// domain with offset `domain_offset`. Returns the bit-reversed evaluations
+/// of the folded polynomial over the domain of size `evals.len() /
+/// folding_factor` with offset `domain_offset^folding_factor`. FFTs run on
+/// the GPU for large enough domains. See [`fold_codeword`] for a CPU only
+/// reference implementation.
 // Example for `folding_factor = 2`:
 // ```text
 // 1. interpolate evals over the evaluation domain to obtain f(x):
@@ -524,23 +1311,90 @@ pub trait ProverChannel {
 //    └────────┴────┴────┴────┴────┘
 // ```
 pub fn apply_drp<F: GpuField + Field + DomainCoeff<F::FftField>>(
-    mut evals: GpuVec<F>,
+    evals: GpuVec<F>,
     domain_offset: F::FftField,
     alpha: F,
     folding_factor: usize,
 ) -> GpuVec<F>
+where
+    F::FftField: FftField,
+{
+    // interpolate, fold and evaluate without a round trip to the host
+    #[cfg(feature = "gpu")]
+    if evals.len() / folding_factor >= gpu_min_domain_size() {
+        let mut evals = evals;
+        let domain = Radix2EvaluationDomain::new_coset(evals.len(), domain_offset).unwrap();
+        let mut folded = Vec::with_capacity_in(evals.len() / folding_factor, GpuAllocator);
+        folded.resize(evals.len() / folding_factor, F::zero());
+        let mut drp = get_planner().plan_drp::<F>(domain, folding_factor);
+        drp.encode(&mut folded, &mut evals, alpha);
+        drp.execute();
+        return folded;
+    }
+
+    let coeffs = drp_coeffs(evals, domain_offset, folding_factor);
+    fold_drp_coeffs(coeffs, domain_offset, alpha, folding_factor)
+}
+
+/// Returns the degree of the polynomial with bit-reversed evaluations `evals`
+/// over the subgroup of their size. The zero polynomial has degree 0.
+#[cfg(debug_assertions)]
+fn codeword_degree<F: GpuField + Field + DomainCoeff<F::FftField>>(evals: &GpuVec<F>) -> usize
+where
+    F::FftField: FftField,
+{
+    let domain = Radix2EvaluationDomain::new(evals.len()).unwrap();
+    let coeffs = ifft_bit_reversed(evals.to_vec_in(GpuAllocator), domain);
+    coeffs.iter().rposition(|c| !c.is_zero()).unwrap_or(0)
+}
+
+/// First half of [`apply_drp`]. Interpolates bit-reversed evaluations and
+/// scales the coefficients by the folding factor. Independent of alpha.
+fn drp_coeffs<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    evals: GpuVec<F>,
+    domain_offset: F::FftField,
+    folding_factor: usize,
+) -> GpuVec<F>
 where
     F::FftField: FftField,
 {
     let n = evals.len();
     let domain = Radix2EvaluationDomain::new_coset(n, domain_offset).unwrap();
-    // TODO: integrate bit reverse into fft
-    bit_reverse(&mut evals);
-    let mut coeffs = ifft(evals, domain);
+    let mut coeffs = ifft_bit_reversed(evals, domain);
     let fold_fact = F::from(folding_factor as u64);
     for coeff in &mut coeffs {
         *coeff *= fold_fact;
     }
+    coeffs
+}
+
+/// Second half of [`apply_drp`]. Combines the coefficients from [`drp_coeffs`]
+/// with powers of alpha and evaluates the result over the folded domain.
+// requires ownership when the gpu feature is enabled
+#[allow(clippy::needless_pass_by_value)]
+fn fold_drp_coeffs<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    coeffs: GpuVec<F>,
+    domain_offset: F::FftField,
+    alpha: F,
+    folding_factor: usize,
+) -> GpuVec<F>
+where
+    F::FftField: FftField,
+{
+    let n = coeffs.len();
+    let drp_offset = domain_offset.pow([folding_factor as u64]);
+    let drp_domain = Radix2EvaluationDomain::new_coset(n / folding_factor, drp_offset).unwrap();
+
+    // fold the coefficients and evaluate them without a round trip to the host
+    #[cfg(feature = "gpu")]
+    if drp_domain.size() >= gpu_min_domain_size() {
+        let mut evals = Vec::with_capacity_in(drp_domain.size(), GpuAllocator);
+        evals.resize(drp_domain.size(), F::zero());
+        let mut fft = GpuFft::from(drp_domain).with_output_order(EvaluationOrder::BitReversed);
+        fft.encode_fold(&mut evals, &coeffs, alpha, folding_factor);
+        fft.execute();
+        return evals;
+    }
 
     let alpha_powers = (0..folding_factor)
         .map(|i| alpha.pow([i as u64]))
@@ -557,40 +1411,134 @@ where
         .collect::<Vec<F>>()
         .to_vec_in(GpuAllocator);
 
-    let drp_offset = domain_offset.pow([folding_factor as u64]);
-    let drp_domain = Radix2EvaluationDomain::new_coset(n / folding_factor, drp_offset).unwrap();
-
     // return the drp evals
-    let mut evals = fft(drp_coeffs, drp_domain);
+    fft_bit_reversed(drp_coeffs, drp_domain)
+}
+
+/// CPU reference implementation of [`apply_drp`]
+///
+/// Each coset of `folding_factor` consecutive (bit-reversed) evaluations is
+/// folded independently in the same way the verifier folds queried rows. Much
+/// slower than [`apply_drp`] but useful for testing custom FRI verifiers.
+///
+/// # Panics
+///
+/// Panics if the number of evaluations or the folding factor are not powers of
+/// two or if the folding factor doesn't divide the number of evaluations.
+pub fn fold_codeword<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    evals: &[F],
+    domain_offset: F::FftField,
+    alpha: F,
+    folding_factor: usize,
+) -> Vec<F>
+where
+    F::FftField: FftField,
+{
+    let n = evals.len();
+    assert!(n.is_power_of_two());
+    assert!(folding_factor.is_power_of_two());
+    assert_eq!(n % folding_factor, 0);
+    let domain = Radix2EvaluationDomain::<F::FftField>::new(n).unwrap();
+    let num_cosets = n / folding_factor;
+    ark_std::cfg_chunks!(evals, folding_factor)
+        .enumerate()
+        .map(|(i, coset_evals)| {
+            let bit_rev_i = if num_cosets == 1 {
+                0
+            } else {
+                bit_reverse_index(num_cosets, i)
+            };
+            let coset_offset = domain_offset * domain.group_gen().pow([bit_rev_i as u64]);
+            fold_coset(coset_evals, coset_offset, alpha)
+        })
+        .collect()
+}
+
+/// Folds the bit-reversed evaluations of a single coset with offset
+/// `coset_offset` i.e. interpolates the evaluations and evaluates the
+/// interpolant (scaled by the size of the coset) at `alpha`.
+pub fn fold_coset<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    coset_evals: &[F],
+    coset_offset: F::FftField,
+    alpha: F,
+) -> F
+where
+    F::FftField: FftField,
+{
+    let n = coset_evals.len();
+    let domain = Radix2EvaluationDomain::new_coset(n, coset_offset).unwrap();
+    let mut evals = coset_evals.to_vec();
     bit_reverse(&mut evals);
-    evals
+    let coeffs = domain.ifft(&evals);
+    horner_evaluate(&coeffs, &alpha) * F::from(n as u64)
 }
 
-// requires ownership when the gpu feature is enabled
-#[allow(clippy::needless_pass_by_value)]
-fn ifft<F: GpuField + Field + DomainCoeff<F::FftField>>(
-    evals: GpuVec<F>,
+/// Maps an element of the FFT field into the field of the codeword
+pub(crate) fn lift<F: GpuField + Field + DomainCoeff<F::FftField>>(x: F::FftField) -> F
+where
+    F::FftField: FftField,
+{
+    let mut res = F::one();
+    res *= x;
+    res
+}
+
+/// Folds coefficients the way [`fold_coset`] folds evaluations i.e. coefficient
+/// `m` of the folded polynomial is `k * Σ_j alpha^j c_(m * k + j)`
+pub(crate) fn fold_coeffs<F: Field>(coeffs: &[F], alpha: F, folding_factor: usize) -> Vec<F> {
+    let scale = F::from(folding_factor as u64);
+    ark_std::cfg_chunks!(coeffs, folding_factor)
+        .map(|chunk| horner_evaluate(chunk, &alpha) * scale)
+        .collect()
+}
+
+/// Folds a row of a layer once for every alpha. The row holds the
+/// bit-reversed evaluations of a coset with offset `row_offset`.
+fn fold_row<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    row: &[F],
+    mut row_offset: F::FftField,
+    alphas: &[F],
+    folding_factor: usize,
+) -> F
+where
+    F::FftField: FftField,
+{
+    let (&last_alpha, alphas) = alphas.split_last().unwrap();
+    let mut evals = row.to_vec();
+    for &alpha in alphas {
+        evals = fold_codeword(&evals, row_offset, alpha, folding_factor);
+        row_offset = row_offset.pow([folding_factor as u64]);
+    }
+    fold_coset(&evals, row_offset, last_alpha)
+}
+
+/// Interpolates bit-reversed evaluations. The GPU permutes the evaluations on
+/// the device rather than on the host.
+fn ifft_bit_reversed<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    mut evals: GpuVec<F>,
     domain: Radix2EvaluationDomain<F::FftField>,
 ) -> GpuVec<F>
 where
     F::FftField: FftField,
 {
     #[cfg(feature = "gpu")]
-    if domain.size() >= GpuFft::<F>::MIN_SIZE {
+    if domain.size() >= gpu_min_domain_size() {
         let mut coeffs = evals;
-        let mut ifft = GpuIfft::from(domain);
+        let mut ifft = GpuIfft::from(domain).with_input_order(EvaluationOrder::BitReversed);
         ifft.encode(&mut coeffs);
         ifft.execute();
         return coeffs;
     }
 
+    bit_reverse(&mut evals);
     let coeffs = domain.ifft(&evals);
     coeffs.to_vec_in(GpuAllocator)
 }
 
+/// Evaluates coefficients and returns the evaluations in bit-reversed order
 // requires ownership when the gpu feature is enabled
 #[allow(clippy::needless_pass_by_value)]
-fn fft<F: GpuField + Field + DomainCoeff<F::FftField>>(
+fn fft_bit_reversed<F: GpuField + Field + DomainCoeff<F::FftField>>(
     coeffs: GpuVec<F>,
     domain: Radix2EvaluationDomain<F::FftField>,
 ) -> GpuVec<F>
@@ -598,67 +1546,419 @@ where
     F::FftField: FftField,
 {
     #[cfg(feature = "gpu")]
-    if domain.size() >= GpuFft::<F>::MIN_SIZE {
+    if domain.size() >= gpu_min_domain_size() {
         let mut evals = coeffs;
-        let mut fft = GpuFft::from(domain);
+        let mut fft = GpuFft::from(domain).with_output_order(EvaluationOrder::BitReversed);
         fft.encode(&mut evals);
         fft.execute();
         return evals;
     }
 
-    let evals = domain.fft(&coeffs);
-    evals.to_vec_in(GpuAllocator)
-}
-
-/// # Panics
-/// Panics is positions are not all unique and sorted
-pub fn fold_positions(positions: &[usize], folding_factor: usize) -> Vec<usize> {
-    assert!(positions.array_windows().all(|[a, b]| a < b));
-    let mut res = positions
-        .iter()
-        .map(|p| p / folding_factor)
-        .collect::<Vec<usize>>();
-    res.dedup();
-    res
-    // let mut res = positions
-    //     .iter()
-    //     .map(|pos| pos % max)
-    //     .collect::<Vec<usize>>();
-    // res.sort_unstable();
-    // res.dedup();
-    // res
-}
-
-// from winterfell
-pub fn get_query_values<F: Field, const N: usize>(
-    chunks: &[[F; N]],
-    positions: &[usize],
-    folded_positions: &[usize],
-) -> Vec<F> {
-    positions
-        .iter()
-        .map(|position| {
-            let i = folded_positions
-                .iter()
-                .position(|&v| v == position / N)
-                .unwrap();
-            chunks[i][position % N]
-        })
-        .collect()
+    let mut evals = domain.fft(&coeffs).to_vec_in(GpuAllocator);
+    bit_reverse(&mut evals);
+    evals
 }
 
-fn query_layer<F: GpuField + Field, D: Digest, M: MatrixMerkleTree<F, Root = D>, const N: usize>(
+pub(crate) fn query_layer<F: GpuField + Field, M: MatrixMerkleTree<F>, const N: usize>(
     layer: &FriLayer<F, M>,
     positions: &[usize],
-) -> LayerProof<F, D, M>
+    cap_height: u32,
+) -> Result<LayerProof<F, M>, merkle::Error>
 where
     F::FftField: FftField,
 {
-    let merkle_proof = layer.merkle_tree.prove_rows(positions).unwrap();
+    // all rows of the leaves containing the positions are opened
+    let rows_per_leaf = layer.rows_per_leaf;
+    let leaf_positions = fold_positions(positions, rows_per_leaf);
+    let merkle_proof = layer
+        .merkle_tree
+        .prove_to_cap(&leaf_positions, cap_height)?;
     let mut rows: Vec<[F; N]> = Vec::new();
-    for &position in positions {
+    // every row of a leaf that could be proven exists
+    for position in leaf_rows(positions, rows_per_leaf) {
         let row = layer.evaluations.get_row(position).unwrap();
         rows.push(row.try_into().unwrap());
     }
-    LayerProof::new(rows, merkle_proof, layer.merkle_tree.root())
+    Ok(LayerProof::new(rows, merkle_proof))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply_drp;
+    use super::draw_layer_positions;
+    use super::fold_codeword;
+    use super::FriOptions;
+    use super::FriProver;
+    use super::FriVerifier;
+    use super::ProverError;
+    use super::VerificationError;
+    use crate::fixtures::TestChannel;
+    use crate::fixtures::TestCoin;
+    use crate::hash::HashFn;
+    use crate::hash::Sha256HashFn;
+    use crate::merkle::MatrixMerkleTreeImpl;
+    use crate::random::PublicCoin;
+    use crate::utils::GpuAllocator;
+    use alloc::collections::BTreeSet;
+    use ark_ff::FftField;
+    use ark_ff::UniformRand;
+    use ark_poly::univariate::DensePolynomial;
+    use ark_poly::DenseUVPolynomial;
+    use ark_poly::EvaluationDomain;
+    use ark_poly::Radix2EvaluationDomain;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+    use ministark_gpu::utils::bit_reverse;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn fold_codeword_matches_apply_drp() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // large enough for the GPU implementation to be used if enabled
+        let n = 1 << 12;
+        let evals = (0..n).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+        let alpha = Fp::rand(&mut rng);
+
+        for folding_factor in [2, 4, 8, 16] {
+            for offset in [Fp::from(1u8), Fp::GENERATOR] {
+                let expected = fold_codeword(&evals, offset, alpha, folding_factor);
+                let actual =
+                    apply_drp(evals.to_vec_in(GpuAllocator), offset, alpha, folding_factor);
+
+                assert_eq!(expected, actual.to_vec(), "folding factor {folding_factor}");
+            }
+        }
+    }
+
+    #[test]
+    fn layer_sizes_fold_to_remainder() {
+        let options = FriOptions::new(4, 4, 8);
+
+        let layer_sizes = options.layer_sizes(4096).collect::<Vec<usize>>();
+
+        assert_eq!([4096, 1024, 256, 64], *layer_sizes);
+        assert_eq!(layer_sizes.len(), options.num_layers(4096));
+        assert_eq!(16, options.remainder_size(4096));
+        assert_eq!(3, options.final_remainder_degree(4096));
+        assert_eq!(0, options.num_layers(32));
+        assert_eq!(32, options.remainder_size(32));
+    }
+
+    #[test]
+    fn low_degree_polynomial_stops_folding_early() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let options = FriOptions::new(4, 2, 8);
+        let max_poly_degree = 255;
+        let domain_size = 1024;
+        let degree = 20;
+        let poly = DensePolynomial::<Fp>::rand(degree, &mut rng);
+        let domain = Radix2EvaluationDomain::new_coset(domain_size, Fp::GENERATOR).unwrap();
+        let mut evals = domain.fft(&poly);
+        bit_reverse(&mut evals);
+        let seed = Sha256HashFn::hash([]);
+        let positions = [1, 100, 500, 900];
+
+        let mut fri_prover = FriProver::<Fp, _, MatrixMerkleTreeImpl<Sha256HashFn>>::new(options);
+        let mut channel = TestChannel(TestCoin::new(seed.clone()));
+        fri_prover
+            .build_layers(&mut channel, evals.to_vec_in(GpuAllocator), degree)
+            .unwrap();
+        let proof = fri_prover.into_proof(&positions).unwrap();
+        let mut public_coin = TestCoin::new(seed);
+        let verifier = FriVerifier::new(&mut public_coin, options, proof, max_poly_degree).unwrap();
+        let evaluations = positions.map(|position| evals[position]);
+
+        assert_eq!(2, verifier.layer_commitments.len());
+        assert!(options.num_layers(domain_size) > 2);
+        verifier.verify(&positions, &evaluations).unwrap();
+    }
+
+    #[test]
+    fn prover_rejects_high_degree_evaluations() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let options = FriOptions::new(4, 2, 8);
+        let domain_size = 1024;
+        let poly = DensePolynomial::<Fp>::rand(100, &mut rng);
+        let domain = Radix2EvaluationDomain::new_coset(domain_size, Fp::GENERATOR).unwrap();
+        let mut evals = domain.fft(&poly);
+        bit_reverse(&mut evals);
+        let build_layers = |degree| {
+            let mut fri_prover =
+                FriProver::<Fp, _, MatrixMerkleTreeImpl<Sha256HashFn>>::new(options);
+            let mut channel = TestChannel(TestCoin::new(Sha256HashFn::hash([])));
+            fri_prover.build_layers(&mut channel, evals.to_vec_in(GpuAllocator), degree)
+        };
+
+        assert!(matches!(
+            build_layers(20),
+            Err(ProverError::NotLowDegree { max_degree: 7 })
+        ));
+        assert!(matches!(
+            build_layers(300),
+            Err(ProverError::DegreeTooHigh {
+                degree: 300,
+                degree_bound: 256
+            })
+        ));
+    }
+
+    #[test]
+    fn split_tail_verifies_separately() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let options = FriOptions::new(2, 2, 8);
+        let max_poly_degree = 255;
+        let domain_size = 512;
+        let poly = DensePolynomial::<Fp>::rand(max_poly_degree, &mut rng);
+        let domain = Radix2EvaluationDomain::new_coset(domain_size, Fp::GENERATOR).unwrap();
+        let mut evals = domain.fft(&poly);
+        bit_reverse(&mut evals);
+        let seed = Sha256HashFn::hash([]);
+        let positions = [1, 100, 300, 500];
+        let evaluations = positions.map(|position| evals[position]);
+
+        let mut fri_prover = FriProver::<Fp, _, MatrixMerkleTreeImpl<Sha256HashFn>>::new(options);
+        let mut channel = TestChannel(TestCoin::new(seed.clone()));
+        fri_prover
+            .build_layers(&mut channel, evals.to_vec_in(GpuAllocator), max_poly_degree)
+            .unwrap();
+        let mut proof = fri_prover.into_proof(&positions).unwrap();
+        let tail = proof.split_tail(2);
+        let mut public_coin = TestCoin::new(seed.clone());
+        let verifier =
+            FriVerifier::new(&mut public_coin, options, proof.clone(), max_poly_degree).unwrap();
+        assert!(verifier.verify(&positions, &evaluations).is_err());
+
+        let mut public_coin = TestCoin::new(seed);
+        let mut verifier =
+            FriVerifier::new(&mut public_coin, options, proof, max_poly_degree).unwrap();
+        let claim = verifier.verify_head(&positions, &evaluations).unwrap();
+        assert_eq!(2, claim.layer);
+        verifier.verify_tail(claim, tail).unwrap();
+    }
+
+    #[test]
+    fn folds_per_layer_skips_commitments() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let options = FriOptions::new(2, 2, 4).with_folds_per_layer(3);
+        let max_poly_degree = 255;
+        let domain_size = 512;
+        let poly = DensePolynomial::<Fp>::rand(max_poly_degree, &mut rng);
+        let domain = Radix2EvaluationDomain::new_coset(domain_size, Fp::GENERATOR).unwrap();
+        let mut evals = domain.fft(&poly);
+        bit_reverse(&mut evals);
+        let seed = Sha256HashFn::hash([]);
+        let positions = [1, 100, 300, 500];
+        let evaluations = positions.map(|position| evals[position]);
+
+        let mut fri_prover = FriProver::<Fp, _, MatrixMerkleTreeImpl<Sha256HashFn>>::new(options);
+        let mut channel = TestChannel(TestCoin::new(seed.clone()));
+        fri_prover
+            .build_layers(&mut channel, evals.to_vec_in(GpuAllocator), max_poly_degree)
+            .unwrap();
+        let proof = fri_prover.into_proof(&positions).unwrap();
+        let mut public_coin = TestCoin::new(seed.clone());
+        let verifier =
+            FriVerifier::new(&mut public_coin, options, proof.clone(), max_poly_degree).unwrap();
+
+        assert_eq!(2, verifier.layer_commitments.len());
+        assert_eq!(6, verifier.layer_alphas.len());
+        verifier.verify(&positions, &evaluations).unwrap();
+
+        // a verifier that folds once per layer expects rows of 2 evaluations
+        let mut public_coin = TestCoin::new(seed);
+        let options = options.with_folds_per_layer(1);
+        let verifier = FriVerifier::new(&mut public_coin, options, proof, max_poly_degree).unwrap();
+        assert!(matches!(
+            verifier.verify(&positions, &evaluations),
+            Err(VerificationError::NumLayerQueriesMismatch {
+                layer: 0,
+                expected: 4,
+                actual: 16
+            })
+        ));
+    }
+
+    #[test]
+    fn mismatched_domain_offsets_are_rejected() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let options = FriOptions::new(2, 4, 8);
+        let max_poly_degree = 255;
+        let domain_size = 512;
+        let poly = DensePolynomial::<Fp>::rand(max_poly_degree, &mut rng);
+        let domain = Radix2EvaluationDomain::new_coset(domain_size, Fp::GENERATOR).unwrap();
+        let mut evals = domain.fft(&poly);
+        bit_reverse(&mut evals);
+        let seed = Sha256HashFn::hash([]);
+        let positions = [1, 100, 300, 500];
+
+        let mut fri_prover = FriProver::<Fp, _, MatrixMerkleTreeImpl<Sha256HashFn>>::new(options);
+        let mut channel = TestChannel(TestCoin::new(seed.clone()));
+        fri_prover
+            .build_layers(&mut channel, evals.to_vec_in(GpuAllocator), max_poly_degree)
+            .unwrap();
+        let mut proof = fri_prover.into_proof(&positions).unwrap();
+        assert_eq!(options.num_layers(domain_size) + 1, proof.domain_offsets.len());
+        let num_offsets = proof.domain_offsets.len();
+        let mut missing_offset = proof.clone();
+        missing_offset.domain_offsets.pop();
+        proof.domain_offsets[1] = Fp::GENERATOR;
+        let mut public_coin = TestCoin::new(seed.clone());
+
+        assert!(matches!(
+            FriVerifier::new(&mut public_coin, options, proof, max_poly_degree),
+            Err(VerificationError::DomainOffsetMismatch { layer: 1, .. })
+        ));
+        let mut public_coin = TestCoin::new(seed);
+        let res = FriVerifier::new(&mut public_coin, options, missing_offset, max_poly_degree);
+        assert!(matches!(
+            res,
+            Err(VerificationError::NumDomainOffsetsMismatch { expected, actual })
+                if expected == num_offsets && actual == num_offsets - 1
+        ));
+    }
+
+    #[test]
+    fn values_per_leaf_shortens_paths() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let max_poly_degree = 255;
+        let domain_size = 512;
+        let poly = DensePolynomial::<Fp>::rand(max_poly_degree, &mut rng);
+        let domain = Radix2EvaluationDomain::new_coset(domain_size, Fp::GENERATOR).unwrap();
+        let mut evals = domain.fft(&poly);
+        bit_reverse(&mut evals);
+        let seed = Sha256HashFn::hash([]);
+        let positions = [1, 100, 300, 500];
+        let evaluations = positions.map(|position| evals[position]);
+
+        for values_per_leaf in [1, 8, 128] {
+            let leaf_size = values_per_leaf.max(2);
+            let leaves = positions.map(|position| position / leaf_size);
+            let num_leaves = BTreeSet::from(leaves).len();
+            let options = FriOptions::new(2, 2, 8).with_values_per_leaf(values_per_leaf);
+            let mut fri_prover =
+                FriProver::<Fp, _, MatrixMerkleTreeImpl<Sha256HashFn>>::new(options);
+            let mut channel = TestChannel(TestCoin::new(seed.clone()));
+            fri_prover
+                .build_layers(&mut channel, evals.to_vec_in(GpuAllocator), max_poly_degree)
+                .unwrap();
+            let proof = fri_prover.into_proof(&positions).unwrap();
+            let first_layer = &proof.layers[0];
+            let num_values = first_layer.flattenend_rows.len();
+            let height = first_layer.merkle_proof.height;
+            let mut public_coin = TestCoin::new(seed.clone());
+            let verifier =
+                FriVerifier::new(&mut public_coin, options, proof.clone(), max_poly_degree)
+                    .unwrap();
+
+            assert_eq!(domain_size / leaf_size, 1 << height);
+            assert_eq!(num_leaves * leaf_size, num_values);
+            verifier.verify(&positions, &evaluations).unwrap();
+
+            // leaves hold at least one coset so quadruple the leaf size
+            // to open more rows than were committed
+            let mut public_coin = TestCoin::new(seed.clone());
+            let options = options.with_values_per_leaf(values_per_leaf * 4);
+            let verifier =
+                FriVerifier::new(&mut public_coin, options, proof, max_poly_degree).unwrap();
+            assert!(matches!(
+                verifier.verify(&positions, &evaluations),
+                Err(VerificationError::NumLayerQueriesMismatch { layer: 0, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn layers_commit_to_caps() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let options = FriOptions::new(2, 2, 8).with_cap_height(2);
+        let max_poly_degree = 255;
+        let domain_size = 512;
+        let poly = DensePolynomial::<Fp>::rand(max_poly_degree, &mut rng);
+        let domain = Radix2EvaluationDomain::new_coset(domain_size, Fp::GENERATOR).unwrap();
+        let mut evals = domain.fft(&poly);
+        bit_reverse(&mut evals);
+        let seed = Sha256HashFn::hash([]);
+        let positions = [1, 100, 300, 500];
+        let evaluations = positions.map(|position| evals[position]);
+
+        let mut fri_prover = FriProver::<Fp, _, MatrixMerkleTreeImpl<Sha256HashFn>>::new(options);
+        let mut channel = TestChannel(TestCoin::new(seed.clone()));
+        fri_prover
+            .build_layers(&mut channel, evals.to_vec_in(GpuAllocator), max_poly_degree)
+            .unwrap();
+        let proof = fri_prover.into_proof(&positions).unwrap();
+        let mut public_coin = TestCoin::new(seed.clone());
+        let verifier =
+            FriVerifier::new(&mut public_coin, options, proof.clone(), max_poly_degree).unwrap();
+
+        assert!(proof.layer_caps.iter().all(|cap| cap.len() == 4));
+        verifier.verify(&positions, &evaluations).unwrap();
+        let mut public_coin = TestCoin::new(seed);
+        let options = options.with_cap_height(3);
+        assert!(matches!(
+            FriVerifier::new(&mut public_coin, options, proof, max_poly_degree),
+            Err(VerificationError::CapSizeMismatch {
+                layer: 0,
+                expected: 8,
+                actual: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn independent_layer_queries_verify() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let options = FriOptions::new(2, 2, 8).with_independent_queries(true);
+        let max_poly_degree = 255;
+        let domain_size = 512;
+        let poly = DensePolynomial::<Fp>::rand(max_poly_degree, &mut rng);
+        let domain = Radix2EvaluationDomain::new_coset(domain_size, Fp::GENERATOR).unwrap();
+        let mut evals = domain.fft(&poly);
+        bit_reverse(&mut evals);
+        let seed = Sha256HashFn::hash([]);
+        let positions = [1, 100, 300, 500];
+        let evaluations = positions.map(|position| evals[position]);
+
+        let mut fri_prover = FriProver::<Fp, _, MatrixMerkleTreeImpl<Sha256HashFn>>::new(options);
+        let mut channel = TestChannel(TestCoin::new(seed.clone()));
+        fri_prover
+            .build_layers(&mut channel, evals.to_vec_in(GpuAllocator), max_poly_degree)
+            .unwrap();
+        let num_layers = fri_prover.num_layers();
+        let layer_positions =
+            draw_layer_positions(&mut channel.0, &options, domain_size, num_layers, 4);
+        let proof = fri_prover
+            .into_proof_with_layer_positions(&positions, &layer_positions)
+            .unwrap();
+        let mut public_coin = TestCoin::new(seed.clone());
+        let mut verifier =
+            FriVerifier::new(&mut public_coin, options, proof.clone(), max_poly_degree).unwrap();
+        verifier.draw_layer_positions(&mut public_coin, 4);
+
+        assert_eq!(num_layers - 1, verifier.layer_positions.len());
+        assert_eq!(layer_positions, verifier.layer_positions);
+        verifier.verify(&positions, &evaluations).unwrap();
+
+        // layer positions weren't drawn
+        let mut public_coin = TestCoin::new(seed.clone());
+        let verifier =
+            FriVerifier::new(&mut public_coin, options, proof.clone(), max_poly_degree).unwrap();
+        assert!(matches!(
+            verifier.verify(&positions, &evaluations),
+            Err(VerificationError::NumLayerQueriesMismatch { layer: 1, .. })
+        ));
+
+        // folded positions open fewer rows than the independent positions
+        let mut public_coin = TestCoin::new(seed);
+        let options = options.with_independent_queries(false);
+        let verifier = FriVerifier::new(&mut public_coin, options, proof, max_poly_degree).unwrap();
+        assert!(matches!(
+            verifier.verify(&positions, &evaluations),
+            Err(VerificationError::NumLayerQueriesMismatch {
+                layer: 1,
+                expected: 4,
+                actual: 8
+            })
+        ));
+    }
 }
@@ -1,7 +1,10 @@
 use crate::utils::SerdeOutput;
+use ark_ff::BigInteger;
 use ark_ff::Field;
+use ark_ff::PrimeField;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use blake3::Hasher as Blake3;
 use core::fmt::Debug;
 use digest::Digest as _;
 use sha2::Sha256;
@@ -24,9 +27,106 @@ pub trait HashFn: Send + Sync + 'static {
     /// construction of Merkle trees.
     fn merge(v0: &Self::Digest, v1: &Self::Digest) -> Self::Digest;
 
+    /// Returns a keyed hash of the provided sequence of byte chunks. The key
+    /// acts as a domain separator i.e. hashing the same chunks under two
+    /// different keys gives unrelated outputs. This method is intended for use
+    /// in construction of Merkle trees.
+    fn hash_chunks_keyed<'a>(
+        key: &'a [u8; 32],
+        chunks: impl IntoIterator<Item = &'a [u8]>,
+    ) -> Self::Digest {
+        Self::hash_chunks(core::iter::once(&key[..]).chain(chunks))
+    }
+
     /// Returns hash(`seed` || `value`). This method is intended for use in PRNG
     /// and proof-of-work contexts.
     fn merge_with_int(seed: &Self::Digest, value: u64) -> Self::Digest;
+
+    /// Returns the implementation hashing dispatches to on this CPU
+    fn backend() -> HashBackend {
+        HashBackend::Portable
+    }
+}
+
+/// Implementation of a hash function selected at runtime.
+///
+/// The `sha2` and `blake3` crates detect CPU features the first time they hash
+/// and dispatch to the fastest implementation available. This way the same
+/// binary uses the x86 SHA extensions or the Arm SHA-2 instructions for
+/// SHA-256 and the widest available SIMD instructions for BLAKE3. Merkle tree
+/// commitments spend most of their time hashing so this is the main factor in
+/// commit times (see the `merkle_tree` benchmark). All implementations return
+/// the same digests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashBackend {
+    /// x86 SHA extensions
+    ShaNi,
+    /// Arm SHA-2 cryptography extensions
+    ArmSha2,
+    /// x86 AVX-512 (BLAKE3 only)
+    Avx512,
+    /// x86 AVX2 (BLAKE3 only)
+    Avx2,
+    /// x86 SSE4.1 (BLAKE3 only)
+    Sse41,
+    /// x86 SSE2 (BLAKE3 only)
+    Sse2,
+    /// ARM NEON (BLAKE3 only)
+    Neon,
+    /// Software implementation without SIMD instructions
+    Portable,
+}
+
+impl HashBackend {
+    /// Returns true if hashing uses instructions beyond the portable
+    /// implementation
+    pub const fn is_accelerated(self) -> bool {
+        !matches!(self, Self::Portable)
+    }
+}
+
+/// Returns the implementation the `sha2` crate uses for SHA-256 on this CPU.
+/// Mirrors the detection in `sha2::sha256`.
+fn sha256_backend() -> HashBackend {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if std::is_x86_feature_detected!("sha")
+        && std::is_x86_feature_detected!("sse2")
+        && std::is_x86_feature_detected!("ssse3")
+        && std::is_x86_feature_detected!("sse4.1")
+    {
+        return HashBackend::ShaNi;
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("sha2") {
+        return HashBackend::ArmSha2;
+    }
+    HashBackend::Portable
+}
+
+/// Returns the implementation the `blake3` crate uses on this CPU. Mirrors
+/// `blake3::platform::Platform::detect` which always uses NEON on little
+/// endian `aarch64`.
+fn blake3_backend() -> HashBackend {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if std::is_x86_feature_detected!("avx512f") && std::is_x86_feature_detected!("avx512vl") {
+            return HashBackend::Avx512;
+        }
+        if std::is_x86_feature_detected!("avx2") {
+            return HashBackend::Avx2;
+        }
+        if std::is_x86_feature_detected!("sse4.1") {
+            return HashBackend::Sse41;
+        }
+        if std::is_x86_feature_detected!("sse2") {
+            return HashBackend::Sse2;
+        }
+    }
+    if cfg!(all(target_arch = "aarch64", target_endian = "little")) {
+        HashBackend::Neon
+    } else {
+        HashBackend::Portable
+    }
 }
 
 /// Defines a cryptographic hash function for hashing field elements.
@@ -35,6 +135,9 @@ pub trait HashFn: Send + Sync + 'static {
 /// elements can be either in the base field specified for this hasher, or in an
 /// extension of the base field.
 /// Trait inspired by Winterfell: <https://github.com/facebook/winterfell/blob/main/crypto/src/hash/mod.rs#L55>
+///
+/// Implementations must hash the canonical encoding of the elements (see
+/// [`write_canonical_element`]) so digests don't depend on the architecture.
 pub trait ElementHashFn<F: Field>: HashFn {
     /// Returns a hash of the provided field elements.
     fn hash_elements(elements: impl IntoIterator<Item = F>) -> Self::Digest;
@@ -55,6 +158,21 @@ pub trait Digest:
     fn as_bytes(&self) -> [u8; 32];
 }
 
+/// Appends the canonical encoding of a field element to `buffer`.
+///
+/// Each base prime field coefficient of the element is written in canonical
+/// (non-Montgomery) form as `ceil(MODULUS_BIT_SIZE / 8)` little-endian bytes,
+/// starting with the lowest degree coefficient. The encoding doesn't depend on
+/// the endianness of the target and for fields built on [`ark_ff::Fp`] it
+/// matches arkworks' uncompressed serialization.
+pub fn write_canonical_element<F: Field>(element: &F, buffer: &mut Vec<u8>) {
+    let num_bytes = (F::BasePrimeField::MODULUS_BIT_SIZE as usize).div_ceil(8);
+    for coeff in element.to_base_prime_field_elements() {
+        let bytes = coeff.into_bigint().to_bytes_le();
+        buffer.extend_from_slice(&bytes[..num_bytes]);
+    }
+}
+
 pub struct Sha256HashFn;
 
 impl HashFn for Sha256HashFn {
@@ -87,14 +205,135 @@ impl HashFn for Sha256HashFn {
         hasher.update(value.to_be_bytes());
         SerdeOutput::new(hasher.finalize())
     }
+
+    fn backend() -> HashBackend {
+        sha256_backend()
+    }
 }
 
 impl<F: Field> ElementHashFn<F> for Sha256HashFn {
     fn hash_elements(elements: impl IntoIterator<Item = F>) -> Self::Digest {
         let mut byte_buffer = Vec::new();
         for element in elements {
-            element.serialize_uncompressed(&mut byte_buffer).unwrap();
+            write_canonical_element(&element, &mut byte_buffer);
+        }
+        Self::hash_chunks([&*byte_buffer])
+    }
+}
+
+pub struct Blake3HashFn;
+
+impl HashFn for Blake3HashFn {
+    type Digest = SerdeOutput<Blake3>;
+
+    const COLLISION_RESISTANCE: u32 = 128;
+
+    fn hash(bytes: impl IntoIterator<Item = u8>) -> SerdeOutput<Blake3> {
+        // BLAKE3 is only fast when it's given whole chunks
+        let bytes = bytes.into_iter().collect::<Vec<u8>>();
+        let mut hasher = Blake3::new();
+        hasher.update(&bytes);
+        SerdeOutput::new(hasher.finalize())
+    }
+
+    fn hash_chunks<'a>(slices: impl IntoIterator<Item = &'a [u8]>) -> SerdeOutput<Blake3> {
+        let mut hasher = Blake3::new();
+        slices.into_iter().for_each(|s| {
+            hasher.update(s);
+        });
+        SerdeOutput::new(hasher.finalize())
+    }
+
+    fn merge(v0: &SerdeOutput<Blake3>, v1: &SerdeOutput<Blake3>) -> SerdeOutput<Blake3> {
+        let mut hasher = Blake3::new();
+        hasher.update(v0);
+        hasher.update(v1);
+        SerdeOutput::new(hasher.finalize())
+    }
+
+    /// Uses BLAKE3's native keyed hashing mode
+    fn hash_chunks_keyed<'a>(
+        key: &'a [u8; 32],
+        slices: impl IntoIterator<Item = &'a [u8]>,
+    ) -> SerdeOutput<Blake3> {
+        let mut hasher = Blake3::new_keyed(key);
+        slices.into_iter().for_each(|s| {
+            hasher.update(s);
+        });
+        SerdeOutput::new(hasher.finalize())
+    }
+
+    fn merge_with_int(seed: &SerdeOutput<Blake3>, value: u64) -> SerdeOutput<Blake3> {
+        let mut hasher = Blake3::new();
+        hasher.update(seed);
+        hasher.update(&value.to_be_bytes());
+        SerdeOutput::new(hasher.finalize())
+    }
+
+    fn backend() -> HashBackend {
+        blake3_backend()
+    }
+}
+
+impl<F: Field> ElementHashFn<F> for Blake3HashFn {
+    fn hash_elements(elements: impl IntoIterator<Item = F>) -> Self::Digest {
+        let mut byte_buffer = Vec::new();
+        for element in elements {
+            write_canonical_element(&element, &mut byte_buffer);
         }
         Self::hash_chunks([&*byte_buffer])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::write_canonical_element;
+    use ark_ff::Field;
+    use ark_ff_optimized::fp64::Fp as Fp64;
+    use ark_serialize::CanonicalSerialize;
+
+    #[test]
+    fn canonical_encoding_is_little_endian() {
+        let element = Fp64::from(0x0102_0304_0506_0708_u64);
+        let mut bytes = Vec::new();
+
+        write_canonical_element(&element, &mut bytes);
+
+        assert_eq!([8, 7, 6, 5, 4, 3, 2, 1], *bytes);
+    }
+
+    #[test]
+    fn canonical_encoding_matches_arkworks_serialization() {
+        let elements = [
+            Fp64::ZERO,
+            Fp64::ONE,
+            -Fp64::ONE,
+            Fp64::from(0x0102_0304_0506_0708_u64),
+        ];
+        let mut bytes = Vec::new();
+        let mut serialized = Vec::new();
+
+        for element in &elements {
+            write_canonical_element(element, &mut bytes);
+            element.serialize_uncompressed(&mut serialized).unwrap();
+        }
+
+        assert_eq!(serialized, bytes);
+    }
+
+    #[test]
+    fn blake3_hash_matches_reference() {
+        use super::Blake3HashFn;
+        use super::HashFn;
+        // longer than a BLAKE3 chunk
+        let bytes = (0..=u8::MAX).cycle().take(3000).collect::<Vec<u8>>();
+
+        let digest = Blake3HashFn::hash(bytes.iter().copied());
+
+        assert_eq!(blake3::hash(&bytes).as_bytes()[..], digest[..]);
+        assert_eq!(
+            Blake3HashFn::hash_chunks([&bytes[..100], &bytes[100..]]),
+            digest
+        );
+    }
+}
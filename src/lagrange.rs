@@ -0,0 +1,174 @@
+//! Lagrange kernel columns
+//!
+//! For a point `r = (r_0, ..., r_(v-1))` the Lagrange kernel column of a trace
+//! with `n = 2^v` rows has the value `c_i = eq(r, i) = ∏_j (r_j if bit_j(i) = 1
+//! else 1 - r_j)` in row `i`. The inner product of the column with any other
+//! column `f` is the multilinear extension of `f` evaluated at `r`. GKR and
+//! `LogUp` style protocols use this to reduce a claim about a sum over the
+//! whole trace to a single column of the trace without the verifier having to
+//! do work linear in the trace length.
+//!
+//! The column is committed to as an extension column with `r` drawn from the
+//! verifier's challenges. The constraints returned by
+//! [`lagrange_kernel_constraints`] only need `O(v)` work to check:
+//! * boundary: `c_0 = ∏_j (1 - r_j)`
+//! * transitions for each `j`: `(1 - r_j) * c_(i + 2^j) = r_j * c_i` for every
+//!   row `i` that is a multiple of `2^(j + 1)`
+//!
+//! Starting from the first row, transition `v - 1` fixes row `2^(v-1)`,
+//! transition `v - 2` fixes rows `2^(v-2)` and `3 * 2^(v-2)` and so on until
+//! transition `0` fixes all odd rows. Together they fix every value in the
+//! column.
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::constraints::ExecutionTraceColumn;
+use crate::constraints::VerifierChallenge;
+use crate::expression::Expr;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::StarkExtensionOf;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ministark_gpu::GpuFftField;
+use num_traits::Pow;
+
+/// Returns the values of the Lagrange kernel column for the point `r`. The
+/// column has `2^r.len()` rows.
+pub fn lagrange_kernel_column<F: Field>(r: &[F]) -> GpuVec<F> {
+    let mut column = Vec::with_capacity_in(1 << r.len(), GpuAllocator);
+    column.push(F::one());
+    // each iteration doubles the column. The new half has bit `j` set
+    for &r_j in r {
+        let len = column.len();
+        column.extend_from_within(..);
+        let (low, high) = column.split_at_mut(len);
+        let one_minus_r_j = F::one() - r_j;
+        for (lo, hi) in low.iter_mut().zip(high) {
+            *hi *= r_j;
+            *lo *= one_minus_r_j;
+        }
+    }
+    column
+}
+
+/// Evaluates the Lagrange kernel `eq(r, point) = ∏_j (r_j * p_j + (1 - r_j) *
+/// (1 - p_j))`. When `point` is a binary decomposition of `i` this is the value
+/// of the Lagrange kernel column in row `i`.
+pub fn eval_lagrange_kernel<F: Field>(r: &[F], point: &[F]) -> F {
+    assert_eq!(r.len(), point.len());
+    r.iter()
+        .zip(point)
+        .map(|(&r_j, &p_j)| r_j * p_j + (F::one() - r_j) * (F::one() - p_j))
+        .product()
+}
+
+/// Returns the constraints for a Lagrange kernel column. The `j`-th challenge
+/// is the `j`-th coordinate of the kernel's point and there must be
+/// `log2(trace_len)` challenges.
+pub fn lagrange_kernel_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    column: &impl ExecutionTraceColumn,
+    challenges: &[impl VerifierChallenge],
+    trace_len: usize,
+) -> Vec<Constraint<FieldVariant<Fp, Fq>>> {
+    use AlgebraicItem::*;
+    assert!(trace_len.is_power_of_two());
+    assert_eq!(trace_len.ilog2() as usize, challenges.len());
+    let one = Constant(FieldVariant::Fp(Fp::one()));
+
+    // ensure `c_0 = ∏_j (1 - r_j)`
+    // symbolically divide `(x - t_0)` where `t_0 = 1`
+    let first_row_value = challenges
+        .iter()
+        .map(|r_j| Expr::from(one) - r_j.challenge())
+        .product::<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>();
+    let boundary_constraint = (column.curr() - first_row_value) / (X - one);
+
+    // ensure `(1 - r_j) * c_(i + 2^j) = r_j * c_i` for rows that are a multiple
+    // of `2^(j + 1)`. These rows are the subgroup of size `trace_len / 2^(j+1)`
+    // NOTE: `x^m - 1 = ∏ (x - w)` for all `w` in the subgroup of size `m`
+    let transition_constraints = challenges.iter().enumerate().map(|(j, r_j)| {
+        let step = 1 << j;
+        let subgroup_size = trace_len >> (j + 1);
+        let constraint = (Expr::from(one) - r_j.challenge()) * column.offset(step)
+            - r_j.challenge() * column.curr();
+        constraint / (X.pow(subgroup_size) - one)
+    });
+
+    core::iter::once(boundary_constraint)
+        .chain(transition_constraints)
+        .map(Constraint::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::eval_lagrange_kernel;
+    use super::lagrange_kernel_column;
+    use super::lagrange_kernel_constraints;
+    use crate::constraints::AlgebraicItem;
+    use crate::utils::FieldVariant;
+    use ark_ff::One;
+    use ark_ff::UniformRand;
+    use ark_ff::Zero;
+    use ark_poly::EvaluationDomain;
+    use ark_poly::Radix2EvaluationDomain;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    fn check_column(column: &[Fp], r: &[Fp]) -> bool {
+        let trace_len = column.len();
+        let domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+        let challenges = (0..r.len()).collect::<Vec<usize>>();
+        let constraints = lagrange_kernel_constraints::<Fp, Fp>(&0, &challenges, trace_len);
+        constraints.iter().all(|constraint| {
+            (0..trace_len).all(|i| {
+                use AlgebraicItem::*;
+                let x = domain.element(i);
+                let eval = constraint.check(&mut |leaf| match leaf {
+                    X => FieldVariant::Fp(x),
+                    &Constant(v) => v,
+                    &Challenge(j) => FieldVariant::Fp(r[j]),
+                    &Trace(0, offset) => {
+                        // NOTE: all offsets are non-negative
+                        let row = (i + offset.unsigned_abs()) % trace_len;
+                        FieldVariant::Fp(column[row])
+                    }
+                    _ => unreachable!(),
+                });
+                eval.is_some()
+            })
+        })
+    }
+
+    #[test]
+    fn lagrange_kernel_column_satisfies_constraints() {
+        let mut rng = ark_std::test_rng();
+        let r = (0..5).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+        let mut column = lagrange_kernel_column(&r);
+
+        assert!(check_column(&column, &r));
+        column[13] += Fp::one();
+        assert!(!check_column(&column, &r));
+    }
+
+    #[test]
+    fn lagrange_kernel_column_matches_kernel() {
+        let mut rng = ark_std::test_rng();
+        let r = (0..4).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+        let column = lagrange_kernel_column(&r);
+
+        for (i, value) in column.iter().enumerate() {
+            let bits = (0..r.len())
+                .map(|j| {
+                    if i >> j & 1 == 1 {
+                        Fp::one()
+                    } else {
+                        Fp::zero()
+                    }
+                })
+                .collect::<Vec<Fp>>();
+            assert_eq!(eval_lagrange_kernel(&r, &bits), *value);
+        }
+    }
+}
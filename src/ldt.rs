@@ -0,0 +1,645 @@
+//! Low-degree tests
+//!
+//! The verifier checks that the DEEP composition codeword is close to a low
+//! degree polynomial with a low-degree test. FRI is used for most domains.
+//! For tiny domains the Merkle paths of the FRI layers cost more than the
+//! polynomial itself so the proof carries the polynomial's coefficients
+//! instead and the verifier evaluates them at the query points (see
+//! [`DirectProver`] and [`DirectVerifier`]). The prover and verifier select
+//! the test by the size of the LDE domain (see [`is_direct`]). Larger domains
+//! can be tested with the experimental STIR protocol instead of FRI (see
+//! [`crate::stir`] and [`FriOptions::with_stir`]).
+//!
+//! All tests produce a [`FriProof`] so the proof format doesn't depend on the
+//! test. A direct proof has no layers and its remainder holds the coefficients
+//! of the polynomial over the LDE coset (rather than the subgroup).
+use crate::domain::coeffs_domain_size;
+use crate::fri::check_codeword;
+use crate::fri::FriOptions;
+use crate::fri::FriProof;
+use crate::fri::FriProver;
+use crate::fri::FriTailClaim;
+use crate::fri::FriVerifier;
+use crate::fri::LayerProof;
+use crate::fri::ProverChannel;
+use crate::fri::ProverError;
+use crate::fri::VerificationError;
+use crate::hash::Digest;
+use crate::merkle::MatrixMerkleTree;
+use crate::random::PublicCoin;
+use crate::random::ReseedTag;
+use crate::rs_code;
+use crate::stir::StirProver;
+use crate::stir::StirVerifier;
+use crate::utils::GpuVec;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_poly::domain::DomainCoeff;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::DenseUVPolynomial;
+use ark_poly::EvaluationDomain;
+use ark_poly::Polynomial;
+use ark_poly::Radix2EvaluationDomain;
+use ministark_gpu::GpuField;
+use std::marker::PhantomData;
+
+/// Largest LDE domain that's tested directly rather than with FRI
+pub const MAX_DIRECT_DOMAIN_SIZE: usize = 256;
+
+/// Returns true if codewords over an LDE domain of size `domain_size` are
+/// tested directly
+pub const fn is_direct(domain_size: usize) -> bool {
+    domain_size <= MAX_DIRECT_DOMAIN_SIZE
+}
+
+/// Prover of a low-degree test
+pub trait LowDegreeTest<F: GpuField + Field, D: Digest, M: MatrixMerkleTree<F>> {
+    /// Commits to the bit-reversed `evaluations` over the LDE domain of a
+    /// polynomial of degree `degree`
+    fn commit(
+        &mut self,
+        channel: &mut impl ProverChannel<Field = F, Digest = D>,
+        evaluations: GpuVec<F>,
+        degree: usize,
+    ) -> Result<(), ProverError>;
+
+    /// Returns the number of committed layers. Used to draw independent layer
+    /// positions (see [`FriOptions::with_independent_queries`]).
+    fn num_layers(&self) -> usize;
+
+    /// Opens the commitments at the query positions `positions` and
+    /// `layer_positions`
+    fn into_proof(
+        self,
+        positions: &[usize],
+        layer_positions: &[Vec<usize>],
+    ) -> Result<FriProof<F, D, M>, ProverError>;
+}
+
+/// Verifier of a low-degree test
+pub trait LowDegreeTestVerifier<F: GpuField + Field, D: Digest, M: MatrixMerkleTree<F>> {
+    /// Alphas of every fold (see [`FriVerifier::layer_alphas`])
+    fn layer_alphas(&self) -> &[F];
+
+    /// Draws the query positions of the layers after the first (see
+    /// [`FriVerifier::draw_layer_positions`])
+    fn draw_layer_positions(
+        &mut self,
+        public_coin: &mut impl PublicCoin<Field = F, Digest = D>,
+        num_queries: usize,
+    );
+
+    /// See [`FriVerifier::verify_head`]
+    fn verify_head(
+        &mut self,
+        positions: &[usize],
+        evaluations: &[F],
+    ) -> Result<FriTailClaim<F>, VerificationError>;
+
+    /// See [`FriVerifier::verify_tail_layers`]
+    fn verify_tail_layers(
+        &self,
+        claim: FriTailClaim<F>,
+        layers: impl IntoIterator<Item = LayerProof<F, M>>,
+    ) -> Result<(), VerificationError>;
+}
+
+impl<
+        F: GpuField + Field + DomainCoeff<F::FftField>,
+        D: Digest,
+        M: MatrixMerkleTree<F, Root = D>,
+    > LowDegreeTest<F, D, M> for FriProver<F, D, M>
+where
+    F::FftField: FftField,
+{
+    fn commit(
+        &mut self,
+        channel: &mut impl ProverChannel<Field = F, Digest = D>,
+        evaluations: GpuVec<F>,
+        degree: usize,
+    ) -> Result<(), ProverError> {
+        self.build_layers(channel, evaluations, degree)
+    }
+
+    fn num_layers(&self) -> usize {
+        Self::num_layers(self)
+    }
+
+    fn into_proof(
+        self,
+        positions: &[usize],
+        layer_positions: &[Vec<usize>],
+    ) -> Result<FriProof<F, D, M>, ProverError> {
+        self.into_proof_with_layer_positions(positions, layer_positions)
+    }
+}
+
+impl<
+        F: GpuField + Field + DomainCoeff<F::FftField>,
+        D: Digest,
+        M: MatrixMerkleTree<F, Root = D>,
+    > LowDegreeTestVerifier<F, D, M> for FriVerifier<F, D, M>
+where
+    F::FftField: FftField,
+{
+    fn layer_alphas(&self) -> &[F] {
+        &self.layer_alphas
+    }
+
+    fn draw_layer_positions(
+        &mut self,
+        public_coin: &mut impl PublicCoin<Field = F, Digest = D>,
+        num_queries: usize,
+    ) {
+        Self::draw_layer_positions(self, public_coin, num_queries);
+    }
+
+    fn verify_head(
+        &mut self,
+        positions: &[usize],
+        evaluations: &[F],
+    ) -> Result<FriTailClaim<F>, VerificationError> {
+        Self::verify_head(self, positions, evaluations)
+    }
+
+    fn verify_tail_layers(
+        &self,
+        claim: FriTailClaim<F>,
+        layers: impl IntoIterator<Item = LayerProof<F, M>>,
+    ) -> Result<(), VerificationError> {
+        Self::verify_tail_layers(self, claim, layers)
+    }
+}
+
+/// Sends the coefficients of the polynomial to the verifier
+pub struct DirectProver<F: GpuField, D: Digest, M: MatrixMerkleTree<F>> {
+    options: FriOptions,
+    coeffs: Vec<F>,
+    _phantom: PhantomData<(D, M)>,
+}
+
+impl<
+        F: GpuField + Field + DomainCoeff<F::FftField>,
+        D: Digest,
+        M: MatrixMerkleTree<F, Root = D>,
+    > DirectProver<F, D, M>
+where
+    F::FftField: FftField,
+{
+    pub const fn new(options: FriOptions) -> Self {
+        Self {
+            options,
+            coeffs: Vec::new(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<
+        F: GpuField + Field + DomainCoeff<F::FftField>,
+        D: Digest,
+        M: MatrixMerkleTree<F, Root = D>,
+    > LowDegreeTest<F, D, M> for DirectProver<F, D, M>
+where
+    F::FftField: FftField,
+{
+    fn commit(
+        &mut self,
+        channel: &mut impl ProverChannel<Field = F, Digest = D>,
+        evaluations: GpuVec<F>,
+        degree: usize,
+    ) -> Result<(), ProverError> {
+        check_codeword(&self.options, evaluations.len(), degree)?;
+        let offset = self.options.domain_offset::<F>();
+        let coeffs = rs_code::interpolate(&evaluations, offset);
+        let (coeffs, zero_coeffs) = coeffs.split_at(degree + 1);
+        if !zero_coeffs.iter().all(F::is_zero) {
+            return Err(ProverError::NotLowDegree { max_degree: degree });
+        }
+        channel.commit_remainder(coeffs);
+        self.coeffs = coeffs.to_vec();
+        Ok(())
+    }
+
+    fn num_layers(&self) -> usize {
+        0
+    }
+
+    fn into_proof(
+        self,
+        _positions: &[usize],
+        _layer_positions: &[Vec<usize>],
+    ) -> Result<FriProof<F, D, M>, ProverError> {
+        Ok(FriProof::new(Vec::new(), Vec::new(), self.coeffs))
+    }
+}
+
+/// Evaluates the coefficients sent by a [`DirectProver`] at the query
+/// positions
+pub struct DirectVerifier<F: GpuField + Field, D: Digest, M: MatrixMerkleTree<F, Root = D>>
+where
+    F::FftField: FftField,
+{
+    options: FriOptions,
+    max_poly_degree: usize,
+    proof: FriProof<F, D, M>,
+    domain: Radix2EvaluationDomain<F::FftField>,
+}
+
+impl<
+        F: GpuField + Field + DomainCoeff<F::FftField>,
+        D: Digest,
+        M: MatrixMerkleTree<F, Root = D>,
+    > DirectVerifier<F, D, M>
+where
+    F::FftField: FftField,
+{
+    pub fn new(
+        public_coin: &mut impl PublicCoin<Field = F, Digest = D>,
+        options: FriOptions,
+        proof: FriProof<F, D, M>,
+        max_poly_degree: usize,
+    ) -> Result<Self, VerificationError> {
+        let num_layers = proof.layer_caps.len().max(proof.layers.len());
+        if num_layers != 0 {
+            return Err(VerificationError::NumLayersMismatch {
+                expected: 0,
+                actual: num_layers,
+            });
+        }
+        let domain = coeffs_domain_size(max_poly_degree)
+            .checked_mul(options.blowup_factor())
+            .and_then(Radix2EvaluationDomain::new)
+            .ok_or_else(|| VerificationError::DomainTooLarge {
+                max_poly_degree,
+                blowup_factor: options.blowup_factor(),
+            })?;
+        public_coin
+            .reseed_with_field_element_vector(ReseedTag::FriRemainder, &proof.remainder_coeffs);
+        Ok(Self {
+            options,
+            max_poly_degree,
+            proof,
+            domain,
+        })
+    }
+}
+
+impl<
+        F: GpuField + Field + DomainCoeff<F::FftField>,
+        D: Digest,
+        M: MatrixMerkleTree<F, Root = D>,
+    > LowDegreeTestVerifier<F, D, M> for DirectVerifier<F, D, M>
+where
+    F::FftField: FftField,
+{
+    fn layer_alphas(&self) -> &[F] {
+        &[]
+    }
+
+    fn draw_layer_positions(
+        &mut self,
+        _public_coin: &mut impl PublicCoin<Field = F, Digest = D>,
+        _num_queries: usize,
+    ) {
+    }
+
+    fn verify_head(
+        &mut self,
+        positions: &[usize],
+        evaluations: &[F],
+    ) -> Result<FriTailClaim<F>, VerificationError> {
+        if positions.len() != evaluations.len() {
+            return Err(VerificationError::NumPositionEvaluationMismatch);
+        }
+        Ok(FriTailClaim {
+            layer: 0,
+            positions: positions.to_vec(),
+            evaluations: evaluations.to_vec(),
+            domain_size: self.domain.size(),
+            domain_generator: self.domain.group_gen(),
+        })
+    }
+
+    fn verify_tail_layers(
+        &self,
+        claim: FriTailClaim<F>,
+        layers: impl IntoIterator<Item = LayerProof<F, M>>,
+    ) -> Result<(), VerificationError> {
+        let num_layers = layers.into_iter().count();
+        if num_layers != 0 {
+            return Err(VerificationError::NumLayersMismatch {
+                expected: 0,
+                actual: num_layers,
+            });
+        }
+
+        let poly = DensePolynomial::from_coefficients_slice(&self.proof.remainder_coeffs);
+        if poly.degree() > self.max_poly_degree {
+            return Err(VerificationError::RemainderDegreeTooHigh {
+                degree: poly.degree(),
+                max_degree: self.max_poly_degree,
+            });
+        }
+        let offset = self.options.domain_offset::<F>();
+        for (&position, expected) in claim.positions.iter().zip(&claim.evaluations) {
+            let x = rs_code::evaluation_point(claim.domain_size, offset, position);
+            let y = poly.iter().rfold(F::zero(), |mut result, coeff| {
+                result *= x;
+                result + coeff
+            });
+            if *expected != y {
+                return Err(VerificationError::RemainderMismatch {
+                    position,
+                    expected: expected.to_string(),
+                    got: y.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Low-degree test prover selected by the size of the LDE domain (see
+/// [`is_direct`]) and the options
+pub enum LdtProver<F: GpuField, D: Digest, M: MatrixMerkleTree<F>> {
+    Fri(FriProver<F, D, M>),
+    Direct(DirectProver<F, D, M>),
+    Stir(StirProver<F, D, M>),
+}
+
+impl<
+        F: GpuField + Field + DomainCoeff<F::FftField>,
+        D: Digest,
+        M: MatrixMerkleTree<F, Root = D>,
+    > LdtProver<F, D, M>
+where
+    F::FftField: FftField,
+{
+    pub const fn new(options: FriOptions, domain_size: usize) -> Self {
+        if is_direct(domain_size) {
+            Self::Direct(DirectProver::new(options))
+        } else if let Some(num_queries) = options.stir_queries() {
+            Self::Stir(StirProver::new(options, num_queries))
+        } else {
+            Self::Fri(FriProver::new(options))
+        }
+    }
+}
+
+impl<
+        F: GpuField + Field + DomainCoeff<F::FftField>,
+        D: Digest,
+        M: MatrixMerkleTree<F, Root = D>,
+    > LowDegreeTest<F, D, M> for LdtProver<F, D, M>
+where
+    F::FftField: FftField,
+{
+    fn commit(
+        &mut self,
+        channel: &mut impl ProverChannel<Field = F, Digest = D>,
+        evaluations: GpuVec<F>,
+        degree: usize,
+    ) -> Result<(), ProverError> {
+        match self {
+            Self::Fri(prover) => LowDegreeTest::commit(prover, channel, evaluations, degree),
+            Self::Direct(prover) => prover.commit(channel, evaluations, degree),
+            Self::Stir(prover) => prover.commit(channel, evaluations, degree),
+        }
+    }
+
+    fn num_layers(&self) -> usize {
+        match self {
+            Self::Fri(prover) => LowDegreeTest::num_layers(prover),
+            Self::Direct(prover) => prover.num_layers(),
+            Self::Stir(prover) => prover.num_layers(),
+        }
+    }
+
+    fn into_proof(
+        self,
+        positions: &[usize],
+        layer_positions: &[Vec<usize>],
+    ) -> Result<FriProof<F, D, M>, ProverError> {
+        match self {
+            Self::Fri(prover) => LowDegreeTest::into_proof(prover, positions, layer_positions),
+            Self::Direct(prover) => prover.into_proof(positions, layer_positions),
+            Self::Stir(prover) => prover.into_proof(positions, layer_positions),
+        }
+    }
+}
+
+/// Low-degree test verifier selected by the size of the LDE domain (see
+/// [`is_direct`]) and the options
+pub enum LdtVerifier<F: GpuField + Field, D: Digest, M: MatrixMerkleTree<F, Root = D>>
+where
+    F::FftField: FftField,
+{
+    Fri(FriVerifier<F, D, M>),
+    Direct(DirectVerifier<F, D, M>),
+    Stir(StirVerifier<F, D, M>),
+}
+
+impl<
+        F: GpuField + Field + DomainCoeff<F::FftField>,
+        D: Digest,
+        M: MatrixMerkleTree<F, Root = D>,
+    > LdtVerifier<F, D, M>
+where
+    F::FftField: FftField,
+{
+    pub fn new(
+        public_coin: &mut impl PublicCoin<Field = F, Digest = D>,
+        options: FriOptions,
+        proof: FriProof<F, D, M>,
+        max_poly_degree: usize,
+    ) -> Result<Self, VerificationError> {
+        let domain_size =
+            coeffs_domain_size(max_poly_degree).saturating_mul(options.blowup_factor());
+        Ok(if is_direct(domain_size) {
+            Self::Direct(DirectVerifier::new(
+                public_coin,
+                options,
+                proof,
+                max_poly_degree,
+            )?)
+        } else if let Some(num_queries) = options.stir_queries() {
+            Self::Stir(StirVerifier::new(
+                public_coin,
+                options,
+                num_queries,
+                proof,
+                max_poly_degree,
+            )?)
+        } else {
+            Self::Fri(FriVerifier::new(
+                public_coin,
+                options,
+                proof,
+                max_poly_degree,
+            )?)
+        })
+    }
+}
+
+impl<
+        F: GpuField + Field + DomainCoeff<F::FftField>,
+        D: Digest,
+        M: MatrixMerkleTree<F, Root = D>,
+    > LowDegreeTestVerifier<F, D, M> for LdtVerifier<F, D, M>
+where
+    F::FftField: FftField,
+{
+    fn layer_alphas(&self) -> &[F] {
+        match self {
+            Self::Fri(verifier) => LowDegreeTestVerifier::layer_alphas(verifier),
+            Self::Direct(verifier) => verifier.layer_alphas(),
+            Self::Stir(verifier) => verifier.layer_alphas(),
+        }
+    }
+
+    fn draw_layer_positions(
+        &mut self,
+        public_coin: &mut impl PublicCoin<Field = F, Digest = D>,
+        num_queries: usize,
+    ) {
+        match self {
+            Self::Fri(verifier) => {
+                LowDegreeTestVerifier::draw_layer_positions(verifier, public_coin, num_queries);
+            }
+            Self::Direct(verifier) => verifier.draw_layer_positions(public_coin, num_queries),
+            Self::Stir(verifier) => verifier.draw_layer_positions(public_coin, num_queries),
+        }
+    }
+
+    fn verify_head(
+        &mut self,
+        positions: &[usize],
+        evaluations: &[F],
+    ) -> Result<FriTailClaim<F>, VerificationError> {
+        match self {
+            Self::Fri(verifier) => {
+                LowDegreeTestVerifier::verify_head(verifier, positions, evaluations)
+            }
+            Self::Direct(verifier) => verifier.verify_head(positions, evaluations),
+            Self::Stir(verifier) => verifier.verify_head(positions, evaluations),
+        }
+    }
+
+    fn verify_tail_layers(
+        &self,
+        claim: FriTailClaim<F>,
+        layers: impl IntoIterator<Item = LayerProof<F, M>>,
+    ) -> Result<(), VerificationError> {
+        match self {
+            Self::Fri(verifier) => {
+                LowDegreeTestVerifier::verify_tail_layers(verifier, claim, layers)
+            }
+            Self::Direct(verifier) => verifier.verify_tail_layers(claim, layers),
+            Self::Stir(verifier) => verifier.verify_tail_layers(claim, layers),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DirectProver;
+    use super::DirectVerifier;
+    use super::LowDegreeTest;
+    use super::LowDegreeTestVerifier;
+    use crate::fixtures::random_codeword;
+    use crate::fixtures::run_ldt;
+    use crate::fixtures::MerkleTree;
+    use crate::fixtures::TestChannel;
+    use crate::fixtures::TestCoin;
+    use crate::fri::FriOptions;
+    use crate::fri::VerificationError;
+    use crate::hash::HashFn;
+    use crate::hash::Sha256HashFn;
+    use crate::random::PublicCoin;
+    use crate::rs_code;
+    use crate::utils::GpuAllocator;
+    use ark_ff::FftField;
+    use ark_poly::univariate::DensePolynomial;
+    use ark_poly::DenseUVPolynomial;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn direct_test_checks_evaluations() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let options = FriOptions::new(4, 2, 4);
+        let max_poly_degree = 31;
+        let poly = DensePolynomial::<Fp>::rand(max_poly_degree, &mut rng);
+        let codeword = rs_code::encode(&poly.coeffs, 4, Fp::GENERATOR);
+        let seed = Sha256HashFn::hash([]);
+        let positions = [1, 20, 100];
+        let evaluations = positions.map(|position| codeword[position]);
+
+        let mut prover = DirectProver::<Fp, _, MerkleTree>::new(options);
+        let mut channel = TestChannel(TestCoin::new(seed.clone()));
+        prover
+            .commit(
+                &mut channel,
+                codeword.to_vec_in(GpuAllocator),
+                max_poly_degree,
+            )
+            .unwrap();
+        let proof = prover.into_proof(&positions, &[]).unwrap();
+        assert_eq!(poly.coeffs, proof.remainder_coeffs);
+
+        let verify = |max_poly_degree, evaluations: &[Fp]| {
+            let mut public_coin = TestCoin::new(seed.clone());
+            let mut verifier =
+                DirectVerifier::new(&mut public_coin, options, proof.clone(), max_poly_degree)?;
+            assert_eq!(channel.0.seed, public_coin.seed);
+            let claim = verifier.verify_head(&positions, evaluations)?;
+            verifier.verify_tail_layers(claim, [])
+        };
+        let mut wrong_evaluations = evaluations;
+        wrong_evaluations[1] += Fp::from(1u8);
+
+        verify(max_poly_degree, &evaluations).unwrap();
+        assert!(matches!(
+            verify(max_poly_degree, &wrong_evaluations),
+            Err(VerificationError::RemainderMismatch { position: 20, .. })
+        ));
+        assert!(matches!(
+            verify(max_poly_degree - 1, &evaluations),
+            Err(VerificationError::RemainderDegreeTooHigh { .. })
+        ));
+    }
+
+    #[test]
+    fn fri_and_stir_agree_on_shared_instances() {
+        let fri = FriOptions::new(4, 4, 4);
+        let stir = fri.with_stir(16);
+        let max_poly_degree = 255;
+        let (_, codeword) = random_codeword(max_poly_degree, 4, 0);
+        let positions = [1, 20, 100, 1000];
+        let evaluations = positions.map(|position| codeword[position]);
+        let mut wrong_evaluations = evaluations;
+        wrong_evaluations[2] += Fp::from(1u8);
+
+        for (label, options) in [("FRI", fri), ("STIR", stir)] {
+            let run = |max_poly_degree, evaluations: &[Fp]| {
+                let degree = 255;
+                run_ldt(
+                    options,
+                    &codeword,
+                    degree,
+                    max_poly_degree,
+                    &positions,
+                    evaluations,
+                )
+            };
+
+            assert!(run(max_poly_degree, &evaluations).is_ok(), "{label}");
+            assert!(run(max_poly_degree, &wrong_evaluations).is_err(), "{label}");
+            assert!(run(127, &evaluations).is_err(), "{label}");
+        }
+    }
+}
@@ -16,7 +16,6 @@
     allocator_api,
     let_chains,
     array_windows,
-    array_chunks,
     iter_partition_in_place,
     slice_flatten,
     slice_as_chunks,
@@ -26,31 +25,60 @@
     exclusive_range_pattern,
     vec_into_raw_parts,
     return_position_impl_trait_in_trait,
+    return_type_notation,
     iter_collect_into
 )]
 
 // TODO: make some of these modules private
 #[macro_use]
 pub mod macros;
+pub mod advice;
 pub mod air;
+pub mod air_composer;
+pub mod air_spec;
+pub mod any;
+#[cfg(feature = "bench")]
+pub mod bench;
+pub mod bitwise;
+pub mod byte_table;
 pub mod challenges;
 pub mod channel;
+pub mod compare;
 pub mod composer;
 pub mod constraints;
+pub mod continuation;
 pub mod debug;
+pub mod domain;
 pub mod eval_cpu;
 pub mod eval_gpu;
 pub mod expression;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(test)]
+mod fixtures;
 pub mod fri;
 pub mod hash;
 pub mod hints;
+pub mod lagrange;
+pub mod ldt;
 pub mod matrix;
 pub mod merkle;
+pub mod positions;
 pub mod proof;
 pub mod prover;
+pub mod public_memory;
+pub mod ram;
 pub mod random;
+pub mod rs_code;
+#[cfg(feature = "service")]
+pub mod service;
+pub mod setup;
+pub mod stack;
 pub mod stark;
+pub mod stir;
 pub mod trace;
+pub mod transcript;
+pub mod u32_chip;
 pub mod utils;
 pub mod verifier;
 
@@ -70,13 +98,18 @@ use core::ops::MulAssign;
 use core::ops::Sub;
 use core::ops::SubAssign;
 use fri::FriOptions;
+use hash::Blake3HashFn;
+use hash::Digest;
+use hash::HashFn;
 pub use matrix::Matrix;
 use ministark_gpu::GpuAdd;
 use ministark_gpu::GpuFftField;
 use ministark_gpu::GpuField;
 use ministark_gpu::GpuFrom;
 use ministark_gpu::GpuMul;
+use positions::leaf_size;
 pub use proof::Proof;
+pub use proof::ProofView;
 pub use trace::Trace;
 
 // TODO: include ability to specify:
@@ -90,6 +123,13 @@ pub struct ProofOptions {
     pub grinding_factor: u8,
     pub fri_folding_factor: u8,
     pub fri_max_remainder_coeffs: u8,
+    pub fri_folds_per_layer: u8,
+    pub fri_independent_queries: bool,
+    pub fri_values_per_leaf: u8,
+    pub trace_values_per_leaf: u8,
+    pub merkle_cap_height: u8,
+    pub fri_stir: bool,
+    pub degree_adjusted_composition: bool,
 }
 
 impl ProofOptions {
@@ -118,16 +158,135 @@ impl ProofOptions {
             grinding_factor,
             fri_folding_factor,
             fri_max_remainder_coeffs,
+            fri_folds_per_layer: 1,
+            fri_independent_queries: false,
+            fri_values_per_leaf: 1,
+            trace_values_per_leaf: 1,
+            merkle_cap_height: 0,
+            fri_stir: false,
+            degree_adjusted_composition: true,
         }
     }
 
+    /// Folds FRI layers `fri_folds_per_layer` times between commitments (see
+    /// [`FriOptions::with_folds_per_layer`])
+    pub const fn with_fri_folds_per_layer(mut self, fri_folds_per_layer: u8) -> Self {
+        let layer_folding_factor =
+            (self.fri_folding_factor as usize).checked_pow(fri_folds_per_layer as u32);
+        assert!(fri_folds_per_layer >= 1);
+        assert!(matches!(layer_folding_factor, Some(2 | 4 | 8 | 16)));
+        self.fri_folds_per_layer = fri_folds_per_layer;
+        self
+    }
+
+    /// Samples the queries of each FRI layer independently (see
+    /// [`FriOptions::with_independent_queries`])
+    pub const fn with_fri_independent_queries(mut self, fri_independent_queries: bool) -> Self {
+        self.fri_independent_queries = fri_independent_queries;
+        self
+    }
+
+    /// Tests the DEEP composition codeword with the experimental STIR protocol
+    /// instead of FRI (see [`FriOptions::with_stir`]). The first round is
+    /// queried `num_queries` times.
+    pub const fn with_fri_stir(mut self, fri_stir: bool) -> Self {
+        self.fri_stir = fri_stir;
+        self
+    }
+
+    /// Commits to `fri_values_per_leaf` consecutive evaluations of a FRI layer
+    /// in each merkle leaf (see [`FriOptions::with_values_per_leaf`])
+    pub const fn with_fri_values_per_leaf(mut self, fri_values_per_leaf: u8) -> Self {
+        assert!(fri_values_per_leaf.is_power_of_two());
+        self.fri_values_per_leaf = fri_values_per_leaf;
+        self
+    }
+
+    /// Commits to `trace_values_per_leaf` consecutive rows of the trace LDEs in
+    /// each merkle leaf. Queries open every row of a leaf so the opened values
+    /// grow but the paths are `log2(trace_values_per_leaf)` shorter.
+    pub const fn with_trace_values_per_leaf(mut self, trace_values_per_leaf: u8) -> Self {
+        assert!(trace_values_per_leaf.is_power_of_two());
+        self.trace_values_per_leaf = trace_values_per_leaf;
+        self
+    }
+
+    /// Commits to the trace and FRI layers with the merkle caps of height
+    /// `merkle_cap_height` i.e. the `2^merkle_cap_height` nodes at that depth
+    /// are sent instead of the roots (see [`merkle::MerkleTree::cap`]). Every
+    /// path is `merkle_cap_height` nodes shorter.
+    pub const fn with_merkle_cap_height(mut self, merkle_cap_height: u8) -> Self {
+        self.merkle_cap_height = merkle_cap_height;
+        self
+    }
+
+    /// Composes constraints with degree adjusted terms i.e. `c * (alpha *
+    /// x^(D - d) + beta)` if true (the default) or a single coefficient per
+    /// constraint i.e. `c * alpha` if false (see
+    /// [`air::AirConfig::composition_constraint_with`]). A single coefficient
+    /// halves the number of composition coefficients but constraints are
+    /// only checked up to the composition degree rather than their own
+    /// degree. The security levels of [`Proof::security_report`] are the
+    /// same either way. A constraint only has a quotient below the
+    /// composition degree if it holds over the trace domain so soundness
+    /// doesn't rely on the degree of each quotient and the coefficients are
+    /// still drawn from the extension field.
+    pub const fn with_degree_adjusted_composition(
+        mut self,
+        degree_adjusted_composition: bool,
+    ) -> Self {
+        self.degree_adjusted_composition = degree_adjusted_composition;
+        self
+    }
+
+    /// Returns true if the options satisfy the checks of [`Self::new`] and the
+    /// builder methods. Options deserialized from a proof skip these checks so
+    /// the verifier rejects proofs with invalid options.
+    pub fn is_valid(&self) -> bool {
+        let layer_folding_factor =
+            usize::from(self.fri_folding_factor).checked_pow(self.fri_folds_per_layer.into());
+        (Self::MIN_NUM_QUERIES..=Self::MAX_NUM_QUERIES).contains(&self.num_queries)
+            && (Self::MIN_BLOWUP_FACTOR..=Self::MAX_BLOWUP_FACTOR).contains(&self.lde_blowup_factor)
+            && self.lde_blowup_factor.is_power_of_two()
+            && self.grinding_factor <= Self::MAX_GRINDING_FACTOR
+            && self.fri_folds_per_layer >= 1
+            && matches!(layer_folding_factor, Some(2 | 4 | 8 | 16))
+            && self.fri_max_remainder_coeffs.is_power_of_two()
+            && self.fri_values_per_leaf.is_power_of_two()
+            && self.trace_values_per_leaf.is_power_of_two()
+    }
+
+    /// Returns a hash of the options. Stable across releases as long as the
+    /// serialization of the options doesn't change.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut bytes = b"ministark-options".to_vec();
+        self.serialize_uncompressed(&mut bytes).unwrap();
+        Blake3HashFn::hash_chunks([&*bytes]).as_bytes()
+    }
+
+    /// Returns the number of rows of the trace LDEs in each merkle leaf of the
+    /// trace commitments for a trace of length `trace_len`
+    pub const fn trace_rows_per_leaf(&self, trace_len: usize) -> usize {
+        let lde_domain_size = trace_len * self.lde_blowup_factor as usize;
+        leaf_size(self.trace_values_per_leaf as usize, lde_domain_size)
+    }
+
     pub fn into_fri_options(self) -> FriOptions {
         // TODO: move fri params into struct
-        FriOptions::new(
+        let options = FriOptions::new(
             self.lde_blowup_factor.into(),
             self.fri_folding_factor.into(),
             self.fri_max_remainder_coeffs.into(),
         )
+        .with_folds_per_layer(self.fri_folds_per_layer.into())
+        .with_independent_queries(self.fri_independent_queries)
+        .with_values_per_leaf(self.fri_values_per_leaf.into())
+        .with_cap_height(self.merkle_cap_height.into());
+        if self.fri_stir {
+            options.with_stir(self.num_queries.into())
+        } else {
+            options
+        }
     }
 }
 
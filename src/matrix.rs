@@ -1,8 +1,13 @@
 use crate::constraints::ExecutionTraceColumn;
 use crate::hash::ElementHashFn;
+use crate::utils::four_step_fft_in_place;
+use crate::utils::four_step_ifft_in_place;
+#[cfg(feature = "gpu")]
+use crate::utils::gpu_min_domain_size;
 use crate::utils::horner_evaluate;
 use crate::utils::GpuAllocator;
 use crate::utils::GpuVec;
+use crate::utils::FOUR_STEP_FFT_MIN_DOMAIN_SIZE;
 use alloc::string::String;
 use alloc::string::ToString;
 use alloc::vec::Vec;
@@ -104,6 +109,16 @@ impl<F: Field> Matrix<F> {
         F: GpuField + DomainCoeff<F::FftField>,
         F::FftField: FftField,
     {
+        let planner = get_planner();
+        let max_fft_size = planner.max_fft_size::<F>();
+        if domain.size() > max_fft_size {
+            let ifft = planner.plan_chunked_ifft(domain, max_fft_size);
+            for column in &mut self.0 {
+                ifft.transform(column);
+            }
+            return self;
+        }
+
         let mut ifft = GpuIfft::from(domain);
 
         for column in &mut self.0 {
@@ -115,7 +130,6 @@ impl<F: Field> Matrix<F> {
         self
     }
 
-    #[cfg(not(feature = "gpu"))]
     fn into_polynomials_cpu(self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
     where
         F: GpuField + DomainCoeff<F::FftField>,
@@ -131,7 +145,11 @@ impl<F: Field> Matrix<F> {
                     // prevent cloning the memory we have to reconstruct a Vec from a GpuVec and
                     // convert it back to a GpuVec after the fft
                     let mut column = gpu_vec_to_vec(column);
-                    domain.ifft_in_place(&mut column);
+                    if domain.size() >= FOUR_STEP_FFT_MIN_DOMAIN_SIZE {
+                        four_step_ifft_in_place(domain, &mut column);
+                    } else {
+                        domain.ifft_in_place(&mut column);
+                    }
                     vec_to_gpu_vec(column)
                 })
                 .collect(),
@@ -147,10 +165,11 @@ impl<F: Field> Matrix<F> {
         // TODO: using the newtype pattern for type safety would be cool
         // i.e. take as input Matrix<Evaluations> and return Matrix<Polynomials>
         // https://doc.rust-lang.org/book/ch19-04-advanced-types.html
-        #[cfg(not(feature = "gpu"))]
-        return self.into_polynomials_cpu(domain);
         #[cfg(feature = "gpu")]
-        return self.into_polynomials_gpu(domain);
+        if domain.size() >= gpu_min_domain_size() {
+            return self.into_polynomials_gpu(domain);
+        }
+        self.into_polynomials_cpu(domain)
     }
 
     /// Interpolates the columns of the matrix over the domain
@@ -162,7 +181,6 @@ impl<F: Field> Matrix<F> {
         self.clone().into_polynomials(domain)
     }
 
-    #[cfg(not(feature = "gpu"))]
     fn into_evaluations_cpu(self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
     where
         F: GpuField + DomainCoeff<F::FftField>,
@@ -182,7 +200,12 @@ impl<F: Field> Matrix<F> {
                     // underlying memory. This wouldn't necessarily be page aligned (what gpu vec
                     // enforces) so it'll be unsafe to use for GPU.
                     let mut column = gpu_vec_to_vec(column);
-                    domain.fft_in_place(&mut column);
+                    if domain.size() >= FOUR_STEP_FFT_MIN_DOMAIN_SIZE {
+                        column.resize(domain.size(), F::zero());
+                        four_step_fft_in_place(domain, &mut column);
+                    } else {
+                        domain.fft_in_place(&mut column);
+                    }
                     vec_to_gpu_vec(column)
                 })
                 .collect(),
@@ -190,12 +213,31 @@ impl<F: Field> Matrix<F> {
     }
 
     #[cfg(feature = "gpu")]
-    fn into_evaluations_gpu(mut self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
+    fn into_evaluations_gpu(
+        mut self,
+        domain: Radix2EvaluationDomain<F::FftField>,
+        order: EvaluationOrder,
+    ) -> Self
     where
         F: GpuField,
         F::FftField: FftField,
     {
-        let mut fft = GpuFft::from(domain);
+        let planner = get_planner();
+        let max_fft_size = planner.max_fft_size::<F>();
+        if domain.size() > max_fft_size {
+            // LDE doesn't fit into GPU memory so it's split into multiple passes
+            let fft = planner.plan_chunked_fft(domain, max_fft_size);
+            for column in &mut self.0 {
+                column.resize(domain.size(), F::zero());
+                fft.transform(column);
+            }
+            if order == EvaluationOrder::BitReversed {
+                self.bit_reverse_rows();
+            }
+            return self;
+        }
+
+        let mut fft = GpuFft::from(domain).with_output_order(order);
 
         for column in &mut self.0 {
             column.resize(domain.size(), F::zero());
@@ -216,10 +258,11 @@ impl<F: Field> Matrix<F> {
         // TODO: using the newtype pattern for type safety would be cool
         // i.e. take as input Matrix<Polynomials> and return Matrix<Evaluations>
         // https://doc.rust-lang.org/book/ch19-04-advanced-types.html
-        #[cfg(not(feature = "gpu"))]
-        return self.into_evaluations_cpu(domain);
         #[cfg(feature = "gpu")]
-        return self.into_evaluations_gpu(domain);
+        if domain.size() >= gpu_min_domain_size() {
+            return self.into_evaluations_gpu(domain, EvaluationOrder::Natural);
+        }
+        self.into_evaluations_cpu(domain)
     }
 
     pub fn into_bit_reversed_evaluations(self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
@@ -227,8 +270,12 @@ impl<F: Field> Matrix<F> {
         F: GpuField + DomainCoeff<F::FftField>,
         F::FftField: FftField,
     {
-        let mut evaluations = self.into_evaluations(domain);
-        // TODO: remove this and just do regular in-order->out-of-order CT FFT
+        // the GPU FFT outputs bit-reversed evaluations without a permutation
+        #[cfg(feature = "gpu")]
+        if domain.size() >= gpu_min_domain_size() {
+            return self.into_evaluations_gpu(domain, EvaluationOrder::BitReversed);
+        }
+        let mut evaluations = self.into_evaluations_cpu(domain);
         evaluations.bit_reverse_rows();
         evaluations
     }
@@ -349,7 +396,17 @@ impl<F: Field> Matrix<F> {
         Self::new(vec![accumulator])
     }
 
-    pub fn bit_reverse_rows(&mut self) {
+    pub fn bit_reverse_rows(&mut self)
+    where
+        F: GpuField,
+    {
+        #[cfg(feature = "gpu")]
+        if self.num_rows() >= gpu_min_domain_size() {
+            for column in &mut self.0 {
+                ministark_gpu::plan::bit_reverse(column);
+            }
+            return;
+        }
         ark_std::cfg_iter_mut!(self.0).for_each(|col| bit_reverse(col));
     }
 
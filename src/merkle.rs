@@ -1,11 +1,17 @@
 use crate::hash::Digest;
 use crate::hash::ElementHashFn;
+use crate::hash::HashBackend;
 use crate::hash::HashFn;
 use crate::Matrix;
 use alloc::vec::Vec;
 use ark_ff::Field;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use ark_serialize::Compress;
+use ark_serialize::Read;
+use ark_serialize::SerializationError;
+use ark_serialize::Valid;
+use ark_serialize::Validate;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use snafu::Snafu;
@@ -34,22 +40,72 @@ pub trait MerkleTree: Sized + Send + Sync + Clone {
     /// Returns the root of the merkle tree
     fn root(&self) -> Self::Root;
 
+    /// Returns the merkle cap of height `cap_height` i.e. the `2^cap_height`
+    /// nodes at depth `cap_height` ordered from left to right. The cap of
+    /// height zero is the root. Paths of proofs against the cap are
+    /// `cap_height` nodes shorter. Caps never include the leaves so the cap
+    /// of a small tree can have fewer nodes (see [`Self::cap_size`]).
+    fn cap(&self, cap_height: u32) -> Vec<Self::Root>;
+
+    /// Returns the number of nodes in the cap of height `cap_height` of a tree
+    /// with `num_leaves` leaves
+    fn cap_size(num_leaves: usize, cap_height: u32) -> usize {
+        let max_cap_height = num_leaves.max(2).ilog2() - 1;
+        1 << cap_height.min(max_cap_height)
+    }
+
     /// Generates a merkle proof
     ///
     /// # Errors
     ///
     /// Returns an error if the leaf index is out of bounds.
-    fn prove(&self, indices: &[usize]) -> Result<Self::Proof, Error>;
+    fn prove(&self, indices: &[usize]) -> Result<Self::Proof, Error> {
+        self.prove_to_cap(indices, 0)
+    }
+
+    /// Generates a merkle proof whose paths end at the nodes of the cap of
+    /// height `cap_height` (see [`Self::cap`])
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the leaf index is out of bounds.
+    fn prove_to_cap(&self, indices: &[usize], cap_height: u32) -> Result<Self::Proof, Error>;
 
     /// Verifies a merkle proof
     ///
     /// # Errors
     ///
     /// This function returns an error if the proof fails verification.
-    fn verify(root: &Self::Root, proof: Self::Proof, indices: &[usize]) -> Result<(), Error>;
+    fn verify(
+        root: &Self::Root,
+        domain: MerkleTreeDomain,
+        proof: Self::Proof,
+        indices: &[usize],
+    ) -> Result<(), Error> {
+        Self::verify_against_cap(core::slice::from_ref(root), domain, proof, indices)
+    }
+
+    /// Verifies a merkle proof generated with [`Self::prove_to_cap`]. The
+    /// height of the cap is implied by its number of nodes.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the proof fails verification or the
+    /// number of nodes isn't the size of a cap of the tree.
+    fn verify_against_cap(
+        cap: &[Self::Root],
+        domain: MerkleTreeDomain,
+        proof: Self::Proof,
+        indices: &[usize],
+    ) -> Result<(), Error>;
 
     /// Returns the number of security bits
     fn security_level_bits() -> u32;
+
+    /// Returns the implementation of the hash function used on this CPU
+    fn hash_backend() -> HashBackend {
+        HashBackend::Portable
+    }
 }
 
 // TODO: all these merkle tree abstractions are way out of control. need to
@@ -58,26 +114,156 @@ pub trait MerkleTreeConfig: Send + Sync + Sized + 'static {
     type Digest: Digest;
     type Leaf: CanonicalDeserialize + CanonicalSerialize + Clone + Send + Sync + Sized + 'static;
 
-    fn hash_leaves(depth: u32, l0: &Self::Leaf, l1: &Self::Leaf) -> Self::Digest;
+    fn hash_leaves(key: &[u8; 32], depth: u32, l0: &Self::Leaf, l1: &Self::Leaf) -> Self::Digest;
 
-    fn hash_nodes(depth: u32, n0: &Self::Digest, n1: &Self::Digest) -> Self::Digest;
+    fn hash_nodes(key: &[u8; 32], depth: u32, n0: &Self::Digest, n1: &Self::Digest)
+        -> Self::Digest;
 
     fn security_level_bits() -> u32;
+
+    fn hash_backend() -> HashBackend {
+        HashBackend::Portable
+    }
 }
 
+/// Identifies the commitment a merkle tree belongs to.
+///
+/// Each tree is hashed under a key derived from its domain. This way a path
+/// opened against one tree (e.g. the base trace) can never resolve to the root
+/// of another tree (e.g. the composition trace or a FRI layer) even if both
+/// trees commit to the same values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MerkleTreeDomain {
+    BaseTrace,
+    ExtensionTrace,
+    CompositionTrace,
+    FriLayer(usize),
+    /// Domain for trees built by applications e.g. a commitment to public
+    /// inputs
+    Custom(usize),
+    /// Separately committed base trace column (see
+    /// [`crate::air::AirConfig::SEPARATELY_COMMITTED_BASE_COLUMNS`])
+    BaseTraceColumn(usize),
+    /// Memory of a VM snapshot (see [`crate::continuation::MachineState`])
+    MachineMemory,
+    /// Preprocessed base trace columns (see
+    /// [`crate::air::AirConfig::PREPROCESSED_BASE_COLUMNS`])
+    Preprocessed,
+}
+
+impl MerkleTreeDomain {
+    const KEY_PREFIX: &'static [u8; 16] = b"ministark-merkle";
+
+    /// Returns the 32 byte key used for hashing the nodes of a tree in this
+    /// domain. The key is of the form `prefix || tag || 0.. || layer`:
+    ///
+    /// ```text
+    /// ┌──────────────────┬─────┬─────────┬──────────────┐
+    /// │ bytes 0..16      │ 16  │ 17..24  │ 24..32       │
+    /// ├──────────────────┼─────┼─────────┼──────────────┤
+    /// │ ministark-merkle │ tag │ 0       │ layer (u64)  │
+    /// └──────────────────┴─────┴─────────┴──────────────┘
+    /// ```
+    pub fn key(self) -> [u8; 32] {
+        let (tag, layer) = match self {
+            Self::BaseTrace => (0, 0),
+            Self::ExtensionTrace => (1, 0),
+            Self::CompositionTrace => (2, 0),
+            Self::FriLayer(layer) => (3, layer),
+            Self::Custom(id) => (4, id),
+            Self::BaseTraceColumn(column) => (5, column),
+            Self::MachineMemory => (6, 0),
+            Self::Preprocessed => (7, 0),
+        };
+        let mut key = [0; 32];
+        key[0..16].copy_from_slice(Self::KEY_PREFIX);
+        key[16] = tag;
+        key[24..32].copy_from_slice(&(layer as u64).to_be_bytes());
+        key
+    }
+}
+
+/// Maximum height of a merkle tree i.e. trees have at most `2^MAX_HEIGHT`
+/// leaves. Proofs claiming a larger height are rejected.
+pub const MAX_HEIGHT: u32 = 32;
+
 /// Merkle View contains information needed to verify multiple Merkle paths.
 ///
+/// The root is never part of the view. Views are verified against a root the
+/// verifier obtained independently (e.g. from the proof's commitments). The
+/// height of the tree is encoded first so the size of the view can be bounded
+/// before verification.
+///
 /// Inspired by Starkware's Solidity verifier
 /// <https://etherscan.io/address/0xe9664D230490d5A515ef7Ef30033d8075a8D0E24#code#F24#L1>
-#[derive(Debug, Clone, PartialEq, Eq, CanonicalDeserialize, CanonicalSerialize)]
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize)]
 pub struct MerkleView<
     N: CanonicalDeserialize + CanonicalSerialize + Clone,
     L: CanonicalDeserialize + CanonicalSerialize + Clone,
 > {
+    pub height: u32,
     pub nodes: Vec<N>,
     pub initial_leaves: Vec<L>,
     pub sibling_leaves: Vec<L>,
-    pub height: u32,
+}
+
+impl<
+        N: CanonicalDeserialize + CanonicalSerialize + Clone,
+        L: CanonicalDeserialize + CanonicalSerialize + Clone,
+    > MerkleView<N, L>
+{
+    /// Returns true if the number of nodes and leaves could belong to a tree of
+    /// the view's height
+    pub fn is_well_formed(&self) -> bool {
+        let height = self.height;
+        let num_initial = self.initial_leaves.len();
+        (1..=MAX_HEIGHT).contains(&height)
+            && num_initial <= 1 << height
+            && self.sibling_leaves.len() <= num_initial
+            && self.nodes.len() <= num_initial * (height as usize - 1)
+    }
+}
+
+impl<
+        N: CanonicalDeserialize + CanonicalSerialize + Clone,
+        L: CanonicalDeserialize + CanonicalSerialize + Clone,
+    > Valid for MerkleView<N, L>
+{
+    fn check(&self) -> Result<(), SerializationError> {
+        if self.is_well_formed() {
+            Ok(())
+        } else {
+            Err(SerializationError::InvalidData)
+        }
+    }
+}
+
+impl<
+        N: CanonicalDeserialize + CanonicalSerialize + Clone,
+        L: CanonicalDeserialize + CanonicalSerialize + Clone,
+    > CanonicalDeserialize for MerkleView<N, L>
+{
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let height = u32::deserialize_with_mode(&mut reader, compress, validate)?;
+        // bound the size before reading any nodes or leaves
+        if !(1..=MAX_HEIGHT).contains(&height) {
+            return Err(SerializationError::InvalidData);
+        }
+        let view = Self {
+            height,
+            nodes: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            initial_leaves: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            sibling_leaves: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+        };
+        if validate == Validate::Yes {
+            view.check()?;
+        }
+        Ok(view)
+    }
 }
 
 /// Merkle tree implemented as a full power-of-two arity tree.
@@ -92,6 +278,7 @@ pub struct MerkleView<
 pub struct MerkleTreeImpl<C: MerkleTreeConfig> {
     pub nodes: Vec<C::Digest>,
     pub leaves: Vec<C::Leaf>,
+    pub domain: MerkleTreeDomain,
 }
 
 impl<C: MerkleTreeConfig> Clone for MerkleTreeImpl<C> {
@@ -99,6 +286,7 @@ impl<C: MerkleTreeConfig> Clone for MerkleTreeImpl<C> {
         Self {
             nodes: self.nodes.clone(),
             leaves: self.leaves.clone(),
+            domain: self.domain,
         }
     }
 }
@@ -109,7 +297,7 @@ impl<C: MerkleTreeConfig> MerkleTreeImpl<C> {
     /// This function will return an error if:
     /// * there are less than two leaves
     /// * the number of leaves is not a power of two
-    pub fn new(leaves: Vec<C::Leaf>) -> Result<Self, Error> {
+    pub fn new(leaves: Vec<C::Leaf>, domain: MerkleTreeDomain) -> Result<Self, Error> {
         const MIN_LEAVES: usize = 2;
 
         let n = leaves.len();
@@ -122,8 +310,12 @@ impl<C: MerkleTreeConfig> MerkleTreeImpl<C> {
             return Err(Error::NumberOfLeavesNotPowerOfTwo { n });
         }
 
-        let nodes = build_merkle_nodes::<C>(&leaves);
-        Ok(Self { nodes, leaves })
+        let nodes = build_merkle_nodes::<C>(&leaves, &domain.key());
+        Ok(Self {
+            nodes,
+            leaves,
+            domain,
+        })
     }
 
     /// Returns the height of the merkle tree
@@ -146,8 +338,18 @@ impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
         self.nodes[1].clone()
     }
 
-    fn prove(&self, indices: &[usize]) -> Result<MerkleView<C::Digest, C::Leaf>, Error> {
+    fn cap(&self, cap_height: u32) -> Vec<C::Digest> {
+        let num_cap_nodes = Self::cap_size(self.leaves.len(), cap_height);
+        self.nodes[num_cap_nodes..2 * num_cap_nodes].to_vec()
+    }
+
+    fn prove_to_cap(
+        &self,
+        indices: &[usize],
+        cap_height: u32,
+    ) -> Result<MerkleView<C::Digest, C::Leaf>, Error> {
         let num_leaves = self.leaves.len();
+        let cap_height = Self::cap_size(num_leaves, cap_height).ilog2();
         for &i in indices {
             if i >= num_leaves {
                 return Err(Error::LeafIndexOutOfBounds { i, n: num_leaves });
@@ -183,9 +385,12 @@ impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
         // handle internal nodes
         let mut nodes = Vec::new();
         while let Some(index) = node_queue.pop_front() {
-            if index > 2 {
-                node_queue.push_back(index >> 1);
+            // paths end at the nodes of the cap. The leaves of a tree with two
+            // leaves are the children of the root.
+            if index.ilog2() == cap_height {
+                continue;
             }
+            node_queue.push_back(index >> 1);
 
             if let Some(next_index) = node_queue.front() {
                 let are_siblings = index ^ 1 == *next_index;
@@ -199,20 +404,33 @@ impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
         }
 
         Ok(MerkleView {
+            height: self.height(),
             nodes,
             initial_leaves,
             sibling_leaves,
-            height: self.height(),
         })
     }
 
-    fn verify(
-        root: &C::Digest,
+    fn verify_against_cap(
+        cap: &[C::Digest],
+        domain: MerkleTreeDomain,
         proof: MerkleView<C::Digest, C::Leaf>,
         indices: &[usize],
     ) -> Result<(), Error> {
+        if !proof.is_well_formed() {
+            return Err(Error::InvalidProof);
+        }
+
+        let key = domain.key();
         let height = proof.height;
         let num_leaves = 1 << height;
+        let num_cap_nodes = cap.len();
+        if !num_cap_nodes.is_power_of_two()
+            || num_cap_nodes != Self::cap_size(num_leaves, num_cap_nodes.ilog2())
+        {
+            return Err(Error::InvalidProof);
+        }
+        let cap_height = num_cap_nodes.ilog2();
         for &i in indices {
             if i >= num_leaves {
                 return Err(Error::LeafIndexOutOfBounds { i, n: num_leaves });
@@ -233,71 +451,119 @@ impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
             if let Some((next_index, next_leaf)) = leaf_queue.front() {
                 let are_siblings = index ^ 1 == *next_index;
                 if are_siblings {
-                    let running_hash = C::hash_leaves(height - 1, &leaf, next_leaf);
+                    let running_hash = C::hash_leaves(&key, height - 1, &leaf, next_leaf);
                     node_queue.push_back((node_index, running_hash));
                     leaf_queue.pop_front();
                     continue;
                 }
             }
 
-            let sibling = siblings.pop_front().unwrap();
+            let sibling = siblings.pop_front().ok_or(Error::InvalidProof)?;
             let running_hash = if index % 2 == 0 {
-                C::hash_leaves(height - 1, &leaf, &sibling)
+                C::hash_leaves(&key, height - 1, &leaf, &sibling)
             } else {
-                C::hash_leaves(height - 1, &sibling, &leaf)
+                C::hash_leaves(&key, height - 1, &sibling, &leaf)
             };
             node_queue.push_back((node_index, running_hash));
         }
-        assert!(siblings.is_empty());
+        if !siblings.is_empty() {
+            return Err(Error::InvalidProof);
+        }
 
         // handle internal nodes
         let mut nodes = VecDeque::from_iter(proof.nodes);
         while let Some((index, hash)) = node_queue.pop_front() {
             let depth = index.ilog2();
 
-            if depth == 0 {
-                assert!(node_queue.is_empty());
-                // compare against the root
-                return if *root == hash {
-                    Ok(())
-                } else {
-                    Err(Error::InvalidProof)
-                };
+            if depth == cap_height {
+                // compare against the cap
+                if cap[index - num_cap_nodes] != hash {
+                    return Err(Error::InvalidProof);
+                }
+                continue;
             }
 
             if let Some((next_index, next_hash)) = node_queue.front() {
                 let are_siblings = index ^ 1 == *next_index;
                 if are_siblings {
-                    let running_hash = C::hash_nodes(depth - 1, &hash, next_hash);
+                    let running_hash = C::hash_nodes(&key, depth - 1, &hash, next_hash);
                     node_queue.push_back((index >> 1, running_hash));
                     node_queue.pop_front();
                     continue;
                 }
             }
 
-            let sibling = nodes.pop_front().unwrap();
+            let sibling = nodes.pop_front().ok_or(Error::InvalidProof)?;
             let running_hash = if index % 2 == 0 {
-                C::hash_nodes(depth - 1, &hash, &sibling)
+                C::hash_nodes(&key, depth - 1, &hash, &sibling)
             } else {
-                C::hash_nodes(depth - 1, &sibling, &hash)
+                C::hash_nodes(&key, depth - 1, &sibling, &hash)
             };
             node_queue.push_back((index >> 1, running_hash));
         }
 
-        Ok(())
+        if nodes.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidProof)
+        }
     }
 
     fn security_level_bits() -> u32 {
         C::security_level_bits()
     }
+
+    fn hash_backend() -> HashBackend {
+        C::hash_backend()
+    }
 }
 
 /// Merkle tree that supports proving/verifying rows of a matrix
 ///
+/// Rows must be hashed from the canonical encoding of their elements (see
+/// [`crate::hash::write_canonical_element`]) so a commitment made on one
+/// architecture can be opened on another.
+///
+/// The canonical encoding of an extension field element is the encoding of its
+/// base field coordinates. A row of extension field elements therefore hashes
+/// to the same leaf as the row of their coordinates i.e. the extension trace is
+/// already committed as packed base field columns. A verifier can open either
+/// representation against the same commitment.
+///
 /// Inspired by plonky3's MMCS
 /// <https://github.com/Plonky3/Plonky3/blob/main/commit/src/mmcs.rs>
 pub trait MatrixMerkleTree<T>: MerkleTree + Sized {
-    fn from_matrix(m: &Matrix<T>) -> Self;
+    fn from_matrix(m: &Matrix<T>, domain: MerkleTreeDomain) -> Self;
+
+    /// Returns the leaf hash of a row
+    fn hash_row(domain: MerkleTreeDomain, row: &[T]) -> Self::Root;
+
+    /// Builds a tree with a leaf for every row. Rows are hashed with
+    /// [`Self::hash_row`]. Implementations also have an inherent `new`
+    /// constructor that takes leaf hashes and doesn't hash them again.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    /// * there are less than two rows
+    /// * the number of rows is not a power of two
+    fn from_rows(rows: &[impl AsRef<[T]>], domain: MerkleTreeDomain) -> Result<Self, Error>;
+
+    /// Builds a tree where every leaf holds `rows_per_leaf` consecutive rows
+    /// of the matrix. Leaf `i` hashes rows `i * rows_per_leaf..(i + 1) *
+    /// rows_per_leaf` concatenated. Leaves are opened with
+    /// [`Self::prove_rows`] at leaf indices and verified with
+    /// [`Self::verify_row_chunks`].
+    fn from_matrix_chunked(m: &Matrix<T>, domain: MerkleTreeDomain, rows_per_leaf: usize) -> Self
+    where
+        T: Field,
+    {
+        if rows_per_leaf == 1 {
+            return Self::from_matrix(m, domain);
+        }
+        let leaves = m.rows().chunks(rows_per_leaf).map(<[_]>::concat).collect::<Vec<_>>();
+        Self::from_rows(&leaves, domain).unwrap()
+    }
 
     fn prove_rows(&self, row_ids: &[usize]) -> Result<Self::Proof, Error> {
         self.prove(row_ids)
@@ -305,10 +571,48 @@ pub trait MatrixMerkleTree<T>: MerkleTree + Sized {
 
     fn verify_rows(
         root: &Self::Root,
+        domain: MerkleTreeDomain,
+        row_ids: &[usize],
+        rows: &[impl AsRef<[T]>],
+        proof: Self::Proof,
+    ) -> Result<(), Error> {
+        Self::verify_rows_against_cap(core::slice::from_ref(root), domain, row_ids, rows, proof)
+    }
+
+    /// Verifies rows opened with [`MerkleTree::prove_to_cap`] against the cap
+    /// of the tree (see [`MerkleTree::cap`])
+    fn verify_rows_against_cap(
+        cap: &[Self::Root],
+        domain: MerkleTreeDomain,
         row_ids: &[usize],
         rows: &[impl AsRef<[T]>],
         proof: Self::Proof,
     ) -> Result<(), Error>;
+
+    /// Verifies the leaves at `leaf_ids` of a tree built with
+    /// [`Self::from_matrix_chunked`] against the cap of the tree. `rows` holds
+    /// the `rows_per_leaf` rows of every leaf in order.
+    fn verify_row_chunks(
+        cap: &[Self::Root],
+        domain: MerkleTreeDomain,
+        leaf_ids: &[usize],
+        rows: &[impl AsRef<[T]>],
+        rows_per_leaf: usize,
+        proof: Self::Proof,
+    ) -> Result<(), Error>
+    where
+        T: Clone,
+    {
+        if rows.len() != leaf_ids.len() * rows_per_leaf {
+            return Err(Error::InvalidProof);
+        }
+        let leaves = rows
+            .chunks(rows_per_leaf)
+            .map(|rows| rows.iter().flat_map(|row| row.as_ref().iter().cloned()))
+            .map(Vec::from_iter)
+            .collect::<Vec<Vec<T>>>();
+        Self::verify_rows_against_cap(cap, domain, leaf_ids, &leaves, proof)
+    }
 }
 
 pub struct MatrixMerkleTreeImpl<H: HashFn> {
@@ -324,14 +628,145 @@ impl<H: HashFn> Clone for MatrixMerkleTreeImpl<H> {
 }
 
 impl<H: HashFn> MatrixMerkleTreeImpl<H> {
-    fn new(leaves: Vec<H::Digest>) -> Result<Self, Error> {
-        assert!(leaves.len().is_power_of_two());
+    /// Builds a tree from leaf hashes. Leaf hashes should be obtained with
+    /// [`hash_leaf`] using the key of `domain`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    /// * there are less than two leaves
+    /// * the number of leaves is not a power of two
+    pub fn new(leaves: Vec<H::Digest>, domain: MerkleTreeDomain) -> Result<Self, Error> {
         Ok(Self {
-            merkle_tree: MerkleTreeImpl::new(leaves)?,
+            merkle_tree: MerkleTreeImpl::new(leaves, domain)?,
+        })
+    }
+
+    /// Returns the leaf hash at `index`
+    pub fn leaf_hash(&self, index: usize) -> Option<&H::Digest> {
+        self.merkle_tree.leaves.get(index)
+    }
+
+    /// Generates an authentication path for a single leaf
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the leaf index is out of bounds.
+    pub fn prove_leaf(&self, index: usize) -> Result<MerkleProof<H::Digest>, Error> {
+        let MerkleTreeImpl { nodes, leaves, .. } = &self.merkle_tree;
+        let n = leaves.len();
+        if index >= n {
+            return Err(Error::LeafIndexOutOfBounds { i: index, n });
+        }
+
+        let mut path = vec![leaves[index ^ 1].clone()];
+        let mut node_index = (n + index) >> 1;
+        while node_index > 1 {
+            path.push(nodes[node_index ^ 1].clone());
+            node_index >>= 1;
+        }
+
+        Ok(MerkleProof {
+            height: self.merkle_tree.height(),
+            path,
         })
     }
 }
 
+/// Authentication path of a single leaf in a [`MatrixMerkleTreeImpl`]
+///
+/// Siblings are ordered from the leaf layer up to the children of the root.
+/// Like [`MerkleView`] the proof never contains the root and the height of the
+/// tree is encoded explicitly.
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize)]
+pub struct MerkleProof<D: Digest> {
+    pub height: u32,
+    pub path: Vec<D>,
+}
+
+impl<D: Digest> MerkleProof<D> {
+    /// Returns true if the length of the path matches the height
+    pub fn is_well_formed(&self) -> bool {
+        (1..=MAX_HEIGHT).contains(&self.height) && self.path.len() == self.height as usize
+    }
+}
+
+impl<D: Digest> Valid for MerkleProof<D> {
+    fn check(&self) -> Result<(), SerializationError> {
+        if self.is_well_formed() {
+            Ok(())
+        } else {
+            Err(SerializationError::InvalidData)
+        }
+    }
+}
+
+impl<D: Digest> CanonicalDeserialize for MerkleProof<D> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let height = u32::deserialize_with_mode(&mut reader, compress, validate)?;
+        if !(1..=MAX_HEIGHT).contains(&height) {
+            return Err(SerializationError::InvalidData);
+        }
+        let proof = Self {
+            height,
+            path: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+        };
+        if validate == Validate::Yes {
+            proof.check()?;
+        }
+        Ok(proof)
+    }
+}
+
+/// Verifies that `leaf_hash` is the leaf at `index` of the tree in `domain`
+/// with root `root`. Trees built with [`MatrixMerkleTreeImpl`] can be checked
+/// with this function without having to go through FRI or the STARK verifier.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// * the proof is malformed
+/// * the index is out of bounds for the height of the proof
+/// * the proof does not resolve to `root`
+pub fn verify<H: HashFn>(
+    root: &H::Digest,
+    domain: MerkleTreeDomain,
+    index: usize,
+    leaf_hash: &H::Digest,
+    proof: &MerkleProof<H::Digest>,
+) -> Result<(), Error> {
+    if !proof.is_well_formed() {
+        return Err(Error::InvalidProof);
+    }
+
+    let n = 1 << proof.height;
+    if index >= n {
+        return Err(Error::LeafIndexOutOfBounds { i: index, n });
+    }
+
+    let key = domain.key();
+    let mut node_index = index;
+    let mut running_hash = leaf_hash.clone();
+    for sibling in &proof.path {
+        running_hash = if node_index.is_multiple_of(2) {
+            hash_node::<H>(&key, &running_hash, sibling)
+        } else {
+            hash_node::<H>(&key, sibling, &running_hash)
+        };
+        node_index >>= 1;
+    }
+
+    if *root == running_hash {
+        Ok(())
+    } else {
+        Err(Error::InvalidProof)
+    }
+}
+
 impl<H: HashFn> MerkleTree for MatrixMerkleTreeImpl<H> {
     type Proof = MerkleView<H::Digest, H::Digest>;
     type Root = H::Digest;
@@ -340,28 +775,57 @@ impl<H: HashFn> MerkleTree for MatrixMerkleTreeImpl<H> {
         self.merkle_tree.root()
     }
 
-    fn prove(&self, indices: &[usize]) -> Result<Self::Proof, Error> {
-        self.merkle_tree.prove(indices)
+    fn cap(&self, cap_height: u32) -> Vec<Self::Root> {
+        self.merkle_tree.cap(cap_height)
     }
 
-    fn verify(root: &Self::Root, proof: Self::Proof, indices: &[usize]) -> Result<(), Error> {
-        MerkleTreeImpl::<HashedLeafConfig<H>>::verify(root, proof, indices)
+    fn prove_to_cap(&self, indices: &[usize], cap_height: u32) -> Result<Self::Proof, Error> {
+        self.merkle_tree.prove_to_cap(indices, cap_height)
+    }
+
+    fn verify_against_cap(
+        cap: &[Self::Root],
+        domain: MerkleTreeDomain,
+        proof: Self::Proof,
+        indices: &[usize],
+    ) -> Result<(), Error> {
+        MerkleTreeImpl::<HashedLeafConfig<H>>::verify_against_cap(cap, domain, proof, indices)
     }
 
     fn security_level_bits() -> u32 {
         H::COLLISION_RESISTANCE
     }
+
+    fn hash_backend() -> HashBackend {
+        H::backend()
+    }
 }
 
 impl<F: Field, H: ElementHashFn<F> + Send + Sync + 'static> MatrixMerkleTree<F>
     for MatrixMerkleTreeImpl<H>
 {
-    fn from_matrix(m: &Matrix<F>) -> Self {
-        Self::new(hash_rows::<F, H>(m)).unwrap()
+    fn from_matrix(m: &Matrix<F>, domain: MerkleTreeDomain) -> Self {
+        Self::new(hash_rows::<F, H>(m, &domain.key()), domain).unwrap()
     }
 
-    fn verify_rows(
-        root: &Self::Root,
+    fn hash_row(domain: MerkleTreeDomain, row: &[F]) -> H::Digest {
+        hash_leaf::<F, H>(&domain.key(), row)
+    }
+
+    fn from_rows(rows: &[impl AsRef<[F]>], domain: MerkleTreeDomain) -> Result<Self, Error> {
+        let key = domain.key();
+        let leaves = rows.iter().map(|row| hash_leaf::<F, H>(&key, row.as_ref()));
+        Self::new(leaves.collect(), domain)
+    }
+
+    fn from_matrix_chunked(m: &Matrix<F>, domain: MerkleTreeDomain, rows_per_leaf: usize) -> Self {
+        let leaves = hash_row_chunks::<F, H>(m, &domain.key(), rows_per_leaf);
+        Self::new(leaves, domain).unwrap()
+    }
+
+    fn verify_rows_against_cap(
+        cap: &[Self::Root],
+        domain: MerkleTreeDomain,
         row_ids: &[usize],
         rows: &[impl AsRef<[F]>],
         proof: Self::Proof,
@@ -371,13 +835,370 @@ impl<F: Field, H: ElementHashFn<F> + Send + Sync + 'static> MatrixMerkleTree<F>
         instances.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
         instances.dedup_by(|(a, _), (b, _)| a == b);
 
+        let key = domain.key();
         let (indices, rows): (Vec<_>, Vec<_>) = instances.into_iter().unzip();
         let initial_leaves = rows
             .iter()
-            .map(|r| H::hash_elements(r.as_ref().iter().copied()))
+            .map(|r| hash_leaf::<F, H>(&key, r.as_ref()))
             .collect::<Vec<_>>();
         if proof.initial_leaves == initial_leaves {
-            Self::verify(root, proof, &indices)
+            Self::verify_against_cap(cap, domain, proof, &indices)
+        } else {
+            Err(Error::InvalidProof)
+        }
+    }
+}
+
+/// Merkle tree where every internal node hashes `ARITY` children
+///
+/// Wide trees need fewer hashes per path which suits algebraic hash functions
+/// (e.g. Poseidon) that can absorb many children at once. If the number of
+/// leaves isn't a power of `ARITY` the root hashes the remaining
+/// `num_leaves / ARITY^k` children where `k` is the number of full layers
+/// below it. Internal nodes hash to `H_key(0x01 || c_0 || .. || c_{ARITY-1})`
+/// so a tree with `ARITY = 2` has the same root as [`MatrixMerkleTreeImpl`].
+///
+/// ```text
+///          #             <- root node
+///     /  /   \  \
+///    +  +     +  +       <- leaves (ARITY = 4)
+/// ```
+pub struct WideMatrixMerkleTree<H: HashFn, const ARITY: usize> {
+    /// Layers of the tree starting from the leaves and ending at the root
+    layers: Vec<Vec<H::Digest>>,
+}
+
+impl<H: HashFn, const ARITY: usize> Clone for WideMatrixMerkleTree<H, ARITY> {
+    fn clone(&self) -> Self {
+        Self {
+            layers: self.layers.clone(),
+        }
+    }
+}
+
+impl<H: HashFn, const ARITY: usize> WideMatrixMerkleTree<H, ARITY> {
+    /// Builds a tree from leaf hashes. Leaf hashes should be obtained with
+    /// [`hash_leaf`] using the key of `domain`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    /// * there are less than two leaves
+    /// * the number of leaves is not a power of two
+    pub fn new(leaves: Vec<H::Digest>, domain: MerkleTreeDomain) -> Result<Self, Error> {
+        const MIN_LEAVES: usize = 2;
+        assert!(
+            ARITY >= 2 && ARITY.is_power_of_two(),
+            "arity must be a power of two"
+        );
+
+        let n = leaves.len();
+        if n < MIN_LEAVES {
+            return Err(Error::TooFewLeaves {
+                min: MIN_LEAVES,
+                actual: n,
+            });
+        } else if !n.is_power_of_two() {
+            return Err(Error::NumberOfLeavesNotPowerOfTwo { n });
+        }
+
+        let key = domain.key();
+        let mut layers = vec![leaves];
+        while layers.last().unwrap().len() > 1 { let layer = layers.last().unwrap();
+            let num_children = ARITY.min(layer.len());
+            let next_layer = ark_std::cfg_chunks!(layer, num_children)
+                .map(|children| hash_children::<H>(&key, children))
+                .collect();
+            layers.push(next_layer);
+        }
+
+        Ok(Self { layers })
+    }
+
+    /// Returns the number of children hashed by the nodes of each layer above
+    /// the leaves in a tree with `2^height` leaves
+    fn layer_arities(height: u32) -> impl Iterator<Item = usize> {
+        let mut layer_size = 1usize << height;
+        core::iter::from_fn(move || {
+            (layer_size > 1).then(|| {
+                let num_children = ARITY.min(layer_size);
+                layer_size /= num_children;
+                num_children
+            })
+        })
+    }
+}
+
+/// Returns the hash of an internal node with any number of children i.e.
+/// `H_key(0x01 || c_0 || c_1 || ..)`
+fn hash_children<H: HashFn>(key: &[u8; 32], children: &[H::Digest]) -> H::Digest {
+    let children = children.iter().map(Digest::as_bytes).collect::<Vec<_>>();
+    H::hash_chunks_keyed(
+        key,
+        core::iter::once(&[NODE_HASH_PREFIX][..]).chain(children.iter().map(|c| &c[..])),
+    )
+}
+
+/// Authentication paths for leaves of a [`WideMatrixMerkleTree`]
+///
+/// Each path contains the `ARITY - 1` siblings of every layer ordered from the
+/// leaves up to the children of the root. Like [`MerkleView`] the proof never
+/// contains the root and the height of the tree is encoded explicitly.
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize)]
+pub struct WideMerkleProof<D: Digest> {
+    pub height: u32,
+    pub leaves: Vec<D>,
+    pub paths: Vec<Vec<D>>,
+}
+
+impl<D: Digest> Valid for WideMerkleProof<D> {
+    fn check(&self) -> Result<(), SerializationError> {
+        if (1..=MAX_HEIGHT).contains(&self.height) && self.leaves.len() == self.paths.len() {
+            Ok(())
+        } else {
+            Err(SerializationError::InvalidData)
+        }
+    }
+}
+
+impl<D: Digest> CanonicalDeserialize for WideMerkleProof<D> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let height = u32::deserialize_with_mode(&mut reader, compress, validate)?;
+        if !(1..=MAX_HEIGHT).contains(&height) {
+            return Err(SerializationError::InvalidData);
+        }
+        let proof = Self {
+            height,
+            leaves: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            paths: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+        };
+        if validate == Validate::Yes {
+            proof.check()?;
+        }
+        Ok(proof)
+    }
+}
+
+impl<H: HashFn, const ARITY: usize> MerkleTree for WideMatrixMerkleTree<H, ARITY> {
+    type Proof = WideMerkleProof<H::Digest>;
+    type Root = H::Digest;
+
+    fn root(&self) -> Self::Root {
+        self.layers.last().unwrap()[0].clone()
+    }
+
+    /// Layers of a wide tree don't hold every power of two nodes. The cap is
+    /// the highest layer above the leaves with at least `2^cap_height` nodes.
+    fn cap(&self, cap_height: u32) -> Vec<Self::Root> {
+        let num_cap_nodes = Self::cap_size(self.layers[0].len(), cap_height);
+        let cap = self.layers[1..].iter().find(|l| l.len() == num_cap_nodes);
+        cap.unwrap().clone()
+    }
+
+    fn cap_size(num_leaves: usize, cap_height: u32) -> usize {
+        let height = num_leaves.max(1).ilog2();
+        let mut layer_sizes = Self::layer_arities(height).scan(num_leaves, |size, num_children| {
+            *size /= num_children;
+            Some(*size)
+        });
+        let largest = layer_sizes.next().unwrap_or(1);
+        let smaller = layer_sizes.take_while(|size| size.ilog2() >= cap_height);
+        smaller.last().unwrap_or(largest)
+    }
+
+    fn prove_to_cap(&self, indices: &[usize], cap_height: u32) -> Result<Self::Proof, Error> {
+        let leaves = &self.layers[0];
+        let num_leaves = leaves.len();
+        for &i in indices {
+            if i >= num_leaves {
+                return Err(Error::LeafIndexOutOfBounds { i, n: num_leaves });
+            }
+        }
+        let num_cap_nodes = Self::cap_size(num_leaves, cap_height);
+        let cap_layer = self.layers.iter().rposition(|l| l.len() == num_cap_nodes);
+        let layers_below_cap = &self.layers[..cap_layer.unwrap()];
+
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+
+        let paths = indices
+            .iter()
+            .map(|&leaf_index| {
+                let mut path = Vec::new();
+                let mut index = leaf_index;
+                for layer in layers_below_cap {
+                    let num_children = ARITY.min(layer.len());
+                    let start = index - index % num_children;
+                    let siblings = (start..start + num_children).filter(|&i| i != index);
+                    path.extend(siblings.map(|i| layer[i].clone()));
+                    index /= num_children;
+                }
+                path
+            })
+            .collect();
+
+        Ok(WideMerkleProof {
+            height: num_leaves.ilog2(),
+            leaves: indices.iter().map(|&i| leaves[i].clone()).collect(),
+            paths,
+        })
+    }
+
+    fn verify_against_cap(
+        cap: &[Self::Root],
+        domain: MerkleTreeDomain,
+        proof: Self::Proof,
+        indices: &[usize],
+    ) -> Result<(), Error> {
+        if proof.check().is_err() {
+            return Err(Error::InvalidProof);
+        }
+
+        let height = proof.height;
+        let num_leaves = 1 << height;
+        for &i in indices {
+            if i >= num_leaves {
+                return Err(Error::LeafIndexOutOfBounds { i, n: num_leaves });
+            }
+        }
+        let num_cap_nodes = cap.len();
+        if !num_cap_nodes.is_power_of_two()
+            || num_cap_nodes != Self::cap_size(num_leaves, num_cap_nodes.ilog2())
+        {
+            return Err(Error::InvalidProof);
+        }
+        // arities of the layers below the cap
+        let mut layer_size = num_leaves;
+        let arities = Self::layer_arities(height)
+            .map_while(|num_children| {
+                (layer_size > num_cap_nodes).then(|| {
+                    layer_size /= num_children;
+                    num_children
+                })
+            })
+            .collect::<Vec<usize>>();
+
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        if indices.len() != proof.leaves.len() {
+            return Err(Error::InvalidProof);
+        }
+
+        let key = domain.key();
+        let path_len = arities.iter().map(|a| a - 1).sum::<usize>();
+        if proof.paths.iter().any(|path| path.len() != path_len) {
+            return Err(Error::InvalidProof);
+        }
+
+        // Sweep the tree layer by layer so nodes shared by several paths are
+        // hashed once. Each node carries the paths that pass through it and
+        // every sibling in those paths must agree with the other children.
+        let mut layer = zip(indices, zip(proof.leaves, proof.paths))
+            .map(|(index, (leaf, path))| (index, leaf, vec![path.into_iter()]))
+            .collect::<Vec<_>>();
+        for &num_children in &arities {
+            let mut parents = Vec::new();
+            let mut nodes = layer.into_iter().peekable();
+            while let Some((index, hash, mut paths)) = nodes.next() {
+                let parent = index / num_children;
+                let mut children = vec![None; num_children];
+                let mut members = vec![(index % num_children, paths.len())];
+                children[index % num_children] = Some(hash);
+                while let Some((index, hash, sibling_paths)) =
+                    nodes.next_if(|(index, ..)| index / num_children == parent)
+                {
+                    members.push((index % num_children, sibling_paths.len()));
+                    children[index % num_children] = Some(hash);
+                    paths.extend(sibling_paths);
+                }
+
+                let mut paths_iter = paths.iter_mut();
+                for (position, num_paths) in members {
+                    for path in paths_iter.by_ref().take(num_paths) {
+                        let slots = children.iter_mut().enumerate();
+                        let siblings = slots.filter(|(i, _)| *i != position).map(|(_, c)| c);
+                        for (child, sibling) in zip(siblings, path.by_ref()) {
+                            match child {
+                                Some(child) if *child != sibling => {
+                                    return Err(Error::InvalidProof)
+                                }
+                                Some(_) => {}
+                                None => *child = Some(sibling),
+                            }
+                        }
+                    }
+                }
+
+                let children = children.into_iter().collect::<Option<Vec<_>>>().unwrap();
+                parents.push((parent, hash_children::<H>(&key, &children), paths));
+            }
+            layer = parents;
+        }
+
+        if layer.iter().any(|(index, hash, _)| *hash != cap[*index]) {
+            return Err(Error::InvalidProof);
+        }
+
+        Ok(())
+    }
+
+    fn security_level_bits() -> u32 {
+        H::COLLISION_RESISTANCE
+    }
+
+    fn hash_backend() -> HashBackend {
+        H::backend()
+    }
+}
+
+impl<F: Field, H: ElementHashFn<F> + Send + Sync + 'static, const ARITY: usize> MatrixMerkleTree<F>
+    for WideMatrixMerkleTree<H, ARITY>
+{
+    fn from_matrix(m: &Matrix<F>, domain: MerkleTreeDomain) -> Self {
+        Self::new(hash_rows::<F, H>(m, &domain.key()), domain).unwrap()
+    }
+
+    fn hash_row(domain: MerkleTreeDomain, row: &[F]) -> H::Digest {
+        hash_leaf::<F, H>(&domain.key(), row)
+    }
+
+    fn from_rows(rows: &[impl AsRef<[F]>], domain: MerkleTreeDomain) -> Result<Self, Error> {
+        let key = domain.key();
+        let leaves = rows.iter().map(|row| hash_leaf::<F, H>(&key, row.as_ref()));
+        Self::new(leaves.collect(), domain)
+    }
+
+    fn from_matrix_chunked(m: &Matrix<F>, domain: MerkleTreeDomain, rows_per_leaf: usize) -> Self {
+        let leaves = hash_row_chunks::<F, H>(m, &domain.key(), rows_per_leaf);
+        Self::new(leaves, domain).unwrap()
+    }
+
+    fn verify_rows_against_cap(
+        cap: &[Self::Root],
+        domain: MerkleTreeDomain,
+        row_ids: &[usize],
+        rows: &[impl AsRef<[F]>],
+        proof: Self::Proof,
+    ) -> Result<(), Error> {
+        // remove duplicates and sort
+        let mut instances = zip(row_ids, rows).collect::<Vec<_>>();
+        instances.sort_unstable_by_key(|&(&a, _)| a);
+        instances.dedup_by(|(a, _), (b, _)| a == b);
+
+        let key = domain.key();
+        let (indices, rows): (Vec<_>, Vec<_>) = instances.into_iter().unzip();
+        let leaves = rows
+            .iter()
+            .map(|r| hash_leaf::<F, H>(&key, r.as_ref()))
+            .collect::<Vec<_>>();
+        if proof.leaves == leaves {
+            Self::verify_against_cap(cap, domain, proof, &indices)
         } else {
             Err(Error::InvalidProof)
         }
@@ -396,22 +1217,70 @@ impl<H: HashFn> MerkleTreeConfig for HashedLeafConfig<H> {
     type Digest = H::Digest;
     type Leaf = H::Digest;
 
-    fn hash_leaves(_: u32, l0: &H::Digest, l1: &H::Digest) -> H::Digest {
-        H::merge(l0, l1)
+    fn hash_leaves(key: &[u8; 32], _: u32, l0: &H::Digest, l1: &H::Digest) -> H::Digest {
+        hash_node::<H>(key, l0, l1)
     }
 
-    fn hash_nodes(_: u32, n0: &Self::Digest, n1: &Self::Digest) -> Self::Digest {
-        H::merge(n0, n1)
+    fn hash_nodes(key: &[u8; 32], _: u32, n0: &Self::Digest, n1: &Self::Digest) -> Self::Digest {
+        hash_node::<H>(key, n0, n1)
     }
 
     fn security_level_bits() -> u32 {
         H::COLLISION_RESISTANCE
     }
+
+    fn hash_backend() -> HashBackend {
+        H::backend()
+    }
 }
 
-pub fn hash_rows<F: Field, H: ElementHashFn<F>>(matrix: &Matrix<F>) -> Vec<H::Digest> {
+/// Prefix of all leaf hashes.
+///
+/// Leaves and internal nodes are hashed with distinct prefixes (as in RFC 6962)
+/// so an internal node can never be passed off as a leaf. Without this a prover
+/// could open a "row" whose encoding matches the children of an internal node
+/// and have it verify against a shorter path.
+pub const LEAF_HASH_PREFIX: u8 = 0x00;
+
+/// Prefix of all internal node hashes. See [`LEAF_HASH_PREFIX`].
+pub const NODE_HASH_PREFIX: u8 = 0x01;
+
+/// Returns the leaf hash of a row i.e. `H_key(0x00 || H(row))`
+pub fn hash_leaf<F: Field, H: ElementHashFn<F>>(key: &[u8; 32], row: &[F]) -> H::Digest {
+    let row_hash = H::hash_elements(row.iter().copied());
+    H::hash_chunks_keyed(key, [&[LEAF_HASH_PREFIX][..], &row_hash.as_bytes()])
+}
+
+/// Returns the hash of an internal node i.e. `H_key(0x01 || n0 || n1)`
+pub fn hash_node<H: HashFn>(key: &[u8; 32], n0: &H::Digest, n1: &H::Digest) -> H::Digest {
+    H::hash_chunks_keyed(
+        key,
+        [&[NODE_HASH_PREFIX][..], &n0.as_bytes(), &n1.as_bytes()],
+    )
+}
+
+/// Returns the leaf hash of each row of the matrix
+pub fn hash_rows<F: Field, H: ElementHashFn<F>>(
+    matrix: &Matrix<F>,
+    key: &[u8; 32],
+) -> Vec<H::Digest> {
+    hash_row_chunks::<F, H>(matrix, key, 1)
+}
+
+/// Returns the leaf hash of every `rows_per_leaf` consecutive rows of the
+/// matrix. The rows of a leaf are concatenated before hashing.
+///
+/// # Panics
+///
+/// Panics if `rows_per_leaf` doesn't divide the number of rows
+pub fn hash_row_chunks<F: Field, H: ElementHashFn<F>>(
+    matrix: &Matrix<F>,
+    key: &[u8; 32],
+    rows_per_leaf: usize,
+) -> Vec<H::Digest> {
     let num_rows = matrix.num_rows();
-    let mut row_hashes = vec![H::Digest::default(); num_rows];
+    assert_eq!(num_rows % rows_per_leaf, 0);
+    let mut row_hashes = vec![H::Digest::default(); num_rows / rows_per_leaf];
 
     #[cfg(not(feature = "parallel"))]
     let chunk_size = row_hashes.len();
@@ -425,10 +1294,14 @@ pub fn hash_rows<F: Field, H: ElementHashFn<F>>(matrix: &Matrix<F>) -> Vec<H::Di
         .enumerate()
         .for_each(|(chunk_offset, chunk)| {
             let offset = chunk_size * chunk_offset;
-            let mut row_buffer = vec![F::zero(); matrix.num_cols()];
+            let num_cols = matrix.num_cols();
+            let mut row_buffer = vec![F::zero(); num_cols * rows_per_leaf];
             for (i, row_hash) in chunk.iter_mut().enumerate() {
-                matrix.read_row(offset + i, &mut row_buffer);
-                *row_hash = H::hash_elements(row_buffer.iter().copied());
+                let first_row = (offset + i) * rows_per_leaf;
+                for (j, row) in row_buffer.chunks_mut(num_cols).enumerate() {
+                    matrix.read_row(first_row + j, row);
+                }
+                *row_hash = hash_leaf::<F, H>(key, &row_buffer);
             }
         });
 
@@ -436,7 +1309,10 @@ pub fn hash_rows<F: Field, H: ElementHashFn<F>>(matrix: &Matrix<F>) -> Vec<H::Di
 }
 
 #[cfg(feature = "parallel")]
-pub fn build_merkle_nodes<C: MerkleTreeConfig>(leaves: &[C::Leaf]) -> Vec<C::Digest> {
+pub fn build_merkle_nodes<C: MerkleTreeConfig>(
+    leaves: &[C::Leaf],
+    key: &[u8; 32],
+) -> Vec<C::Digest> {
     let n = leaves.len();
     let num_subtrees = core::cmp::min(rayon::current_num_threads().next_power_of_two(), n / 2);
     let mut nodes = vec![C::Digest::default(); n];
@@ -454,20 +1330,20 @@ pub fn build_merkle_nodes<C: MerkleTreeConfig>(leaves: &[C::Leaf]) -> Vec<C::Dig
                 for j in (0..batch_size).step_by(2) {
                     let lhs = &leaves[leaf_offset + j];
                     let rhs = &leaves[leaf_offset + j + 1];
-                    nodes[(n + leaf_offset + j) / 2] = C::hash_leaves(depth, lhs, rhs);
+                    nodes[(n + leaf_offset + j) / 2] = C::hash_leaves(key, depth, lhs, rhs);
                 }
 
                 // generate remaining nodes
                 let mut batch_size = n / num_subtrees / 4;
                 let mut start_idx = n / 4 + batch_size * i;
-                let mut depth = (n / 4).ilog2();
-                while start_idx >= num_subtrees {
+                // the subtree can reach the root when there is only one thread
+                while start_idx >= num_subtrees && batch_size > 0 {
+                    let depth = start_idx.ilog2();
                     for k in (start_idx..(start_idx + batch_size)).rev() {
-                        nodes[k] = C::hash_nodes(depth, &nodes[k * 2], &nodes[k * 2 + 1]);
+                        nodes[k] = C::hash_nodes(key, depth, &nodes[k * 2], &nodes[k * 2 + 1]);
                     }
                     start_idx /= 2;
                     batch_size /= 2;
-                    depth -= 1;
                 }
             });
         }
@@ -476,14 +1352,17 @@ pub fn build_merkle_nodes<C: MerkleTreeConfig>(leaves: &[C::Leaf]) -> Vec<C::Dig
     // finish the tip of the tree
     for i in (1..num_subtrees).rev() {
         let layer = i.ilog2();
-        nodes[i] = C::hash_nodes(layer, &nodes[i * 2], &nodes[i * 2 + 1]);
+        nodes[i] = C::hash_nodes(key, layer, &nodes[i * 2], &nodes[i * 2 + 1]);
     }
 
     nodes
 }
 
 #[cfg(not(feature = "parallel"))]
-pub fn build_merkle_nodes<C: MerkleTreeConfig>(leaves: &[C::Leaf]) -> Vec<C::Digest> {
+pub fn build_merkle_nodes<C: MerkleTreeConfig>(
+    leaves: &[C::Leaf],
+    key: &[u8; 32],
+) -> Vec<C::Digest> {
     let n = leaves.len();
     assert!(n.is_power_of_two());
     let mut nodes = vec![C::Digest::default(); n];
@@ -491,7 +1370,7 @@ pub fn build_merkle_nodes<C: MerkleTreeConfig>(leaves: &[C::Leaf]) -> Vec<C::Dig
     // generate first layer of nodes from leaf nodes
     let depth = (n / 2).ilog2();
     for i in 0..n / 2 {
-        nodes[n / 2 + i] = C::hash_leaves(depth, &leaves[i * 2], &leaves[i * 2 + 1]);
+        nodes[n / 2 + i] = C::hash_leaves(key, depth, &leaves[i * 2], &leaves[i * 2 + 1]);
     }
 
     // generate remaining nodes
@@ -500,7 +1379,7 @@ pub fn build_merkle_nodes<C: MerkleTreeConfig>(leaves: &[C::Leaf]) -> Vec<C::Dig
         let size = 1 << depth;
         let offset = size;
         for i in offset..offset + size {
-            nodes[i] = C::hash_nodes(depth, &nodes[i * 2], &nodes[i * 2 + 1]);
+            nodes[i] = C::hash_nodes(key, depth, &nodes[i * 2], &nodes[i * 2 + 1]);
         }
     }
 
@@ -509,18 +1388,29 @@ pub fn build_merkle_nodes<C: MerkleTreeConfig>(leaves: &[C::Leaf]) -> Vec<C::Dig
 
 #[cfg(test)]
 mod tests {
+    use super::hash_leaf;
+    use super::hash_node;
     use super::Error;
     use super::MatrixMerkleTree;
     use super::MatrixMerkleTreeImpl;
     use super::MerkleTree;
     use super::MerkleTreeConfig;
+    use super::MerkleTreeDomain;
     use super::MerkleTreeImpl;
+    use super::MerkleProof;
+    use super::MerkleView;
+    use super::WideMatrixMerkleTree;
+    use super::MAX_HEIGHT;
+    use crate::hash::Blake3HashFn;
+    use crate::hash::Digest as _;
     use crate::hash::HashFn;
     use crate::hash::Sha256HashFn;
     use crate::utils::GpuAllocator;
     use crate::utils::SerdeOutput;
     use crate::Matrix;
     use ark_ff::MontFp as Fp;
+    use ark_serialize::CanonicalDeserialize;
+    use ark_serialize::CanonicalSerialize;
     use digest::Digest;
     use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
     use sha2::Sha256;
@@ -528,27 +1418,98 @@ mod tests {
     #[test]
     fn verify() -> Result<(), Error> {
         let leaves = vec![1u32, 2, 3, 4, 5, 6, 7, 8];
-        let tree = MerkleTreeImpl::<UnhashedLeafConfig>::new(leaves)?;
+        let tree = MerkleTreeImpl::<UnhashedLeafConfig>::new(leaves, DOMAIN)?;
         let commitment = tree.root();
         let i = 3;
 
         let proof = tree.prove(&[i])?;
 
-        MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, proof, &[i])
+        MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, DOMAIN, proof, &[i])
     }
 
     #[test]
     fn prove_all_leaves() -> Result<(), Error> {
         let column: &[Fp] = &[Fp!("1"), Fp!("2"), Fp!("3"), Fp!("4")];
         let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
-        let tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix);
+        let tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix, DOMAIN);
         let commitment = tree.root();
         let row_ids = [0, 1, 2, 3];
         let rows = row_ids.map(|i| [column[i]]);
 
         let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
 
-        MatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(&commitment, &row_ids, &rows, proof)
+        MatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(
+            &commitment,
+            DOMAIN,
+            &row_ids,
+            &rows,
+            proof,
+        )
+    }
+
+    #[test]
+    fn leaf_hashes_match_rows() -> Result<(), Error> {
+        type Tree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        let rows = (0..8u64).map(|i| vec![Fp::from(i), Fp::from(2 * i)]).collect::<Vec<_>>();
+        let leaves = rows.iter().map(|row| Tree::hash_row(DOMAIN, row)).collect();
+
+        let tree = Tree::from_matrix(&Matrix::from_rows(rows.clone()), DOMAIN);
+
+        assert_eq!(tree.root(), Tree::from_rows(&rows, DOMAIN)?.root());
+        assert_eq!(tree.root(), Tree::new(leaves, DOMAIN)?.root());
+        Ok(())
+    }
+
+    #[test]
+    fn prove_rows_blake3() -> Result<(), Error> {
+        let column: &[Fp] = &[Fp!("1"), Fp!("2"), Fp!("3"), Fp!("4")];
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = MatrixMerkleTreeImpl::<Blake3HashFn>::from_matrix(&matrix, DOMAIN);
+        let commitment = tree.root();
+        let row_ids = [1, 2];
+        let rows = row_ids.map(|i| [column[i]]);
+
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+
+        MatrixMerkleTreeImpl::<Blake3HashFn>::verify_rows(
+            &commitment,
+            DOMAIN,
+            &row_ids,
+            &rows,
+            proof,
+        )
+    }
+
+    #[test]
+    fn trees_in_different_domains_have_different_roots() {
+        let column: &[Fp] = &[Fp!("1"), Fp!("2"), Fp!("3"), Fp!("4")];
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+
+        let base_tree =
+            MatrixMerkleTreeImpl::<Blake3HashFn>::from_matrix(&matrix, MerkleTreeDomain::BaseTrace);
+        let fri_tree = MatrixMerkleTreeImpl::<Blake3HashFn>::from_matrix(
+            &matrix,
+            MerkleTreeDomain::FriLayer(0),
+        );
+
+        assert_ne!(base_tree.root(), fri_tree.root());
+    }
+
+    #[test]
+    fn path_cannot_be_replayed_in_another_domain() -> Result<(), Error> {
+        let leaves = vec![1u32, 2, 3, 4, 5, 6, 7, 8];
+        let tree =
+            MerkleTreeImpl::<UnhashedLeafConfig>::new(leaves, MerkleTreeDomain::FriLayer(0))?;
+        let commitment = tree.root();
+        let i = 5;
+
+        let proof = tree.prove(&[i])?;
+
+        let other_domain = MerkleTreeDomain::FriLayer(1);
+        let res =
+            MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, other_domain, proof, &[i]);
+        assert!(matches!(res, Err(Error::InvalidProof)));
+        Ok(())
     }
 
     #[test]
@@ -559,27 +1520,416 @@ mod tests {
             .map(|&v| Sha256::digest(v.to_be_bytes()))
             .map(SerdeOutput::new)
             .collect();
-        let tree = MerkleTreeImpl::<HashedLeafConfig>::new(hashed_leaves)?;
+        let tree = MerkleTreeImpl::<HashedLeafConfig>::new(hashed_leaves, DOMAIN)?;
         let commitment = tree.root();
         let i = 3;
 
         let proof = tree.prove(&[i])?;
 
-        MerkleTreeImpl::<HashedLeafConfig>::verify(&commitment, proof, &[i])
+        MerkleTreeImpl::<HashedLeafConfig>::verify(&commitment, DOMAIN, proof, &[i])
     }
 
     #[test]
     fn verify_large_tree() -> Result<(), Error> {
         let leaves = (0..1 << 10).collect::<Vec<u32>>();
-        let tree = MerkleTreeImpl::<UnhashedLeafConfig>::new(leaves)?;
+        let tree = MerkleTreeImpl::<UnhashedLeafConfig>::new(leaves, DOMAIN)?;
         let commitment = tree.root();
         let i = 378;
 
         let proof = tree.prove(&[i])?;
 
-        MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, proof, &[i])
+        MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, DOMAIN, proof, &[i])
+    }
+
+    #[test]
+    fn verify_single_leaf() -> Result<(), Error> {
+        let rows = (0..8u64).map(|i| vec![Fp::from(i)]).collect::<Vec<_>>();
+        let matrix = Matrix::from_rows(rows.clone());
+        let domain = MerkleTreeDomain::Custom(0);
+        let tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix, domain);
+        let i = 5;
+
+        let proof = tree.prove_leaf(i)?;
+        let leaf_hash = hash_leaf::<Fp, Sha256HashFn>(&domain.key(), &rows[i]);
+
+        assert_eq!(tree.leaf_hash(i), Some(&leaf_hash));
+        super::verify::<Sha256HashFn>(&tree.root(), domain, i, &leaf_hash, &proof)
+    }
+
+    #[test]
+    fn verify_single_leaf_fails_for_wrong_index() -> Result<(), Error> {
+        let rows = (0..8u64).map(|i| vec![Fp::from(i)]).collect::<Vec<_>>();
+        let matrix = Matrix::from_rows(rows.clone());
+        let tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix, DOMAIN);
+        let leaf_hash = hash_leaf::<Fp, Sha256HashFn>(&DOMAIN.key(), &rows[5]);
+
+        let proof = tree.prove_leaf(5)?;
+        let wrong_index =
+            super::verify::<Sha256HashFn>(&tree.root(), DOMAIN, 4, &leaf_hash, &proof);
+        let out_of_bounds =
+            super::verify::<Sha256HashFn>(&tree.root(), DOMAIN, 8, &leaf_hash, &proof);
+
+        assert!(matches!(wrong_index, Err(Error::InvalidProof)));
+        assert!(matches!(
+            out_of_bounds,
+            Err(Error::LeafIndexOutOfBounds { i: 8, n: 8 })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_view_is_rejected() -> Result<(), Error> {
+        let leaves = (0..1 << 4).collect::<Vec<u32>>();
+        let tree = MerkleTreeImpl::<UnhashedLeafConfig>::new(leaves, DOMAIN)?;
+        let mut proof = tree.prove(&[3])?;
+        proof.nodes.pop();
+
+        let res = MerkleTreeImpl::<UnhashedLeafConfig>::verify(&tree.root(), DOMAIN, proof, &[3]);
+        assert!(matches!(res, Err(Error::InvalidProof)));
+        Ok(())
+    }
+
+    #[test]
+    fn view_height_is_bounded() -> Result<(), Error> {
+        let leaves = (0..1 << 4).collect::<Vec<u32>>();
+        let tree = MerkleTreeImpl::<UnhashedLeafConfig>::new(leaves, DOMAIN)?;
+        let mut proof = tree.prove(&[3])?;
+        proof.height = MAX_HEIGHT + 1;
+
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        let deserialized = MerkleView::<SerdeOutput<Sha256>, u32>::deserialize_compressed(&*bytes);
+        let res = MerkleTreeImpl::<UnhashedLeafConfig>::verify(&tree.root(), DOMAIN, proof, &[3]);
+
+        assert!(deserialized.is_err());
+        assert!(matches!(res, Err(Error::InvalidProof)));
+        Ok(())
+    }
+
+    #[test]
+    fn single_leaf_proof_roundtrip() -> Result<(), Error> {
+        let rows = (0..8u64).map(|i| vec![Fp::from(i)]).collect::<Vec<_>>();
+        let tree =
+            MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&Matrix::from_rows(rows), DOMAIN);
+        let proof = tree.prove_leaf(2)?;
+
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        let deserialized = MerkleProof::deserialize_compressed(&*bytes).unwrap();
+        let mut truncated = proof.clone();
+        truncated.path.pop();
+        let mut truncated_bytes = Vec::new();
+        truncated
+            .serialize_compressed(&mut truncated_bytes)
+            .unwrap();
+
+        assert_eq!(proof, deserialized);
+        assert!(
+            MerkleProof::<SerdeOutput<Sha256>>::deserialize_compressed(&*truncated_bytes).is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn digest_length_is_checked() {
+        let mut bytes = Vec::new();
+        vec![0u8; 31].serialize_compressed(&mut bytes).unwrap();
+
+        let deserialized = SerdeOutput::<Sha256>::deserialize_compressed(&*bytes);
+
+        assert!(deserialized.is_err());
+    }
+
+    #[test]
+    fn prove_both_leaves_of_two_leaf_tree() -> Result<(), Error> {
+        let leaves = vec![1u32, 2];
+        let tree = MerkleTreeImpl::<UnhashedLeafConfig>::new(leaves, DOMAIN)?;
+
+        let proof = tree.prove(&[0, 1])?;
+
+        assert!(proof.nodes.is_empty());
+        MerkleTreeImpl::<UnhashedLeafConfig>::verify(&tree.root(), DOMAIN, proof, &[0, 1])
+    }
+
+    #[test]
+    fn chunked_leaves_hash_concatenated_rows() -> Result<(), Error> {
+        let rows = (0..16u64)
+            .map(|i| vec![Fp::from(i), Fp::from(i * i)])
+            .collect::<Vec<_>>();
+        let matrix = Matrix::from_rows(rows.clone());
+        let leaves = rows.chunks(4).map(<[_]>::concat).collect::<Vec<_>>();
+        let leaf_ids = [1, 3];
+        let opened_rows = [&rows[4..8], &rows[12..16]].concat();
+
+        let tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix_chunked(&matrix, DOMAIN, 4);
+        let expected =
+            MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&Matrix::from_rows(leaves), DOMAIN);
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &leaf_ids)?;
+
+        assert_eq!(expected.root(), tree.root());
+        // the rows only hash to the leaves when chunked the same way
+        let res = MatrixMerkleTreeImpl::<Sha256HashFn>::verify_row_chunks(
+            &[tree.root()],
+            DOMAIN,
+            &leaf_ids,
+            &opened_rows,
+            2,
+            proof.clone(),
+        );
+        assert!(matches!(res, Err(Error::InvalidProof)));
+        MatrixMerkleTreeImpl::<Sha256HashFn>::verify_row_chunks(
+            &[tree.root()],
+            DOMAIN,
+            &leaf_ids,
+            &opened_rows,
+            4,
+            proof,
+        )
+    }
+
+    #[test]
+    fn wide_tree_prove_rows() -> Result<(), Error> {
+        // 2^5 leaves isn't a power of 4 or 8
+        let rows = (0..32u64).map(|i| vec![Fp::from(i)]).collect::<Vec<_>>();
+        let matrix = Matrix::from_rows(rows.clone());
+        let row_ids = [0, 5, 6, 31];
+        let queried_rows = row_ids.map(|i| rows[i].clone());
+
+        let tree4 = WideMatrixMerkleTree::<Sha256HashFn, 4>::from_matrix(&matrix, DOMAIN);
+        let proof4 = tree4.prove(&row_ids)?;
+        let tree8 = WideMatrixMerkleTree::<Sha256HashFn, 8>::from_matrix(&matrix, DOMAIN);
+        let proof8 = tree8.prove(&row_ids)?;
+
+        assert!(proof4.paths.iter().all(|path| path.len() == 3 + 3 + 1));
+        assert!(proof8.paths.iter().all(|path| path.len() == 7 + 3));
+        WideMatrixMerkleTree::<Sha256HashFn, 4>::verify_rows(
+            &tree4.root(),
+            DOMAIN,
+            &row_ids,
+            &queried_rows,
+            proof4,
+        )?;
+        WideMatrixMerkleTree::<Sha256HashFn, 8>::verify_rows(
+            &tree8.root(),
+            DOMAIN,
+            &row_ids,
+            &queried_rows,
+            proof8,
+        )
+    }
+
+    #[test]
+    fn wide_tree_rejects_wrong_row() -> Result<(), Error> {
+        let rows = (0..16u64).map(|i| vec![Fp::from(i)]).collect::<Vec<_>>();
+        let matrix = Matrix::from_rows(rows.clone());
+        let tree = WideMatrixMerkleTree::<Sha256HashFn, 4>::from_matrix(&matrix, DOMAIN);
+        let proof = tree.prove(&[3])?;
+
+        let res = WideMatrixMerkleTree::<Sha256HashFn, 4>::verify(
+            &tree.root(),
+            DOMAIN,
+            proof.clone(),
+            &[2],
+        );
+        let wrong_rows = [rows[2].clone()];
+        let wrong_row_res = WideMatrixMerkleTree::<Sha256HashFn, 4>::verify_rows(
+            &tree.root(),
+            DOMAIN,
+            &[3],
+            &wrong_rows,
+            proof,
+        );
+
+        assert!(matches!(res, Err(Error::InvalidProof)));
+        assert!(matches!(wrong_row_res, Err(Error::InvalidProof)));
+        Ok(())
+    }
+
+    #[test]
+    fn wide_tree_rejects_inconsistent_shared_siblings() -> Result<(), Error> {
+        type Tree = WideMatrixMerkleTree<Sha256HashFn, 4>;
+        let rows = (0..64u64).map(|i| vec![Fp::from(i)]).collect::<Vec<_>>();
+        let tree = Tree::from_matrix(&Matrix::from_rows(rows), DOMAIN);
+        // leaves 4 and 5 share every node above the leaves
+        let proof = tree.prove(&[4, 5])?;
+        Tree::verify(&tree.root(), DOMAIN, proof.clone(), &[4, 5])?;
+
+        // a sibling that is also a queried leaf and one from the next layer
+        for i in [0, 5] {
+            let mut wrong_proof = proof.clone();
+            wrong_proof.paths[1][i] = SerdeOutput::default();
+            let res = Tree::verify(&tree.root(), DOMAIN, wrong_proof, &[4, 5]);
+            assert!(matches!(res, Err(Error::InvalidProof)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn paths_end_at_cap() -> Result<(), Error> {
+        type Tree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        let rows = (0..32u64).map(|i| vec![Fp::from(i)]).collect::<Vec<_>>();
+        let tree = Tree::from_matrix(&Matrix::from_rows(rows.clone()), DOMAIN);
+        let row_ids = [0, 5, 6, 31];
+        let queried_rows = row_ids.map(|i| rows[i].clone());
+        assert_eq!(vec![tree.root()], tree.cap(0));
+
+        // caps never include the leaves
+        for (cap_height, cap_size) in [(0, 1), (1, 2), (3, 8), (4, 16), (9, 16)] {
+            let cap = tree.cap(cap_height);
+            let proof = tree.prove_to_cap(&row_ids, cap_height)?;
+            let mut wrong_cap = cap.clone();
+            wrong_cap[cap_size - 1] = SerdeOutput::default();
+
+            assert_eq!(cap_size, cap.len());
+            Tree::verify_rows_against_cap(&cap, DOMAIN, &row_ids, &queried_rows, proof.clone())?;
+            let res = Tree::verify_rows_against_cap(
+                &wrong_cap,
+                DOMAIN,
+                &row_ids,
+                &queried_rows,
+                proof.clone(),
+            );
+            assert!(matches!(res, Err(Error::InvalidProof)));
+            let res =
+                Tree::verify_rows_against_cap(&cap[1..], DOMAIN, &row_ids, &queried_rows, proof);
+            assert!(matches!(res, Err(Error::InvalidProof)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn wide_tree_paths_end_at_cap() -> Result<(), Error> {
+        type Tree = WideMatrixMerkleTree<Sha256HashFn, 4>;
+        // layers hold 32, 8, 2 and 1 nodes
+        let rows = (0..32u64).map(|i| vec![Fp::from(i)]).collect::<Vec<_>>();
+        let tree = Tree::from_matrix(&Matrix::from_rows(rows.clone()), DOMAIN);
+        let row_ids = [0, 5, 6, 31];
+        let queried_rows = row_ids.map(|i| rows[i].clone());
+
+        for (cap_height, cap_size, path_len) in [(0, 1, 7), (1, 2, 6), (2, 8, 3), (5, 8, 3)] {
+            let cap = tree.cap(cap_height);
+            let proof = tree.prove_to_cap(&row_ids, cap_height)?;
+
+            assert_eq!(cap_size, cap.len());
+            assert!(proof.paths.iter().all(|path| path.len() == path_len));
+            Tree::verify_rows_against_cap(&cap, DOMAIN, &row_ids, &queried_rows, proof)?;
+        }
+        // there's no layer with four nodes
+        let proof = tree.prove_to_cap(&row_ids, 2)?;
+        let res = Tree::verify_rows_against_cap(
+            &tree.cap(2)[..4],
+            DOMAIN,
+            &row_ids,
+            &queried_rows,
+            proof,
+        );
+        assert!(matches!(res, Err(Error::InvalidProof)));
+        Ok(())
+    }
+
+    #[test]
+    fn binary_wide_tree_matches_binary_tree() {
+        let rows = (0..16u64).map(|i| vec![Fp::from(i)]).collect::<Vec<_>>();
+        let matrix = Matrix::from_rows(rows);
+
+        let wide_tree = WideMatrixMerkleTree::<Sha256HashFn, 2>::from_matrix(&matrix, DOMAIN);
+        let tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix, DOMAIN);
+
+        assert_eq!(wide_tree.root(), tree.root());
+    }
+
+    #[test]
+    fn extension_rows_match_base_coordinate_rows() {
+        use ark_ff::Field;
+        use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+        use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
+        let rows = (0..8u64)
+            .map(|i| {
+                vec![Fq3::from_base_prime_field_elems(&[i, i + 1, i + 2].map(Fp::from)).unwrap()]
+            })
+            .collect::<Vec<_>>();
+        let coordinate_rows = rows
+            .iter()
+            .map(|row| row[0].to_base_prime_field_elements().collect::<Vec<Fp>>())
+            .collect::<Vec<_>>();
+        let tree =
+            MatrixMerkleTreeImpl::<Blake3HashFn>::from_matrix(&Matrix::from_rows(rows), DOMAIN);
+        let coordinate_tree = MatrixMerkleTreeImpl::<Blake3HashFn>::from_matrix(
+            &Matrix::from_rows(coordinate_rows.clone()),
+            DOMAIN,
+        );
+        let row_ids = [1, 6];
+
+        let proof = MatrixMerkleTree::<Fq3>::prove_rows(&tree, &row_ids).unwrap();
+
+        assert_eq!(tree.root(), coordinate_tree.root());
+        let opened = row_ids.map(|i| coordinate_rows[i].clone());
+        <MatrixMerkleTreeImpl<Blake3HashFn> as MatrixMerkleTree<Fp>>::verify_rows(
+            &tree.root(),
+            DOMAIN,
+            &row_ids,
+            &opened,
+            proof,
+        )
+        .unwrap();
     }
 
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn root_is_independent_of_thread_count() {
+        let rows = (0..64u64).map(|i| vec![Fp::from(i)]).collect::<Vec<_>>();
+        let matrix = Matrix::from_rows(rows);
+
+        let roots = [1, 2, 3, 8].map(|num_threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix, DOMAIN).root()
+            })
+        });
+
+        assert!(roots.iter().all(|root| *root == roots[0]));
+    }
+
+    #[test]
+    fn internal_node_is_not_a_valid_leaf() {
+        // 64-bit field so any internal node payload can be encoded as a row
+        use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+        let rows = (0..4u64).map(|i| vec![Fp::from(i)]).collect::<Vec<_>>();
+        let matrix = Matrix::from_rows(rows);
+        let tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix, DOMAIN);
+        let MerkleTreeImpl { nodes, leaves, .. } = &tree.merkle_tree;
+
+        // forge a "row" that serializes to the payload of internal node 2 and open
+        // it as leaf 0 of a tree with half the height
+        let mut payload = DOMAIN.key().to_vec();
+        payload.extend(leaves[0].as_bytes());
+        payload.extend(leaves[1].as_bytes());
+        let forged_row = payload
+            .chunks(8)
+            .map(|chunk| Fp::deserialize_uncompressed(chunk).unwrap())
+            .collect::<Vec<_>>();
+        let forged_proof = MerkleView {
+            height: 1,
+            nodes: Vec::new(),
+            initial_leaves: vec![nodes[2].clone()],
+            sibling_leaves: vec![nodes[3].clone()],
+        };
+
+        let res = MatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(
+            &tree.root(),
+            DOMAIN,
+            &[0],
+            &[forged_row],
+            forged_proof,
+        );
+        assert!(matches!(res, Err(Error::InvalidProof)));
+    }
+
+    const DOMAIN: MerkleTreeDomain = MerkleTreeDomain::BaseTrace;
+
     struct HashedLeafConfig;
 
     impl MerkleTreeConfig for HashedLeafConfig {
@@ -587,15 +1937,21 @@ mod tests {
         type Leaf = SerdeOutput<Sha256>;
 
         fn hash_leaves(
+            key: &[u8; 32],
             _: u32,
             l0: &SerdeOutput<Sha256>,
             l1: &SerdeOutput<Sha256>,
         ) -> SerdeOutput<Sha256> {
-            Sha256HashFn::merge(l0, l1)
+            hash_node::<Sha256HashFn>(key, l0, l1)
         }
 
-        fn hash_nodes(_: u32, n0: &Self::Digest, n1: &Self::Digest) -> Self::Digest {
-            Sha256HashFn::merge(n0, n1)
+        fn hash_nodes(
+            key: &[u8; 32],
+            _: u32,
+            n0: &Self::Digest,
+            n1: &Self::Digest,
+        ) -> Self::Digest {
+            hash_node::<Sha256HashFn>(key, n0, n1)
         }
 
         fn security_level_bits() -> u32 {
@@ -609,14 +1965,19 @@ mod tests {
         type Digest = SerdeOutput<Sha256>;
         type Leaf = u32;
 
-        fn hash_leaves(_: u32, l0: &u32, l1: &u32) -> SerdeOutput<Sha256> {
+        fn hash_leaves(key: &[u8; 32], _: u32, l0: &u32, l1: &u32) -> SerdeOutput<Sha256> {
             let l0_bytes = l0.to_be_bytes();
             let l1_bytes = l1.to_be_bytes();
-            Sha256HashFn::hash_chunks([&l0_bytes[..], &l1_bytes[..]])
+            Sha256HashFn::hash_chunks([&key[..], &l0_bytes[..], &l1_bytes[..]])
         }
 
-        fn hash_nodes(_: u32, n0: &Self::Digest, n1: &Self::Digest) -> Self::Digest {
-            Sha256HashFn::merge(n0, n1)
+        fn hash_nodes(
+            key: &[u8; 32],
+            _: u32,
+            n0: &Self::Digest,
+            n1: &Self::Digest,
+        ) -> Self::Digest {
+            hash_node::<Sha256HashFn>(key, n0, n1)
         }
 
         fn security_level_bits() -> u32 {
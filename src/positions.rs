@@ -0,0 +1,193 @@
+//! Query positions
+//!
+//! The FRI verifier checks every layer at positions that are derived from the
+//! query positions of the first layer. The prover has to open exactly the rows
+//! the verifier checks: a row the prover didn't open makes honest proofs fail
+//! and a position the verifier skips is a position a cheating prover doesn't
+//! have to answer. Both derive the positions of each layer with
+//! [`derive_layer_positions`].
+//!
+//! Positions are indices into the bit-reversed evaluations of a layer. In
+//! bit-reversed order the evaluations that fold into evaluation `i` of the
+//! next layer are the `folding_factor` consecutive evaluations of row `i` so
+//! folding a position is a division rather than a reduction modulo the size
+//! of the next layer.
+//!
+//! Merkle leaves can hold several consecutive rows (see
+//! [`crate::ProofOptions::with_fri_values_per_leaf`]). Checking a row then
+//! opens every row of its leaf (see [`leaf_rows`]).
+use alloc::vec::Vec;
+use ark_ff::Field;
+
+/// Positions of a layer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayerPositions {
+    /// Rows of the layer that are opened. Sorted and unique.
+    pub rows: Vec<usize>,
+    /// Positions of the next layer that the opened rows are folded into.
+    /// Sorted and unique.
+    pub folded: Vec<usize>,
+}
+
+/// Returns the positions of a layer that's checked at `positions`. The rows
+/// containing `independent_positions` are opened as well and are the only rows
+/// folded into the next layer if the layer is sampled independently (see
+/// [`crate::fri::FriOptions::with_independent_queries`]). Otherwise the rows
+/// containing `positions` are folded.
+///
+/// # Panics
+///
+/// Panics if the positions are not all unique and sorted
+pub fn derive_layer_positions(
+    positions: &[usize],
+    independent_positions: Option<&[usize]>,
+    folding_factor: usize,
+) -> LayerPositions {
+    let fold_source = independent_positions.unwrap_or(positions);
+    LayerPositions {
+        rows: fold_positions(&union_positions(positions, fold_source), folding_factor),
+        folded: fold_positions(fold_source, folding_factor),
+    }
+}
+
+/// Returns the rows that contain the given positions i.e. the positions of
+/// the next layer the positions fold into. Positions that fold into the same
+/// row are deduplicated.
+///
+/// # Panics
+/// Panics is positions are not all unique and sorted
+pub fn fold_positions(positions: &[usize], folding_factor: usize) -> Vec<usize> {
+    assert!(positions.array_windows().all(|[a, b]| a < b));
+    let mut res = positions
+        .iter()
+        .map(|p| p / folding_factor)
+        .collect::<Vec<usize>>();
+    res.dedup();
+    res
+}
+
+/// Returns the sorted union of two sorted lists of positions
+pub fn union_positions(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let mut positions = [a, b].concat();
+    positions.sort_unstable();
+    positions.dedup();
+    positions
+}
+
+/// Returns the number of consecutive rows held by each leaf of a tree over
+/// `num_rows` rows if leaves are asked to hold `rows_per_leaf` rows. Trees
+/// have at least two leaves so small trees hold fewer rows per leaf.
+pub const fn leaf_size(rows_per_leaf: usize, num_rows: usize) -> usize {
+    let max_rows_per_leaf = if num_rows < 4 { 1 } else { num_rows / 2 };
+    if rows_per_leaf == 0 {
+        1
+    } else if rows_per_leaf > max_rows_per_leaf {
+        max_rows_per_leaf
+    } else {
+        rows_per_leaf
+    }
+}
+
+/// Returns the rows that have to be opened to check the rows at `positions`
+/// if every leaf holds `rows_per_leaf` consecutive rows i.e. all rows of the
+/// leaves at `fold_positions(positions, rows_per_leaf)`
+///
+/// # Panics
+///
+/// Panics if positions are not all unique and sorted
+pub fn leaf_rows(positions: &[usize], rows_per_leaf: usize) -> Vec<usize> {
+    fold_positions(positions, rows_per_leaf)
+        .into_iter()
+        .flat_map(|leaf| leaf * rows_per_leaf..(leaf + 1) * rows_per_leaf)
+        .collect()
+}
+
+/// Returns the values at `positions` from the rows at `folded_positions` (see
+/// [`fold_positions`])
+///
+/// # Panics
+///
+/// Panics if a position isn't in one of the rows
+// from winterfell
+pub fn get_query_values<F: Field, const N: usize>(
+    chunks: &[[F; N]],
+    positions: &[usize],
+    folded_positions: &[usize],
+) -> Vec<F> {
+    positions
+        .iter()
+        .map(|position| {
+            let i = folded_positions
+                .iter()
+                .position(|&v| v == position / N)
+                .unwrap();
+            chunks[i][position % N]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::derive_layer_positions;
+    use super::fold_positions;
+    use alloc::collections::BTreeSet;
+    use rand::rngs::StdRng;
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    fn random_positions(rng: &mut StdRng, domain_size: usize) -> Vec<usize> {
+        let num_positions = rng.gen_range(1..64);
+        let positions = (0..num_positions).map(|_| rng.gen_range(0..domain_size));
+        positions.collect::<BTreeSet<usize>>().into_iter().collect()
+    }
+
+    #[test]
+    fn folding_layer_by_layer_matches_folding_at_once() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let folding_factor: usize = 1 << rng.gen_range(1..=4);
+            let num_layers = rng.gen_range(1..=6);
+            let domain_size = folding_factor.pow(num_layers) << rng.gen_range(0..4);
+            let positions = random_positions(&mut rng, domain_size);
+
+            let mut layer_positions = positions.clone();
+            for num_folds in 1..=num_layers {
+                let layer = derive_layer_positions(&layer_positions, None, folding_factor);
+                let divisor = folding_factor.pow(num_folds);
+                let expected = positions
+                    .iter()
+                    .map(|p| p / divisor)
+                    .collect::<BTreeSet<_>>();
+
+                assert_eq!(layer.rows, layer.folded);
+                assert_eq!(Vec::from_iter(expected), layer.folded);
+                layer_positions = layer.folded;
+            }
+        }
+    }
+
+    #[test]
+    fn opened_rows_cover_checked_and_folded_positions() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..100 {
+            let folding_factor: usize = 1 << rng.gen_range(1..=4);
+            let domain_size = folding_factor << rng.gen_range(0..8);
+            let positions = random_positions(&mut rng, domain_size);
+            let independent_positions = random_positions(&mut rng, domain_size);
+
+            for independent_positions in [None, Some(&*independent_positions)] {
+                let layer =
+                    derive_layer_positions(&positions, independent_positions, folding_factor);
+                let source = independent_positions.unwrap_or(&positions);
+
+                assert!(layer.rows.array_windows().all(|[a, b]| a < b));
+                assert!(positions
+                    .iter()
+                    .chain(source)
+                    .all(|p| layer.rows.contains(&(p / folding_factor))));
+                assert!(layer.folded.iter().all(|row| layer.rows.contains(row)));
+                assert_eq!(fold_positions(source, folding_factor), layer.folded);
+            }
+        }
+    }
+}
@@ -1,21 +1,55 @@
+use crate::air::AirConfig;
 use crate::fri::FriProof;
+use crate::fri::FriTailProof;
+use crate::fri::LayerProof;
+use crate::hash::Digest;
+use crate::hash::HashBackend;
+use crate::ldt;
 use crate::merkle::MerkleTree;
 use crate::random::PublicCoin;
+use crate::random::ReseedTag;
+use crate::setup::VerifyingKey;
 use crate::stark::Stark;
 use crate::trace::Queries;
 use crate::utils::field_bits;
+use crate::Air;
 use crate::ProofOptions;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_ff::PrimeField;
+use ark_ff::Zero;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use ark_serialize::Compress;
+use ark_serialize::SerializationError;
 use ark_serialize::Valid;
+use ark_serialize::Validate;
+use core::fmt::Write;
+use core::marker::PhantomData;
 
-/// A proof generated by a mini-stark prover
+/// A proof generated by a mini-stark prover. Everything the prover sends is a
+/// typed field rather than an entry in a stream of protocol objects so proofs
+/// don't depend on any particular AIR or its tables.
 pub struct Proof<C: Stark> {
     pub options: ProofOptions,
     pub trace_len: usize,
-    pub base_trace_commitment: C::Digest,
-    pub extension_trace_commitment: Option<C::Digest>,
-    pub composition_trace_commitment: C::Digest,
+    /// Per-proof nonce absorbed at the start of the transcript (see
+    /// [`Stark::gen_proof_nonce`])
+    pub nonce: Option<[u8; 32]>,
+    /// Fingerprint of the AIR the proof was generated for (see
+    /// [`Air::fingerprint`])
+    pub air_fingerprint: [u8; 32],
+    /// Merkle caps of the trace commitments (see
+    /// [`ProofOptions::with_merkle_cap_height`]). A cap of height zero holds
+    /// only the root.
+    pub base_trace_commitment: Vec<C::Digest>,
+    /// Commitments to the separately committed base columns (see
+    /// [`AirConfig::SEPARATELY_COMMITTED_BASE_COLUMNS`])
+    pub base_column_commitments: Vec<Vec<C::Digest>>,
+    pub extension_trace_commitment: Option<Vec<C::Digest>>,
+    pub composition_trace_commitment: Vec<C::Digest>,
     pub fri_proof: FriProof<C::Fq, C::Digest, C::MerkleTree>,
     pub pow_nonce: u64,
     pub trace_queries: Queries<C>,
@@ -28,7 +62,10 @@ impl<C: Stark> Clone for Proof<C> {
         Self {
             options: self.options,
             trace_len: self.trace_len,
+            nonce: self.nonce,
+            air_fingerprint: self.air_fingerprint,
             base_trace_commitment: self.base_trace_commitment.clone(),
+            base_column_commitments: self.base_column_commitments.clone(),
             extension_trace_commitment: self.extension_trace_commitment.clone(),
             composition_trace_commitment: self.composition_trace_commitment.clone(),
             fri_proof: self.fri_proof.clone(),
@@ -48,8 +85,13 @@ impl<C: Stark> CanonicalSerialize for Proof<C> {
     ) -> Result<(), ark_serialize::SerializationError> {
         self.options.serialize_with_mode(&mut writer, compress)?;
         self.trace_len.serialize_with_mode(&mut writer, compress)?;
+        self.nonce.serialize_with_mode(&mut writer, compress)?;
+        self.air_fingerprint
+            .serialize_with_mode(&mut writer, compress)?;
         self.base_trace_commitment
             .serialize_with_mode(&mut writer, compress)?;
+        self.base_column_commitments
+            .serialize_with_mode(&mut writer, compress)?;
         self.extension_trace_commitment
             .serialize_with_mode(&mut writer, compress)?;
         self.composition_trace_commitment
@@ -68,7 +110,10 @@ impl<C: Stark> CanonicalSerialize for Proof<C> {
     fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
         self.options.serialized_size(compress)
             + self.trace_len.serialized_size(compress)
+            + self.nonce.serialized_size(compress)
+            + self.air_fingerprint.serialized_size(compress)
             + self.base_trace_commitment.serialized_size(compress)
+            + self.base_column_commitments.serialized_size(compress)
             + self.extension_trace_commitment.serialized_size(compress)
             + self.composition_trace_commitment.serialized_size(compress)
             + self.fri_proof.serialized_size(compress)
@@ -95,7 +140,10 @@ impl<C: Stark> CanonicalDeserialize for Proof<C> {
         Ok(Self {
             options: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             trace_len: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            nonce: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            air_fingerprint: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             base_trace_commitment: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            base_column_commitments: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             extension_trace_commitment: <_>::deserialize_with_mode(
                 &mut reader,
                 compress,
@@ -119,7 +167,613 @@ impl<C: Stark> CanonicalDeserialize for Proof<C> {
     }
 }
 
+/// Borrowed view of a serialized [`Proof`]. Verifying a view with
+/// [`crate::verifier::verify_view`] decodes the queries of each FRI layer when
+/// the layer is checked rather than materializing every layer up front. The
+/// rest of the proof is decoded when the view is created.
+pub struct ProofView<'a, S: Stark> {
+    /// The proof without the queries of its FRI layers
+    head: Proof<S>,
+    fri_layers: &'a [u8],
+    num_fri_layers: usize,
+    compress: Compress,
+}
+
+impl<'a, S: Stark> ProofView<'a, S> {
+    /// Parses a proof serialized with [`CanonicalSerialize`]. The FRI layers
+    /// are decoded once (one at a time) to find where they end.
+    pub fn new(bytes: &'a [u8], compress: Compress) -> Result<Self, SerializationError> {
+        let validate = Validate::Yes;
+        let reader = &mut &*bytes;
+        let options = <_>::deserialize_with_mode(&mut *reader, compress, validate)?;
+        let trace_len = <_>::deserialize_with_mode(&mut *reader, compress, validate)?;
+        let nonce = <_>::deserialize_with_mode(&mut *reader, compress, validate)?;
+        let air_fingerprint = <_>::deserialize_with_mode(&mut *reader, compress, validate)?;
+        let base_trace_commitment = <_>::deserialize_with_mode(&mut *reader, compress, validate)?;
+        let base_column_commitments = <_>::deserialize_with_mode(&mut *reader, compress, validate)?;
+        let extension_trace_commitment =
+            <_>::deserialize_with_mode(&mut *reader, compress, validate)?;
+        let composition_trace_commitment =
+            <_>::deserialize_with_mode(&mut *reader, compress, validate)?;
+
+        // FRI proof. Vectors are serialized with a u64 length prefix
+        let layer_caps = <_>::deserialize_with_mode(&mut *reader, compress, validate)?;
+        let num_fri_layers = u64::deserialize_with_mode(&mut *reader, compress, validate)?;
+        let num_fri_layers =
+            usize::try_from(num_fri_layers).map_err(|_| SerializationError::InvalidData)?;
+        let layers_start = bytes.len() - reader.len();
+        for _ in 0..num_fri_layers {
+            LayerProof::<S::Fq, S::MerkleTree>::deserialize_with_mode(
+                &mut *reader,
+                compress,
+                validate,
+            )?;
+        }
+        let fri_layers = &bytes[layers_start..bytes.len() - reader.len()];
+        let remainder_coeffs = <_>::deserialize_with_mode(&mut *reader, compress, validate)?;
+        let ood_evals = <_>::deserialize_with_mode(&mut *reader, compress, validate)?;
+        let domain_offsets = <_>::deserialize_with_mode(&mut *reader, compress, validate)?;
+
+        let head = Proof {
+            options,
+            trace_len,
+            nonce,
+            air_fingerprint,
+            base_trace_commitment,
+            base_column_commitments,
+            extension_trace_commitment,
+            composition_trace_commitment,
+            fri_proof: FriProof {
+                ood_evals,
+                domain_offsets,
+                ..FriProof::new(layer_caps, Vec::new(), remainder_coeffs)
+            },
+            pow_nonce: <_>::deserialize_with_mode(&mut *reader, compress, validate)?,
+            trace_queries: <_>::deserialize_with_mode(&mut *reader, compress, validate)?,
+            execution_trace_ood_evals: <_>::deserialize_with_mode(
+                &mut *reader,
+                compress,
+                validate,
+            )?,
+            composition_trace_ood_evals: <_>::deserialize_with_mode(
+                &mut *reader,
+                compress,
+                validate,
+            )?,
+        };
+
+        Ok(Self {
+            head,
+            fri_layers,
+            num_fri_layers,
+            compress,
+        })
+    }
+
+    /// Returns the proof without the queries of its FRI layers
+    pub const fn head(&self) -> &Proof<S> {
+        &self.head
+    }
+
+    pub const fn num_fri_layers(&self) -> usize {
+        self.num_fri_layers
+    }
+
+    /// Returns an iterator that decodes the queries of each FRI layer
+    pub const fn fri_layers(&self) -> FriLayers<'a, S> {
+        FriLayers {
+            bytes: self.fri_layers,
+            remaining: self.num_fri_layers,
+            compress: self.compress,
+            _stark: PhantomData,
+        }
+    }
+
+    /// Returns the proof without the queries of its FRI layers and an
+    /// iterator that decodes them
+    pub fn into_parts(self) -> (Proof<S>, FriLayers<'a, S>) {
+        let fri_layers = self.fri_layers();
+        (self.head, fri_layers)
+    }
+
+    /// Decodes every FRI layer and returns the full proof
+    pub fn to_proof(&self) -> Proof<S> {
+        let mut proof = self.head.clone();
+        proof.fri_proof.layers = self.fri_layers().collect();
+        proof
+    }
+}
+
+/// Iterator over the FRI layers of a [`ProofView`]. See
+/// [`ProofView::fri_layers`].
+pub struct FriLayers<'a, S: Stark> {
+    bytes: &'a [u8],
+    remaining: usize,
+    compress: Compress,
+    _stark: PhantomData<S>,
+}
+
+impl<S: Stark> Iterator for FriLayers<'_, S> {
+    type Item = LayerProof<S::Fq, S::MerkleTree>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        // the same bytes were decoded when the view was created
+        let layer = LayerProof::deserialize_with_mode(&mut self.bytes, self.compress, Validate::No)
+            .expect("layer was decoded when the view was created");
+        Some(layer)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<S: Stark> ExactSizeIterator for FriLayers<'_, S> {}
+
+/// Reads a proof serialized with [`Proof::serialize_streaming`]. Everything
+/// but the queries of the FRI layers is read up front. Layers are then read
+/// from the reader one at a time as the verifier checks them (see
+/// [`crate::verifier::verify_from_reader`]) so the full proof is never
+/// buffered.
+pub struct StreamedFriLayers<S: Stark, R: ark_serialize::Read> {
+    reader: R,
+    remaining: usize,
+    compress: Compress,
+    error: Option<SerializationError>,
+    _stark: PhantomData<S>,
+}
+
+impl<S: Stark, R: ark_serialize::Read> StreamedFriLayers<S, R> {
+    /// Returns the error that stopped the iterator early, if any
+    pub fn finish(self) -> Result<(), SerializationError> {
+        self.error.map_or(Ok(()), Err)
+    }
+}
+
+impl<S: Stark, R: ark_serialize::Read> Iterator for StreamedFriLayers<S, R> {
+    type Item = LayerProof<S::Fq, S::MerkleTree>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 || self.error.is_some() {
+            return None;
+        }
+        self.remaining -= 1;
+        match LayerProof::deserialize_with_mode(&mut self.reader, self.compress, Validate::Yes) {
+            Ok(layer) => Some(layer),
+            Err(error) => {
+                self.error = Some(error);
+                None
+            }
+        }
+    }
+}
+
+/// Estimated amount of work performed by the verifier. See
+/// [`Proof::verification_cost`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VerificationCost {
+    /// Number of hash function invocations for checking Merkle paths and
+    /// updating the public coin
+    pub hash_invocations: usize,
+    /// Number of field additions, multiplications and inversions (each counted
+    /// as one operation) for the DEEP composition and FRI checks
+    pub field_operations: usize,
+    /// Number of Merkle paths checked
+    pub merkle_path_checks: usize,
+}
+
 impl<C: Stark> Proof<C> {
+    /// Splits the queries of all FRI layers after the first `num_layers`
+    /// layers off the proof to cap its size. The returned tail is verified
+    /// with [`crate::verifier::verify_with_fri_tail`] or by a secondary
+    /// verifier using [`crate::fri::FriVerifier::verify_tail`].
+    pub fn split_fri_tail(&mut self, num_layers: usize) -> FriTailProof<C::Fq, C::MerkleTree> {
+        self.fri_proof.split_tail(num_layers)
+    }
+
+    /// Returns the state of the public coin after the verifier has absorbed
+    /// every commitment, out of domain evaluation and the proof of work nonce
+    /// of the proof. The digest commits to the statement and everything the
+    /// queries are checked against so it can be used as an identifier of the
+    /// proof e.g. by binding it into a signature or an on-chain record. The
+    /// proof isn't verified.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the trace length isn't a power of two
+    pub fn transcript_digest(&self, stark: &C) -> C::Digest {
+        self.transcript_digest_impl(stark, None)
+    }
+
+    /// Like [`Self::transcript_digest`] for AIRs with preprocessed columns.
+    /// The commitment to the preprocessed columns is taken from `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the trace length isn't a power of two
+    pub fn transcript_digest_with_key(&self, stark: &C, key: &VerifyingKey<C>) -> C::Digest {
+        self.transcript_digest_impl(stark, Some(&key.preprocessed_commitment))
+    }
+
+    fn transcript_digest_impl(
+        &self,
+        stark: &C,
+        preprocessed_commitment: Option<&[C::Digest]>,
+    ) -> C::Digest {
+        let air = Air::new(self.trace_len, stark.get_public_inputs(), self.options);
+        let mut public_coin = stark.gen_public_coin(&air);
+        air.reseed_with_domain_parameters(&mut public_coin);
+        if let Some(nonce) = &self.nonce {
+            public_coin.reseed_with_nonce(nonce);
+        }
+        if let Some(commitment) = preprocessed_commitment {
+            public_coin.reseed_with_cap(ReseedTag::Preprocessed, commitment);
+        }
+        public_coin.reseed_with_cap(ReseedTag::BaseTrace, &self.base_trace_commitment);
+        for commitment in &self.base_column_commitments {
+            public_coin.reseed_with_cap(ReseedTag::BaseColumn, commitment);
+        }
+        if let Some(commitment) = &self.extension_trace_commitment {
+            public_coin.reseed_with_cap(ReseedTag::ExtensionTrace, commitment);
+        }
+        public_coin.reseed_with_cap(
+            ReseedTag::CompositionTrace,
+            &self.composition_trace_commitment,
+        );
+        let ood_evals = [
+            self.execution_trace_ood_evals.clone(),
+            self.composition_trace_ood_evals.clone(),
+        ]
+        .concat();
+        public_coin.reseed_with_field_elements(ReseedTag::OodEvals, &ood_evals);
+        // STIR absorbs the out of domain evaluation of a round after the round's cap
+        for (i, layer_cap) in self.fri_proof.layer_caps.iter().enumerate() {
+            public_coin.reseed_with_cap(ReseedTag::FriLayer, layer_cap);
+            if let Some(ood_eval) = i
+                .checked_sub(1)
+                .and_then(|i| self.fri_proof.ood_evals.get(i))
+            {
+                public_coin.reseed_with_field_elements(ReseedTag::StirOodEval, &[*ood_eval]);
+            }
+        }
+        public_coin.reseed_with_field_element_vector(
+            ReseedTag::FriRemainder,
+            &self.fri_proof.remainder_coeffs,
+        );
+        if self.options.grinding_factor != 0 {
+            public_coin.reseed_with_int(ReseedTag::ProofOfWork, self.pow_nonce);
+        }
+        public_coin.digest()
+    }
+
+    /// Serializes the proof in the order it's verified: commitments, out of
+    /// domain evaluations, trace queries and lastly the queries of each FRI
+    /// layer. Read it back with [`Proof::deserialize_streaming`].
+    ///
+    /// Proofs aren't compressed. Digests and field elements are close to
+    /// uniformly random bytes and merkle proofs are batched so shared nodes are
+    /// only sent once. The size of the serialization is given by
+    /// [`CanonicalSerialize::compressed_size`] without serializing.
+    pub fn serialize_streaming<W: ark_serialize::Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.options.serialize_with_mode(&mut writer, compress)?;
+        self.trace_len.serialize_with_mode(&mut writer, compress)?;
+        self.nonce.serialize_with_mode(&mut writer, compress)?;
+        self.air_fingerprint
+            .serialize_with_mode(&mut writer, compress)?;
+        self.base_trace_commitment
+            .serialize_with_mode(&mut writer, compress)?;
+        self.base_column_commitments
+            .serialize_with_mode(&mut writer, compress)?;
+        self.extension_trace_commitment
+            .serialize_with_mode(&mut writer, compress)?;
+        self.composition_trace_commitment
+            .serialize_with_mode(&mut writer, compress)?;
+        self.execution_trace_ood_evals
+            .serialize_with_mode(&mut writer, compress)?;
+        self.composition_trace_ood_evals
+            .serialize_with_mode(&mut writer, compress)?;
+        self.fri_proof
+            .layer_caps
+            .serialize_with_mode(&mut writer, compress)?;
+        self.fri_proof
+            .remainder_coeffs
+            .serialize_with_mode(&mut writer, compress)?;
+        self.fri_proof
+            .ood_evals
+            .serialize_with_mode(&mut writer, compress)?;
+        self.fri_proof
+            .domain_offsets
+            .serialize_with_mode(&mut writer, compress)?;
+        self.pow_nonce.serialize_with_mode(&mut writer, compress)?;
+        self.trace_queries
+            .serialize_with_mode(&mut writer, compress)?;
+        // written like a vector so the layers can be read one at a time
+        self.fri_proof
+            .layers
+            .serialize_with_mode(&mut writer, compress)
+    }
+
+    /// Reads a proof serialized with [`Proof::serialize_streaming`]. Returns
+    /// the proof without the queries of its FRI layers and an iterator that
+    /// reads the layers from `reader` on demand.
+    pub fn deserialize_streaming<R: ark_serialize::Read>(
+        mut reader: R,
+        compress: Compress,
+    ) -> Result<(Self, StreamedFriLayers<C, R>), SerializationError> {
+        let validate = Validate::Yes;
+        let options = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let trace_len = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let nonce = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let air_fingerprint = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let base_trace_commitment = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let base_column_commitments = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let extension_trace_commitment =
+            <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let composition_trace_commitment =
+            <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let execution_trace_ood_evals =
+            <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let composition_trace_ood_evals =
+            <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let layer_caps = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let remainder_coeffs = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let ood_evals = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let domain_offsets = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let pow_nonce = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let trace_queries = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let num_fri_layers = u64::deserialize_with_mode(&mut reader, compress, validate)?;
+        let num_fri_layers =
+            usize::try_from(num_fri_layers).map_err(|_| SerializationError::InvalidData)?;
+
+        let head = Self {
+            options,
+            trace_len,
+            nonce,
+            air_fingerprint,
+            base_trace_commitment,
+            base_column_commitments,
+            extension_trace_commitment,
+            composition_trace_commitment,
+            fri_proof: FriProof {
+                ood_evals,
+                domain_offsets,
+                ..FriProof::new(layer_caps, Vec::new(), remainder_coeffs)
+            },
+            pow_nonce,
+            trace_queries,
+            execution_trace_ood_evals,
+            composition_trace_ood_evals,
+        };
+        let fri_layers = StreamedFriLayers {
+            reader,
+            remaining: num_fri_layers,
+            compress,
+            error: None,
+            _stark: PhantomData,
+        };
+        Ok((head, fri_layers))
+    }
+
+    /// Returns an upper bound on the work the verifier performs on this proof.
+    /// Assumes binary Merkle trees and that queries never share a path. Work
+    /// that depends on the AIR (evaluating the constraints at the out of domain
+    /// point and drawing the AIR challenges) isn't included.
+    pub fn verification_cost(&self) -> VerificationCost {
+        // leaves are hashed twice: once for the row and once with the leaf prefix
+        const LEAF_HASHES: usize = 2;
+        // two subtractions, a division (inversion and multiplication), a
+        // multiplication by the DEEP coefficient and an addition
+        const DEEP_TERM_OPS: usize = 6;
+
+        let num_queries = usize::from(self.options.num_queries);
+        let folding_factor = usize::from(self.options.fri_folding_factor);
+        let folds_per_layer = u32::from(self.options.fri_folds_per_layer);
+        let layer_folding_factor = folding_factor.pow(folds_per_layer);
+        let lde_domain_size = self.trace_len * usize::from(self.options.lde_blowup_factor);
+        let num_layers = self.fri_proof.layers.len();
+        let has_preprocessed_columns = self.trace_queries.preprocessed_trace_proof.is_some();
+        let num_trace_trees = 2
+            + usize::from(self.extension_trace_commitment.is_some())
+            + usize::from(has_preprocessed_columns)
+            + self.base_column_commitments.len();
+
+        // merkle paths of the trace commitments and every FRI layer. Leaves can
+        // hold several rows and paths end at the merkle caps which shortens the
+        // paths.
+        let cap_height = u32::from(self.options.merkle_cap_height);
+        let path_len = |num_leaves: usize| {
+            let cap_size = C::MerkleTree::cap_size(num_leaves, cap_height);
+            (num_leaves / cap_size).ilog2() as usize
+        };
+        let mut merkle_path_checks = num_queries * num_trace_trees;
+        let num_trace_leaves = lde_domain_size / self.options.trace_rows_per_leaf(self.trace_len);
+        let mut hash_invocations = merkle_path_checks * (LEAF_HASHES + path_len(num_trace_leaves));
+        // independently sampled layers are also opened at their own positions
+        let independent_queries = self.options.fri_independent_queries;
+        let num_layer_positions = if independent_queries {
+            num_queries * num_layers.saturating_sub(1)
+        } else {
+            0
+        };
+        let fri_options = self.options.into_fri_options();
+        let layer_sizes = fri_options.layer_sizes(lde_domain_size).take(num_layers);
+        for (i, layer_domain_size) in layer_sizes.enumerate() {
+            let rows_per_leaf = fri_options.rows_per_leaf(layer_domain_size);
+            let num_leaves = layer_domain_size / layer_folding_factor / rows_per_leaf;
+            let num_layer_queries = if independent_queries && i != 0 {
+                2 * num_queries
+            } else {
+                num_queries
+            };
+            merkle_path_checks += num_layer_queries;
+            hash_invocations += num_layer_queries * (LEAF_HASHES + path_len(num_leaves));
+        }
+
+        // reseeding with the domain parameters (modulus limbs, extension degree,
+        // domain size and offset), every node of the commitments' caps, ood
+        // evaluations, remainder and proof of work
+        let num_modulus_limbs = <C::Fp as Field>::BasePrimeField::MODULUS.as_ref().len();
+        hash_invocations += num_modulus_limbs + 3;
+        if self.nonce.is_some() {
+            hash_invocations += 4;
+        }
+        let num_cap_nodes = [
+            &self.base_trace_commitment,
+            &self.composition_trace_commitment,
+        ]
+        .into_iter()
+        .chain(&self.base_column_commitments)
+        .chain(&self.extension_trace_commitment)
+        .chain(&self.fri_proof.layer_caps)
+        .map(Vec::len)
+        .sum::<usize>();
+        // the preprocessed columns' cap is taken from the verifying key
+        let num_preprocessed_cap_nodes = if has_preprocessed_columns {
+            C::MerkleTree::cap_size(num_trace_leaves, cap_height)
+        } else {
+            0
+        };
+        hash_invocations += num_cap_nodes + num_preprocessed_cap_nodes + 3;
+        // drawing z, query positions, FRI alphas and DEEP coefficients
+        let num_ood_evals =
+            self.execution_trace_ood_evals.len() + self.composition_trace_ood_evals.len();
+        let num_fri_alphas = num_layers * folds_per_layer as usize;
+        hash_invocations +=
+            1 + num_queries + num_layer_positions + num_fri_alphas + num_ood_evals + 2;
+
+        // DEEP composition evaluations at every query
+        let mut field_operations = num_queries * (num_ood_evals * DEEP_TERM_OPS + 3);
+        // interpolating each queried coset and evaluating it at alpha. Rows of
+        // layers that fold several times hold several cosets.
+        let num_cosets = (0..folds_per_layer)
+            .map(|i| folding_factor.pow(i))
+            .sum::<usize>();
+        let coset_fold_ops = folding_factor * folding_factor.ilog2() as usize + 3 * folding_factor;
+        let fold_ops = num_cosets * coset_fold_ops;
+        field_operations += num_layers * num_queries * fold_ops;
+        // evaluating the remainder at every query
+        let remainder_ops = 2 * self.fri_proof.remainder_coeffs.len();
+        let remainder_domain_size =
+            (0..num_layers).fold(lde_domain_size, |size, _| size / layer_folding_factor);
+        field_operations += num_queries * (remainder_ops + remainder_domain_size.ilog2() as usize);
+
+        VerificationCost {
+            hash_invocations,
+            field_operations,
+            merkle_path_checks,
+        }
+    }
+
+    /// Returns a human readable breakdown of the proof. Intended for debugging
+    /// interoperability issues with other provers and verifiers.
+    pub fn describe(&self) -> String {
+        let Self {
+            options,
+            trace_len,
+            fri_proof,
+            ..
+        } = self;
+        let remainder_degree = fri_proof
+            .remainder_coeffs
+            .iter()
+            .rposition(|coeff| !coeff.is_zero());
+        let num_queries = usize::from(options.num_queries);
+
+        let mut description = String::new();
+        let out = &mut description;
+        writeln!(out, "options: {options:?}").unwrap();
+        writeln!(out, "trace length: {trace_len}").unwrap();
+        let air_fingerprint = hex_bytes(&self.air_fingerprint);
+        writeln!(out, "air fingerprint: {air_fingerprint}").unwrap();
+        if let Some(nonce) = &self.nonce {
+            writeln!(out, "nonce: {}", hex_bytes(nonce)).unwrap();
+        }
+        writeln!(out, "security: {:?}", self.security_report()).unwrap();
+        let base_trace_cap = hex_cap(&self.base_trace_commitment);
+        writeln!(out, "base trace cap: {base_trace_cap}").unwrap();
+        let separate_columns = C::AirConfig::SEPARATELY_COMMITTED_BASE_COLUMNS;
+        for (column, cap) in separate_columns.iter().zip(&self.base_column_commitments) {
+            writeln!(out, "  base column {column} cap: {}", hex_cap(cap)).unwrap();
+        }
+        if let Some(commitment) = &self.extension_trace_commitment {
+            writeln!(out, "extension trace cap: {}", hex_cap(commitment)).unwrap();
+        }
+        let composition_cap = hex_cap(&self.composition_trace_commitment);
+        writeln!(out, "composition trace cap: {composition_cap}").unwrap();
+        let num_ood_evals = self.execution_trace_ood_evals.len();
+        writeln!(out, "execution trace ood evaluations: {num_ood_evals}").unwrap();
+        let num_ood_evals = self.composition_trace_ood_evals.len();
+        writeln!(out, "composition trace ood evaluations: {num_ood_evals}").unwrap();
+        let lde_domain_size = trace_len * usize::from(options.lde_blowup_factor);
+        let ldt = if ldt::is_direct(lde_domain_size) {
+            "direct"
+        } else if options.fri_stir {
+            "stir"
+        } else {
+            "fri"
+        };
+        writeln!(out, "low-degree test: {ldt}").unwrap();
+        writeln!(out, "fri layers: {}", fri_proof.layers.len()).unwrap();
+        for (i, cap) in fri_proof.layer_caps.iter().enumerate() {
+            writeln!(out, "  layer {i} cap: {}", hex_cap(cap)).unwrap();
+        }
+        match remainder_degree {
+            Some(degree) => writeln!(out, "fri remainder degree: {degree}").unwrap(),
+            None => writeln!(out, "fri remainder degree: none (zero polynomial)").unwrap(),
+        }
+        writeln!(out, "proof of work nonce: {}", self.pow_nonce).unwrap();
+        writeln!(out, "queries: {num_queries}").unwrap();
+        description
+    }
+
+    /// Returns a human readable breakdown of the trace values opened at each
+    /// query. Query positions aren't part of the proof. They're drawn by the
+    /// verifier and returned in [`VerifierChannelArtifacts::query_positions`].
+    ///
+    /// [`VerifierChannelArtifacts::query_positions`]: crate::channel::VerifierChannelArtifacts::query_positions
+    pub fn describe_queries(&self, query_positions: &[usize]) -> String {
+        let num_base_columns = C::AirConfig::NUM_BASE_COLUMNS;
+        let num_extension_columns = C::AirConfig::NUM_EXTENSION_COLUMNS;
+        let num_composition_columns = self.composition_trace_ood_evals.len();
+        let queries = &self.trace_queries;
+        // every row of the leaves containing the query positions is opened
+        let rows_per_leaf = self.options.trace_rows_per_leaf(self.trace_len);
+        let leaves = query_positions.iter().map(|p| p / rows_per_leaf);
+        let mut leaves = leaves.collect::<Vec<usize>>();
+        leaves.sort_unstable();
+        leaves.dedup();
+
+        let mut description = String::new();
+        let out = &mut description;
+        for (i, position) in query_positions.iter().enumerate() {
+            writeln!(out, "query {i} (position {position}):").unwrap();
+            let leaf = leaves.binary_search(&(position / rows_per_leaf)).unwrap();
+            let i = leaf * rows_per_leaf + position % rows_per_leaf;
+            let row = queries.base_trace_values.chunks(num_base_columns).nth(i);
+            writeln!(out, "  base trace row: {}", format_row(row)).unwrap();
+            if num_extension_columns != 0 {
+                let values = &queries.extension_trace_values;
+                let row = values.chunks(num_extension_columns).nth(i);
+                writeln!(out, "  extension trace row: {}", format_row(row)).unwrap();
+            }
+            // a proof without composition columns has nothing to open
+            let values = &queries.composition_trace_values;
+            let row = (num_composition_columns != 0)
+                .then(|| values.chunks(num_composition_columns).nth(i))
+                .flatten();
+            writeln!(out, "  composition trace row: {}", format_row(row)).unwrap();
+        }
+        description
+    }
+
     // adapted from Winterfell
     // also https://github.com/starkware-libs/ethSTARK/blob/master/README.md#7-Measuring-Security
     // https://eprint.iacr.org/2020/654.pdf section 7.2 for proven security
@@ -133,8 +787,74 @@ impl<C: Stark> Proof<C> {
         let fri_query_security = {
             let grinding_factor = u32::from(self.options.grinding_factor);
             let security_per_query = self.options.lde_blowup_factor.ilog2();
-            let num_fri_quiries = u32::from(self.options.num_queries);
-            security_per_query * num_fri_quiries + grinding_factor
+            let num_fri_queries = u32::from(self.options.num_queries);
+            security_per_query * num_fri_queries + grinding_factor
+        };
+
+        let merkle_tree_security = C::MerkleTree::security_level_bits();
+        let public_coin_security = C::PublicCoin::security_level_bits();
+
+        field_security
+            .min(fri_query_security)
+            .min(merkle_tree_security)
+            .min(public_coin_security)
+    }
+
+    /// Returns the security parameters this proof was generated with. The
+    /// security levels only depend on the fields, the LDE domain, queries,
+    /// grinding and hash functions e.g. they don't change with
+    /// [`ProofOptions::with_degree_adjusted_composition`].
+    pub fn security_report(&self) -> SecurityReport {
+        SecurityReport {
+            conjectured_bits: self.security_level_bits(),
+            proven_bits: self.proven_security_level_bits(),
+            max_constraint_degree: self.max_constraint_degree(),
+            lde_blowup_factor: self.options.lde_blowup_factor,
+            num_queries: self.options.num_queries,
+            grinding_factor: self.options.grinding_factor,
+            hash_backend: C::MerkleTree::hash_backend(),
+        }
+    }
+
+    /// Returns the degree of the highest degree constraint of the AIR (after
+    /// division) as a multiple of the trace degree rounded up
+    fn max_constraint_degree(&self) -> usize {
+        let trace_degree = self.trace_len - 1;
+        C::AirConfig::constraints(self.trace_len)
+            .iter()
+            .map(|constraint| {
+                let (numerator_degree, denominator_degree) = constraint.degree(trace_degree);
+                let evaluation_degree = numerator_degree.saturating_sub(denominator_degree);
+                evaluation_degree.div_ceil(trace_degree)
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    // proven security of ethSTARK in the list decoding regime (Johnson bound)
+    // https://eprint.iacr.org/2021/582.pdf
+    // bit counts are non-negative and far smaller than u32::MAX
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn proven_security_level_bits(&self) -> u32 {
+        // multiplicity parameter of the Johnson bound
+        const M: f64 = 3.0;
+
+        let rho = 1.0 / f64::from(self.options.lde_blowup_factor);
+        let lde_domain_size = self.trace_len * usize::from(self.options.lde_blowup_factor);
+        let lde_domain_size_squared_bits = f64::from(2 * lde_domain_size.ilog2());
+
+        let field_security = {
+            let extension_field_bits = f64::from(field_bits::<C::Fq>());
+            let list_size = (M + 0.5).powi(7) / (3.0 * rho.powf(1.5));
+            let bits = extension_field_bits - list_size.log2() - lde_domain_size_squared_bits;
+            bits.max(0.0) as u32
+        };
+
+        let fri_query_security = {
+            let grinding_factor = u32::from(self.options.grinding_factor);
+            let alpha = (1.0 + 0.5 / M) * rho.sqrt();
+            let num_fri_queries = f64::from(self.options.num_queries);
+            (-alpha.log2() * num_fri_queries) as u32 + grinding_factor
         };
 
         let merkle_tree_security = C::MerkleTree::security_level_bits();
@@ -146,3 +866,48 @@ impl<C: Stark> Proof<C> {
             .min(public_coin_security)
     }
 }
+
+fn hex(digest: &impl Digest) -> String {
+    hex_bytes(&digest.as_bytes())
+}
+
+/// Returns the nodes of a merkle cap in hex separated by commas
+fn hex_cap(cap: &[impl Digest]) -> String {
+    cap.iter().map(hex).collect::<Vec<String>>().join(", ")
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    let mut hex = String::new();
+    for byte in bytes {
+        write!(hex, "{byte:02x}").unwrap();
+    }
+    hex
+}
+
+fn format_row<F: Field>(row: Option<&[F]>) -> String {
+    row.map_or_else(
+        || String::from("missing"),
+        |row| {
+            let values = row.iter().map(ToString::to_string).collect::<Vec<String>>();
+            format!("[{}]", values.join(", "))
+        },
+    )
+}
+
+/// Security parameters of a proof. See [`Proof::security_report`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SecurityReport {
+    /// Security level under the commonly used FRI conjecture
+    pub conjectured_bits: u32,
+    /// Security level that is proven in the list decoding regime
+    pub proven_bits: u32,
+    /// Degree of the highest degree constraint of the AIR as a multiple of the
+    /// trace degree (rounded up)
+    pub max_constraint_degree: usize,
+    pub lde_blowup_factor: u8,
+    pub num_queries: u8,
+    pub grinding_factor: u8,
+    /// Implementation of the merkle tree hash function used on this CPU. This
+    /// doesn't affect security or the proof but determines commit times.
+    pub hash_backend: HashBackend,
+}
@@ -2,10 +2,22 @@ use crate::air::AirConfig;
 use crate::challenges::Challenges;
 use crate::channel::ProverChannel;
 use crate::composer::DeepPolyComposer;
-use crate::fri::FriProver;
+use crate::domain::lde_domain_size;
+use crate::fri;
+use crate::hash::write_canonical_element;
+use crate::hash::Blake3HashFn;
+use crate::hash::Digest;
+use crate::hash::ElementHashFn;
+use crate::ldt::LdtProver;
+use crate::ldt::LowDegreeTest;
+use crate::merkle;
 use crate::merkle::MatrixMerkleTree;
 use crate::merkle::MerkleTree;
-use crate::random::draw_multiple;
+use crate::merkle::MerkleTreeDomain;
+use crate::random::DrawTag;
+use crate::random::PublicCoin;
+use crate::setup::commit_preprocessed_columns;
+use crate::setup::ProvingKey;
 use crate::stark::Stark;
 use crate::trace::Queries;
 use crate::utils::GpuAllocator;
@@ -15,18 +27,19 @@ use crate::Matrix;
 use crate::Proof;
 use crate::ProofOptions;
 use crate::Trace;
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 use ark_ff::Field;
 use ark_poly::EvaluationDomain;
 use ministark_gpu::utils::bit_reverse;
+use snafu::Snafu;
 use std::time::Instant;
 
-#[allow(clippy::too_many_lines)]
 pub fn default_prove<S: Stark>(
     this: &S,
     options: ProofOptions,
     witness: S::Witness,
-) -> Result<Proof<S>, ProvingError> {
+) -> Result<Proof<S>, ProverError> {
     let now = Instant::now();
     let trace = this.generate_trace(witness);
     println!(
@@ -37,37 +50,151 @@ pub fn default_prove<S: Stark>(
     );
 
     let now = Instant::now();
-    let air = Air::new(trace.len(), this.get_public_inputs(), options);
-    let public_coin = this.gen_public_coin(&air);
-    let mut channel = ProverChannel::<S>::new(&air, public_coin);
+    let air = build_air(this, trace.len(), options)?;
     println!("Init air: {:?}", now.elapsed());
 
+    prove_trace(this, &air, trace)
+}
+
+/// Generates a proof of an execution trace against the AIR
+pub fn prove_trace<S: Stark>(
+    this: &S,
+    air: &Air<S::AirConfig>,
+    trace: impl Trace<Fp = S::Fp, Fq = S::Fq>,
+) -> Result<Proof<S>, ProverError> {
+    prove_trace_impl(this, air, trace, None, None)
+}
+
+/// Like [`default_prove`] but takes the constraints and the commitment to the
+/// preprocessed columns from a key returned by [`crate::setup::setup`]
+pub fn prove_with_key<S: Stark>(
+    this: &S,
+    key: &ProvingKey<S>,
+    witness: S::Witness,
+) -> Result<Proof<S>, ProverError> {
+    let trace = this.generate_trace(witness);
+    check_num_columns("rows", key.trace_len(), trace.len())?;
+    if let Some(column) = key.mismatched_preprocessed_column(trace.base_columns()) {
+        return Err(ProverError::PreprocessedColumnMismatch { column });
+    }
+    let air = key.air(this.get_public_inputs());
+    prove_trace_impl(this, &air, trace, None, key.preprocessed_tree())
+}
+
+/// Like [`default_prove`] but reuses the base trace LDE and commitment from
+/// `artifacts` if the same trace was previously proven with the same blowup
+/// factor
+pub fn prove_with_artifacts<S: Stark>(
+    this: &S,
+    options: ProofOptions,
+    witness: S::Witness,
+    artifacts: &mut ProverArtifacts<S>,
+) -> Result<Proof<S>, ProverError> {
+    let trace = this.generate_trace(witness);
+    let air = build_air(this, trace.len(), options)?;
+    prove_trace_with_artifacts(this, &air, trace, artifacts)
+}
+
+/// Like [`prove_trace`] but reuses the base trace LDE and commitment from
+/// `artifacts` if the same trace was previously proven with the same blowup
+/// factor
+pub fn prove_trace_with_artifacts<S: Stark>(
+    this: &S,
+    air: &Air<S::AirConfig>,
+    trace: impl Trace<Fp = S::Fp, Fq = S::Fq>,
+    artifacts: &mut ProverArtifacts<S>,
+) -> Result<Proof<S>, ProverError> {
+    prove_trace_impl(this, air, trace, Some(artifacts), None)
+}
+
+/// Builds the AIR of a trace of length `trace_len`. Options and trace lengths
+/// the AIR doesn't support are returned as errors rather than panics.
+fn build_air<S: Stark>(
+    this: &S,
+    trace_len: usize,
+    options: ProofOptions,
+) -> Result<Air<S::AirConfig>, ProverError> {
+    if !options.is_valid() {
+        return Err(ProverError::InvalidProofOptions);
+    }
+    let lde_blowup_factor = options.lde_blowup_factor;
+    if lde_domain_size::<S::Fp>(trace_len, lde_blowup_factor.into()).is_none() {
+        return Err(ProverError::InvalidTraceLength { trace_len });
+    }
+    Air::try_new(trace_len, this.get_public_inputs(), options)
+        .ok_or(ProverError::InsufficientBlowupFactor { lde_blowup_factor })
+}
+
+#[allow(clippy::too_many_lines)]
+fn prove_trace_impl<S: Stark>(
+    this: &S,
+    air: &Air<S::AirConfig>,
+    trace: impl Trace<Fp = S::Fp, Fq = S::Fq>,
+    artifacts: Option<&mut ProverArtifacts<S>>,
+    preprocessed_tree: Option<&S::MerkleTree>,
+) -> Result<Proof<S>, ProverError> {
+    let options = air.options();
+    let mut public_coin = this.gen_public_coin(air);
+    air.reseed_with_domain_parameters(&mut public_coin);
+    let mut channel = ProverChannel::<S>::new(air, public_coin);
+    if let Some(nonce) = this.gen_proof_nonce() {
+        channel.commit_nonce(nonce);
+    }
+
     let now = Instant::now();
     let trace_xs = air.trace_domain();
     let lde_xs = air.lde_domain();
     let base_trace = trace.base_columns();
-    assert_eq!(S::AirConfig::NUM_BASE_COLUMNS, base_trace.num_cols());
-    let base_trace_polys = base_trace.interpolate(trace_xs);
-    let mut base_trace_lde = base_trace_polys.bit_reversed_evaluate(lde_xs);
-    let base_trace_tree = S::MerkleTree::from_matrix(&base_trace_lde);
+    check_num_columns(
+        "base columns",
+        S::AirConfig::NUM_BASE_COLUMNS,
+        base_trace.num_cols(),
+    )?;
+    let BaseTraceArtifacts {
+        polys: base_trace_polys,
+        lde: mut base_trace_lde,
+        tree: base_trace_tree,
+        column_trees: base_column_trees,
+        preprocessed_tree,
+    } = artifacts.map_or_else(
+        || BaseTraceArtifacts::commit(air, base_trace, preprocessed_tree),
+        |artifacts| artifacts.get_or_commit(air, base_trace),
+    );
     println!("Base trace commitment: {:?}", now.elapsed());
 
-    channel.commit_base_trace(base_trace_tree.root());
+    let cap_height = options.merkle_cap_height.into();
+    if let Some(tree) = &preprocessed_tree {
+        channel.commit_preprocessed_columns(&tree.cap(cap_height));
+    }
+    channel.commit_base_trace(base_trace_tree.cap(cap_height));
+    let base_column_caps = base_column_trees.iter().map(|t| t.cap(cap_height));
+    channel.commit_base_columns(base_column_caps.collect());
     let num_challenges = air.num_challenges();
-    let challenges = Challenges::new(draw_multiple(&mut channel.public_coin, num_challenges));
+    let challenges = Challenges::new(
+        channel
+            .public_coin
+            .draw_fqx(DrawTag::AirChallenge, num_challenges),
+    );
     let hints = air.gen_hints(&challenges);
 
     let now = Instant::now();
     let extension_trace = trace.build_extension_columns(&challenges);
     let num_extension_cols = extension_trace.as_ref().map_or(0, Matrix::num_cols);
-    assert_eq!(S::AirConfig::NUM_EXTENSION_COLUMNS, num_extension_cols);
+    check_num_columns(
+        "extension columns",
+        S::AirConfig::NUM_EXTENSION_COLUMNS,
+        num_extension_cols,
+    )?;
     let extension_trace_polys = extension_trace.as_ref().map(|t| t.interpolate(trace_xs));
     let mut extension_trace_lde = extension_trace_polys
         .as_ref()
         .map(|p| p.bit_reversed_evaluate(lde_xs));
-    let extension_trace_tree = extension_trace_lde.as_ref().map(S::MerkleTree::from_matrix);
+    let rows_per_leaf = options.trace_rows_per_leaf(air.trace_len());
+    let extension_trace_tree = extension_trace_lde.as_ref().map(|lde| {
+        S::MerkleTree::from_matrix_chunked(lde, MerkleTreeDomain::ExtensionTrace, rows_per_leaf)
+    });
     if let Some(t) = extension_trace_tree.as_ref() {
-        channel.commit_extension_trace(t.root());
+        channel.commit_extension_trace(t.cap(cap_height));
     }
     println!("Extension trace commitment: {:?}", now.elapsed());
 
@@ -91,7 +218,9 @@ pub fn default_prove<S: Stark>(
             .map(|t| bit_reverse_ce_trace(ce_domain_size, t));
 
         let num_composition_coeffs = air.num_composition_constraint_coeffs();
-        let composition_coeffs = draw_multiple(&mut channel.public_coin, num_composition_coeffs);
+        let composition_coeffs = channel
+            .public_coin
+            .draw_fqx(DrawTag::CompositionCoeff, num_composition_coeffs);
         let x_lde = ce_lde_xs.elements().collect::<Vec<_>>();
 
         let now = Instant::now();
@@ -120,8 +249,12 @@ pub fn default_prove<S: Stark>(
         }
         composition_trace_polys = Matrix::new(composition_trace_cols);
         composition_trace_lde = composition_trace_polys.bit_reversed_evaluate(air.lde_domain());
-        composition_trace_tree = S::MerkleTree::from_matrix(&composition_trace_lde);
-        channel.commit_composition_trace(composition_trace_tree.root());
+        composition_trace_tree = S::MerkleTree::from_matrix_chunked(
+            &composition_trace_lde,
+            MerkleTreeDomain::CompositionTrace,
+            rows_per_leaf,
+        );
+        channel.commit_composition_trace(composition_trace_tree.cap(cap_height));
         println!("Composition trace commitment: {:?}", now.elapsed());
 
         bit_reverse_ce_trace(ce_domain_size, &mut base_trace_lde);
@@ -133,7 +266,7 @@ pub fn default_prove<S: Stark>(
     let now = Instant::now();
     let z = channel.get_ood_point();
     let mut deep_poly_composer = DeepPolyComposer::new(
-        &air,
+        air,
         z,
         base_trace_polys,
         extension_trace_polys,
@@ -142,45 +275,230 @@ pub fn default_prove<S: Stark>(
     let (execution_trace_oods, composition_trace_oods) = deep_poly_composer.get_ood_evals();
     channel.send_ood_evals(execution_trace_oods, composition_trace_oods);
 
-    let deep_coeffs = this.gen_deep_coeffs(&mut channel.public_coin, &air);
+    let deep_coeffs = this.gen_deep_coeffs(&mut channel.public_coin, air);
     let deep_composition_poly = deep_poly_composer.into_deep_poly(deep_coeffs);
+    let deep_composition_degree = deep_composition_poly.column_degrees()[0];
     // let deep_xs = Radix2EvaluationDomain::new(lde_xs.size());
     let deep_composition_lde = deep_composition_poly.into_bit_reversed_evaluations(lde_xs);
     println!("Deep composition: {:?}", now.elapsed());
 
     let now = Instant::now();
     let fri_options = options.into_fri_options();
-    let mut fri_prover = FriProver::<S::Fq, S::Digest, S::MerkleTree>::new(fri_options);
-    fri_prover.build_layers(&mut channel, deep_composition_lde.try_into().unwrap());
+    let mut fri_prover =
+        LdtProver::<S::Fq, S::Digest, S::MerkleTree>::new(fri_options, lde_xs.size());
+    fri_prover.commit(
+        &mut channel,
+        deep_composition_lde.try_into().unwrap(),
+        deep_composition_degree,
+    )?;
     println!("FRI: {:?}", now.elapsed());
 
     let now = Instant::now();
-    channel.grind_fri_commitments();
+    channel.grind_fri_commitments()?;
     println!("Proof of work: {:?}", now.elapsed());
 
     let query_positions = Vec::from_iter(channel.get_fri_query_positions());
-    let fri_proof = fri_prover.into_proof(&query_positions);
+    let layer_positions = channel.get_fri_layer_query_positions(fri_prover.num_layers());
+    #[cfg(debug_assertions)]
+    crate::transcript::assert_transcript("prover", &channel.public_coin);
+    let fri_proof = fri_prover.into_proof(&query_positions, &layer_positions)?;
 
     let queries = Queries::new(
         &base_trace_lde,
         extension_trace_lde.as_ref(),
         &composition_trace_lde,
         &base_trace_tree,
+        &base_column_trees,
+        preprocessed_tree.as_ref(),
         extension_trace_tree.as_ref(),
         &composition_trace_tree,
         &query_positions,
-    );
+        rows_per_leaf,
+        cap_height,
+    )
+    .map_err(|source| ProverError::TraceQuery { source })?;
     Ok(channel.build_proof(queries, fri_proof))
 }
 
+const fn check_num_columns(
+    item: &'static str,
+    expected: usize,
+    actual: usize,
+) -> Result<(), ProverError> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(ProverError::TraceShapeMismatch {
+            item,
+            expected,
+            actual,
+        })
+    }
+}
+
+/// Cache of the base trace LDE and commitment. Proving the same trace with
+/// different FRI parameters (number of queries, folding factor, grinding etc.)
+/// only re-runs the constraint evaluation and FRI. Entries are keyed by a hash
+/// of the base trace, the LDE blowup factor, the domain offset and the number
+/// of rows per merkle leaf.
+pub struct ProverArtifacts<S: Stark> {
+    base_traces: BTreeMap<ArtifactKey, BaseTraceArtifacts<S>>,
+}
+
+impl<S: Stark> ProverArtifacts<S> {
+    pub const fn new() -> Self {
+        Self {
+            base_traces: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the number of cached base traces
+    pub fn len(&self) -> usize {
+        self.base_traces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.base_traces.is_empty()
+    }
+
+    /// Removes all cached base traces
+    pub fn clear(&mut self) {
+        self.base_traces.clear();
+    }
+
+    fn get_or_commit(
+        &mut self,
+        air: &Air<S::AirConfig>,
+        base_trace: &Matrix<S::Fp>,
+    ) -> BaseTraceArtifacts<S> {
+        let lde_domain = air.lde_domain();
+        let mut domain_offset = Vec::new();
+        write_canonical_element(&lde_domain.offset, &mut domain_offset);
+        let key = ArtifactKey {
+            trace_hash: Blake3HashFn::hash_elements(base_trace.0.iter().flatten().copied())
+                .as_bytes(),
+            trace_len: air.trace_len(),
+            lde_blowup_factor: air.lde_blowup_factor(),
+            domain_offset,
+            rows_per_leaf: air.options().trace_rows_per_leaf(air.trace_len()),
+        };
+        self.base_traces
+            .entry(key)
+            .or_insert_with(|| BaseTraceArtifacts::commit(air, base_trace, None))
+            .clone()
+    }
+}
+
+impl<S: Stark> Default for ProverArtifacts<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct ArtifactKey {
+    trace_hash: [u8; 32],
+    trace_len: usize,
+    lde_blowup_factor: usize,
+    domain_offset: Vec<u8>,
+    rows_per_leaf: usize,
+}
+
+struct BaseTraceArtifacts<S: Stark> {
+    polys: Matrix<S::Fp>,
+    lde: Matrix<S::Fp>,
+    tree: S::MerkleTree,
+    column_trees: Vec<S::MerkleTree>,
+    preprocessed_tree: Option<S::MerkleTree>,
+}
+
+impl<S: Stark> BaseTraceArtifacts<S> {
+    /// Commits to the base trace. The commitment to the preprocessed columns
+    /// is reused from `preprocessed_tree` if given.
+    fn commit(
+        air: &Air<S::AirConfig>,
+        base_trace: &Matrix<S::Fp>,
+        preprocessed_tree: Option<&S::MerkleTree>,
+    ) -> Self {
+        let polys = base_trace.interpolate(air.trace_domain());
+        let lde = polys.bit_reversed_evaluate(air.lde_domain());
+        let separate_columns = S::AirConfig::SEPARATELY_COMMITTED_BASE_COLUMNS;
+        let rows_per_leaf = air.options().trace_rows_per_leaf(air.trace_len());
+        let preprocessed_columns = S::AirConfig::PREPROCESSED_BASE_COLUMNS;
+        let tree = if separate_columns.is_empty() && preprocessed_columns.is_empty() {
+            S::MerkleTree::from_matrix_chunked(&lde, MerkleTreeDomain::BaseTrace, rows_per_leaf)
+        } else {
+            let columns = air.matrix_committed_base_columns();
+            let matrix = Matrix::new(
+                columns
+                    .into_iter()
+                    .map(|i| lde.0[i].to_vec_in(GpuAllocator))
+                    .collect(),
+            );
+            S::MerkleTree::from_matrix_chunked(&matrix, MerkleTreeDomain::BaseTrace, rows_per_leaf)
+        };
+        let column_trees = separate_columns
+            .iter()
+            .map(|&i| {
+                let column = Matrix::new(vec![lde.0[i].to_vec_in(GpuAllocator)]);
+                let domain = MerkleTreeDomain::BaseTraceColumn(i);
+                S::MerkleTree::from_matrix_chunked(&column, domain, rows_per_leaf)
+            })
+            .collect();
+        let preprocessed_tree = preprocessed_tree
+            .cloned()
+            .or_else(|| commit_preprocessed_columns::<S>(air, &lde));
+        Self {
+            polys,
+            lde,
+            tree,
+            column_trees,
+            preprocessed_tree,
+        }
+    }
+}
+
+impl<S: Stark> Clone for BaseTraceArtifacts<S> {
+    fn clone(&self) -> Self {
+        Self {
+            polys: self.polys.clone(),
+            lde: self.lde.clone(),
+            tree: self.tree.clone(),
+            column_trees: self.column_trees.clone(),
+            preprocessed_tree: self.preprocessed_tree.clone(),
+        }
+    }
+}
+
 /// Errors that can occur during the proving stage
-#[derive(Debug)]
-pub enum ProvingError {
-    Fail,
-    // TODO
+#[derive(Debug, Snafu)]
+pub enum ProverError {
+    #[snafu(display("proof options are invalid"))]
+    InvalidProofOptions,
+    #[snafu(display(
+        "trace length {trace_len} isn't a power of two or its LDE domain exceeds the 2-adicity of the field"
+    ))]
+    InvalidTraceLength { trace_len: usize },
+    #[snafu(display("LDE blowup factor {lde_blowup_factor} is less than the constraint degree"))]
+    InsufficientBlowupFactor { lde_blowup_factor: u8 },
+    #[snafu(display("trace has {actual} {item} but the AIR expects {expected}"))]
+    TraceShapeMismatch {
+        item: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    #[snafu(display("preprocessed column {column} doesn't match the proving key"))]
+    PreprocessedColumnMismatch { column: usize },
+    #[snafu(display("no proof of work nonce meets grinding factor {grinding_factor}"))]
+    ProofOfWorkNotFound { grinding_factor: u8 },
+    #[snafu(display("trace queries can't be opened: {source}"))]
+    TraceQuery { source: merkle::Error },
+    #[snafu(context(false))]
+    #[snafu(display("fri proving failed: {source}"))]
+    FriProving { source: fri::ProverError },
 }
 
-/// Bit reverses the first ce_domain_size many values of the matrix columns.
+/// Bit reverses the first `ce_domain_size` many values of the matrix columns.
 /// Returns a slice to the portion of the columns that were bit reversed
 fn bit_reverse_ce_trace<F: Field>(ce_domain_size: usize, trace: &mut Matrix<F>) -> Vec<&[F]> {
     trace
@@ -0,0 +1,263 @@
+//! Public memory argument
+//!
+//! Makes a long list of public values (e.g. the output of a program) part of
+//! a statement without a boundary constraint for every value. The values are
+//! cells `(address, value)` that the AIR writes to a pair of base columns in
+//! the rows flagged by a selector column. A running product over the
+//! fingerprints (see [`public_memory_fingerprint`]) of the flagged rows is kept
+//! in an extension column and its value in the last row is checked against the
+//! product over the public cells. The verifier computes the product with
+//! [`public_memory_product`] in a few field operations per cell and passes it
+//! to the constraints as a hint. The two products only match if the multiset
+//! of flagged rows is the multiset of public cells (with high probability over
+//! the challenges).
+//!
+//! Binding the flagged rows to the rest of the execution (e.g. that they're
+//! the values the VM output) is left to the AIR.
+use crate::constraints::assert_boolean;
+use crate::constraints::rows_constraint;
+use crate::constraints::transition_constraint;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::constraints::ExecutionTraceColumn;
+use crate::constraints::Exemptions;
+use crate::constraints::Hint;
+use crate::constraints::VerifierChallenge;
+use crate::expression::Expr;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::utils::Scan;
+use crate::StarkExtensionOf;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ministark_gpu::GpuFftField;
+use ministark_gpu::GpuField;
+
+/// A public value at an address e.g. the `i`-th output of a program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicCell<F> {
+    pub address: F,
+    pub value: F,
+}
+
+/// Execution trace columns of the public memory argument
+#[derive(Debug, Clone, Copy)]
+pub struct PublicMemoryColumns<C> {
+    pub address: C,
+    pub value: C,
+    /// Set in the rows that hold a public cell
+    pub selector: C,
+    /// Extension column with the running product of the fingerprints
+    pub product: C,
+}
+
+/// Verifier challenges used to fingerprint cells
+#[derive(Debug, Clone, Copy)]
+pub struct PublicMemoryChallenges<T> {
+    pub z: T,
+    pub alpha: T,
+}
+
+/// Returns the fingerprint `z - address - alpha * value` of a cell
+pub fn public_memory_fingerprint<T: Field + From<F>, F: Field>(
+    challenges: &PublicMemoryChallenges<T>,
+    address: F,
+    value: F,
+) -> T {
+    challenges.z - T::from(address) - challenges.alpha * T::from(value)
+}
+
+/// Returns the product of the fingerprints of the public cells. This is the
+/// value of the product column in the last row.
+pub fn public_memory_product<T: Field + From<F>, F: Field>(
+    challenges: &PublicMemoryChallenges<T>,
+    cells: &[PublicCell<F>],
+) -> T {
+    cells
+        .iter()
+        .map(|cell| public_memory_fingerprint(challenges, cell.address, cell.value))
+        .product()
+}
+
+/// Returns the running product of the fingerprints of the rows where
+/// `selector` is set. Row `i` holds the product over rows `0..=i`.
+///
+/// # Panics
+///
+/// Panics if the columns have different lengths
+pub fn public_memory_product_column<Fp: Field, Fq: GpuField + Field + From<Fp>>(
+    address: &[Fp],
+    value: &[Fp],
+    selector: &[Fp],
+    challenges: &PublicMemoryChallenges<Fq>,
+) -> GpuVec<Fq> {
+    let num_rows = address.len();
+    assert!(value.len() == num_rows && selector.len() == num_rows);
+    let mut column = Vec::with_capacity_in(num_rows, GpuAllocator);
+    for i in 0..num_rows {
+        column.push(if selector[i].is_zero() {
+            Fq::one()
+        } else {
+            public_memory_fingerprint(challenges, address[i], value[i])
+        });
+    }
+    column.scan_product();
+    column
+}
+
+/// Returns the constraints of the public memory argument for a trace of
+/// `trace_len` rows. `product` is the hint holding the product over the public
+/// cells (see [`public_memory_product`]). The challenges must be the ones the
+/// product column was built with.
+pub fn public_memory_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    columns: &PublicMemoryColumns<impl ExecutionTraceColumn>,
+    challenges: &PublicMemoryChallenges<impl VerifierChallenge>,
+    product: &impl Hint,
+    trace_len: usize,
+) -> Vec<Constraint<FieldVariant<Fp, Fq>>> {
+    use AlgebraicItem::*;
+    let one = Constant(FieldVariant::Fp(Fp::one()));
+    let PublicMemoryColumns {
+        address,
+        value,
+        selector,
+        product: product_column,
+    } = columns;
+    // fingerprint in rows with the selector set and one otherwise
+    let factor = |offset: isize| -> Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> {
+        let fingerprint = challenges.z.challenge()
+            - address.offset(offset)
+            - challenges.alpha.challenge() * value.offset(offset);
+        selector.offset(offset) * (fingerprint - one) + one
+    };
+    let every_row = Exemptions::new();
+
+    [
+        rows_constraint(assert_boolean(selector.curr()), trace_len, 1, 0, &every_row),
+        // first row
+        rows_constraint(
+            product_column.curr() - factor(0),
+            trace_len,
+            trace_len,
+            0,
+            &every_row,
+        ),
+        transition_constraint(
+            product_column.next() - product_column.curr() * factor(1),
+            trace_len,
+            1,
+            Exemptions::new(),
+        ),
+        // last row
+        rows_constraint(
+            product_column.curr() - product.hint(),
+            trace_len,
+            trace_len,
+            trace_len - 1,
+            &every_row,
+        ),
+    ]
+    .into_iter()
+    .map(Constraint::from)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::public_memory_constraints;
+    use super::public_memory_product;
+    use super::public_memory_product_column;
+    use super::PublicCell;
+    use super::PublicMemoryChallenges;
+    use super::PublicMemoryColumns;
+    use crate::constraints::AlgebraicItem;
+    use crate::utils::FieldVariant;
+    use alloc::vec::Vec;
+    use ark_ff::UniformRand;
+    use ark_ff::Zero;
+    use ark_poly::EvaluationDomain;
+    use ark_poly::Radix2EvaluationDomain;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    const COLUMNS: PublicMemoryColumns<usize> = PublicMemoryColumns {
+        address: 0,
+        value: 1,
+        selector: 2,
+        product: 3,
+    };
+
+    const CHALLENGES: PublicMemoryChallenges<usize> = PublicMemoryChallenges { z: 0, alpha: 1 };
+
+    /// Returns a trace of 16 rows with the public cells in every other row and
+    /// private cells in between
+    fn gen_trace(
+        cells: &[PublicCell<Fp>],
+        challenges: &PublicMemoryChallenges<Fp>,
+    ) -> Vec<Vec<Fp>> {
+        let mut columns = vec![Vec::new(), Vec::new(), Vec::new()];
+        let mut rng = ark_std::test_rng();
+        for i in 0..16 {
+            let (address, value, selector) = match cells.get(i / 2) {
+                Some(cell) if i % 2 == 0 => (cell.address, cell.value, Fp::from(1u8)),
+                _ => (Fp::rand(&mut rng), Fp::rand(&mut rng), Fp::zero()),
+            };
+            columns[0].push(address);
+            columns[1].push(value);
+            columns[2].push(selector);
+        }
+        let product =
+            public_memory_product_column(&columns[0], &columns[1], &columns[2], challenges);
+        columns.push(product.to_vec());
+        columns
+    }
+
+    fn check_trace(trace: &[Vec<Fp>], challenges: &[Fp], product: Fp) -> bool {
+        let trace_len = trace[0].len();
+        let domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+        let constraints = public_memory_constraints::<Fp, Fp>(&COLUMNS, &CHALLENGES, &0, trace_len);
+        constraints.iter().all(|constraint| {
+            (0..trace_len).all(|i| {
+                use AlgebraicItem::*;
+                let eval = constraint.check(&mut |leaf| match *leaf {
+                    X => FieldVariant::Fp(domain.element(i)),
+                    Constant(v) => v,
+                    Challenge(j) => FieldVariant::Fp(challenges[j]),
+                    Hint(_) => FieldVariant::Fp(product),
+                    Trace(column, offset) => {
+                        let row = (i + trace_len).wrapping_add_signed(offset) % trace_len;
+                        FieldVariant::Fp(trace[column][row])
+                    }
+                    Periodic(_) => unreachable!(),
+                });
+                eval.is_some()
+            })
+        })
+    }
+
+    #[test]
+    fn public_cells_satisfy_constraints() {
+        let mut rng = ark_std::test_rng();
+        let challenges = [Fp::rand(&mut rng), Fp::rand(&mut rng)];
+        let pm_challenges = PublicMemoryChallenges {
+            z: challenges[0],
+            alpha: challenges[1],
+        };
+        let mut cells = (0..5u8)
+            .map(|i| PublicCell {
+                address: Fp::from(i),
+                value: Fp::from(i) * Fp::from(7u8),
+            })
+            .collect::<Vec<_>>();
+        let trace = gen_trace(&cells, &pm_challenges);
+
+        let product = public_memory_product(&pm_challenges, &cells);
+        assert!(check_trace(&trace, &challenges, product));
+
+        // verifier expects a different output
+        cells[3].value += Fp::from(1u8);
+        let product = public_memory_product(&pm_challenges, &cells);
+        assert!(!check_trace(&trace, &challenges, product));
+    }
+}
@@ -0,0 +1,371 @@
+//! RAM argument
+//!
+//! Checks that a VM's memory accesses are consistent without the VM's
+//! execution trace having to be sorted. Every access `(clk, addr, value,
+//! is_write)` is copied into an auxiliary table sorted by address and then by
+//! clock. The constraints returned by [`ram_constraints`] check the table is
+//! consistent locally:
+//! * addresses increase by zero or one between rows
+//! * the clock increases by exactly one between accesses to the same address
+//! * a read returns the value of the previous access to the same address
+//! * the first access to an address that is a read returns zero
+//!
+//! A running product over the fingerprints (see [`ram_fingerprint`]) of the
+//! rows that aren't padding is kept in an extension column. Its value in the
+//! last row must be checked against the same product computed over the
+//! accesses in the VM's execution trace. This is left to the VM's AIR since
+//! only it knows which of its columns represent accesses.
+//!
+//! Gaps in the clock (and address) are filled with padding rows that read the
+//! previous value so the table has one row for every clock cycle between the
+//! first and last access of each address. [`ram_table`] builds the table.
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::constraints::ExecutionTraceColumn;
+use crate::constraints::VerifierChallenge;
+use crate::expression::Expr;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::utils::Scan;
+use crate::Matrix;
+use crate::StarkExtensionOf;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ministark_gpu::GpuFftField;
+use ministark_gpu::GpuField;
+use num_traits::Pow;
+
+/// Number of columns in the table returned by [`ram_table`]. The columns are
+/// the clock, address, value, write flag and padding flag (in that order).
+pub const NUM_RAM_TABLE_COLUMNS: usize = 5;
+
+/// A single memory access of a VM. Each address can be accessed at most once
+/// per clock cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RamAccess<F> {
+    pub clk: u64,
+    pub addr: u64,
+    pub value: F,
+    pub is_write: bool,
+}
+
+/// Execution trace columns of the RAM table
+#[derive(Debug, Clone, Copy)]
+pub struct RamColumns<C> {
+    pub clk: C,
+    pub addr: C,
+    pub value: C,
+    pub is_write: C,
+    /// Set for padding rows. Padding rows aren't part of the running product.
+    pub dummy: C,
+    /// Extension column with the running product of the fingerprints
+    pub permutation: C,
+}
+
+/// Verifier challenges used to fingerprint accesses
+#[derive(Debug, Clone, Copy)]
+pub struct RamChallenges<T> {
+    pub alpha: T,
+    pub clk: T,
+    pub addr: T,
+    pub value: T,
+    pub is_write: T,
+}
+
+/// Returns the fingerprint `alpha - c_clk * clk - c_addr * addr - c_value *
+/// value - c_is_write * is_write` of an access
+pub fn ram_fingerprint<T: Field + From<F>, F: Field>(
+    challenges: &RamChallenges<T>,
+    clk: F,
+    addr: F,
+    value: F,
+    is_write: F,
+) -> T {
+    challenges.alpha
+        - challenges.clk * T::from(clk)
+        - challenges.addr * T::from(addr)
+        - challenges.value * T::from(value)
+        - challenges.is_write * T::from(is_write)
+}
+
+/// Returns the RAM table for the accesses padded to `num_rows` rows. See
+/// [`NUM_RAM_TABLE_COLUMNS`] for the column order.
+///
+/// # Panics
+///
+/// Panics if there are no accesses, an address is accessed more than once in
+/// the same clock cycle or the table has more than `num_rows` rows.
+pub fn ram_table<F: Field>(accesses: &[RamAccess<F>], num_rows: usize) -> Matrix<F> {
+    assert!(!accesses.is_empty(), "no memory accesses");
+    let mut accesses = accesses.to_vec();
+    accesses.sort_by_key(|access| (access.addr, access.clk));
+
+    let mut rows = Vec::<[F; NUM_RAM_TABLE_COLUMNS]>::with_capacity(num_rows);
+    let row = |clk: u64, addr: u64, value: F, is_write: bool, dummy: bool| {
+        let (clk, addr) = (F::from(clk), F::from(addr));
+        [clk, addr, value, F::from(is_write), F::from(dummy)]
+    };
+    rows.push({
+        let first = accesses[0];
+        row(first.clk, first.addr, first.value, first.is_write, false)
+    });
+    for [prev, curr] in accesses.array_windows() {
+        if prev.addr == curr.addr {
+            assert!(
+                prev.clk < curr.clk,
+                "address accessed twice in the same cycle"
+            );
+            // read the previous value until the clock catches up
+            for clk in prev.clk + 1..curr.clk {
+                let value = rows.last().unwrap()[2];
+                rows.push(row(clk, prev.addr, value, false, true));
+            }
+        } else {
+            // read zero from the addresses that were never accessed
+            for addr in prev.addr + 1..curr.addr {
+                rows.push(row(0, addr, F::zero(), false, true));
+            }
+        }
+        rows.push(row(curr.clk, curr.addr, curr.value, curr.is_write, false));
+    }
+
+    assert!(rows.len() <= num_rows, "RAM table has {} rows", rows.len());
+    let last = *rows.last().unwrap();
+    let mut clk = last[0];
+    while rows.len() < num_rows {
+        clk += F::one();
+        rows.push([clk, last[1], last[2], F::zero(), F::one()]);
+    }
+
+    let mut columns = (0..NUM_RAM_TABLE_COLUMNS)
+        .map(|_| Vec::with_capacity_in(num_rows, GpuAllocator))
+        .collect::<Vec<GpuVec<F>>>();
+    for row in rows {
+        for (column, value) in columns.iter_mut().zip(row) {
+            column.push(value);
+        }
+    }
+    Matrix::new(columns)
+}
+
+/// Returns the running product of the fingerprints of the rows of a table
+/// returned by [`ram_table`] that aren't padding. Row `i` holds the product
+/// over rows `0..=i`.
+pub fn ram_permutation_column<Fp: Field, Fq: GpuField + Field + From<Fp>>(
+    table: &Matrix<Fp>,
+    challenges: &RamChallenges<Fq>,
+) -> GpuVec<Fq> {
+    let [clk, addr, value, is_write, dummy] = &*table.0 else {
+        panic!("expected {NUM_RAM_TABLE_COLUMNS} columns");
+    };
+    let mut column = Vec::with_capacity_in(table.num_rows(), GpuAllocator);
+    for i in 0..table.num_rows() {
+        column.push(if dummy[i].is_zero() {
+            ram_fingerprint(challenges, clk[i], addr[i], value[i], is_write[i])
+        } else {
+            Fq::one()
+        });
+    }
+    column.scan_product();
+    column
+}
+
+/// Returns the constraints for a RAM table of `trace_len` rows. The challenges
+/// must be the ones the permutation column was built with.
+pub fn ram_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    columns: &RamColumns<impl ExecutionTraceColumn>,
+    challenges: &RamChallenges<impl VerifierChallenge>,
+    trace_len: usize,
+) -> Vec<Constraint<FieldVariant<Fp, Fq>>> {
+    use AlgebraicItem::*;
+    let one = Constant(FieldVariant::Fp(Fp::one()));
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+    let first_x = Constant(FieldVariant::Fp(trace_domain.element(0)));
+    let last_x = Constant(FieldVariant::Fp(trace_domain.group_gen_inv()));
+    let RamColumns {
+        clk,
+        addr,
+        value,
+        is_write,
+        dummy,
+        permutation,
+    } = columns;
+    let fingerprint = |offset: isize| {
+        challenges.alpha.challenge()
+            - challenges.clk.challenge() * clk.offset(offset)
+            - challenges.addr.challenge() * addr.offset(offset)
+            - challenges.value.challenge() * value.offset(offset)
+            - challenges.is_write.challenge() * is_write.offset(offset)
+    };
+    // running product only includes rows that aren't padding
+    let include = |offset: isize, product: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>| {
+        let prev: Expr<_> = if offset == 0 {
+            Expr::from(one)
+        } else {
+            permutation.offset(offset - 1)
+        };
+        (permutation.offset(offset) - prev * fingerprint(offset))
+            * (Expr::from(one) - dummy.offset(offset))
+            + (permutation.offset(offset) - product) * dummy.offset(offset)
+    };
+
+    // hold in every row
+    let row_constraints = [
+        (is_write.curr() - one) * is_write.curr(),
+        (dummy.curr() - one) * dummy.curr(),
+        // padding rows are reads
+        dummy.curr() * is_write.curr(),
+    ]
+    .map(|constraint| constraint / (X.pow(trace_len) - one));
+
+    // hold in every row except the last
+    // NOTE: `x^trace_len - 1 = (x - t_0)(x - t_1)...(x - t_(n-1))`
+    let addr_delta = addr.next() - addr.curr();
+    let is_read_next = Expr::from(one) - is_write.next();
+    let transition_constraints = [
+        (addr_delta.clone() - one) * addr_delta.clone(),
+        (addr_delta.clone() - one) * (clk.next() - clk.curr() - one),
+        (Expr::from(one) - addr_delta.clone())
+            * is_read_next.clone()
+            * (value.next() - value.curr()),
+        addr_delta * is_read_next * value.next(),
+        include(1, permutation.curr()),
+    ]
+    .map(|constraint| constraint * ((X - last_x) / (X.pow(trace_len) - one)));
+
+    // hold in the first row
+    let boundary_constraints = [
+        (Expr::from(one) - is_write.curr()) * value.curr(),
+        include(0, Expr::from(one)),
+    ]
+    .map(|constraint| constraint / (X - first_x));
+
+    row_constraints
+        .into_iter()
+        .chain(transition_constraints)
+        .chain(boundary_constraints)
+        .map(Constraint::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ram_constraints;
+    use super::ram_permutation_column;
+    use super::ram_table;
+    use super::RamAccess;
+    use super::RamChallenges;
+    use super::RamColumns;
+    use crate::constraints::AlgebraicItem;
+    use crate::utils::FieldVariant;
+    use crate::Matrix;
+    use ark_ff::One;
+    use ark_ff::UniformRand;
+    use ark_poly::EvaluationDomain;
+    use ark_poly::Radix2EvaluationDomain;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    const COLUMNS: RamColumns<usize> = RamColumns {
+        clk: 0,
+        addr: 1,
+        value: 2,
+        is_write: 3,
+        dummy: 4,
+        permutation: 5,
+    };
+
+    const CHALLENGES: RamChallenges<usize> = RamChallenges {
+        alpha: 0,
+        clk: 1,
+        addr: 2,
+        value: 3,
+        is_write: 4,
+    };
+
+    fn check_table(table: &Matrix<Fp>, challenges: &[Fp]) -> bool {
+        let trace_len = table.num_rows();
+        let domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+        let constraints = ram_constraints::<Fp, Fp>(&COLUMNS, &CHALLENGES, trace_len);
+        constraints.iter().all(|constraint| {
+            (0..trace_len).all(|i| {
+                use AlgebraicItem::*;
+                let x = domain.element(i);
+                let eval = constraint.check(&mut |leaf| match leaf {
+                    X => FieldVariant::Fp(x),
+                    &Constant(v) => v,
+                    &Challenge(j) => FieldVariant::Fp(challenges[j]),
+                    &Trace(column, offset) => {
+                        let row = (i + offset.unsigned_abs()) % trace_len;
+                        FieldVariant::Fp(table.0[column][row])
+                    }
+                    _ => unreachable!(),
+                });
+                eval.is_some()
+            })
+        })
+    }
+
+    fn gen_table(accesses: &[RamAccess<Fp>], challenges: &[Fp]) -> Matrix<Fp> {
+        let mut table = ram_table(accesses, 16);
+        let challenges = RamChallenges {
+            alpha: challenges[0],
+            clk: challenges[1],
+            addr: challenges[2],
+            value: challenges[3],
+            is_write: challenges[4],
+        };
+        let permutation = ram_permutation_column(&table, &challenges);
+        table.0.push(permutation);
+        table
+    }
+
+    fn access(clk: u64, addr: u64, value: u64, is_write: bool) -> RamAccess<Fp> {
+        let value = Fp::from(value);
+        RamAccess {
+            clk,
+            addr,
+            value,
+            is_write,
+        }
+    }
+
+    #[test]
+    fn consistent_accesses_satisfy_constraints() {
+        let mut rng = ark_std::test_rng();
+        let challenges = (0..5).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+        let accesses = [
+            access(0, 0, 7, true),
+            access(1, 2, 0, false),
+            access(3, 0, 7, false),
+            access(4, 2, 5, true),
+            access(5, 0, 1, true),
+            access(8, 2, 5, false),
+        ];
+
+        let mut table = gen_table(&accesses, &challenges);
+        assert!(check_table(&table, &challenges));
+
+        // padding row that changes the value of address 0
+        table.0[2][2] += Fp::one();
+        assert!(!check_table(&table, &challenges));
+    }
+
+    #[test]
+    fn read_of_stale_value_fails_constraints() {
+        let mut rng = ark_std::test_rng();
+        let challenges = (0..5).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+        let accesses = [
+            access(0, 0, 7, true),
+            access(2, 0, 8, true),
+            access(3, 0, 7, false),
+        ];
+
+        let table = gen_table(&accesses, &challenges);
+
+        assert!(!check_table(&table, &challenges));
+    }
+}
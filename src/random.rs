@@ -1,8 +1,12 @@
 use crate::hash::Digest;
 use crate::hash::ElementHashFn;
 use crate::hash::HashFn;
+use crate::transcript::TranscriptOp;
 use alloc::vec::Vec;
+use ark_ff::BigInteger;
 use ark_ff::Field;
+use ark_ff::PrimeField;
+use core::ops::Range;
 use rand::Rng;
 use rand::RngCore;
 #[cfg(feature = "parallel")]
@@ -23,6 +27,69 @@ use std::marker::PhantomData;
 // Seedable<Self::Fp> + Seedable<Self::Fp> + Seedable<Self::Fq> +
 // Seedable<FriRemainder<Self::Fq>>
 
+/// Identifies what a value drawn from a [`PublicCoin`] is used for. The tag is
+/// mixed into the randomness so values drawn for different purposes are
+/// domain separated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DrawTag {
+    AirChallenge = 1,
+    CompositionCoeff = 2,
+    OodPoint = 3,
+    DeepCoeff = 4,
+    FriAlpha = 5,
+    Query = 6,
+    /// Values drawn through [`RngCore`] or for an application specific purpose
+    Other = 7,
+    /// Out of domain point of a STIR round (see [`crate::stir`])
+    StirOodPoint = 8,
+    /// Shift queries of a STIR round
+    StirShiftQuery = 9,
+    /// Degree correction coefficient of a STIR round
+    StirCombCoeff = 10,
+}
+
+impl DrawTag {
+    /// Returns what must have been absorbed by the coin before drawing values
+    /// for this purpose. Checked by [`PublicCoinImpl`] in debug builds.
+    pub const fn required_reseed(self) -> Option<ReseedTag> {
+        match self {
+            Self::AirChallenge | Self::CompositionCoeff => Some(ReseedTag::BaseTrace),
+            Self::OodPoint => Some(ReseedTag::CompositionTrace),
+            Self::DeepCoeff => Some(ReseedTag::OodEvals),
+            Self::FriAlpha | Self::StirOodPoint | Self::StirShiftQuery => Some(ReseedTag::FriLayer),
+            Self::Query => Some(ReseedTag::FriRemainder),
+            Self::Other => None,
+            Self::StirCombCoeff => Some(ReseedTag::StirOodEval),
+        }
+    }
+}
+
+/// Identifies what a value absorbed by a [`PublicCoin`] is. Tags aren't mixed
+/// into the seed. They're used to catch challenges that are drawn before the
+/// commitment they depend on is absorbed (see [`DrawTag::required_reseed`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ReseedTag {
+    DomainParameters = 0,
+    Nonce = 1,
+    BaseTrace = 2,
+    BaseColumn = 3,
+    ExtensionTrace = 4,
+    CompositionTrace = 5,
+    OodEvals = 6,
+    FriLayer = 7,
+    FriRemainder = 8,
+    ProofOfWork = 9,
+    /// Values absorbed for an application specific purpose
+    Other = 10,
+    /// Out of domain evaluation of a STIR round (see [`crate::stir`])
+    StirOodEval = 11,
+    /// Commitment to the preprocessed columns (see
+    /// [`crate::setup::VerifyingKey`])
+    Preprocessed = 12,
+}
+
 /// `PublicCoin` trait adapted from Winterfell
 pub trait PublicCoin: Sized + Send + Sync + Debug {
     type Digest: Digest;
@@ -30,20 +97,106 @@ pub trait PublicCoin: Sized + Send + Sync + Debug {
 
     fn new(digest: Self::Digest) -> Self;
 
-    fn reseed_with_digest(&mut self, val: &Self::Digest);
+    fn reseed_with_digest(&mut self, tag: ReseedTag, val: &Self::Digest);
+
+    /// Reseeds the coin with every node of a merkle cap in order (see
+    /// [`crate::merkle::MerkleTree::cap`]). Reseeding with a cap of height
+    /// zero is the same as reseeding with the root.
+    fn reseed_with_cap(&mut self, tag: ReseedTag, cap: &[Self::Digest]) {
+        for node in cap {
+            self.reseed_with_digest(tag, node);
+        }
+    }
 
-    fn reseed_with_field_elements(&mut self, vals: &[Self::Field]);
+    /// Reseeds the coin with the canonical encoding of each element (see
+    /// [`crate::hash::write_canonical_element`])
+    fn reseed_with_field_elements(&mut self, tag: ReseedTag, vals: &[Self::Field]);
 
-    fn reseed_with_field_element_vector(&mut self, vector: &[Self::Field]) {
-        self.reseed_with_field_elements(vector);
+    /// Reseeds the coin with a vector whose length isn't fixed by the protocol
+    /// e.g. the FRI remainder. Implementations may absorb a single hash of
+    /// the vector (see [`PublicCoinImpl`]) as long as the prover and verifier
+    /// absorb the same.
+    fn reseed_with_field_element_vector(&mut self, tag: ReseedTag, vector: &[Self::Field]) {
+        self.reseed_with_field_elements(tag, vector);
     }
 
-    fn reseed_with_int(&mut self, val: u64);
+    fn reseed_with_int(&mut self, tag: ReseedTag, val: u64);
+
+    /// Reseeds the coin with a per-proof nonce (see
+    /// [`crate::stark::Stark::gen_proof_nonce`])
+    fn reseed_with_nonce(&mut self, nonce: &[u8; 32]) {
+        for chunk in nonce.chunks(8) {
+            let val = u64::from_le_bytes(chunk.try_into().unwrap());
+            self.reseed_with_int(ReseedTag::Nonce, val);
+        }
+    }
+
+    /// Returns a digest of everything absorbed by the coin so far
+    fn digest(&self) -> Self::Digest;
+
+    /// Draws `n` uniformly random bytes
+    fn draw_bytes(&mut self, tag: DrawTag, n: usize) -> Vec<u8>;
+
+    /// Draws a uniformly random field element. Each base field coefficient is
+    /// sampled by rejecting integers that aren't smaller than the modulus.
+    fn draw_fq(&mut self, tag: DrawTag) -> Self::Field {
+        let num_bits = <Self::Field as Field>::BasePrimeField::MODULUS_BIT_SIZE as usize;
+        let num_bytes = num_bits.div_ceil(8);
+        let extension_degree = usize::try_from(Self::Field::extension_degree()).unwrap();
+        let coeffs = (0..extension_degree)
+            .map(|_| loop {
+                let bytes = self.draw_bytes(tag, num_bytes);
+                let bits = bytes
+                    .iter()
+                    .flat_map(|byte| (0..8).map(move |i| byte >> i & 1 == 1))
+                    .take(num_bits)
+                    .collect::<Vec<bool>>();
+                let int = BigInteger::from_bits_le(&bits);
+                if let Some(coeff) = <Self::Field as Field>::BasePrimeField::from_bigint(int) {
+                    break coeff;
+                }
+            })
+            .collect::<Vec<_>>();
+        Self::Field::from_base_prime_field_elems(&coeffs).unwrap()
+    }
+
+    /// Draws `n` uniformly random field elements
+    fn draw_fqx(&mut self, tag: DrawTag, n: usize) -> Vec<Self::Field> {
+        (0..n).map(|_| self.draw_fq(tag)).collect()
+    }
 
-    fn draw(&mut self) -> Self::Field;
+    /// Draws a uniformly random integer in `range`. Integers are sampled from
+    /// the smallest power of two range that fits `range` and integers that
+    /// fall outside of `range` are rejected.
+    fn draw_usize(&mut self, tag: DrawTag, range: Range<usize>) -> usize {
+        assert!(!range.is_empty());
+        let n = u64::try_from(range.len()).unwrap();
+        let mask = n.checked_next_power_of_two().map_or(u64::MAX, |m| m - 1);
+        loop {
+            let bytes = self.draw_bytes(tag, 8);
+            let v = u64::from_be_bytes(bytes.try_into().unwrap()) & mask;
+            if v < n {
+                return range.start + usize::try_from(v).unwrap();
+            }
+        }
+    }
 
     /// Draws a maximum of n unique queries in the range `[0, domain_size)`
-    fn draw_queries(&mut self, max_n: usize, domain_size: usize) -> BTreeSet<usize>;
+    fn draw_queries(&mut self, max_n: usize, domain_size: usize) -> BTreeSet<usize> {
+        self.draw_positions(DrawTag::Query, max_n, domain_size)
+    }
+
+    /// Like [`Self::draw_queries`] but draws the positions with `tag`
+    fn draw_positions(
+        &mut self,
+        tag: DrawTag,
+        max_n: usize,
+        domain_size: usize,
+    ) -> BTreeSet<usize> {
+        (0..max_n)
+            .map(|_| self.draw_usize(tag, 0..domain_size))
+            .collect()
+    }
 
     fn grind_proof_of_work(&self, proof_of_work_bits: u8) -> Option<u64> {
         #[cfg(not(feature = "parallel"))]
@@ -51,27 +204,41 @@ pub trait PublicCoin: Sized + Send + Sync + Debug {
         #[cfg(feature = "parallel")]
         return (1..u64::MAX)
             .into_par_iter()
-            .find_any(|&nonce| self.verify_proof_of_work(proof_of_work_bits, nonce));
+            // the smallest nonce is found so proofs don't depend on the number of threads
+            .find_first(|&nonce| self.verify_proof_of_work(proof_of_work_bits, nonce));
     }
 
     fn verify_proof_of_work(&self, proof_of_work_bits: u8, nonce: u64) -> bool;
 
     fn security_level_bits() -> u32;
+
+    /// Returns the operations applied to the coin so far if the coin records
+    /// them (see [`crate::transcript`])
+    fn transcript(&self) -> Option<&[TranscriptOp]> {
+        None
+    }
 }
 
 pub struct PublicCoinImpl<F: Field, H: HashFn> {
     pub seed: H::Digest,
     counter: u64,
     bytes: Vec<u8>,
+    // tag of the draw that the remaining bytes were generated for
+    tag: DrawTag,
+    // bit `i` is set once a value with reseed tag `i` has been absorbed
+    absorbed: u16,
+    num_draws: u64,
+    // operations applied to the coin (see `crate::transcript`)
+    #[cfg(debug_assertions)]
+    transcript: Vec<TranscriptOp>,
     _phantom: PhantomData<F>,
 }
 
 impl<F: Field, H: ElementHashFn<F>> PublicCoinImpl<F, H> {
-    fn reseed_with_field_element(&mut self, val: &F) {
+    fn reseed_with_field_element(&mut self, tag: ReseedTag, val: &F) {
         let val_digest = H::hash_elements([*val]);
         self.seed = H::merge(&self.seed, &val_digest);
-        self.counter = 0;
-        self.bytes = Vec::new();
+        self.reset(tag);
     }
 }
 
@@ -81,17 +248,66 @@ impl<F: Field, H: HashFn> Debug for PublicCoinImpl<F, H> {
             .field("seed", &self.seed)
             .field("counter", &self.counter)
             .field("bytes", &self.bytes)
-            .finish()
+            .field("tag", &self.tag)
+            .field("absorbed", &self.absorbed)
+            .field("num_draws", &self.num_draws)
+            .finish_non_exhaustive()
     }
 }
 
 impl<F: Field, H: HashFn> PublicCoinImpl<F, H> {
     /// Updates the state by incrementing the counter and returns hash(seed ||
-    /// counter)
+    /// tag || counter)
     fn gen_next(&mut self) -> H::Digest {
         self.counter += 1;
         self.bytes = Vec::new();
-        H::merge_with_int(&self.seed, self.counter)
+        let seed = self.seed.as_bytes();
+        let tag = [self.tag as u8];
+        let counter = self.counter.to_be_bytes();
+        H::hash_chunks([&seed[..], &tag, &counter])
+    }
+
+    /// Discards the remaining bytes if they were generated for another tag
+    fn set_tag(&mut self, tag: DrawTag) {
+        if let Some(required) = tag.required_reseed() {
+            debug_assert!(
+                self.absorbed & 1 << required as u8 != 0,
+                "{tag:?} drawn before {required:?} was absorbed"
+            );
+        }
+        #[cfg(debug_assertions)]
+        if tag != DrawTag::Other {
+            self.record(TranscriptOp::Draw(tag));
+        }
+        if self.tag != tag {
+            self.tag = tag;
+            self.bytes = Vec::new();
+        }
+    }
+
+    /// Starts drawing from a new seed
+    fn reset(&mut self, tag: ReseedTag) {
+        #[cfg(debug_assertions)]
+        if tag != ReseedTag::Other {
+            self.record(TranscriptOp::Absorb(tag));
+        }
+        self.absorbed |= 1 << tag as u8;
+        self.counter = 0;
+        self.bytes = Vec::new();
+    }
+
+    /// Records an operation unless it repeats the previous operation
+    #[cfg(debug_assertions)]
+    fn record(&mut self, op: TranscriptOp) {
+        if self.transcript.last() != Some(&op) {
+            self.transcript.push(op);
+        }
+    }
+
+    /// Returns the number of draws made from the coin. Diverging counts
+    /// between a prover and verifier point to a transcript mismatch.
+    pub const fn num_draws(&self) -> u64 {
+        self.num_draws
     }
 }
 
@@ -104,26 +320,43 @@ impl<F: Field, H: ElementHashFn<F>> PublicCoin for PublicCoinImpl<F, H> {
             seed: digest,
             counter: 0,
             bytes: Vec::new(),
+            tag: DrawTag::Other,
+            absorbed: 0,
+            num_draws: 0,
+            #[cfg(debug_assertions)]
+            transcript: Vec::new(),
             _phantom: PhantomData,
         }
     }
 
-    fn reseed_with_digest(&mut self, val: &H::Digest) {
+    fn reseed_with_digest(&mut self, tag: ReseedTag, val: &H::Digest) {
         self.seed = H::merge(&self.seed, val);
-        self.counter = 0;
-        self.bytes = Vec::new();
+        self.reset(tag);
     }
 
-    fn reseed_with_field_elements(&mut self, vals: &[Self::Field]) {
+    fn reseed_with_field_elements(&mut self, tag: ReseedTag, vals: &[Self::Field]) {
         for val in vals {
-            self.reseed_with_field_element(val);
+            self.reseed_with_field_element(tag, val);
         }
     }
 
-    fn reseed_with_int(&mut self, val: u64) {
+    /// Absorbs the length of the vector followed by the hash of its elements
+    /// i.e. `seed = H(H(seed || len) || H(vector))`. The remainder is bound to
+    /// the transcript with two merges rather than one per coefficient.
+    fn reseed_with_field_element_vector(&mut self, tag: ReseedTag, vector: &[F]) {
+        let vector_digest = H::hash_elements(vector.iter().copied());
+        let seed = H::merge_with_int(&self.seed, vector.len() as u64);
+        self.seed = H::merge(&seed, &vector_digest);
+        self.reset(tag);
+    }
+
+    fn reseed_with_int(&mut self, tag: ReseedTag, val: u64) {
         self.seed = H::merge_with_int(&self.seed, val);
-        self.counter = 0;
-        self.bytes = Vec::new();
+        self.reset(tag);
+    }
+
+    fn digest(&self) -> H::Digest {
+        self.seed.clone()
     }
 
     fn verify_proof_of_work(&self, proof_of_work_bits: u8, nonce: u64) -> bool {
@@ -131,17 +364,20 @@ impl<F: Field, H: ElementHashFn<F>> PublicCoin for PublicCoinImpl<F, H> {
         leading_zeros(&digest.as_bytes()) >= u32::from(proof_of_work_bits)
     }
 
-    fn draw(&mut self) -> F {
-        F::rand(self)
-    }
-
-    fn draw_queries(&mut self, max_n: usize, domain_size: usize) -> BTreeSet<usize> {
-        (0..max_n).map(|_| self.gen_range(0..domain_size)).collect()
+    fn draw_bytes(&mut self, tag: DrawTag, n: usize) -> Vec<u8> {
+        self.set_tag(tag);
+        self.num_draws += 1;
+        self.by_ref().take(n).collect()
     }
 
     fn security_level_bits() -> u32 {
         H::COLLISION_RESISTANCE
     }
+
+    #[cfg(debug_assertions)]
+    fn transcript(&self) -> Option<&[TranscriptOp]> {
+        Some(&self.transcript)
+    }
 }
 
 impl<F: Field, H: HashFn> Iterator for PublicCoinImpl<F, H> {
@@ -169,6 +405,8 @@ impl<F: Field, H: HashFn> RngCore for PublicCoinImpl<F, H> {
     }
 
     fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.set_tag(DrawTag::Other);
+        self.num_draws += 1;
         dest.iter_mut().for_each(|v| *v = self.next().unwrap());
     }
 
@@ -178,6 +416,12 @@ impl<F: Field, H: HashFn> RngCore for PublicCoinImpl<F, H> {
     }
 }
 
+/// Returns a random nonce for [`crate::stark::Stark::gen_proof_nonce`] from
+/// the thread local random number generator
+pub fn random_proof_nonce() -> [u8; 32] {
+    rand::thread_rng().gen()
+}
+
 pub fn leading_zeros(hash: &[u8]) -> u32 {
     let mut zeros = 0;
     for byte in hash {
@@ -191,6 +435,85 @@ pub fn leading_zeros(hash: &[u8]) -> u32 {
     zeros
 }
 
-pub fn draw_multiple<P: PublicCoin>(public_coin: &mut P, n: usize) -> Vec<P::Field> {
-    (0..n).map(|_| public_coin.draw()).collect()
+#[cfg(test)]
+mod tests {
+    use super::DrawTag;
+    use super::PublicCoin;
+    use super::PublicCoinImpl;
+    use super::ReseedTag;
+    use crate::hash::Sha256HashFn;
+    use crate::utils::SerdeOutput;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
+
+    #[test]
+    fn draws_are_separated_by_tag() {
+        let mut coin1 = PublicCoinImpl::<Fq3, Sha256HashFn>::new(SerdeOutput::default());
+        let mut coin2 = PublicCoinImpl::<Fq3, Sha256HashFn>::new(SerdeOutput::default());
+        // reseed tags aren't absorbed so both coins have the same seed
+        coin1.reseed_with_digest(ReseedTag::FriLayer, &SerdeOutput::default());
+        coin2.reseed_with_digest(ReseedTag::CompositionTrace, &SerdeOutput::default());
+
+        let alpha = coin1.draw_fq(DrawTag::FriAlpha);
+        let z = coin2.draw_fq(DrawTag::OodPoint);
+
+        assert_ne!(alpha, z);
+    }
+
+    #[test]
+    fn vector_reseed_binds_every_element_and_length() {
+        let reseed = |vector: &[Fp]| {
+            let mut coin = PublicCoinImpl::<Fp, Sha256HashFn>::new(SerdeOutput::default());
+            coin.reseed_with_field_element_vector(ReseedTag::FriRemainder, vector);
+            coin.digest()
+        };
+        let remainder = [Fp::from(1u8), Fp::from(2u8), Fp::from(3u8)];
+
+        assert_ne!(
+            reseed(&remainder),
+            reseed(&[remainder[0], remainder[1], Fp::from(4u8)])
+        );
+        assert_ne!(
+            reseed(&remainder),
+            reseed(&[&remainder[..], &[Fp::from(0u8)]].concat())
+        );
+        assert_eq!(reseed(&remainder), reseed(&remainder));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "OodPoint drawn before CompositionTrace was absorbed")]
+    fn draw_before_commitment_panics() {
+        let mut public_coin = PublicCoinImpl::<Fq3, Sha256HashFn>::new(SerdeOutput::default());
+        public_coin.reseed_with_digest(ReseedTag::BaseTrace, &SerdeOutput::default());
+
+        public_coin.draw_fq(DrawTag::OodPoint);
+    }
+
+    #[test]
+    fn draw_usize_is_in_range() {
+        let mut public_coin = PublicCoinImpl::<Fp, Sha256HashFn>::new(SerdeOutput::default());
+
+        for _ in 0..100 {
+            let v = public_coin.draw_usize(DrawTag::Other, 10..15);
+            assert!((10..15).contains(&v));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn grinding_is_independent_of_thread_count() {
+        let public_coin = PublicCoinImpl::<Fp, Sha256HashFn>::new(SerdeOutput::default());
+        let smallest_nonce =
+            (1..u64::MAX).find(|&nonce| public_coin.verify_proof_of_work(10, nonce));
+
+        for num_threads in [1, 2, 3, 8] {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+                .unwrap();
+            let nonce = pool.install(|| public_coin.grind_proof_of_work(10));
+            assert_eq!(smallest_nonce, nonce);
+        }
+    }
 }
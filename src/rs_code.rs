@@ -0,0 +1,284 @@
+//! Reed-Solomon codes
+//!
+//! FRI checks that a codeword is close to the Reed-Solomon code of
+//! polynomials of degree less than `k` evaluated over a coset of size `n`
+//! where `n / k` is the blowup factor. Codewords are the evaluations of a
+//! polynomial over the coset `offset * <ω>` in bit-reversed order i.e. the
+//! value at position `i` is the evaluation at `offset * ω^bit_reverse(i)` (see
+//! [`evaluation_point`] and [`crate::positions`]).
+//!
+//! These utilities encode, check and decode codewords. Tests and external
+//! tools can use them to generate valid codewords for the FRI prover and
+//! invalid ones (e.g. a codeword with a few corrupted values) to exercise the
+//! FRI verifier. Decoding only recovers erasures i.e. values known to be
+//! missing. It doesn't correct errors and is quadratic in the message length
+//! so it's intended for testing rather than proving.
+use crate::domain::ceil_power_of_two;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_poly::domain::DomainCoeff;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ministark_gpu::utils::bit_reverse;
+use ministark_gpu::utils::bit_reverse_index;
+use ministark_gpu::GpuField;
+use snafu::Snafu;
+
+/// Returns the codeword of the polynomial with coefficients `coeffs` over the
+/// coset `offset * <ω>` of size `blowup_factor` times the number of
+/// coefficients (rounded up to a power of two)
+///
+/// # Panics
+///
+/// Panics if the blowup factor isn't a power of two or the coset doesn't fit
+/// in the field
+pub fn encode<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    coeffs: &[F],
+    blowup_factor: usize,
+    offset: F::FftField,
+) -> Vec<F>
+where
+    F::FftField: FftField,
+{
+    assert!(blowup_factor.is_power_of_two());
+    let size = ceil_power_of_two(coeffs.len()) * blowup_factor;
+    let mut codeword = codeword_domain::<F>(size, offset).fft(coeffs);
+    bit_reverse(&mut codeword);
+    codeword
+}
+
+/// Returns the coefficients of the polynomial of degree less than the length
+/// of the codeword that evaluates to the codeword over the coset `offset *
+/// <ω>`. Every word is the codeword of some polynomial of this degree.
+///
+/// # Panics
+///
+/// Panics if the length of the codeword isn't a power of two
+pub fn interpolate<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    codeword: &[F],
+    offset: F::FftField,
+) -> Vec<F>
+where
+    F::FftField: FftField,
+{
+    let mut evals = codeword.to_vec();
+    bit_reverse(&mut evals);
+    codeword_domain::<F>(codeword.len(), offset).ifft(&evals)
+}
+
+/// Returns the degree of the polynomial a codeword is the evaluation of.
+/// Returns [`None`] for the zero codeword.
+///
+/// # Panics
+///
+/// Panics if the length of the codeword isn't a power of two
+pub fn degree<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    codeword: &[F],
+    offset: F::FftField,
+) -> Option<usize>
+where
+    F::FftField: FftField,
+{
+    let coeffs = interpolate(codeword, offset);
+    coeffs.iter().rposition(|coeff| !coeff.is_zero())
+}
+
+/// Returns true if the codeword is the evaluation of a polynomial of degree at
+/// most `max_degree`
+///
+/// # Panics
+///
+/// Panics if the length of the codeword isn't a power of two
+pub fn is_codeword<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    codeword: &[F],
+    offset: F::FftField,
+    max_degree: usize,
+) -> bool
+where
+    F::FftField: FftField,
+{
+    degree(codeword, offset).is_none_or(|degree| degree <= max_degree)
+}
+
+/// Recovers the coefficients of a polynomial of degree at most `max_degree`
+/// from its codeword with erasures. Erased values are [`None`]. Every value
+/// that isn't erased has to agree with the polynomial. Returns `max_degree +
+/// 1` coefficients.
+///
+/// # Panics
+///
+/// Panics if the length of the codeword isn't a power of two
+pub fn decode<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    codeword: &[Option<F>],
+    offset: F::FftField,
+    max_degree: usize,
+) -> Result<Vec<F>, DecodingError>
+where
+    F::FftField: FftField,
+{
+    let size = codeword.len();
+    let required = max_degree + 1;
+    let known = codeword
+        .iter()
+        .enumerate()
+        .filter_map(|(position, value)| Some((position, (*value)?)))
+        .collect::<Vec<(usize, F)>>();
+    if known.len() < required {
+        return Err(DecodingError::TooManyErasures {
+            available: known.len(),
+            required,
+        });
+    }
+
+    let point = |position| evaluation_point(size, offset, position);
+    let (interpolated, checked) = known.split_at(required);
+    let xs = interpolated
+        .iter()
+        .map(|&(position, _)| point(position))
+        .collect::<Vec<F::FftField>>();
+    let coeffs = lagrange_interpolate(&xs, interpolated.iter().map(|&(_, y)| y));
+
+    for &(position, value) in checked {
+        let x = point(position);
+        let eval = coeffs.iter().rfold(F::zero(), |mut acc, &coeff| {
+            acc *= x;
+            acc + coeff
+        });
+        if eval != value {
+            return Err(DecodingError::NotACodeword { position });
+        }
+    }
+
+    Ok(coeffs)
+}
+
+/// Returns the point the value at `position` of a codeword of length `size`
+/// over the coset `offset * <ω>` is the evaluation at i.e. `offset *
+/// ω^bit_reverse(position)`
+///
+/// # Panics
+///
+/// Panics if size isn't a power of two
+pub fn evaluation_point<F: FftField>(size: usize, offset: F, position: usize) -> F {
+    let domain = Radix2EvaluationDomain::new_coset(size, offset).unwrap();
+    domain.element(bit_reverse_index(size, position))
+}
+
+/// Errors that are returned when decoding a codeword
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum DecodingError {
+    #[snafu(display("{available} values aren't erased but {required} are required to decode"))]
+    TooManyErasures { available: usize, required: usize },
+    #[snafu(display("value at position {position} doesn't agree with the other values"))]
+    NotACodeword { position: usize },
+}
+
+fn codeword_domain<F: GpuField>(
+    size: usize,
+    offset: F::FftField,
+) -> Radix2EvaluationDomain<F::FftField>
+where
+    F::FftField: FftField,
+{
+    Radix2EvaluationDomain::new_coset(size, offset).unwrap()
+}
+
+/// Returns the coefficients of the polynomial of degree less than `xs.len()`
+/// that evaluates to `ys` at `xs`
+fn lagrange_interpolate<Fp: FftField, F: Field + DomainCoeff<Fp>>(
+    xs: &[Fp],
+    ys: impl IntoIterator<Item = F>,
+) -> Vec<F> {
+    // M(x) = ∏ (x - x_i)
+    let mut vanishing = vec![Fp::one()];
+    for &x in xs {
+        vanishing.insert(0, Fp::zero());
+        for i in 0..vanishing.len() - 1 {
+            let next = vanishing[i + 1];
+            vanishing[i] -= next * x;
+        }
+    }
+
+    // each term is y_i * (M(x) / (x - x_i)) / ∏_(j != i) (x_i - x_j)
+    let mut coeffs = vec![F::zero(); xs.len()];
+    for (&x, y) in xs.iter().zip(ys) {
+        let mut quotient = vec![Fp::zero(); xs.len()];
+        let mut remainder = Fp::zero();
+        for (q, &m) in quotient.iter_mut().zip(&vanishing[1..]).rev() {
+            remainder = remainder * x + m;
+            *q = remainder;
+        }
+        let denominator = quotient.iter().rfold(Fp::zero(), |acc, &q| acc * x + q);
+        let weight = denominator.inverse().unwrap();
+        for (coeff, &q) in coeffs.iter_mut().zip(&quotient) {
+            let mut term = y;
+            term *= q * weight;
+            *coeff += term;
+        }
+    }
+    coeffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode;
+    use super::degree;
+    use super::encode;
+    use super::evaluation_point;
+    use super::is_codeword;
+    use super::DecodingError;
+    use ark_ff::FftField;
+    use ark_poly::univariate::DensePolynomial;
+    use ark_poly::DenseUVPolynomial;
+    use ark_poly::Polynomial;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn codeword_is_bit_reversed_evaluation() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let poly = DensePolynomial::<Fp>::rand(15, &mut rng);
+        let offset = Fp::GENERATOR;
+
+        let codeword = encode(&poly.coeffs, 4, offset);
+
+        assert_eq!(64, codeword.len());
+        assert_eq!(Some(15), degree(&codeword, offset));
+        for (position, value) in codeword.iter().enumerate() {
+            let x = evaluation_point(codeword.len(), offset, position);
+            assert_eq!(poly.evaluate(&x), *value);
+        }
+    }
+
+    #[test]
+    fn decodes_erasures_and_detects_corruption() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let poly = DensePolynomial::<Fp>::rand(15, &mut rng);
+        let offset = Fp::GENERATOR;
+        let codeword = encode(&poly.coeffs, 4, offset);
+        let mut erased = codeword.iter().copied().map(Some).collect::<Vec<_>>();
+        erased.iter_mut().step_by(2).for_each(|value| *value = None);
+
+        assert_eq!(poly.coeffs, decode(&erased, offset, 15).unwrap());
+        assert_eq!(
+            Err(DecodingError::TooManyErasures {
+                available: 32,
+                required: 33
+            }),
+            decode(&erased, offset, 32)
+        );
+
+        let mut corrupted = codeword;
+        corrupted[63] += Fp::from(1u8);
+        erased[63] = Some(corrupted[63]);
+
+        assert!(!is_codeword(&corrupted, offset, 15));
+        assert!(is_codeword(&corrupted, offset, 63));
+        assert_eq!(
+            Err(DecodingError::NotACodeword { position: 63 }),
+            decode(&erased, offset, 15)
+        );
+    }
+}
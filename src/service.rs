@@ -0,0 +1,221 @@
+//! Reference job queue for generating proofs concurrently
+//!
+//! Proofs are generated by a fixed number of worker threads which bounds the
+//! number of proofs (and memory) in flight. With the `gpu` feature enabled
+//! workers take turns using the GPU so only one proof runs on it at a time.
+use crate::prover::ProverError;
+use crate::stark::Stark;
+use crate::utils::block_on;
+use crate::ProofOptions;
+use alloc::collections::BTreeMap;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use ark_serialize::CanonicalSerialize;
+use core::any::Any;
+use core::panic::AssertUnwindSafe;
+use std::panic;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+
+/// Identifies a job submitted to a [`ProverService`]
+pub type JobId = u64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    /// Waiting for a worker
+    Queued,
+    /// A worker is generating the proof
+    Running,
+    /// The proof is ready to be fetched
+    Done,
+    /// Proof generation failed
+    Failed(String),
+}
+
+struct Job<S: Stark> {
+    id: JobId,
+    stark: S,
+    witness: S::Witness,
+    options: ProofOptions,
+}
+
+enum JobState {
+    Queued,
+    Running,
+    Done(Vec<u8>),
+    Failed(String),
+}
+
+struct State<S: Stark> {
+    next_id: JobId,
+    queue: VecDeque<Job<S>>,
+    jobs: BTreeMap<JobId, JobState>,
+    shutdown: bool,
+}
+
+struct Shared<S: Stark> {
+    state: Mutex<State<S>>,
+    job_available: Condvar,
+    // held by a worker for the duration of a proof that uses the GPU
+    #[cfg(feature = "gpu")]
+    gpu: Mutex<()>,
+}
+
+/// Job queue that generates proofs on a bounded number of worker threads
+pub struct ProverService<S: Stark + 'static>
+where
+    S::Witness: Send,
+{
+    shared: Arc<Shared<S>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl<S: Stark + 'static> ProverService<S>
+where
+    S::Witness: Send,
+{
+    /// Starts a service that generates at most `max_concurrent_jobs` proofs at
+    /// a time
+    pub fn new(max_concurrent_jobs: usize) -> Self {
+        assert!(max_concurrent_jobs > 0);
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                next_id: 0,
+                queue: VecDeque::new(),
+                jobs: BTreeMap::new(),
+                shutdown: false,
+            }),
+            job_available: Condvar::new(),
+            #[cfg(feature = "gpu")]
+            gpu: Mutex::new(()),
+        });
+        let workers = (0..max_concurrent_jobs)
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                std::thread::spawn(move || run_worker(&shared))
+            })
+            .collect();
+        Self { shared, workers }
+    }
+
+    /// Queues a proof of `stark` for the given witness
+    pub fn submit(&self, stark: S, witness: S::Witness, options: ProofOptions) -> JobId {
+        let mut state = self.shared.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.jobs.insert(id, JobState::Queued);
+        state.queue.push_back(Job {
+            id,
+            stark,
+            witness,
+            options,
+        });
+        drop(state);
+        self.shared.job_available.notify_one();
+        id
+    }
+
+    /// Returns the status of a job or `None` if the job is unknown or its
+    /// proof has already been fetched
+    pub fn status(&self, id: JobId) -> Option<JobStatus> {
+        let state = self.shared.state.lock().unwrap();
+        let status = state.jobs.get(&id).map(|job_state| match job_state {
+            JobState::Queued => JobStatus::Queued,
+            JobState::Running => JobStatus::Running,
+            JobState::Done(_) => JobStatus::Done,
+            JobState::Failed(err) => JobStatus::Failed(err.clone()),
+        });
+        drop(state);
+        status
+    }
+
+    /// Removes a finished proof from the service and returns its compressed
+    /// serialization. Returns `None` if the proof isn't ready.
+    pub fn take_proof(&self, id: JobId) -> Option<Vec<u8>> {
+        let mut state = self.shared.state.lock().unwrap();
+        if !matches!(state.jobs.get(&id)?, JobState::Done(_)) {
+            return None;
+        }
+        let job_state = state.jobs.remove(&id);
+        drop(state);
+        match job_state {
+            Some(JobState::Done(proof)) => Some(proof),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<S: Stark + 'static> Drop for ProverService<S>
+where
+    S::Witness: Send,
+{
+    /// Finishes all queued jobs before stopping the workers
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.job_available.notify_all();
+        for worker in self.workers.drain(..) {
+            // panics while proving are caught by the workers so there's nothing
+            // to report here and panicking in drop would abort
+            let _ = worker.join();
+        }
+    }
+}
+
+fn run_worker<S: Stark>(shared: &Shared<S>) {
+    loop {
+        let job = {
+            let mut state = shared.state.lock().unwrap();
+            loop {
+                if let Some(job) = state.queue.pop_front() {
+                    state.jobs.insert(job.id, JobState::Running);
+                    break job;
+                }
+                if state.shutdown {
+                    return;
+                }
+                state = shared.job_available.wait(state).unwrap();
+            }
+        };
+
+        let id = job.id;
+        let result = {
+            #[cfg(feature = "gpu")]
+            let _gpu = shared.gpu.lock().unwrap();
+            // a panicking proof shouldn't take down the worker or leave the job
+            // running forever
+            panic::catch_unwind(AssertUnwindSafe(|| prove(job)))
+        };
+
+        let job_state = match result {
+            Ok(Ok(proof)) => JobState::Done(proof),
+            Ok(Err(err)) => JobState::Failed(err.to_string()),
+            Err(payload) => JobState::Failed(panic_message(payload.as_ref())),
+        };
+        shared.state.lock().unwrap().jobs.insert(id, job_state);
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+    format!("prover panicked: {message}")
+}
+
+fn prove<S: Stark>(job: Job<S>) -> Result<Vec<u8>, ProverError> {
+    let Job {
+        stark,
+        witness,
+        options,
+        ..
+    } = job;
+    let proof = block_on(stark.prove(options, witness))?;
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+    Ok(bytes)
+}
@@ -0,0 +1,325 @@
+//! Setup of an AIR
+//!
+//! Work that only depends on the AIR, the trace length and the proof options
+//! is done once by [`setup`] and reused for every proof of the AIR. The
+//! returned [`ProvingKey`] holds the constraints and the composition
+//! constraint (whose construction dominates building an [`Air`]) along with
+//! the commitment to the preprocessed columns.
+//!
+//! Some base columns are the same in every execution of an AIR e.g. the
+//! instruction ROM of a program or a lookup table. Columns listed in
+//! [`AirConfig::PREPROCESSED_BASE_COLUMNS`] are committed to once when the AIR
+//! is set up. The commitment is part of a [`VerifyingKey`] that's reused to
+//! verify every proof with the same trace length and commitment parameters so
+//! proofs don't carry a commitment to these columns. Openings of the columns
+//! are still sent with each proof and checked against the key.
+use crate::air::air_fingerprint;
+use crate::air::trace_domain;
+use crate::air::AirConfig;
+use crate::constraints::CompositionConstraint;
+use crate::constraints::Constraint;
+use crate::domain::lde_domain_size;
+use crate::merkle::MatrixMerkleTree;
+use crate::merkle::MerkleTree;
+use crate::merkle::MerkleTreeDomain;
+use crate::prover::ProverError;
+use crate::stark::Stark;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::Air;
+use crate::Matrix;
+use crate::ProofOptions;
+use alloc::vec::Vec;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use ark_serialize::Valid;
+
+/// Sets up the AIR of `S` for proving traces of length `trace_len` with
+/// `options`. `preprocessed_columns` holds the trace values of the columns in
+/// the order of [`AirConfig::PREPROCESSED_BASE_COLUMNS`] and is empty if the
+/// AIR has none.
+///
+/// # Errors
+///
+/// Returns an error if the options or trace length aren't supported by the
+/// AIR or the preprocessed columns don't match it
+pub fn setup<S: Stark>(
+    trace_len: usize,
+    options: ProofOptions,
+    preprocessed_columns: Matrix<S::Fp>,
+) -> Result<(ProvingKey<S>, VerifyingKey<S>), ProverError> {
+    if !options.is_valid() {
+        return Err(ProverError::InvalidProofOptions);
+    }
+    let lde_blowup_factor = options.lde_blowup_factor;
+    if lde_domain_size::<S::Fp>(trace_len, lde_blowup_factor.into()).is_none() {
+        return Err(ProverError::InvalidTraceLength { trace_len });
+    }
+    let constraints = S::AirConfig::constraints(trace_len);
+    let composition_constraint =
+        S::AirConfig::composition_constraint_with(trace_len, &constraints, options);
+    if composition_constraint.blowup_factor(trace_len) > lde_blowup_factor.into() {
+        return Err(ProverError::InsufficientBlowupFactor { lde_blowup_factor });
+    }
+
+    let preprocessed_tree = commit_setup_columns::<S>(trace_len, options, &preprocessed_columns)?;
+    let fingerprint = air_fingerprint::<S::AirConfig>(trace_len, options, &constraints);
+    let verifying_key =
+        VerifyingKey::new(trace_len, options, fingerprint, preprocessed_tree.as_ref());
+    let proving_key = ProvingKey {
+        trace_len,
+        options,
+        constraints,
+        composition_constraint,
+        fingerprint,
+        preprocessed_columns,
+        preprocessed_tree,
+    };
+    Ok((proving_key, verifying_key))
+}
+
+/// Artifacts of an AIR shared by all proofs of traces of the same length
+/// generated with the same options (see [`setup`] and
+/// [`crate::prover::prove_with_key`])
+pub struct ProvingKey<S: Stark> {
+    trace_len: usize,
+    options: ProofOptions,
+    constraints: Vec<Constraint<FieldVariant<S::Fp, S::Fq>>>,
+    composition_constraint: CompositionConstraint<FieldVariant<S::Fp, S::Fq>>,
+    fingerprint: [u8; 32],
+    preprocessed_columns: Matrix<S::Fp>,
+    preprocessed_tree: Option<S::MerkleTree>,
+}
+
+impl<S: Stark> ProvingKey<S> {
+    pub const fn trace_len(&self) -> usize {
+        self.trace_len
+    }
+
+    pub const fn options(&self) -> ProofOptions {
+        self.options
+    }
+
+    /// Returns the fingerprint of the AIR the key was set up for (see
+    /// [`Air::fingerprint`])
+    pub const fn fingerprint(&self) -> [u8; 32] {
+        self.fingerprint
+    }
+
+    /// Returns the AIR of the statement with public inputs `public_inputs`
+    /// without deriving the constraints again
+    pub fn air(
+        &self,
+        public_inputs: <S::AirConfig as AirConfig>::PublicInputs,
+    ) -> Air<S::AirConfig> {
+        Air::from_parts(
+            self.trace_len,
+            public_inputs,
+            self.options,
+            self.constraints.clone(),
+            self.composition_constraint.clone(),
+        )
+    }
+
+    /// Returns the commitment to the preprocessed columns. `None` if the AIR
+    /// has no preprocessed columns.
+    pub const fn preprocessed_tree(&self) -> Option<&S::MerkleTree> {
+        self.preprocessed_tree.as_ref()
+    }
+
+    /// Returns the first preprocessed column of `base_trace` that differs from
+    /// the column the key was set up with
+    pub fn mismatched_preprocessed_column(&self, base_trace: &Matrix<S::Fp>) -> Option<usize> {
+        let columns = S::AirConfig::PREPROCESSED_BASE_COLUMNS;
+        columns
+            .iter()
+            .zip(&self.preprocessed_columns.0)
+            .find(|(&i, column)| base_trace.0.get(i) != Some(column))
+            .map(|(&i, _)| i)
+    }
+}
+
+/// Commitment to the preprocessed columns of an AIR along with the parameters
+/// it was built with. A key only verifies proofs with the same trace length,
+/// LDE blowup factor, trace values per leaf and merkle cap height.
+pub struct VerifyingKey<S: Stark> {
+    pub trace_len: usize,
+    pub lde_blowup_factor: u8,
+    pub trace_values_per_leaf: u8,
+    pub merkle_cap_height: u8,
+    /// Fingerprint of the AIR the key was set up for (see
+    /// [`Air::fingerprint`]). Proofs of other AIRs or options are rejected.
+    pub air_fingerprint: [u8; 32],
+    /// Merkle cap of the LDE of the preprocessed columns
+    pub preprocessed_commitment: Vec<S::Digest>,
+}
+
+impl<S: Stark> VerifyingKey<S> {
+    /// Commits to the preprocessed columns of `air`. `columns` holds the trace
+    /// values of the columns in the order of
+    /// [`AirConfig::PREPROCESSED_BASE_COLUMNS`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the number of columns or rows doesn't match the AIR
+    pub fn setup(air: &Air<S::AirConfig>, columns: &Matrix<S::Fp>) -> Result<Self, ProverError> {
+        let trace_len = air.trace_len();
+        let options = air.options();
+        let tree = commit_setup_columns::<S>(trace_len, options, columns)?;
+        Ok(Self::new(
+            trace_len,
+            options,
+            air.fingerprint(),
+            tree.as_ref(),
+        ))
+    }
+
+    fn new(
+        trace_len: usize,
+        options: ProofOptions,
+        air_fingerprint: [u8; 32],
+        tree: Option<&S::MerkleTree>,
+    ) -> Self {
+        let cap_height = options.merkle_cap_height;
+        Self {
+            trace_len,
+            lde_blowup_factor: options.lde_blowup_factor,
+            trace_values_per_leaf: options.trace_values_per_leaf,
+            merkle_cap_height: cap_height,
+            air_fingerprint,
+            preprocessed_commitment: tree.map_or_else(Vec::new, |t| t.cap(cap_height.into())),
+        }
+    }
+
+    /// Returns true if proofs of a trace of length `trace_len` generated with
+    /// `options` can be verified with this key
+    pub const fn is_compatible(&self, trace_len: usize, options: &ProofOptions) -> bool {
+        self.trace_len == trace_len
+            && self.lde_blowup_factor == options.lde_blowup_factor
+            && self.trace_values_per_leaf == options.trace_values_per_leaf
+            && self.merkle_cap_height == options.merkle_cap_height
+    }
+}
+
+/// Commits to the LDE of the preprocessed columns given by their trace values
+fn commit_setup_columns<S: Stark>(
+    trace_len: usize,
+    options: ProofOptions,
+    columns: &Matrix<S::Fp>,
+) -> Result<Option<S::MerkleTree>, ProverError> {
+    let num_columns = S::AirConfig::PREPROCESSED_BASE_COLUMNS.len();
+    let num_rows = if num_columns == 0 { 0 } else { trace_len };
+    for (item, expected, actual) in [
+        ("preprocessed columns", num_columns, columns.num_cols()),
+        ("preprocessed rows", num_rows, columns.num_rows()),
+    ] {
+        if expected != actual {
+            return Err(ProverError::TraceShapeMismatch {
+                item,
+                expected,
+                actual,
+            });
+        }
+    }
+    if num_columns == 0 {
+        return Ok(None);
+    }
+
+    let lde_domain_size = trace_len * usize::from(options.lde_blowup_factor);
+    let offset = S::AirConfig::domain_offset();
+    let lde_domain = Radix2EvaluationDomain::new_coset(lde_domain_size, offset).unwrap();
+    let polys = columns.interpolate(trace_domain::<S::AirConfig>(trace_len));
+    let lde = polys.bit_reversed_evaluate(lde_domain);
+    let rows_per_leaf = options.trace_rows_per_leaf(trace_len);
+    Ok(Some(commit_columns::<S>(&lde, rows_per_leaf)))
+}
+
+/// Commits to the preprocessed columns of the base trace LDE `lde`. Returns
+/// `None` if the AIR has no preprocessed columns.
+pub(crate) fn commit_preprocessed_columns<S: Stark>(
+    air: &Air<S::AirConfig>,
+    lde: &Matrix<S::Fp>,
+) -> Option<S::MerkleTree> {
+    let preprocessed_columns = S::AirConfig::PREPROCESSED_BASE_COLUMNS;
+    if preprocessed_columns.is_empty() {
+        return None;
+    }
+    let columns = preprocessed_columns
+        .iter()
+        .map(|&i| lde.0[i].to_vec_in(GpuAllocator))
+        .collect();
+    let rows_per_leaf = air.options().trace_rows_per_leaf(air.trace_len());
+    Some(commit_columns::<S>(&Matrix::new(columns), rows_per_leaf))
+}
+
+fn commit_columns<S: Stark>(lde: &Matrix<S::Fp>, rows_per_leaf: usize) -> S::MerkleTree {
+    S::MerkleTree::from_matrix_chunked(lde, MerkleTreeDomain::Preprocessed, rows_per_leaf)
+}
+
+impl<S: Stark> Clone for VerifyingKey<S> {
+    fn clone(&self) -> Self {
+        Self {
+            trace_len: self.trace_len,
+            lde_blowup_factor: self.lde_blowup_factor,
+            trace_values_per_leaf: self.trace_values_per_leaf,
+            merkle_cap_height: self.merkle_cap_height,
+            air_fingerprint: self.air_fingerprint,
+            preprocessed_commitment: self.preprocessed_commitment.clone(),
+        }
+    }
+}
+
+impl<S: Stark> CanonicalSerialize for VerifyingKey<S> {
+    fn serialize_with_mode<W: ark_serialize::Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), ark_serialize::SerializationError> {
+        self.trace_len.serialize_with_mode(&mut writer, compress)?;
+        self.lde_blowup_factor
+            .serialize_with_mode(&mut writer, compress)?;
+        self.trace_values_per_leaf
+            .serialize_with_mode(&mut writer, compress)?;
+        self.merkle_cap_height
+            .serialize_with_mode(&mut writer, compress)?;
+        self.air_fingerprint
+            .serialize_with_mode(&mut writer, compress)?;
+        self.preprocessed_commitment
+            .serialize_with_mode(&mut writer, compress)?;
+        Ok(())
+    }
+
+    fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        self.trace_len.serialized_size(compress)
+            + self.lde_blowup_factor.serialized_size(compress)
+            + self.trace_values_per_leaf.serialized_size(compress)
+            + self.merkle_cap_height.serialized_size(compress)
+            + self.air_fingerprint.serialized_size(compress)
+            + self.preprocessed_commitment.serialized_size(compress)
+    }
+}
+
+impl<S: Stark> Valid for VerifyingKey<S> {
+    fn check(&self) -> Result<(), ark_serialize::SerializationError> {
+        Ok(())
+    }
+}
+
+impl<S: Stark> CanonicalDeserialize for VerifyingKey<S> {
+    fn deserialize_with_mode<R: ark_serialize::Read>(
+        mut reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, ark_serialize::SerializationError> {
+        Ok(Self {
+            trace_len: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            lde_blowup_factor: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            trace_values_per_leaf: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            merkle_cap_height: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            air_fingerprint: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            preprocessed_commitment: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+        })
+    }
+}
@@ -0,0 +1,476 @@
+//! Stack machine arithmetization
+//!
+//! Reusable constraints for VMs whose instructions operate on a stack. Every
+//! row executes one operation ([`StackOp`]) that takes the stack from the
+//! state in the current row to the state in the next row. The two topmost
+//! elements are cached in the `s0` (top) and `s1` columns and the rest of the
+//! stack lives in memory. The element at depth `d` is at address `sp + 1 - d`
+//! where `sp` is the number of elements on the stack so:
+//! * push and dup spill `s1` to address `sp`
+//! * pop loads the new `s1` from address `sp - 1`
+//! * swap doesn't access memory
+//!
+//! Each row holds its memory access (if any) and an extension column keeps
+//! the running product of their fingerprints (see [`ram_fingerprint`]). The
+//! accesses are checked for consistency with the RAM argument in [`crate::ram`]
+//! by requiring the product to equal the permutation column of a RAM table of
+//! the same accesses in the last row. [`stack_trace`] returns the stack
+//! columns and the accesses to build the RAM table from.
+//!
+//! The stack starts empty. Elements that were never pushed read as zero.
+//!
+//! [`ram_fingerprint`]: crate::ram::ram_fingerprint
+use crate::constraints::assert_boolean;
+use crate::constraints::rows_constraint;
+use crate::constraints::transition_constraint;
+use crate::constraints::when;
+use crate::constraints::when_not;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::constraints::ExecutionTraceColumn;
+use crate::constraints::Exemptions;
+use crate::constraints::VerifierChallenge;
+use crate::expression::Expr;
+use crate::ram::ram_fingerprint;
+use crate::ram::RamAccess;
+use crate::ram::RamChallenges;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::utils::Scan;
+use crate::Matrix;
+use crate::StarkExtensionOf;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ministark_gpu::GpuFftField;
+use ministark_gpu::GpuField;
+
+/// Number of base columns in the matrix returned by [`stack_trace`]. The
+/// columns are in the order of the fields of [`StackColumns`].
+pub const NUM_STACK_COLUMNS: usize = 13;
+
+/// Operation executed by a row of a stack machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackOp<F> {
+    Push(F),
+    Pop,
+    Dup,
+    Swap,
+    Nop,
+}
+
+/// Execution trace columns of a stack machine
+#[derive(Debug, Clone, Copy)]
+pub struct StackColumns<C> {
+    /// Row index. Used as the clock of the memory accesses.
+    pub clk: C,
+    /// Number of elements on the stack
+    pub sp: C,
+    /// Top of the stack
+    pub s0: C,
+    /// Element below the top of the stack
+    pub s1: C,
+    /// Value pushed by a push
+    pub imm: C,
+    pub is_push: C,
+    pub is_pop: C,
+    pub is_dup: C,
+    pub is_swap: C,
+    pub mem_addr: C,
+    pub mem_value: C,
+    pub mem_is_write: C,
+    /// Set in rows that don't access memory
+    pub mem_dummy: C,
+    /// Extension column with the running product of the fingerprints of the
+    /// memory accesses
+    pub accesses: C,
+}
+
+/// Returns the base columns of a stack machine executing `ops` padded with
+/// [`StackOp::Nop`] to `num_rows` rows along with its memory accesses. The
+/// RAM table of the accesses is built with [`crate::ram::ram_table`] and has
+/// to have `num_rows` rows as well.
+///
+/// # Panics
+///
+/// Panics if an operation pops or swaps fewer elements than are on the stack
+/// or there aren't more rows than operations (the last row holds the final
+/// state)
+pub fn stack_trace<F: Field>(
+    ops: &[StackOp<F>],
+    num_rows: usize,
+) -> (Matrix<F>, Vec<RamAccess<F>>) {
+    assert!(
+        ops.len() < num_rows,
+        "{} operations need more than {num_rows} rows",
+        ops.len()
+    );
+    let mut stack = Vec::new();
+    let mut accesses = Vec::new();
+    let mut columns = (0..NUM_STACK_COLUMNS)
+        .map(|_| Vec::with_capacity_in(num_rows, GpuAllocator))
+        .collect::<Vec<GpuVec<F>>>();
+    for clk in 0..num_rows {
+        let op = ops.get(clk).copied().unwrap_or(StackOp::Nop);
+        let sp = stack.len();
+        let depth = |d: usize| if d < sp { stack[sp - 1 - d] } else { F::zero() };
+        let (s0, s1) = (depth(0), depth(1));
+        let mut imm = F::zero();
+        // (address, value, is_write)
+        let mut access = None;
+        match op {
+            StackOp::Push(value) => {
+                imm = value;
+                access = Some((sp, s1, true));
+                stack.push(value);
+            }
+            StackOp::Dup => {
+                access = Some((sp, s1, true));
+                stack.push(s0);
+            }
+            StackOp::Pop => {
+                assert!(sp > 0, "pop from an empty stack in row {clk}");
+                access = Some((sp - 1, depth(2), false));
+                stack.pop();
+            }
+            StackOp::Swap => {
+                assert!(sp > 1, "swap with fewer than two elements in row {clk}");
+                stack.swap(sp - 1, sp - 2);
+            }
+            StackOp::Nop => {}
+        }
+        let (mem_addr, mem_value, mem_is_write) = access.unwrap_or_else(|| (0, F::zero(), false));
+        if access.is_some() {
+            accesses.push(RamAccess {
+                clk: clk as u64,
+                addr: mem_addr as u64,
+                value: mem_value,
+                is_write: mem_is_write,
+            });
+        }
+        let row = [
+            F::from(clk as u64),
+            F::from(sp as u64),
+            s0,
+            s1,
+            imm,
+            F::from(matches!(op, StackOp::Push(_))),
+            F::from(op == StackOp::Pop),
+            F::from(op == StackOp::Dup),
+            F::from(op == StackOp::Swap),
+            F::from(mem_addr as u64),
+            mem_value,
+            F::from(mem_is_write),
+            F::from(access.is_none()),
+        ];
+        for (column, value) in columns.iter_mut().zip(row) {
+            column.push(value);
+        }
+    }
+    (Matrix::new(columns), accesses)
+}
+
+/// Returns the running product of the fingerprints of the memory accesses of
+/// the base columns returned by [`stack_trace`]. Row `i` holds the product
+/// over rows `0..=i`.
+pub fn stack_access_column<Fp: Field, Fq: GpuField + Field + From<Fp>>(
+    columns: &Matrix<Fp>,
+    challenges: &RamChallenges<Fq>,
+) -> GpuVec<Fq> {
+    assert_eq!(columns.num_cols(), NUM_STACK_COLUMNS);
+    let [clk, .., mem_addr, mem_value, mem_is_write, mem_dummy] = &*columns.0 else {
+        unreachable!()
+    };
+    let mut column = Vec::with_capacity_in(columns.num_rows(), GpuAllocator);
+    for i in 0..columns.num_rows() {
+        column.push(if mem_dummy[i].is_zero() {
+            ram_fingerprint(
+                challenges,
+                clk[i],
+                mem_addr[i],
+                mem_value[i],
+                mem_is_write[i],
+            )
+        } else {
+            Fq::one()
+        });
+    }
+    column.scan_product();
+    column
+}
+
+/// Returns the constraints of a stack machine with a trace of `trace_len`
+/// rows. `ram_permutation` is the permutation column of the RAM table of the
+/// stack's memory accesses (see [`crate::ram::ram_constraints`]). The
+/// challenges must be the ones the RAM table and access column were built
+/// with.
+pub fn stack_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    columns: &StackColumns<impl ExecutionTraceColumn>,
+    ram_permutation: &impl ExecutionTraceColumn,
+    challenges: &RamChallenges<impl VerifierChallenge>,
+    trace_len: usize,
+) -> Vec<Constraint<FieldVariant<Fp, Fq>>> {
+    use AlgebraicItem::*;
+    let one = Constant(FieldVariant::Fp(Fp::one()));
+    let StackColumns {
+        clk,
+        sp,
+        s0,
+        s1,
+        imm,
+        is_push,
+        is_pop,
+        is_dup,
+        is_swap,
+        mem_addr,
+        mem_value,
+        mem_is_write,
+        mem_dummy,
+        accesses,
+    } = columns;
+    let is_op = is_push.curr() + is_pop.curr() + is_dup.curr() + is_swap.curr();
+    let is_spill = is_push.curr() + is_dup.curr();
+    let fingerprint = |offset: isize| {
+        challenges.alpha.challenge()
+            - challenges.clk.challenge() * clk.offset(offset)
+            - challenges.addr.challenge() * mem_addr.offset(offset)
+            - challenges.value.challenge() * mem_value.offset(offset)
+            - challenges.is_write.challenge() * mem_is_write.offset(offset)
+    };
+    // running product only includes rows that access memory
+    let include = |offset: isize, prev: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>| {
+        let factor = fingerprint(offset) * (Expr::from(one) - mem_dummy.offset(offset))
+            + mem_dummy.offset(offset);
+        accesses.offset(offset) - prev * factor
+    };
+
+    // hold in every row
+    let row_constraints = [
+        assert_boolean(is_push.curr()),
+        assert_boolean(is_pop.curr()),
+        assert_boolean(is_dup.curr()),
+        assert_boolean(is_swap.curr()),
+        // at most one operation
+        assert_boolean(is_op.clone()),
+        mem_dummy.curr() - (Expr::from(one) - is_spill.clone() - is_pop.curr()),
+        when(is_spill.clone()).assert_eq(mem_addr.curr(), sp.curr()),
+        when(is_spill.clone()).assert_eq(mem_value.curr(), s1.curr()),
+        when(is_spill.clone()).assert_eq(mem_is_write.curr(), one),
+        when(is_pop.curr()).assert_eq(mem_addr.curr(), sp.curr() - one),
+        when(is_pop.curr()).assert_zero(mem_is_write.curr()),
+    ]
+    .map(|constraint| rows_constraint(constraint, trace_len, 1, 0, &Exemptions::new()));
+
+    // hold in every row except the last
+    let transition_constraints = [
+        clk.next() - clk.curr() - one,
+        sp.next() - sp.curr() - is_spill.clone() + is_pop.curr(),
+        when(is_push.curr()).assert_eq(s0.next(), imm.curr()),
+        when(is_dup.curr()).assert_eq(s0.next(), s0.curr()),
+        when(is_spill).assert_eq(s1.next(), s0.curr()),
+        when(is_pop.curr()).assert_eq(s0.next(), s1.curr()),
+        when(is_pop.curr()).assert_eq(s1.next(), mem_value.curr()),
+        when(is_swap.curr()).assert_eq(s0.next(), s1.curr()),
+        when(is_swap.curr()).assert_eq(s1.next(), s0.curr()),
+        when_not(is_op.clone()).assert_eq(s0.next(), s0.curr()),
+        when_not(is_op).assert_eq(s1.next(), s1.curr()),
+        include(1, accesses.curr()),
+    ]
+    .map(|constraint| transition_constraint(constraint, trace_len, 1, Exemptions::new()));
+
+    // hold in the first row
+    let first_row_constraints = [
+        clk.curr(),
+        sp.curr(),
+        s0.curr(),
+        s1.curr(),
+        include(0, Expr::from(one)),
+    ]
+    .map(|constraint| rows_constraint(constraint, trace_len, trace_len, 0, &Exemptions::new()));
+
+    // hold in the last row
+    let last_row_constraint = rows_constraint(
+        accesses.curr() - ram_permutation.curr(),
+        trace_len,
+        trace_len,
+        trace_len - 1,
+        &Exemptions::new(),
+    );
+
+    row_constraints
+        .into_iter()
+        .chain(transition_constraints)
+        .chain(first_row_constraints)
+        .chain([last_row_constraint])
+        .map(Constraint::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stack_access_column;
+    use super::stack_constraints;
+    use super::stack_trace;
+    use super::StackColumns;
+    use super::StackOp;
+    use super::NUM_STACK_COLUMNS;
+    use crate::constraints::AlgebraicItem;
+    use crate::ram::ram_constraints;
+    use crate::ram::ram_permutation_column;
+    use crate::ram::ram_table;
+    use crate::ram::RamChallenges;
+    use crate::ram::RamColumns;
+    use crate::utils::FieldVariant;
+    use crate::utils::GpuAllocator;
+    use crate::Matrix;
+    use alloc::vec::Vec;
+    use ark_ff::UniformRand;
+    use ark_poly::EvaluationDomain;
+    use ark_poly::Radix2EvaluationDomain;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    const COLUMNS: StackColumns<usize> = StackColumns {
+        clk: 0,
+        sp: 1,
+        s0: 2,
+        s1: 3,
+        imm: 4,
+        is_push: 5,
+        is_pop: 6,
+        is_dup: 7,
+        is_swap: 8,
+        mem_addr: 9,
+        mem_value: 10,
+        mem_is_write: 11,
+        mem_dummy: 12,
+        accesses: 13,
+    };
+
+    // RAM table after the stack columns
+    const RAM_COLUMNS: RamColumns<usize> = RamColumns {
+        clk: 14,
+        addr: 15,
+        value: 16,
+        is_write: 17,
+        dummy: 18,
+        permutation: 19,
+    };
+
+    const CHALLENGES: RamChallenges<usize> = RamChallenges {
+        alpha: 0,
+        clk: 1,
+        addr: 2,
+        value: 3,
+        is_write: 4,
+    };
+
+    const fn ram_challenges(challenges: &[Fp]) -> RamChallenges<Fp> {
+        RamChallenges {
+            alpha: challenges[0],
+            clk: challenges[1],
+            addr: challenges[2],
+            value: challenges[3],
+            is_write: challenges[4],
+        }
+    }
+
+    fn gen_trace(ops: &[StackOp<Fp>], challenges: &[Fp]) -> Matrix<Fp> {
+        let num_rows = 32;
+        let challenges = ram_challenges(challenges);
+        let (mut trace, accesses) = stack_trace(ops, num_rows);
+        let ram = ram_table(&accesses, num_rows);
+        let ram_permutation = ram_permutation_column(&ram, &challenges);
+        trace.0.push(stack_access_column(&trace, &challenges));
+        trace.append(ram);
+        trace.0.push(ram_permutation);
+        assert_eq!(trace.num_cols(), NUM_STACK_COLUMNS + 7);
+        trace
+    }
+
+    fn check_trace(trace: &Matrix<Fp>, challenges: &[Fp]) -> bool {
+        let trace_len = trace.num_rows();
+        let domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+        let constraints =
+            stack_constraints::<Fp, Fp>(&COLUMNS, &RAM_COLUMNS.permutation, &CHALLENGES, trace_len)
+                .into_iter()
+                .chain(ram_constraints(&RAM_COLUMNS, &CHALLENGES, trace_len))
+                .collect::<Vec<_>>();
+        constraints.iter().all(|constraint| {
+            (0..trace_len).all(|i| {
+                use AlgebraicItem::*;
+                let eval = constraint.check(&mut |leaf| match *leaf {
+                    X => FieldVariant::Fp(domain.element(i)),
+                    Constant(v) => v,
+                    Challenge(j) => FieldVariant::Fp(challenges[j]),
+                    Trace(column, offset) => {
+                        let row = (i + trace_len).wrapping_add_signed(offset) % trace_len;
+                        FieldVariant::Fp(trace.0[column][row])
+                    }
+                    Hint(_) | Periodic(_) => unreachable!(),
+                });
+                eval.is_some()
+            })
+        })
+    }
+
+    #[test]
+    fn stack_program_satisfies_constraints() {
+        use StackOp::*;
+        let mut rng = ark_std::test_rng();
+        let challenges = (0..5).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+        let ops = [
+            Push(Fp::from(3u8)),
+            Push(Fp::from(5u8)),
+            Push(Fp::from(7u8)),
+            Dup,
+            Swap,
+            Pop,
+            Pop,
+            Nop,
+            Pop,
+            Push(Fp::from(9u8)),
+            Pop,
+            Pop,
+        ];
+
+        let mut trace = gen_trace(&ops, &challenges);
+        assert!(check_trace(&trace, &challenges));
+
+        // claim the pop in row 6 exposes 4 rather than 5
+        trace.0[COLUMNS.s0][7] = Fp::from(4u8);
+        assert!(!check_trace(&trace, &challenges));
+    }
+
+    #[test]
+    fn tampered_memory_fails_constraints() {
+        use StackOp::*;
+        let mut rng = ark_std::test_rng();
+        let challenges = (0..5).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+        let ops = [
+            Push(Fp::from(3u8)),
+            Push(Fp::from(5u8)),
+            Push(Fp::from(7u8)),
+            Pop,
+            Pop,
+        ];
+        let mut trace = gen_trace(&ops, &challenges);
+
+        // load a different value from memory in the second pop and keep the
+        // access column consistent so only the RAM argument can catch it
+        trace.0[COLUMNS.mem_value][4] = Fp::from(1u8);
+        for row in 5..trace.num_rows() {
+            trace.0[COLUMNS.s1][row] = Fp::from(1u8);
+        }
+        let stack_columns = Matrix::new(
+            trace.0[..NUM_STACK_COLUMNS]
+                .iter()
+                .map(|column| column.to_vec_in(GpuAllocator))
+                .collect(),
+        );
+        trace.0[COLUMNS.accesses] =
+            stack_access_column(&stack_columns, &ram_challenges(&challenges));
+
+        assert!(!check_trace(&trace, &challenges));
+    }
+}
@@ -7,9 +7,10 @@ use crate::hash::Digest;
 use crate::hints::Hints;
 use crate::merkle::MatrixMerkleTree;
 use crate::merkle::MerkleTree;
+use crate::proof::SecurityReport;
 use crate::prover::default_prove;
-use crate::prover::ProvingError;
-use crate::random::draw_multiple;
+use crate::prover::ProverError;
+use crate::random::DrawTag;
 use crate::random::PublicCoin;
 use crate::verifier::default_verify;
 use crate::verifier::VerificationError;
@@ -20,6 +21,7 @@ use crate::ProofOptions;
 use crate::StarkExtensionOf;
 use crate::Trace;
 use ark_ff::FftField;
+use core::future::Future;
 use ministark_gpu::GpuFftField;
 
 pub trait Stark: Sized + Send + Sync {
@@ -38,6 +40,17 @@ pub trait Stark: Sized + Send + Sync {
 
     fn gen_public_coin(&self, air: &Air<Self::AirConfig>) -> Self::PublicCoin;
 
+    /// Returns a nonce that's absorbed into the transcript right after the
+    /// public coin is generated and sent with the proof. Proofs are
+    /// deterministic by default so proving the same statement twice gives the
+    /// same proof. Return a random nonce (e.g. from
+    /// [`crate::random::random_proof_nonce`]) if proofs of identical statements
+    /// shouldn't share a transcript. Note the base trace commitment only
+    /// depends on the trace so it's the same in both proofs.
+    fn gen_proof_nonce(&self) -> Option<[u8; 32]> {
+        None
+    }
+
     fn gen_deep_coeffs(
         &self,
         public_coin: &mut Self::PublicCoin,
@@ -46,9 +59,12 @@ pub trait Stark: Sized + Send + Sync {
         let num_execution_trace = air.trace_arguments().len();
         let num_composition_trace = air.ce_blowup_factor();
         DeepCompositionCoeffs {
-            execution_trace: draw_multiple(public_coin, num_execution_trace),
-            composition_trace: draw_multiple(public_coin, num_composition_trace),
-            degree: (public_coin.draw(), public_coin.draw()),
+            execution_trace: public_coin.draw_fqx(DrawTag::DeepCoeff, num_execution_trace),
+            composition_trace: public_coin.draw_fqx(DrawTag::DeepCoeff, num_composition_trace),
+            degree: (
+                public_coin.draw_fq(DrawTag::DeepCoeff),
+                public_coin.draw_fq(DrawTag::DeepCoeff),
+            ),
         }
     }
 
@@ -58,10 +74,27 @@ pub trait Stark: Sized + Send + Sync {
         &self,
         options: ProofOptions,
         witness: Self::Witness,
-    ) -> Result<Proof<Self>, ProvingError> {
+    ) -> Result<Proof<Self>, ProverError> {
         default_prove(self, options, witness)
     }
 
+    /// Generates a proof along with a report of its security parameters
+    fn prove_with_report(
+        &self,
+        options: ProofOptions,
+        witness: Self::Witness,
+    ) -> impl Future<Output = Result<(Proof<Self>, SecurityReport), ProverError>> + Send
+    where
+        Self::Witness: Send,
+        Self::prove(..): Send,
+    {
+        async move {
+            let proof = self.prove(options, witness).await?;
+            let report = proof.security_report();
+            Ok((proof, report))
+        }
+    }
+
     /// Check the AIR constraints are valid
     fn validate_constraints(
         &self,
@@ -83,3 +116,24 @@ pub trait Stark: Sized + Send + Sync {
         default_verify(self, proof, required_security_bits)
     }
 }
+
+/// A [`Stark`] whose statement makes some values public e.g. the output of a
+/// program. Outputs are typically the values of specific trace cells that the
+/// AIR binds to the public inputs with boundary constraints.
+pub trait PublicOutputs: Stark {
+    type Outputs;
+
+    /// Returns the outputs claimed by the statement
+    fn public_outputs(&self) -> Self::Outputs;
+
+    /// Verifies a proof of the statement and returns its outputs. Outputs are
+    /// only returned if the proof is valid.
+    fn verify_and_extract(
+        &self,
+        proof: Proof<Self>,
+        required_security_bits: u32,
+    ) -> Result<Self::Outputs, VerificationError> {
+        self.verify(proof, required_security_bits)?;
+        Ok(self.public_outputs())
+    }
+}
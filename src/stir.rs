@@ -0,0 +1,866 @@
+//! STIR low-degree test (experimental)
+//!
+//! STIR (<https://eprint.iacr.org/2024/390>) is an alternative to FRI. Every
+//! round folds the codeword by [`FriOptions::layer_folding_factor`] like FRI
+//! but the folded polynomial is evaluated over a domain that's only half the
+//! size of the previous one. The rate of the code improves every round so
+//! later rounds need fewer queries (see [`round_parameters`]).
+//!
+//! After committing to the folded codeword the verifier samples an out of
+//! domain point and "shift" queries. The verifier learns the folded
+//! polynomial's value at the shift queries by folding the rows of the previous
+//! codeword. The next round tests the quotient of the folded polynomial by the
+//! sampled points. The quotient's degree is shifted back up to the folded
+//! degree bound with a random combination of powers of `x` so every codeword of
+//! a round has the same degree bound. The verifier never sees the quotient's
+//! codeword. It evaluates it from the folded codeword and the answers of the
+//! previous round.
+//!
+//! Codewords use the layout of FRI layers: evaluations are bit-reversed and
+//! each row holds the evaluations that fold together. All domains are cosets
+//! with the same offset so a domain never meets the shift queries of the
+//! previous round. A proof is a [`FriProof`]:
+//! - `layer_caps` holds the caps of the first codeword and of every folded
+//!   codeword
+//! - `layers` opens every codeword at the rows of its round's shift queries.
+//!   The first codeword is opened at the rows of the STARK queries as well.
+//! - `ood_evals` holds the out of domain evaluation of every round
+//! - `remainder_coeffs` holds the folded polynomial of the last round
+//!
+//! This is an experimental implementation. The security report of a proof
+//! (see [`crate::Proof::security_report`]) still assumes FRI.
+use crate::domain::coeffs_domain_size;
+use crate::domain::max_radix2_domain_size;
+use crate::fri::check_codeword;
+use crate::fri::fold_coeffs;
+use crate::fri::fold_coset;
+use crate::fri::lift;
+use crate::fri::query_layer;
+use crate::fri::unsupported_folding;
+use crate::fri::FriLayer;
+use crate::fri::FriOptions;
+use crate::fri::FriProof;
+use crate::fri::FriTailClaim;
+use crate::fri::LayerProof;
+use crate::fri::ProverChannel;
+use crate::fri::ProverError;
+use crate::fri::VerificationError;
+use crate::hash::Digest;
+use crate::ldt::LowDegreeTest;
+use crate::ldt::LowDegreeTestVerifier;
+use crate::merkle::MatrixMerkleTree;
+use crate::merkle::MerkleTree;
+use crate::merkle::MerkleTreeDomain;
+use crate::positions::fold_positions;
+use crate::positions::union_positions;
+use crate::random::DrawTag;
+use crate::random::PublicCoin;
+use crate::random::ReseedTag;
+use crate::rs_code;
+use crate::utils::horner_evaluate;
+use crate::utils::GpuVec;
+use crate::Matrix;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_poly::domain::DomainCoeff;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::DenseUVPolynomial;
+use ark_poly::EvaluationDomain;
+use ark_poly::Polynomial;
+use ark_poly::Radix2EvaluationDomain;
+use ministark_gpu::utils::bit_reverse_index;
+use ministark_gpu::GpuField;
+use std::iter::once;
+use std::iter::zip;
+use std::marker::PhantomData;
+
+/// Parameters of a STIR round
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundParameters {
+    /// Size of the domain of the round's codeword
+    pub domain_size: usize,
+    /// The round's codeword is the evaluation of a polynomial of degree less
+    /// than `degree_bound`
+    pub degree_bound: usize,
+    /// Number of shift queries i.e. rows of the codeword that are folded
+    pub num_queries: usize,
+}
+
+/// Returns the parameters of every round for a codeword over a domain of size
+/// `domain_size` that's queried `num_queries` times in the first round. Rounds
+/// continue until the folded polynomial has at most
+/// [`FriOptions::max_remainder_coeffs`] coefficients or is too small to divide
+/// by the next round's points. The folded polynomial of the last round is
+/// sent to the verifier.
+pub fn round_parameters(
+    options: &FriOptions,
+    domain_size: usize,
+    num_queries: usize,
+) -> Vec<RoundParameters> {
+    let folding_factor = options.layer_folding_factor();
+    let log_rate = options.blowup_factor().ilog2() as usize;
+    // halving the domain and dividing the degree by the folding factor improves
+    // the rate by `folding_factor / 2`
+    let log_rate_gain = folding_factor.ilog2() as usize - 1;
+    let security_bits = num_queries * log_rate;
+    let mut rounds = Vec::from([RoundParameters {
+        domain_size,
+        degree_bound: domain_size / options.blowup_factor(),
+        num_queries,
+    }]);
+    loop {
+        let round = *rounds.last().unwrap();
+        let degree_bound = round.degree_bound / folding_factor;
+        // the next codeword is divided by the shift queries and out of domain point
+        if degree_bound <= options.max_remainder_coeffs()
+            || degree_bound <= round.num_queries + 1
+            || round.domain_size / 2 < 2 * folding_factor
+        {
+            return rounds;
+        }
+        let log_rate = log_rate + rounds.len() * log_rate_gain;
+        let num_queries = if log_rate == 0 {
+            num_queries
+        } else {
+            security_bits.div_ceil(log_rate)
+        };
+        rounds.push(RoundParameters {
+            domain_size: round.domain_size / 2,
+            degree_bound,
+            num_queries,
+        });
+    }
+}
+
+/// STIR prover
+pub struct StirProver<F: GpuField, D: Digest, M: MerkleTree> {
+    options: FriOptions,
+    num_queries: usize,
+    layers: Vec<FriLayer<F, M>>,
+    ood_evals: Vec<F>,
+    round_positions: Vec<Vec<usize>>,
+    final_coeffs: Vec<F>,
+    _phantom: PhantomData<D>,
+}
+
+impl<
+        F: GpuField + Field + DomainCoeff<F::FftField>,
+        D: Digest,
+        M: MatrixMerkleTree<F, Root = D>,
+    > StirProver<F, D, M>
+where
+    F::FftField: FftField,
+{
+    pub const fn new(options: FriOptions, num_queries: usize) -> Self {
+        Self {
+            options,
+            num_queries,
+            layers: Vec::new(),
+            ood_evals: Vec::new(),
+            round_positions: Vec::new(),
+            final_coeffs: Vec::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    fn commit_codeword(
+        &mut self,
+        channel: &mut impl ProverChannel<Field = F, Digest = D>,
+        codeword: &[F],
+    ) -> Result<(), ProverError> {
+        match self.options.layer_folding_factor() {
+            2 => self.commit_rows::<2>(channel, codeword),
+            4 => self.commit_rows::<4>(channel, codeword),
+            8 => self.commit_rows::<8>(channel, codeword),
+            16 => self.commit_rows::<16>(channel, codeword),
+            _ => Err(unsupported_folding(&self.options)),
+        }
+    }
+
+    fn commit_rows<const N: usize>(
+        &mut self,
+        channel: &mut impl ProverChannel<Field = F, Digest = D>,
+        codeword: &[F],
+    ) -> Result<(), ProverError> {
+        let (rows, remainder) = codeword.as_chunks::<N>();
+        if !remainder.is_empty() {
+            return Err(ProverError::CodewordNotDivisible {
+                size: codeword.len(),
+                folding_factor: N,
+            });
+        }
+        let evaluations = Matrix::from_arrays(rows);
+        let domain = MerkleTreeDomain::FriLayer(self.layers.len());
+        let rows_per_leaf = self.options.rows_per_leaf(codeword.len());
+        let merkle_tree = M::from_matrix_chunked(&evaluations, domain, rows_per_leaf);
+        channel.commit_fri_layer(merkle_tree.cap(self.options.cap_height()));
+        self.layers.push(FriLayer {
+            merkle_tree,
+            evaluations,
+            rows_per_leaf,
+        });
+        Ok(())
+    }
+}
+
+impl<
+        F: GpuField + Field + DomainCoeff<F::FftField>,
+        D: Digest,
+        M: MatrixMerkleTree<F, Root = D>,
+    > LowDegreeTest<F, D, M> for StirProver<F, D, M>
+where
+    F::FftField: FftField,
+{
+    fn commit(
+        &mut self,
+        channel: &mut impl ProverChannel<Field = F, Digest = D>,
+        evaluations: GpuVec<F>,
+        degree: usize,
+    ) -> Result<(), ProverError> {
+        if !self.layers.is_empty() {
+            return Err(ProverError::AlreadyCommitted);
+        }
+        check_codeword(&self.options, evaluations.len(), degree)?;
+        let rounds = round_parameters(&self.options, evaluations.len(), self.num_queries);
+        let folding_factor = self.options.layer_folding_factor();
+        let offset = self.options.domain_offset::<F>();
+        let degree_bound = rounds[0].degree_bound;
+        let mut coeffs = rs_code::interpolate(&evaluations, offset);
+        if !coeffs[degree_bound..].iter().all(F::is_zero) {
+            return Err(ProverError::NotLowDegree { max_degree: degree });
+        }
+        coeffs.truncate(degree_bound);
+        self.commit_codeword(channel, &evaluations)?;
+
+        for [round, next] in rounds.array_windows() {
+            let folded = fold_coeffs(&coeffs, channel.draw_fri_alpha(), folding_factor);
+            let blowup_factor = next.domain_size / next.degree_bound;
+            self.commit_codeword(channel, &rs_code::encode(&folded, blowup_factor, offset))?;
+
+            let ood_point = channel.draw_challenge(DrawTag::StirOodPoint);
+            let ood_eval = horner_evaluate(&folded, &ood_point);
+            channel.commit_ood_eval(ood_eval);
+            let num_rows = round.domain_size / folding_factor;
+            let rows = channel.draw_positions(DrawTag::StirShiftQuery, round.num_queries, num_rows);
+            let comb_coeff = channel.draw_challenge(DrawTag::StirCombCoeff);
+
+            let shift_points = rows.iter().map(|&row| {
+                let row_offset =
+                    rs_code::evaluation_point(round.domain_size, offset, row * folding_factor);
+                lift::<F>(row_offset.pow([folding_factor as u64]))
+            });
+            let points = once(ood_point).chain(shift_points).collect::<Vec<F>>();
+            let quotient = divide(&folded, &vanishing_poly(&points));
+            coeffs = correct_degree(&quotient, comb_coeff, points.len());
+            coeffs.resize(next.degree_bound, F::zero());
+            self.ood_evals.push(ood_eval);
+            self.round_positions.push(Vec::from_iter(rows));
+        }
+
+        let last = rounds.last().unwrap();
+        self.final_coeffs = fold_coeffs(&coeffs, channel.draw_fri_alpha(), folding_factor);
+        channel.commit_remainder(&self.final_coeffs);
+        let num_rows = last.domain_size / folding_factor;
+        let rows = channel.draw_positions(DrawTag::StirShiftQuery, last.num_queries, num_rows);
+        self.round_positions.push(Vec::from_iter(rows));
+        Ok(())
+    }
+
+    /// STIR samples the queries of every round itself so no layer positions
+    /// are drawn
+    fn num_layers(&self) -> usize {
+        0
+    }
+
+    fn into_proof(
+        self,
+        positions: &[usize],
+        _layer_positions: &[Vec<usize>],
+    ) -> Result<FriProof<F, D, M>, ProverError> {
+        let folding_factor = self.options.layer_folding_factor();
+        let cap_height = self.options.cap_height();
+        let query_rows = fold_positions(positions, folding_factor);
+        let mut proof_layers = Vec::new();
+        for (i, (layer, rows)) in zip(&self.layers, &self.round_positions).enumerate() {
+            let rows = if i == 0 {
+                union_positions(&query_rows, rows)
+            } else {
+                rows.clone()
+            };
+            let layer_proof = match folding_factor {
+                2 => query_layer::<F, M, 2>(layer, &rows, cap_height),
+                4 => query_layer::<F, M, 4>(layer, &rows, cap_height),
+                8 => query_layer::<F, M, 8>(layer, &rows, cap_height),
+                16 => query_layer::<F, M, 16>(layer, &rows, cap_height),
+                _ => return Err(unsupported_folding(&self.options)),
+            };
+            proof_layers
+                .push(layer_proof.map_err(|source| ProverError::LayerQuery { layer: i, source })?);
+        }
+        let layer_caps = self.layers.iter().map(|l| l.merkle_tree.cap(cap_height));
+        Ok(FriProof {
+            ood_evals: self.ood_evals,
+            ..FriProof::new(layer_caps.collect(), proof_layers, self.final_coeffs)
+        })
+    }
+}
+
+/// STIR verifier
+pub struct StirVerifier<F: GpuField + Field, D: Digest, M: MatrixMerkleTree<F, Root = D>>
+where
+    F::FftField: FftField,
+{
+    options: FriOptions,
+    rounds: Vec<RoundParameters>,
+    /// Alpha of every round. The last alpha folds the final polynomial.
+    alphas: Vec<F>,
+    ood_points: Vec<F>,
+    comb_coeffs: Vec<F>,
+    /// Rows of the shift queries of every round
+    round_positions: Vec<Vec<usize>>,
+    /// Opened rows of the layers verified by [`Self::verify_head`]
+    head_rows: Vec<Vec<F>>,
+    proof: FriProof<F, D, M>,
+}
+
+impl<
+        F: GpuField + Field + DomainCoeff<F::FftField>,
+        D: Digest,
+        M: MatrixMerkleTree<F, Root = D>,
+    > StirVerifier<F, D, M>
+where
+    F::FftField: FftField,
+{
+    /// Replays the transcript of a proof for a polynomial of degree at most
+    /// `max_poly_degree` whose first round is queried `num_queries` times
+    pub fn new(
+        public_coin: &mut impl PublicCoin<Field = F, Digest = D>,
+        options: FriOptions,
+        num_queries: usize,
+        proof: FriProof<F, D, M>,
+        max_poly_degree: usize,
+    ) -> Result<Self, VerificationError> {
+        let folding_factor = options.layer_folding_factor();
+        if !matches!(folding_factor, 2 | 4 | 8 | 16) {
+            return Err(VerificationError::UnsupportedFolding {
+                folding_factor: options.folding_factor(),
+                folds_per_layer: options.folds_per_layer(),
+            });
+        }
+        if !options.values_per_leaf().is_power_of_two() {
+            return Err(VerificationError::InvalidValuesPerLeaf {
+                values_per_leaf: options.values_per_leaf(),
+            });
+        }
+        let domain_size = coeffs_domain_size(max_poly_degree)
+            .checked_mul(options.blowup_factor())
+            .filter(|&size| size <= max_radix2_domain_size::<F::FftField>())
+            .ok_or_else(|| VerificationError::DomainTooLarge {
+                max_poly_degree,
+                blowup_factor: options.blowup_factor(),
+            })?;
+        let rounds = round_parameters(&options, domain_size, num_queries);
+        let num_rounds = rounds.len();
+        for num_layers in [proof.layer_caps.len(), proof.layers.len()] {
+            // the queries of the last layers may have been split off the proof
+            if num_layers > num_rounds || proof.layer_caps.len() != num_rounds {
+                return Err(VerificationError::NumLayersMismatch {
+                    expected: num_rounds,
+                    actual: num_layers,
+                });
+            }
+        }
+        if proof.ood_evals.len() != num_rounds - 1 {
+            return Err(VerificationError::NumOodEvalsMismatch {
+                expected: num_rounds - 1,
+                actual: proof.ood_evals.len(),
+            });
+        }
+        for (i, (round, cap)) in zip(&rounds, &proof.layer_caps).enumerate() {
+            let num_rows = round.domain_size / folding_factor;
+            let num_leaves = num_rows / options.rows_per_leaf(round.domain_size);
+            let cap_size = M::cap_size(num_leaves, options.cap_height());
+            if cap.len() != cap_size {
+                return Err(VerificationError::CapSizeMismatch {
+                    layer: i,
+                    expected: cap_size,
+                    actual: cap.len(),
+                });
+            }
+        }
+        let last = rounds.last().unwrap();
+        let max_final_coeffs = (last.degree_bound / folding_factor).max(1);
+        let final_poly = DensePolynomial::from_coefficients_slice(&proof.remainder_coeffs);
+        if final_poly.degree() >= max_final_coeffs {
+            return Err(VerificationError::RemainderDegreeTooHigh {
+                degree: final_poly.degree(),
+                max_degree: max_final_coeffs - 1,
+            });
+        }
+
+        let mut alphas = Vec::new();
+        let mut ood_points = Vec::new();
+        let mut comb_coeffs = Vec::new();
+        let mut round_positions = Vec::new();
+        public_coin.reseed_with_cap(ReseedTag::FriLayer, &proof.layer_caps[0]);
+        for (round, (cap, ood_eval)) in zip(&rounds, zip(&proof.layer_caps[1..], &proof.ood_evals))
+        {
+            alphas.push(public_coin.draw_fq(DrawTag::FriAlpha));
+            public_coin.reseed_with_cap(ReseedTag::FriLayer, cap);
+            ood_points.push(public_coin.draw_fq(DrawTag::StirOodPoint));
+            public_coin.reseed_with_field_elements(ReseedTag::StirOodEval, &[*ood_eval]);
+            let num_rows = round.domain_size / folding_factor;
+            let rows =
+                public_coin.draw_positions(DrawTag::StirShiftQuery, round.num_queries, num_rows);
+            round_positions.push(Vec::from_iter(rows));
+            comb_coeffs.push(public_coin.draw_fq(DrawTag::StirCombCoeff));
+        }
+        alphas.push(public_coin.draw_fq(DrawTag::FriAlpha));
+        public_coin
+            .reseed_with_field_element_vector(ReseedTag::FriRemainder, &proof.remainder_coeffs);
+        let num_rows = last.domain_size / folding_factor;
+        let rows = public_coin.draw_positions(DrawTag::StirShiftQuery, last.num_queries, num_rows);
+        round_positions.push(Vec::from_iter(rows));
+
+        Ok(Self {
+            options,
+            rounds,
+            alphas,
+            ood_points,
+            comb_coeffs,
+            round_positions,
+            head_rows: Vec::new(),
+            proof,
+        })
+    }
+
+    /// Returns the rows of layer `i` that are opened if the STARK queries are
+    /// at `positions`
+    fn opened_rows(&self, i: usize, positions: &[usize]) -> Vec<usize> {
+        let rows = &self.round_positions[i];
+        if i == 0 {
+            let folding_factor = self.options.layer_folding_factor();
+            union_positions(&fold_positions(positions, folding_factor), rows)
+        } else {
+            rows.clone()
+        }
+    }
+
+    /// Verifies the opened rows of layer `i` against the layer's cap. Returns
+    /// the values of the rows (see [`Self::opened_rows`]) one after another.
+    fn open_layer(
+        &self,
+        i: usize,
+        positions: &[usize],
+        layer: LayerProof<F, M>,
+    ) -> Result<Vec<F>, VerificationError> {
+        match self.options.layer_folding_factor() {
+            2 => self.open_rows::<2>(i, positions, layer),
+            4 => self.open_rows::<4>(i, positions, layer),
+            8 => self.open_rows::<8>(i, positions, layer),
+            16 => self.open_rows::<16>(i, positions, layer),
+            folding_factor => unreachable!("folding factor {folding_factor} not supported"),
+        }
+    }
+
+    fn open_rows<const N: usize>(
+        &self,
+        i: usize,
+        positions: &[usize],
+        layer: LayerProof<F, M>,
+    ) -> Result<Vec<F>, VerificationError> {
+        let num_rounds = self.rounds.len();
+        if i == num_rounds {
+            return Err(VerificationError::NumLayersMismatch {
+                expected: num_rounds,
+                actual: num_rounds + 1,
+            });
+        }
+        let rows = self.opened_rows(i, positions);
+        let rows_per_leaf = self.options.rows_per_leaf(self.rounds[i].domain_size);
+        let leaf_positions = fold_positions(&rows, rows_per_leaf);
+        let (leaf_rows, _) = &layer.flattenend_rows.as_chunks::<N>();
+        if leaf_rows.len() != leaf_positions.len() * rows_per_leaf {
+            return Err(VerificationError::NumLayerQueriesMismatch {
+                layer: i,
+                expected: leaf_positions.len() * rows_per_leaf,
+                actual: leaf_rows.len(),
+            });
+        }
+        M::verify_row_chunks(
+            &self.proof.layer_caps[i],
+            MerkleTreeDomain::FriLayer(i),
+            &leaf_positions,
+            leaf_rows,
+            rows_per_leaf,
+            layer.merkle_proof,
+        )
+        .map_err(|source| VerificationError::MerklePathInvalid { layer: i, source })?;
+        Ok(rows
+            .iter()
+            .flat_map(|row| {
+                let leaf = leaf_positions.binary_search(&(row / rows_per_leaf));
+                leaf_rows[leaf.unwrap() * rows_per_leaf + row % rows_per_leaf]
+            })
+            .collect())
+    }
+
+    /// Checks the STARK evaluations against the first codeword and the folds of
+    /// every round given the opened rows of every layer
+    fn verify_rounds(
+        &self,
+        claim: &FriTailClaim<F>,
+        layer_rows: &[Vec<F>],
+    ) -> Result<(), VerificationError> {
+        let folding_factor = self.options.layer_folding_factor();
+        let offset = self.options.domain_offset::<F>();
+        let mut quotient: Option<RoundQuotient<F>> = None;
+        for (i, (round, values)) in zip(&self.rounds, layer_rows).enumerate() {
+            let opened_rows = self.opened_rows(i, &claim.positions);
+            let row = |row: usize| {
+                let i = opened_rows.binary_search(&row).unwrap();
+                &values[i * folding_factor..(i + 1) * folding_factor]
+            };
+            if i == 0 {
+                for (&position, evaluation) in zip(&claim.positions, &claim.evaluations) {
+                    let value = row(position / folding_factor)[position % folding_factor];
+                    if value != *evaluation {
+                        return Err(VerificationError::FoldMismatch {
+                            layer: 0,
+                            position,
+                            expected: evaluation.to_string(),
+                            got: value.to_string(),
+                        });
+                    }
+                }
+            }
+
+            let domain = Radix2EvaluationDomain::new_coset(round.domain_size, offset).unwrap();
+            let point = |position| domain.element(bit_reverse_index(round.domain_size, position));
+            let rows = &self.round_positions[i];
+            let mut folds = Vec::new();
+            for &j in rows {
+                let mut values = row(j).to_vec();
+                if let Some(quotient) = &quotient {
+                    for (m, value) in values.iter_mut().enumerate() {
+                        let x = lift(point(j * folding_factor + m));
+                        *value = quotient
+                            .evaluate(x, *value)
+                            .ok_or(VerificationError::OodPointCollision { round: i - 1 })?;
+                    }
+                }
+                folds.push(fold_coset(
+                    &values,
+                    point(j * folding_factor),
+                    self.alphas[i],
+                ));
+            }
+            let shift_points = rows
+                .iter()
+                .map(|&j| point(j * folding_factor).pow([folding_factor as u64]));
+
+            if i == self.rounds.len() - 1 {
+                let final_coeffs = &self.proof.remainder_coeffs;
+                for ((&position, fold), y) in zip(zip(rows, &folds), shift_points) {
+                    let eval = final_coeffs.iter().rfold(F::zero(), |mut result, coeff| {
+                        result *= y;
+                        result + coeff
+                    });
+                    if *fold != eval {
+                        return Err(VerificationError::RemainderMismatch {
+                            position,
+                            expected: fold.to_string(),
+                            got: eval.to_string(),
+                        });
+                    }
+                }
+            } else {
+                let points = once(self.ood_points[i]).chain(shift_points.map(lift));
+                let answers = once(self.proof.ood_evals[i]).chain(folds);
+                quotient = Some(
+                    RoundQuotient::new(points.collect(), answers.collect(), self.comb_coeffs[i])
+                        .ok_or(VerificationError::OodPointCollision { round: i })?,
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<
+        F: GpuField + Field + DomainCoeff<F::FftField>,
+        D: Digest,
+        M: MatrixMerkleTree<F, Root = D>,
+    > LowDegreeTestVerifier<F, D, M> for StirVerifier<F, D, M>
+where
+    F::FftField: FftField,
+{
+    fn layer_alphas(&self) -> &[F] {
+        &self.alphas
+    }
+
+    fn draw_layer_positions(
+        &mut self,
+        _public_coin: &mut impl PublicCoin<Field = F, Digest = D>,
+        _num_queries: usize,
+    ) {
+    }
+
+    /// Verifies the merkle paths of the layers that are part of the proof. The
+    /// folds are checked by [`Self::verify_tail_layers`] once every layer is
+    /// opened.
+    fn verify_head(
+        &mut self,
+        positions: &[usize],
+        evaluations: &[F],
+    ) -> Result<FriTailClaim<F>, VerificationError> {
+        if positions.len() != evaluations.len() {
+            return Err(VerificationError::NumPositionEvaluationMismatch);
+        }
+        let layers = core::mem::take(&mut self.proof.layers);
+        for (i, layer) in layers.into_iter().enumerate() {
+            let rows = self.open_layer(i, positions, layer)?;
+            self.head_rows.push(rows);
+        }
+        let domain_size = self.rounds[0].domain_size;
+        let domain = Radix2EvaluationDomain::<F::FftField>::new(domain_size).unwrap();
+        Ok(FriTailClaim {
+            layer: self.head_rows.len(),
+            positions: positions.to_vec(),
+            evaluations: evaluations.to_vec(),
+            domain_size,
+            domain_generator: domain.group_gen(),
+        })
+    }
+
+    fn verify_tail_layers(
+        &self,
+        claim: FriTailClaim<F>,
+        layers: impl IntoIterator<Item = LayerProof<F, M>>,
+    ) -> Result<(), VerificationError> {
+        let mut layer_rows = self.head_rows.clone();
+        for layer in layers {
+            let rows = self.open_layer(layer_rows.len(), &claim.positions, layer)?;
+            layer_rows.push(rows);
+        }
+        let num_rounds = self.rounds.len();
+        if layer_rows.len() != num_rounds {
+            return Err(VerificationError::NumLayersMismatch {
+                expected: num_rounds,
+                actual: layer_rows.len(),
+            });
+        }
+        self.verify_rounds(&claim, &layer_rows)
+    }
+}
+
+/// Quotient of a round's folded polynomial by the polynomial that vanishes at
+/// the round's out of domain point and shift queries with its degree shifted
+/// back up
+struct RoundQuotient<F> {
+    points: Vec<F>,
+    answers: Vec<F>,
+    /// barycentric weights `1 / ∏_(k != j) (z_j - z_k)`
+    weights: Vec<F>,
+    comb_coeff: F,
+}
+
+impl<F: Field> RoundQuotient<F> {
+    /// Returns [`None`] if the points aren't unique
+    fn new(points: Vec<F>, answers: Vec<F>, comb_coeff: F) -> Option<Self> {
+        let weights = points
+            .iter()
+            .enumerate()
+            .map(|(j, z_j)| {
+                let others = points.iter().enumerate().filter(|&(k, _)| k != j);
+                others.map(|(_, z_k)| *z_j - z_k).product::<F>().inverse()
+            })
+            .collect::<Option<Vec<F>>>()?;
+        Some(Self {
+            points,
+            answers,
+            weights,
+            comb_coeff,
+        })
+    }
+
+    /// Returns the evaluation at `x` given the evaluation `g` of the folded
+    /// polynomial at `x`. Returns [`None`] if `x` is one of the points.
+    fn evaluate(&self, x: F, g: F) -> Option<F> {
+        // (g(x) - ans(x)) / v(x) where ans interpolates the answers and v vanishes
+        // at the points
+        let mut vanishing = F::one();
+        let mut interpolant = F::zero();
+        for ((z, answer), weight) in zip(zip(&self.points, &self.answers), &self.weights) {
+            let difference = x - z;
+            vanishing *= difference;
+            interpolant += *answer * weight * difference.inverse()?;
+        }
+        let quotient = g * vanishing.inverse()? - interpolant;
+
+        // degree correction Σ_(l=0)^e (c * x)^l = ((c * x)^(e + 1) - 1) / (c * x - 1)
+        let num_terms = self.points.len() as u64 + 1;
+        let cx = self.comb_coeff * x;
+        let correction = if cx.is_one() {
+            F::from(num_terms)
+        } else {
+            (cx.pow([num_terms]) - F::one()) * (cx - F::one()).inverse()?
+        };
+        Some(quotient * correction)
+    }
+}
+
+/// Returns the coefficients of `∏ (x - z)` for every point `z`
+fn vanishing_poly<F: Field>(points: &[F]) -> Vec<F> {
+    let mut coeffs = Vec::from([F::one()]);
+    for &z in points {
+        coeffs.insert(0, F::zero());
+        for i in 0..coeffs.len() - 1 {
+            let next = coeffs[i + 1];
+            coeffs[i] -= next * z;
+        }
+    }
+    coeffs
+}
+
+/// Returns the quotient of dividing `numerator` by the monic `denominator`.
+/// The remainder is discarded.
+fn divide<F: Field>(numerator: &[F], denominator: &[F]) -> Vec<F> {
+    let degree = denominator.len() - 1;
+    if numerator.len() <= degree {
+        return Vec::new();
+    }
+    let mut remainder = numerator.to_vec();
+    let mut quotient = vec![F::zero(); numerator.len() - degree];
+    for i in (0..quotient.len()).rev() {
+        let coeff = remainder[i + degree];
+        quotient[i] = coeff;
+        for (r, &d) in zip(&mut remainder[i..i + degree], &denominator[..degree]) {
+            *r -= coeff * d;
+        }
+    }
+    quotient
+}
+
+/// Multiplies a polynomial by `Σ_(l=0)^e (c * x)^l` where `e` is the number of
+/// points the polynomial was divided by
+fn correct_degree<F: Field>(coeffs: &[F], comb_coeff: F, num_points: usize) -> Vec<F> {
+    // h = q * (1 - (c * x)^(e + 1)) / (1 - c * x) so h_k = s_k + c * h_(k - 1)
+    // where s = q * (1 - (c * x)^(e + 1))
+    let shift = comb_coeff.pow([num_points as u64 + 1]);
+    let mut acc = F::zero();
+    (0..coeffs.len() + num_points)
+        .map(|k| {
+            let mut s = coeffs.get(k).copied().unwrap_or_else(F::zero);
+            if k > num_points {
+                s -= shift * coeffs[k - num_points - 1];
+            }
+            acc = s + comb_coeff * acc;
+            acc
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::round_parameters;
+    use super::RoundParameters;
+    use super::StirProver;
+    use super::StirVerifier;
+    use crate::fixtures::MerkleTree;
+    use crate::fixtures::TestChannel;
+    use crate::fixtures::TestCoin;
+    use crate::fri::FriOptions;
+    use crate::fri::VerificationError;
+    use crate::hash::HashFn;
+    use crate::hash::Sha256HashFn;
+    use crate::ldt::LowDegreeTest;
+    use crate::ldt::LowDegreeTestVerifier;
+    use crate::random::PublicCoin;
+    use crate::rs_code;
+    use crate::utils::GpuAllocator;
+    use ark_ff::FftField;
+    use ark_poly::univariate::DensePolynomial;
+    use ark_poly::DenseUVPolynomial;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn later_rounds_need_fewer_queries() {
+        let options = FriOptions::new(4, 4, 4).with_stir(16);
+
+        let rounds = round_parameters(&options, 1024, 16);
+
+        let round = |domain_size, degree_bound, num_queries| RoundParameters {
+            domain_size,
+            degree_bound,
+            num_queries,
+        };
+        assert_eq!(
+            Vec::from([round(1024, 256, 16), round(512, 64, 11), round(256, 16, 8)]),
+            rounds
+        );
+    }
+
+    #[test]
+    fn stir_checks_evaluations() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let options = FriOptions::new(4, 4, 4).with_stir(16);
+        let max_poly_degree = 255;
+        let poly = DensePolynomial::<Fp>::rand(max_poly_degree, &mut rng);
+        let codeword = rs_code::encode(&poly.coeffs, 4, Fp::GENERATOR);
+        let seed = Sha256HashFn::hash([]);
+        let positions = [1, 20, 100, 1000];
+        let evaluations = positions.map(|position| codeword[position]);
+
+        let mut prover = StirProver::<Fp, _, MerkleTree>::new(options, 16);
+        let mut channel = TestChannel(TestCoin::new(seed.clone()));
+        prover
+            .commit(
+                &mut channel,
+                codeword.to_vec_in(GpuAllocator),
+                max_poly_degree,
+            )
+            .unwrap();
+        let proof = prover.into_proof(&positions, &[]).unwrap();
+        assert_eq!(3, proof.layer_caps.len());
+        assert_eq!(2, proof.ood_evals.len());
+
+        let verify = |proof, evaluations: &[Fp]| {
+            let mut public_coin = TestCoin::new(seed.clone());
+            let mut verifier =
+                StirVerifier::new(&mut public_coin, options, 16, proof, max_poly_degree)?;
+            let claim = verifier.verify_head(&positions, evaluations)?;
+            verifier.verify_tail_layers(claim, [])?;
+            Ok(public_coin.seed)
+        };
+        let mut wrong_evaluations = evaluations;
+        wrong_evaluations[2] += Fp::from(1u8);
+
+        let mut missing_ood_eval = proof.clone();
+        missing_ood_eval.ood_evals.pop();
+        let mut wrong_ood_eval = proof.clone();
+        wrong_ood_eval.ood_evals[1] += Fp::from(1u8);
+
+        assert_eq!(channel.0.seed, verify(proof.clone(), &evaluations).unwrap());
+        assert!(matches!(
+            verify(proof, &wrong_evaluations),
+            Err(VerificationError::FoldMismatch { position: 100, .. })
+        ));
+        assert!(matches!(
+            verify(missing_ood_eval, &evaluations),
+            Err(VerificationError::NumOodEvalsMismatch {
+                expected: 2,
+                actual: 1
+            })
+        ));
+        // the ood evaluations are absorbed so a wrong one draws different rows
+        assert!(matches!(
+            verify(wrong_ood_eval, &evaluations),
+            Err(VerificationError::NumLayerQueriesMismatch { layer: 1, .. })
+        ));
+    }
+}
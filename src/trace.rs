@@ -1,6 +1,8 @@
 use crate::challenges::Challenges;
-use crate::merkle::MatrixMerkleTree;
+use crate::merkle;
 use crate::merkle::MerkleTree;
+use crate::positions::fold_positions;
+use crate::positions::leaf_rows;
 use crate::stark::Stark;
 use crate::Matrix;
 use alloc::vec::Vec;
@@ -35,11 +37,22 @@ pub trait Trace: Send + Sync {
     }
 }
 
+/// Trace values opened at the query positions. Every row of a leaf is opened
+/// if leaves hold several rows (see
+/// [`crate::ProofOptions::with_trace_values_per_leaf`]) so the values are the
+/// rows of the opened leaves in order.
 pub struct Queries<C: Stark> {
     pub base_trace_values: Vec<C::Fp>,
     pub extension_trace_values: Vec<C::Fq>,
     pub composition_trace_values: Vec<C::Fq>,
     pub base_trace_proof: <C::MerkleTree as MerkleTree>::Proof,
+    /// Openings of the separately committed base columns. The opened values
+    /// are part of `base_trace_values`.
+    pub base_column_proofs: Vec<<C::MerkleTree as MerkleTree>::Proof>,
+    /// Opening of the preprocessed columns (see
+    /// [`crate::setup::VerifyingKey`]). The opened values are part of
+    /// `base_trace_values`.
+    pub preprocessed_trace_proof: Option<<C::MerkleTree as MerkleTree>::Proof>,
     pub extension_trace_proof: Option<<C::MerkleTree as MerkleTree>::Proof>,
     pub composition_trace_proof: <C::MerkleTree as MerkleTree>::Proof,
 }
@@ -58,6 +71,10 @@ impl<C: Stark> CanonicalSerialize for Queries<C> {
             .serialize_with_mode(&mut writer, compress)?;
         self.base_trace_proof
             .serialize_with_mode(&mut writer, compress)?;
+        self.base_column_proofs
+            .serialize_with_mode(&mut writer, compress)?;
+        self.preprocessed_trace_proof
+            .serialize_with_mode(&mut writer, compress)?;
         self.extension_trace_proof
             .serialize_with_mode(&mut writer, compress)?;
         self.composition_trace_proof
@@ -70,6 +87,8 @@ impl<C: Stark> CanonicalSerialize for Queries<C> {
             + self.extension_trace_values.serialized_size(compress)
             + self.composition_trace_values.serialized_size(compress)
             + self.base_trace_proof.serialized_size(compress)
+            + self.base_column_proofs.serialized_size(compress)
+            + self.preprocessed_trace_proof.serialized_size(compress)
             + self.extension_trace_proof.serialized_size(compress)
             + self.composition_trace_proof.serialized_size(compress)
     }
@@ -92,6 +111,8 @@ impl<C: Stark> CanonicalDeserialize for Queries<C> {
             extension_trace_values: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             composition_trace_values: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             base_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            base_column_proofs: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            preprocessed_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             extension_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             composition_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
         })
@@ -105,6 +126,8 @@ impl<C: Stark> Clone for Queries<C> {
             extension_trace_values: self.extension_trace_values.clone(),
             composition_trace_values: self.composition_trace_values.clone(),
             base_trace_proof: self.base_trace_proof.clone(),
+            base_column_proofs: self.base_column_proofs.clone(),
+            preprocessed_trace_proof: self.preprocessed_trace_proof.clone(),
             extension_trace_proof: self.extension_trace_proof.clone(),
             composition_trace_proof: self.composition_trace_proof.clone(),
         }
@@ -112,26 +135,42 @@ impl<C: Stark> Clone for Queries<C> {
 }
 
 impl<C: Stark> Queries<C> {
+    /// Opens the trace commitments at the query positions
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a position is out of bounds.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         base_trace_lde: &Matrix<C::Fp>,
         extension_trace_lde: Option<&Matrix<C::Fq>>,
         composition_trace_lde: &Matrix<C::Fq>,
         base_tree: &C::MerkleTree,
+        base_column_trees: &[C::MerkleTree],
+        preprocessed_tree: Option<&C::MerkleTree>,
         extension_tree: Option<&C::MerkleTree>,
         composition_tree: &C::MerkleTree,
         positions: &[usize],
-    ) -> Self {
-        let base_trace_proof = MatrixMerkleTree::<C::Fp>::prove_rows(base_tree, positions).unwrap();
-        let extension_trace_proof = extension_tree.map(|extension_tree| {
-            MatrixMerkleTree::<C::Fq>::prove_rows(extension_tree, positions).unwrap()
-        });
-        let composition_trace_proof =
-            MatrixMerkleTree::<C::Fq>::prove_rows(composition_tree, positions).unwrap();
+        rows_per_leaf: usize,
+        cap_height: u32,
+    ) -> Result<Self, merkle::Error> {
+        // all rows of the leaves containing the positions are opened. Paths end
+        // at the merkle caps that were committed to.
+        let leaves = fold_positions(positions, rows_per_leaf);
+        let prove = |tree: &C::MerkleTree| tree.prove_to_cap(&leaves, cap_height);
+        let base_trace_proof = prove(base_tree)?;
+        let base_column_proofs = base_column_trees
+            .iter()
+            .map(prove)
+            .collect::<Result<_, _>>()?;
+        let preprocessed_trace_proof = preprocessed_tree.map(prove).transpose()?;
+        let extension_trace_proof = extension_tree.map(prove).transpose()?;
+        let composition_trace_proof = prove(composition_tree)?;
 
         let mut base_trace_values = Vec::new();
         let mut extension_trace_values = Vec::new();
         let mut composition_trace_values = Vec::new();
-        for &position in positions {
+        for position in leaf_rows(positions, rows_per_leaf) {
             // execution trace
             let base_trace_row = base_trace_lde.get_row(position).unwrap();
             base_trace_values.extend(base_trace_row);
@@ -146,13 +185,15 @@ impl<C: Stark> Queries<C> {
             let composition_trace_row = composition_trace_lde.get_row(position).unwrap();
             composition_trace_values.extend(composition_trace_row);
         }
-        Self {
+        Ok(Self {
             base_trace_values,
             extension_trace_values,
             composition_trace_values,
             base_trace_proof,
+            base_column_proofs,
+            preprocessed_trace_proof,
             extension_trace_proof,
             composition_trace_proof,
-        }
+        })
     }
 }
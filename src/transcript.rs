@@ -0,0 +1,278 @@
+//! Golden transcripts
+//!
+//! The prover and verifier have to absorb and draw the same values in the same
+//! order for Fiat-Shamir challenges to match. A reordering on only one side
+//! shows up as an unhelpful "proof is invalid" error at best and a soundness
+//! bug at worst. The expected order of operations is written down here as a
+//! list of phases and, in debug builds, the prover and verifier both check the
+//! operations applied to their [`PublicCoinImpl`] against it (see
+//! [`check_transcript`]).
+//!
+//! Operations are recorded by tag rather than value and consecutive repeats of
+//! the same operation are recorded once. Operations tagged
+//! [`ReseedTag::Other`] or [`DrawTag::Other`] are application specific and
+//! aren't recorded.
+//!
+//! [`PublicCoinImpl`]: crate::random::PublicCoinImpl
+use crate::random::DrawTag;
+use crate::random::PublicCoin;
+use crate::random::ReseedTag;
+use snafu::Snafu;
+use Repeat::*;
+use TranscriptOp::*;
+
+/// Version of the transcript described by [`spec`]. Any change to the order
+/// of operations must add a new version.
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// An operation applied to a [`crate::random::PublicCoin`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptOp {
+    Absorb(ReseedTag),
+    Draw(DrawTag),
+}
+
+/// How many times the operations of a [`Phase`] occur in a row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    Once,
+    Optional,
+    /// Zero or more times
+    Many,
+}
+
+/// Ordered operations that make up one phase of the protocol
+#[derive(Debug, Clone, Copy)]
+pub struct Phase {
+    pub name: &'static str,
+    pub ops: &'static [TranscriptOp],
+    pub repeat: Repeat,
+}
+
+impl Phase {
+    const fn new(name: &'static str, ops: &'static [TranscriptOp], repeat: Repeat) -> Self {
+        Self { name, ops, repeat }
+    }
+}
+
+const SPEC_V1: &[Phase] = &[
+    Phase::new(
+        "domain parameters",
+        &[Absorb(ReseedTag::DomainParameters)],
+        Once,
+    ),
+    Phase::new("nonce", &[Absorb(ReseedTag::Nonce)], Optional),
+    Phase::new(
+        "preprocessed columns",
+        &[Absorb(ReseedTag::Preprocessed)],
+        Optional,
+    ),
+    Phase::new("base trace", &[Absorb(ReseedTag::BaseTrace)], Once),
+    Phase::new("base columns", &[Absorb(ReseedTag::BaseColumn)], Optional),
+    Phase::new("air challenges", &[Draw(DrawTag::AirChallenge)], Optional),
+    Phase::new(
+        "extension trace",
+        &[Absorb(ReseedTag::ExtensionTrace)],
+        Optional,
+    ),
+    Phase::new(
+        "composition coefficients",
+        &[Draw(DrawTag::CompositionCoeff)],
+        Optional,
+    ),
+    Phase::new(
+        "composition trace",
+        &[Absorb(ReseedTag::CompositionTrace)],
+        Once,
+    ),
+    Phase::new(
+        "out of domain evaluations",
+        &[Draw(DrawTag::OodPoint), Absorb(ReseedTag::OodEvals)],
+        Once,
+    ),
+    Phase::new("deep coefficients", &[Draw(DrawTag::DeepCoeff)], Once),
+    // FRI layers and the first layer of STIR
+    Phase::new(
+        "layer",
+        &[Absorb(ReseedTag::FriLayer), Draw(DrawTag::FriAlpha)],
+        Many,
+    ),
+    Phase::new(
+        "stir round",
+        &[
+            Absorb(ReseedTag::FriLayer),
+            Draw(DrawTag::StirOodPoint),
+            Absorb(ReseedTag::StirOodEval),
+            Draw(DrawTag::StirShiftQuery),
+            Draw(DrawTag::StirCombCoeff),
+            Draw(DrawTag::FriAlpha),
+        ],
+        Many,
+    ),
+    Phase::new("remainder", &[Absorb(ReseedTag::FriRemainder)], Once),
+    Phase::new(
+        "stir final queries",
+        &[Draw(DrawTag::StirShiftQuery)],
+        Optional,
+    ),
+    Phase::new("proof of work", &[Absorb(ReseedTag::ProofOfWork)], Optional),
+    Phase::new("queries", &[Draw(DrawTag::Query)], Once),
+];
+
+/// Returns the phases of the transcript of protocol `version`
+pub const fn spec(version: u16) -> Option<&'static [Phase]> {
+    match version {
+        1 => Some(SPEC_V1),
+        _ => None,
+    }
+}
+
+/// Errors returned when a transcript doesn't follow the spec
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum TranscriptError {
+    #[snafu(display("no transcript spec for protocol version {version}"))]
+    UnknownVersion { version: u16 },
+    #[snafu(display("unexpected transcript operation {op:?} at position {position}"))]
+    UnexpectedOp { position: usize, op: TranscriptOp },
+    #[snafu(display("transcript ended after {len} operations"))]
+    Incomplete { len: usize },
+}
+
+/// Checks that `ops` is a complete transcript of protocol `version`
+pub fn check_transcript(version: u16, ops: &[TranscriptOp]) -> Result<(), TranscriptError> {
+    let phases = spec(version).ok_or(TranscriptError::UnknownVersion { version })?;
+    let mut furthest = 0;
+    if match_phases(phases, ops, 0, &mut furthest) {
+        return Ok(());
+    }
+    Err(ops
+        .get(furthest)
+        .map_or(TranscriptError::Incomplete { len: ops.len() }, |&op| {
+            TranscriptError::UnexpectedOp {
+                position: furthest,
+                op,
+            }
+        }))
+}
+
+/// Checks the operations recorded by the coin of the `party` that just drew
+/// its query positions. Coins that don't record their operations aren't
+/// checked.
+///
+/// # Panics
+///
+/// Panics if the recorded operations don't follow the spec of
+/// [`PROTOCOL_VERSION`]
+pub fn assert_transcript(party: &str, public_coin: &impl PublicCoin) {
+    if let Some(ops) = public_coin.transcript() {
+        if let Err(err) = check_transcript(PROTOCOL_VERSION, ops) {
+            panic!("{party} transcript doesn't follow the spec: {err}");
+        }
+    }
+}
+
+/// Returns true if `ops[pos..]` matches `phases`. `furthest` is set to the
+/// furthest position any attempted match reached.
+fn match_phases(phases: &[Phase], ops: &[TranscriptOp], pos: usize, furthest: &mut usize) -> bool {
+    *furthest = (*furthest).max(pos);
+    let Some((phase, rest)) = phases.split_first() else {
+        return pos == ops.len();
+    };
+    let next = ops[pos..]
+        .starts_with(phase.ops)
+        .then_some(pos + phase.ops.len());
+    match phase.repeat {
+        Once => next.is_some_and(|next| match_phases(rest, ops, next, furthest)),
+        Optional => {
+            next.is_some_and(|next| match_phases(rest, ops, next, furthest))
+                || match_phases(rest, ops, pos, furthest)
+        }
+        Many => {
+            next.is_some_and(|next| match_phases(phases, ops, next, furthest))
+                || match_phases(rest, ops, pos, furthest)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_transcript;
+    use super::TranscriptError;
+    use super::TranscriptOp::*;
+    use super::PROTOCOL_VERSION;
+    use crate::random::DrawTag;
+    use crate::random::ReseedTag;
+
+    const PREFIX: [super::TranscriptOp; 7] = [
+        Absorb(ReseedTag::DomainParameters),
+        Absorb(ReseedTag::BaseTrace),
+        Draw(DrawTag::CompositionCoeff),
+        Absorb(ReseedTag::CompositionTrace),
+        Draw(DrawTag::OodPoint),
+        Absorb(ReseedTag::OodEvals),
+        Draw(DrawTag::DeepCoeff),
+    ];
+
+    #[test]
+    fn fri_transcript_matches_spec() {
+        let fri = [
+            Absorb(ReseedTag::FriLayer),
+            Draw(DrawTag::FriAlpha),
+            Absorb(ReseedTag::FriLayer),
+            Draw(DrawTag::FriAlpha),
+            Absorb(ReseedTag::FriRemainder),
+            Absorb(ReseedTag::ProofOfWork),
+            Draw(DrawTag::Query),
+        ];
+
+        assert_eq!(
+            Ok(()),
+            check_transcript(PROTOCOL_VERSION, &[&PREFIX[..], &fri].concat())
+        );
+    }
+
+    #[test]
+    fn stir_transcript_matches_spec() {
+        let stir = [
+            Absorb(ReseedTag::FriLayer),
+            Draw(DrawTag::FriAlpha),
+            Absorb(ReseedTag::FriLayer),
+            Draw(DrawTag::StirOodPoint),
+            Absorb(ReseedTag::StirOodEval),
+            Draw(DrawTag::StirShiftQuery),
+            Draw(DrawTag::StirCombCoeff),
+            Draw(DrawTag::FriAlpha),
+            Absorb(ReseedTag::FriRemainder),
+            Draw(DrawTag::StirShiftQuery),
+            Draw(DrawTag::Query),
+        ];
+
+        assert_eq!(
+            Ok(()),
+            check_transcript(PROTOCOL_VERSION, &[&PREFIX[..], &stir].concat())
+        );
+    }
+
+    #[test]
+    fn reordered_transcript_is_rejected() {
+        let mut ops = PREFIX.to_vec();
+        ops.swap(3, 4);
+        ops.extend([Absorb(ReseedTag::FriRemainder), Draw(DrawTag::Query)]);
+
+        assert_eq!(
+            Err(TranscriptError::UnexpectedOp {
+                position: 3,
+                op: Draw(DrawTag::OodPoint),
+            }),
+            check_transcript(PROTOCOL_VERSION, &ops)
+        );
+    }
+
+    #[test]
+    fn truncated_transcript_is_rejected() {
+        assert_eq!(
+            Err(TranscriptError::Incomplete { len: PREFIX.len() }),
+            check_transcript(PROTOCOL_VERSION, &PREFIX)
+        );
+    }
+}
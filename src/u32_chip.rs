@@ -0,0 +1,380 @@
+//! 32-bit arithmetic
+//!
+//! Constraints for wrapping and carrying `u32` arithmetic over a prime field.
+//! A [`U32`] is held in two 16-bit limb columns and operations relate the
+//! limbs of their operands and results through carry columns. The constraints
+//! only imply the integer relation if every limb and carry is in range so each
+//! operation lists the columns that need a 16-bit range check
+//! ([`U32Add::range_checked`] etc.). Range checks are left to the AIR e.g. a
+//! lookup into a table of all 16-bit values or [`bits_range_check`] for AIRs
+//! with only a few checked columns.
+//!
+//! Constraints are returned as plain expressions that are zero when the
+//! operation is valid. The AIR applies selectors (see
+//! [`crate::constraints::when`]) and restricts them to the rows the operation
+//! is in (see [`crate::constraints::rows_constraint`]). The field modulus must
+//! be larger than `2^40` so intermediate sums don't wrap around.
+use crate::constraints::assert_boolean;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::ExecutionTraceColumn;
+use crate::expression::Expr;
+use crate::utils::FieldVariant;
+use crate::Matrix;
+use crate::StarkExtensionOf;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_ff::One;
+use ministark_gpu::GpuFftField;
+
+type Item<Fp, Fq> = AlgebraicItem<FieldVariant<Fp, Fq>>;
+
+fn constant<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(value: u64) -> Item<Fp, Fq> {
+    AlgebraicItem::Constant(FieldVariant::Fp(Fp::from(value)))
+}
+
+fn limb_base<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>() -> Item<Fp, Fq> {
+    constant(1 << 16)
+}
+
+/// A `u32` held in a low and high 16-bit limb column
+#[derive(Debug, Clone, Copy)]
+pub struct U32<C> {
+    pub lo: C,
+    pub hi: C,
+}
+
+impl<C: ExecutionTraceColumn> U32<C> {
+    /// Returns the value `lo + 2^16 * hi` in the current row
+    pub fn curr<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(&self) -> Expr<Item<Fp, Fq>> {
+        self.lo.curr() + self.hi.curr() * limb_base()
+    }
+
+    /// Writes `value` to the limb columns in row `row`
+    pub fn assign<F: Field>(&self, trace: &mut Matrix<F>, row: usize, value: u32) {
+        trace.0[self.lo.index()][row] = F::from(value & 0xFFFF);
+        trace.0[self.hi.index()][row] = F::from(value >> 16);
+    }
+
+    const fn limbs(&self) -> [&C; 2] {
+        [&self.lo, &self.hi]
+    }
+}
+
+/// `a + b = c + carry * 2^32`
+#[derive(Debug, Clone, Copy)]
+pub struct U32Add<C> {
+    pub a: U32<C>,
+    pub b: U32<C>,
+    pub c: U32<C>,
+    /// Carry from the low limbs into the high limbs
+    pub carry_lo: C,
+    pub carry: C,
+}
+
+impl<C: ExecutionTraceColumn> U32Add<C> {
+    pub fn constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<Item<Fp, Fq>>> {
+        add_constraints(&self.a, &self.b, &self.c, &self.carry_lo, &self.carry)
+    }
+
+    /// Columns that need a 16-bit range check. The carries are
+    /// constrained to be boolean so only the result is range checked.
+    pub fn range_checked(&self) -> Vec<&C> {
+        self.c.limbs().to_vec()
+    }
+
+    /// Writes `a`, `b` and their sum to row `row` and returns the wrapping sum
+    pub fn assign<F: Field>(&self, trace: &mut Matrix<F>, row: usize, a: u32, b: u32) -> u32 {
+        let (c, carry) = a.overflowing_add(b);
+        let carry_lo = (a & 0xFFFF) + (b & 0xFFFF) > 0xFFFF;
+        self.a.assign(trace, row, a);
+        self.b.assign(trace, row, b);
+        self.c.assign(trace, row, c);
+        trace.0[self.carry_lo.index()][row] = F::from(carry_lo);
+        trace.0[self.carry.index()][row] = F::from(carry);
+        c
+    }
+}
+
+/// `a - b = c - borrow * 2^32`
+#[derive(Debug, Clone, Copy)]
+pub struct U32Sub<C> {
+    pub a: U32<C>,
+    pub b: U32<C>,
+    pub c: U32<C>,
+    /// Borrow of the low limbs from the high limbs
+    pub borrow_lo: C,
+    pub borrow: C,
+}
+
+impl<C: ExecutionTraceColumn> U32Sub<C> {
+    pub fn constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<Item<Fp, Fq>>> {
+        // checked as `b + c = a + borrow * 2^32`
+        add_constraints(&self.b, &self.c, &self.a, &self.borrow_lo, &self.borrow)
+    }
+
+    /// Returns an expression that's 1 where `a < b` and 0 otherwise
+    pub fn lt<T>(&self) -> Expr<AlgebraicItem<T>> {
+        self.borrow.curr()
+    }
+
+    /// Returns an expression that's 1 where `a >= b` and 0 otherwise
+    pub fn ge<T: One>(&self) -> Expr<AlgebraicItem<T>> {
+        Expr::from(AlgebraicItem::Constant(T::one())) - self.borrow.curr()
+    }
+
+    /// See [`U32Add::range_checked`]. The difference and `a` are range checked
+    /// since `a` is the sum in the addition that's checked.
+    pub fn range_checked(&self) -> Vec<&C> {
+        let [c_lo, c_hi] = self.c.limbs();
+        let [a_lo, a_hi] = self.a.limbs();
+        vec![c_lo, c_hi, a_lo, a_hi]
+    }
+
+    /// Writes `a`, `b` and their difference to row `row` and returns the
+    /// wrapping difference
+    pub fn assign<F: Field>(&self, trace: &mut Matrix<F>, row: usize, a: u32, b: u32) -> u32 {
+        let (c, borrow) = a.overflowing_sub(b);
+        let borrow_lo = (a & 0xFFFF) < (b & 0xFFFF);
+        self.a.assign(trace, row, a);
+        self.b.assign(trace, row, b);
+        self.c.assign(trace, row, c);
+        trace.0[self.borrow_lo.index()][row] = F::from(borrow_lo);
+        trace.0[self.borrow.index()][row] = F::from(borrow);
+        c
+    }
+}
+
+/// `a * b = lo + hi * 2^32`. With limbs `a = a0 + a1 * 2^16` (the same for
+/// `b`) the product is checked limb by limb:
+///
+/// ```text
+/// a0 * b0                = lo0 + k0 * 2^16
+/// a0 * b1 + a1 * b0 + k0 = lo1 + k1 * 2^16
+/// a1 * b1 + k1           = hi0 + hi1 * 2^16
+/// ```
+///
+/// The carry `k1` can be up to 17 bits so it's split into a 16-bit limb and a
+/// boolean.
+#[derive(Debug, Clone, Copy)]
+pub struct U32Mul<C> {
+    pub a: U32<C>,
+    pub b: U32<C>,
+    pub lo: U32<C>,
+    pub hi: U32<C>,
+    pub k0: C,
+    pub k1_lo: C,
+    pub k1_hi: C,
+}
+
+impl<C: ExecutionTraceColumn> U32Mul<C> {
+    pub fn constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<Item<Fp, Fq>>> {
+        let Self {
+            a,
+            b,
+            lo,
+            hi,
+            k0,
+            k1_lo,
+            k1_hi,
+        } = self;
+        let base = limb_base::<Fp, Fq>;
+        let k1 = k1_lo.curr() + k1_hi.curr() * base();
+        vec![
+            a.lo.curr() * b.lo.curr() - lo.lo.curr() - k0.curr() * base(),
+            a.lo.curr() * b.hi.curr() + a.hi.curr() * b.lo.curr() + k0.curr()
+                - lo.hi.curr()
+                - &k1 * base(),
+            a.hi.curr() * b.hi.curr() + k1 - hi.curr(),
+            assert_boolean(k1_hi.curr()),
+        ]
+    }
+
+    /// See [`U32Add::range_checked`]
+    pub fn range_checked(&self) -> Vec<&C> {
+        let [lo_lo, lo_hi] = self.lo.limbs();
+        let [hi_lo, hi_hi] = self.hi.limbs();
+        vec![lo_lo, lo_hi, hi_lo, hi_hi, &self.k0, &self.k1_lo]
+    }
+
+    /// Writes `a`, `b` and their product to row `row` and returns the low and
+    /// high 32 bits of the product
+    pub fn assign<F: Field>(
+        &self,
+        trace: &mut Matrix<F>,
+        row: usize,
+        a: u32,
+        b: u32,
+    ) -> (u32, u32) {
+        let (a0, a1) = (u64::from(a & 0xFFFF), u64::from(a >> 16));
+        let (b0, b1) = (u64::from(b & 0xFFFF), u64::from(b >> 16));
+        let k0 = (a0 * b0) >> 16;
+        let k1 = (a0 * b1 + a1 * b0 + k0) >> 16;
+        let product = u64::from(a) * u64::from(b);
+        #[allow(clippy::cast_possible_truncation)]
+        let (lo, hi) = (product as u32, (product >> 32) as u32);
+        self.a.assign(trace, row, a);
+        self.b.assign(trace, row, b);
+        self.lo.assign(trace, row, lo);
+        self.hi.assign(trace, row, hi);
+        trace.0[self.k0.index()][row] = F::from(k0);
+        trace.0[self.k1_lo.index()][row] = F::from(k1 & 0xFFFF);
+        trace.0[self.k1_hi.index()][row] = F::from(k1 >> 16);
+        (lo, hi)
+    }
+}
+
+/// `x + y = z + carry * 2^32` with boolean carries
+fn add_constraints<
+    Fp: GpuFftField + FftField,
+    Fq: StarkExtensionOf<Fp>,
+    C: ExecutionTraceColumn,
+>(
+    x: &U32<C>,
+    y: &U32<C>,
+    z: &U32<C>,
+    carry_lo: &C,
+    carry: &C,
+) -> Vec<Expr<Item<Fp, Fq>>> {
+    let base = limb_base::<Fp, Fq>;
+    vec![
+        x.lo.curr() + y.lo.curr() - z.lo.curr() - carry_lo.curr() * base(),
+        x.hi.curr() + y.hi.curr() + carry_lo.curr() - z.hi.curr() - carry.curr() * base(),
+        assert_boolean(carry_lo.curr()),
+        assert_boolean(carry.curr()),
+    ]
+}
+
+/// Returns constraints that `value` is less than `2^bits.len()` by decomposing
+/// it into boolean columns (least significant first). Costs a column per bit.
+pub fn bits_range_check<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    value: Expr<Item<Fp, Fq>>,
+    bits: &[impl ExecutionTraceColumn],
+) -> Vec<Expr<Item<Fp, Fq>>> {
+    let sum = bits
+        .iter()
+        .enumerate()
+        .map(|(i, bit)| bit.curr() * constant(1 << i))
+        .sum::<Expr<_>>();
+    bits.iter()
+        .map(|bit| assert_boolean(bit.curr()))
+        .chain([value - sum])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::U32Add;
+    use super::U32Mul;
+    use super::U32Sub;
+    use super::U32;
+    use crate::constraints::AlgebraicItem;
+    use crate::constraints::Constraint;
+    use crate::constraints::ExecutionTraceColumn;
+    use crate::utils::FieldVariant;
+    use crate::utils::GpuAllocator;
+    use crate::Matrix;
+    use alloc::vec::Vec;
+    use ark_ff::PrimeField;
+    use ark_ff::Zero;
+    use ark_std::rand::Rng;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    const fn u32_at(i: usize) -> U32<usize> {
+        U32 { lo: i, hi: i + 1 }
+    }
+
+    const ADD: U32Add<usize> = U32Add {
+        a: u32_at(0),
+        b: u32_at(2),
+        c: u32_at(4),
+        carry_lo: 6,
+        carry: 7,
+    };
+
+    const SUB: U32Sub<usize> = U32Sub {
+        a: u32_at(8),
+        b: u32_at(10),
+        c: u32_at(12),
+        borrow_lo: 14,
+        borrow: 15,
+    };
+
+    const MUL: U32Mul<usize> = U32Mul {
+        a: u32_at(16),
+        b: u32_at(18),
+        lo: u32_at(20),
+        hi: u32_at(22),
+        k0: 24,
+        k1_lo: 25,
+        k1_hi: 26,
+    };
+
+    const NUM_COLUMNS: usize = 27;
+
+    fn constraints_hold(trace: &Matrix<Fp>, row: usize) -> bool {
+        let constraints = [
+            ADD.constraints::<Fp, Fp>(),
+            SUB.constraints(),
+            MUL.constraints(),
+        ]
+        .concat();
+        constraints.into_iter().all(|expr| {
+            let eval = Constraint::new(expr).check(&mut |leaf| match *leaf {
+                AlgebraicItem::Constant(v) => v,
+                AlgebraicItem::Trace(column, 0) => FieldVariant::Fp(trace.0[column][row]),
+                _ => unreachable!(),
+            });
+            eval.unwrap().is_zero()
+        })
+    }
+
+    fn in_range(trace: &Matrix<Fp>, row: usize) -> bool {
+        let checked = [
+            ADD.range_checked(),
+            SUB.range_checked(),
+            MUL.range_checked(),
+        ]
+        .concat();
+        checked
+            .into_iter()
+            .all(|column| trace.0[column.index()][row].into_bigint().0[0] < 1 << 16)
+    }
+
+    #[test]
+    fn u32_operations_satisfy_constraints() {
+        let mut rng = ark_std::test_rng();
+        let num_rows = 64;
+        let columns = (0..NUM_COLUMNS)
+            .map(|_| vec![Fp::zero(); num_rows].to_vec_in(GpuAllocator))
+            .collect::<Vec<_>>();
+        let mut trace = Matrix::new(columns);
+        let edge_cases = [(0, 0), (u32::MAX, 1), (u32::MAX, u32::MAX), (0xFFFF, 1)];
+        for row in 0..num_rows {
+            let (a, b) = edge_cases.get(row).copied().unwrap_or_else(|| rng.gen());
+            assert_eq!(ADD.assign(&mut trace, row, a, b), a.wrapping_add(b));
+            assert_eq!(SUB.assign(&mut trace, row, a, b), a.wrapping_sub(b));
+            let product = u64::from(a) * u64::from(b);
+            let (lo, hi) = MUL.assign(&mut trace, row, a, b);
+            assert_eq!(u64::from(lo) + (u64::from(hi) << 32), product);
+            assert!(constraints_hold(&trace, row) && in_range(&trace, row));
+        }
+
+        // wrong sum
+        ADD.c.assign(&mut trace, 0, 1);
+        assert!(!constraints_hold(&trace, 0));
+        // 0xFFFF + 1 with the carry folded into an out of range low limb
+        ADD.assign(&mut trace, 1, 0xFFFF, 1);
+        trace.0[ADD.c.lo][1] = Fp::from(1u32 << 16);
+        trace.0[ADD.c.hi][1] = Fp::zero();
+        trace.0[ADD.carry_lo][1] = Fp::zero();
+        assert!(constraints_hold(&trace, 1));
+        assert!(!in_range(&trace, 1));
+    }
+}
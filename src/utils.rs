@@ -6,6 +6,7 @@ use ark_ff::Field;
 use ark_ff::One;
 use ark_ff::PrimeField;
 use ark_ff::Zero;
+use ark_poly::domain::DomainCoeff;
 use ark_poly::domain::Radix2EvaluationDomain;
 use ark_poly::EvaluationDomain;
 use ark_serialize::CanonicalDeserialize;
@@ -21,6 +22,10 @@ use core::ops::Div;
 use core::ops::Mul;
 use core::ops::Neg;
 use core::ptr::NonNull;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+use ministark_gpu::GpuFftField;
+use ministark_gpu::GpuField;
 use num_traits::Pow;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
@@ -50,16 +55,107 @@ impl<'a> Drop for Timer<'a> {
     }
 }
 
+/// Environment variable used to override [`DEFAULT_GPU_MIN_DOMAIN_SIZE`]
+pub const GPU_MIN_DOMAIN_SIZE_ENV: &str = "MINISTARK_GPU_MIN_DOMAIN_SIZE";
+
+/// Smallest domain the GPU can handle. Matches `GpuFft::MIN_SIZE`.
+pub const DEFAULT_GPU_MIN_DOMAIN_SIZE: usize = 2048;
+
+// zero means uninitialized
+static GPU_MIN_DOMAIN_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the minimum domain size at which FFTs are dispatched to the GPU.
+/// Smaller domains run on the CPU. On first use the value is read from the
+/// `MINISTARK_GPU_MIN_DOMAIN_SIZE` environment variable and falls back to
+/// [`DEFAULT_GPU_MIN_DOMAIN_SIZE`].
+pub fn gpu_min_domain_size() -> usize {
+    match GPU_MIN_DOMAIN_SIZE.load(Ordering::Relaxed) {
+        0 => {
+            let size = std::env::var(GPU_MIN_DOMAIN_SIZE_ENV)
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(DEFAULT_GPU_MIN_DOMAIN_SIZE);
+            set_gpu_min_domain_size(size)
+        }
+        size => size,
+    }
+}
+
+/// Sets the minimum domain size at which FFTs are dispatched to the GPU.
+/// Values below [`DEFAULT_GPU_MIN_DOMAIN_SIZE`] are raised to it. Returns the
+/// value that was set.
+pub fn set_gpu_min_domain_size(size: usize) -> usize {
+    let size = size.max(DEFAULT_GPU_MIN_DOMAIN_SIZE);
+    GPU_MIN_DOMAIN_SIZE.store(size, Ordering::Relaxed);
+    size
+}
+
+/// Benchmarks CPU and GPU FFTs over increasing domain sizes and sets
+/// the minimum GPU domain size to the first size where the GPU is faster.
+/// Intended to be called once at startup. Returns the value that was set.
+#[cfg(feature = "gpu")]
+pub fn calibrate_gpu_min_domain_size<F: ministark_gpu::GpuFftField + FftField>() -> usize {
+    use ministark_gpu::prelude::GpuFft;
+    use std::time::Instant;
+    const MAX_LOG_SIZE: u32 = 22;
+
+    let time_gpu = |domain: Radix2EvaluationDomain<F>| {
+        let mut evals = vec![F::one(); domain.size()].to_vec_in(GpuAllocator);
+        let start = Instant::now();
+        let mut fft = GpuFft::from(domain);
+        fft.encode(&mut evals);
+        fft.execute();
+        start.elapsed()
+    };
+
+    let time_cpu = |domain: Radix2EvaluationDomain<F>| {
+        let mut evals = vec![F::one(); domain.size()];
+        let start = Instant::now();
+        domain.fft_in_place(&mut evals);
+        start.elapsed()
+    };
+
+    // warm up the GPU so shader compilation isn't included in the measurements
+    let min_log_size = DEFAULT_GPU_MIN_DOMAIN_SIZE.ilog2();
+    time_gpu(Radix2EvaluationDomain::new(1 << min_log_size).unwrap());
+
+    for log_size in min_log_size..=MAX_LOG_SIZE {
+        let domain = Radix2EvaluationDomain::new(1 << log_size).unwrap();
+        if time_gpu(domain) < time_cpu(domain) {
+            return set_gpu_min_domain_size(1 << log_size);
+        }
+    }
+
+    set_gpu_min_domain_size(usize::MAX)
+}
+
+/// Splits `source` into `RADIX` consecutive parts and returns the rows whose
+/// `j`-th value is from part `j` i.e. row `i` holds `source[i + j * n]` where
+/// `n` is the number of rows. Sources whose length isn't a multiple of `RADIX`
+/// are padded with `T::default()` (see [`interleave_padded`]).
 pub fn interleave<T: Copy + Send + Sync + Default, const RADIX: usize>(
     source: &[T],
 ) -> Vec<[T; RADIX]> {
-    let n = source.len() / RADIX;
-    let mut res = vec![[T::default(); RADIX]; n];
+    interleave_padded::<T, RADIX>(source, T::default())
+}
+
+/// Interleaves `source` like [`interleave`]. If the length of `source` isn't a
+/// multiple of `RADIX` it's padded at the end with `padding` so there are
+/// `ceil(len / RADIX)` rows and the values missing from the last part are
+/// `padding`.
+pub fn interleave_padded<T: Copy + Send + Sync, const RADIX: usize>(
+    source: &[T],
+    padding: T,
+) -> Vec<[T; RADIX]> {
+    let n = source.len().div_ceil(RADIX);
+    let mut res = vec![[padding; RADIX]; n];
     ark_std::cfg_iter_mut!(res)
         .enumerate()
         .for_each(|(i, element)| {
-            for j in 0..RADIX {
-                element[j] = source[i + j * n];
+            for (j, value) in element.iter_mut().enumerate() {
+                if let Some(&v) = source.get(i + j * n) {
+                    *value = v;
+                }
             }
         });
     res
@@ -72,13 +168,209 @@ pub fn interleave<T: Copy + Send + Sync + Default, const RADIX: usize>(
 //     println!()
 // }
 
-/// Rounds the input value up the the nearest power of two
-pub const fn ceil_power_of_two(value: usize) -> usize {
-    if value.is_power_of_two() {
-        value
-    } else {
-        value.next_power_of_two()
+// re-exported for compatibility. See [`crate::domain`]
+pub use crate::domain::ceil_power_of_two;
+pub use crate::domain::max_radix2_domain_size;
+
+/// Domains at least this large are transformed on the CPU with the four-step
+/// FFT. Below this size the values fit in L2 cache and arkworks' radix-2 FFT is
+/// faster.
+pub const FOUR_STEP_FFT_MIN_DOMAIN_SIZE: usize = 1 << 20;
+
+/// Evaluates coefficients over the domain in place with the four-step FFT.
+///
+/// The values are viewed as a row-major `n1 x n2` matrix with `n1 * n2 = n`.
+/// The FFT is computed with `n2` FFTs of size `n1`, a twiddle multiplication
+/// and `n1` FFTs of size `n2`. Every small FFT runs on contiguous memory that
+/// fits in cache which avoids the cache thrashing of a single large radix-2
+/// FFT.
+pub fn four_step_fft_in_place<F: FftField, T: DomainCoeff<F>>(
+    domain: Radix2EvaluationDomain<F>,
+    values: &mut [T],
+) {
+    assert_eq!(domain.size(), values.len());
+    distribute_powers(values, domain.coset_offset());
+    four_step(values, domain.group_gen(), |sub_domain, row| {
+        sub_domain.fft_in_place(row);
+    });
+}
+
+/// Interpolates evaluations over the domain in place with the four-step FFT.
+/// See [`four_step_fft_in_place`].
+pub fn four_step_ifft_in_place<F: FftField, T: DomainCoeff<F>>(
+    domain: Radix2EvaluationDomain<F>,
+    values: &mut [T],
+) {
+    assert_eq!(domain.size(), values.len());
+    // each IFFT of size m normalizes by 1/m so the result is normalized by 1/n
+    four_step(values, domain.group_gen_inv(), |sub_domain, row| {
+        sub_domain.ifft_in_place(row);
+    });
+    distribute_powers(values, domain.coset_offset_inv());
+}
+
+fn four_step<F: FftField, T: DomainCoeff<F>>(
+    values: &mut [T],
+    root: F,
+    transform: impl Fn(Radix2EvaluationDomain<F>, &mut Vec<T>) + Send + Sync,
+) {
+    use ministark_gpu::utils::transpose;
+    let n = values.len();
+    let n1 = 1 << (n.ilog2() / 2);
+    let n2 = n / n1;
+
+    let transform_rows = |values: &mut [T], row_len: usize| {
+        let sub_domain = Radix2EvaluationDomain::new(row_len).unwrap();
+        // arkworks only transforms vectors so each thread reuses one buffer
+        // rather than allocating a vector per row
+        let transform_row = |buffer: &mut Vec<T>, row: &mut [T]| {
+            buffer.clear();
+            buffer.extend_from_slice(row);
+            transform(sub_domain, buffer);
+            row.copy_from_slice(buffer);
+        };
+        #[cfg(feature = "parallel")]
+        values
+            .par_chunks_mut(row_len)
+            .for_each_init(|| Vec::with_capacity(row_len), transform_row);
+        #[cfg(not(feature = "parallel"))]
+        {
+            let mut buffer = Vec::with_capacity(row_len);
+            for row in values.chunks_mut(row_len) {
+                transform_row(&mut buffer, row);
+            }
+        }
+    };
+
+    let mut scratch = vec![T::zero(); n];
+    // 1. FFTs of size n1 down the columns
+    transpose(values, &mut scratch, n1, n2);
+    transform_rows(&mut scratch, n1);
+    transpose(&scratch, values, n2, n1);
+    // 2. multiply row i and column j with root^(i * j)
+    ark_std::cfg_chunks_mut!(values, n2)
+        .enumerate()
+        .for_each(|(i, row)| {
+            let row_root = root.pow([i as u64]);
+            let mut twiddle = F::one();
+            for value in row {
+                *value *= twiddle;
+                twiddle *= row_root;
+            }
+        });
+    // 3. FFTs of size n2 along the rows
+    transform_rows(values, n2);
+    // 4. the result is read column by column
+    transpose(values, &mut scratch, n1, n2);
+    values.copy_from_slice(&scratch);
+}
+
+/// Multiplies the `i`-th value by `g^i`
+fn distribute_powers<F: FftField, T: DomainCoeff<F>>(values: &mut [T], g: F) {
+    if g.is_one() {
+        return;
     }
+
+    #[cfg(feature = "parallel")]
+    let chunk_size = core::cmp::max(values.len() / rayon::current_num_threads(), 1024);
+    #[cfg(not(feature = "parallel"))]
+    let chunk_size = values.len();
+
+    ark_std::cfg_chunks_mut!(values, chunk_size)
+        .enumerate()
+        .for_each(|(i, chunk)| {
+            let mut pow = g.pow([(i * chunk_size) as u64]);
+            for value in chunk {
+                *value *= pow;
+                pow *= g;
+            }
+        });
+}
+
+/// Evaluates `coeffs` over the domain and returns the bit-reversed
+/// evaluations split into `num_cols` columns where row `i` holds evaluations
+/// `i * num_cols..(i + 1) * num_cols` i.e. the layout of a FRI layer (see
+/// [`crate::Matrix::from_arrays`]). Evaluations are written into their column
+/// directly so there's no vector of evaluations to copy into the columns.
+///
+/// The FFT of size `n = num_cols * m` is split into `num_cols` FFTs of size `m`
+/// over the coefficients `j, j + num_cols, j + 2 * num_cols, ...` and DFTs of
+/// size `num_cols` across them. Afterwards buffer `k` holds the evaluations
+/// `k * m..(k + 1) * m` which, bit-reversed, are column `bit_reverse(k)`.
+///
+/// # Panics
+///
+/// Panics if `num_cols` isn't a power of two that divides the domain size or
+/// if there are more coefficients than the domain size
+pub fn fft_bit_reversed_columns<F: FftField, T: DomainCoeff<F>>(
+    domain: Radix2EvaluationDomain<F>,
+    coeffs: &[T],
+    num_cols: usize,
+) -> Vec<GpuVec<T>> {
+    use ministark_gpu::utils::bit_reverse;
+    use ministark_gpu::utils::bit_reverse_index;
+    let n = domain.size();
+    assert!(num_cols.is_power_of_two() && n.is_multiple_of(num_cols));
+    assert!(coeffs.len() <= n);
+    if num_cols == 1 {
+        let mut evaluations = domain.fft(coeffs);
+        bit_reverse(&mut evaluations);
+        return vec![vec_to_gpu_vec(evaluations)];
+    }
+    let m = n / num_cols;
+    let sub_domain = Radix2EvaluationDomain::<F>::new(m).unwrap();
+    let offset = domain.coset_offset();
+    let generator = domain.group_gen();
+
+    // 1. FFTs of size m over every `num_cols`-th coefficient (scaled by powers of
+    // the offset) followed by the twiddles g^(j * i)
+    let offset_stride = offset.pow([num_cols as u64]);
+    let mut buffers = ark_std::cfg_into_iter!(0..num_cols)
+        .map(|j| {
+            let mut buffer = Vec::with_capacity_in(m, GpuAllocator);
+            buffer.resize(m, T::zero());
+            // arkworks only takes a Vec with the global allocator (see `Matrix`)
+            let mut buffer = gpu_vec_to_vec(buffer);
+            let mut scale = offset.pow([j as u64]);
+            for (value, coeff) in zip(&mut buffer, coeffs.iter().skip(j).step_by(num_cols)) {
+                *value = *coeff;
+                *value *= scale;
+                scale *= offset_stride;
+            }
+            sub_domain.fft_in_place(&mut buffer);
+            distribute_powers(&mut buffer, generator.pow([j as u64]));
+            buffer
+        })
+        .collect::<Vec<Vec<T>>>();
+
+    // 2. DFTs of size `num_cols` across the buffers. Evaluation `k * m + i` is
+    // the sum of `ω^(j * k) * buffers[j][i]` where ω has order `num_cols`.
+    let root = generator.pow([m as u64]);
+    let roots = (0..num_cols)
+        .map(|i| root.pow([i as u64]))
+        .collect::<Vec<F>>();
+    let mut sums = vec![T::zero(); num_cols];
+    for i in 0..m {
+        for (k, sum) in sums.iter_mut().enumerate() {
+            *sum = T::zero();
+            for (j, buffer) in buffers.iter().enumerate() {
+                let mut term = buffer[i];
+                term *= roots[j * k % num_cols];
+                *sum += term;
+            }
+        }
+        for (buffer, sum) in zip(&mut buffers, &sums) {
+            buffer[i] = *sum;
+        }
+    }
+
+    // 3. buffer `k` becomes column `bit_reverse(k)`
+    let mut columns = (0..num_cols).map(|_| None).collect::<Vec<_>>();
+    for (k, mut buffer) in buffers.into_iter().enumerate() {
+        bit_reverse(&mut buffer);
+        columns[bit_reverse_index(num_cols, k)] = Some(vec_to_gpu_vec(buffer));
+    }
+    columns.into_iter().map(Option::unwrap).collect()
 }
 
 // from arkworks
@@ -118,6 +410,77 @@ pub fn fill_vanishing_polynomial<F: FftField>(
         });
 }
 
+/// Divides evaluations over `eval_domain` (in natural order) by the vanishing
+/// polynomial of `vanish_domain`. The vanishing polynomial only takes
+/// `eval_domain.size() / vanish_domain.size()` distinct values over
+/// `eval_domain` so only those are inverted. Large domains are divided on the
+/// GPU (see [`gpu_min_domain_size`]).
+pub fn divide_by_vanishing_polynomial<F: GpuFftField<FftField = F> + FftField, T>(
+    evals: &mut [T],
+    vanish_domain: &Radix2EvaluationDomain<F>,
+    eval_domain: &Radix2EvaluationDomain<F>,
+) where
+    T: GpuField<FftField = F> + Field + for<'a> core::ops::MulAssign<&'a F>,
+{
+    assert_eq!(evals.len(), eval_domain.size());
+
+    #[cfg(feature = "gpu")]
+    if evals.len() >= gpu_min_domain_size() {
+        ministark_gpu::plan::divide_by_vanishing(evals, *vanish_domain, *eval_domain);
+        return;
+    }
+
+    // x^n for x in the evaluation domain is offset^n * (g^n)^i
+    let n = vanish_domain.size();
+    let period = eval_domain.size() / n;
+    let scaled_eval_offset = eval_domain.coset_offset().pow([n as u64]);
+    let scaled_eval_generator = eval_domain.group_gen().pow([n as u64]);
+    let scaled_vanish_offset = vanish_domain.coset_offset_pow_size();
+    let mut inv_vanishing = (0..period)
+        .scan(scaled_eval_offset, |acc, _| {
+            let v = *acc - scaled_vanish_offset;
+            *acc *= scaled_eval_generator;
+            Some(v)
+        })
+        .collect::<Vec<F>>();
+    ark_ff::batch_inversion(&mut inv_vanishing);
+    assert!(
+        !inv_vanishing.iter().any(F::is_zero),
+        "evaluation domain intersects the vanishing domain"
+    );
+
+    ark_std::cfg_iter_mut!(evals)
+        .enumerate()
+        .for_each(|(i, eval)| *eval *= &inv_vanishing[i % period]);
+}
+
+/// Evaluates the polynomial with evaluations `evals` over `domain` (in natural
+/// order) at `point` with the barycentric formula. Useful for evaluating a
+/// codeword (e.g. a FRI remainder) without interpolating it first. Uses a
+/// single field inversion.
+pub fn barycentric_evaluate<F: FftField, T: Field + From<F>>(
+    domain: &Radix2EvaluationDomain<F>,
+    evals: &[T],
+    point: T,
+) -> T {
+    assert_eq!(domain.size(), evals.len());
+    let xs = domain.elements().map(T::from).collect::<Vec<T>>();
+    if let Some(i) = xs.iter().position(|&x| x == point) {
+        return evals[i];
+    }
+
+    // p(z) = Z(z) / (n * c) * Σ e_i * x_i / (z - x_i) where Z(x) = x^n - c
+    let mut denominators = xs.iter().map(|&x| point - x).collect::<Vec<T>>();
+    ark_ff::batch_inversion(&mut denominators);
+    let sum = zip(zip(evals, &xs), denominators)
+        .map(|((&eval, &x), denominator)| eval * x * denominator)
+        .sum::<T>();
+    let vanishing = evaluate_vanishing_polynomial(domain, point);
+    let c = T::from(domain.coset_offset_pow_size());
+    let n = T::from(F::from(domain.size() as u64));
+    vanishing * sum / (n * c)
+}
+
 // taken from arkworks-rs
 /// Horner's method for polynomial evaluation
 #[inline]
@@ -460,7 +823,7 @@ unsafe impl Allocator for GpuAllocator {
 }
 
 pub fn gpu_vec_to_vec<T>(v: GpuVec<T>) -> Vec<T> {
-    let (ptr, length, capacity) = v.into_raw_parts();
+    let (ptr, length, capacity, _) = v.into_raw_parts_with_alloc();
     unsafe { Vec::from_raw_parts(ptr, length, capacity) }
 }
 
@@ -469,6 +832,154 @@ pub fn vec_to_gpu_vec<T>(v: Vec<T>) -> GpuVec<T> {
     unsafe { Vec::from_raw_parts_in(ptr, length, capacity, GpuAllocator) }
 }
 
+/// Running sums and products of the values of a column e.g. the columns of
+/// permutation and lookup arguments. The scans are inclusive so the `i`-th
+/// value becomes the sum (or product) of values `0..=i`. Long columns are
+/// scanned on the GPU (see [`gpu_min_domain_size`]).
+pub trait Scan {
+    fn scan_sum(&mut self);
+
+    fn scan_product(&mut self);
+}
+
+impl<F: GpuField + Field> Scan for GpuVec<F> {
+    fn scan_sum(&mut self) {
+        #[cfg(feature = "gpu")]
+        if self.len() >= gpu_min_domain_size() {
+            ministark_gpu::plan::scan_sum(self);
+            return;
+        }
+
+        scan_in_place(self, F::zero(), |a, b| a + b);
+    }
+
+    fn scan_product(&mut self) {
+        #[cfg(feature = "gpu")]
+        if self.len() >= gpu_min_domain_size() {
+            ministark_gpu::plan::scan_product(self);
+            return;
+        }
+
+        scan_in_place(self, F::one(), |a, b| a * b);
+    }
+}
+
+/// Element-wise operations on columns so trace builders and composition code
+/// don't have to loop over every value. Long columns are processed on the
+/// GPU (see [`gpu_min_domain_size`]).
+pub trait ColumnOps<F> {
+    /// Adds `other` to the column element-wise
+    fn add_in_place(&mut self, other: &Self);
+
+    /// Multiplies the column by `other` element-wise
+    fn mul_in_place(&mut self, other: &Self);
+
+    /// Replaces each value `v` with `mul * v + add`
+    fn affine_in_place(&mut self, mul: F, add: F);
+
+    /// Raises each value to the power `exponent`
+    fn pow_in_place(&mut self, exponent: usize);
+
+    /// Inverts each value. Zeros are left as zero.
+    fn inverse_in_place(&mut self);
+}
+
+impl<F: GpuField + Field> ColumnOps<F> for GpuVec<F> {
+    fn add_in_place(&mut self, other: &Self) {
+        assert_eq!(self.len(), other.len());
+
+        #[cfg(feature = "gpu")]
+        if self.len() >= gpu_min_domain_size() {
+            ministark_gpu::plan::add_assign(self, other);
+            return;
+        }
+
+        ark_std::cfg_iter_mut!(self)
+            .zip(&**other)
+            .for_each(|(v, other)| *v += other);
+    }
+
+    fn mul_in_place(&mut self, other: &Self) {
+        assert_eq!(self.len(), other.len());
+
+        #[cfg(feature = "gpu")]
+        if self.len() >= gpu_min_domain_size() {
+            ministark_gpu::plan::mul_assign(self, other);
+            return;
+        }
+
+        ark_std::cfg_iter_mut!(self)
+            .zip(&**other)
+            .for_each(|(v, other)| *v *= other);
+    }
+
+    fn affine_in_place(&mut self, mul: F, add: F) {
+        #[cfg(feature = "gpu")]
+        if self.len() >= gpu_min_domain_size() {
+            ministark_gpu::plan::affine_assign(self, mul, add);
+            return;
+        }
+
+        ark_std::cfg_iter_mut!(self).for_each(|v| *v = mul * *v + add);
+    }
+
+    fn pow_in_place(&mut self, exponent: usize) {
+        #[cfg(feature = "gpu")]
+        if self.len() >= gpu_min_domain_size() {
+            ministark_gpu::plan::exp_in_place(self, exponent);
+            return;
+        }
+
+        ark_std::cfg_iter_mut!(self).for_each(|v| *v = v.pow([exponent as u64]));
+    }
+
+    fn inverse_in_place(&mut self) {
+        #[cfg(feature = "gpu")]
+        if self.len() >= gpu_min_domain_size() {
+            ministark_gpu::plan::inverse_in_place(self);
+            return;
+        }
+
+        ark_ff::batch_inversion(self);
+    }
+}
+
+/// Inclusive scan of `values` with an associative `op`. Chunks are scanned in
+/// parallel and then offset by the totals of the chunks before them.
+fn scan_in_place<F: Field>(values: &mut [F], identity: F, op: impl Fn(F, F) -> F + Sync) {
+    #[cfg(feature = "parallel")]
+    let chunk_size = core::cmp::max(values.len() / rayon::current_num_threads(), 1024);
+    #[cfg(not(feature = "parallel"))]
+    let chunk_size = values.len().max(1);
+
+    let totals = ark_std::cfg_chunks_mut!(values, chunk_size)
+        .map(|chunk| {
+            let mut acc = identity;
+            for value in chunk {
+                acc = op(acc, *value);
+                *value = acc;
+            }
+            acc
+        })
+        .collect::<Vec<F>>();
+    let offsets = totals
+        .iter()
+        .scan(identity, |acc, &total| {
+            let offset = *acc;
+            *acc = op(*acc, total);
+            Some(offset)
+        })
+        .collect::<Vec<F>>();
+    ark_std::cfg_chunks_mut!(values, chunk_size)
+        .zip(offsets)
+        .skip(1)
+        .for_each(|(chunk, offset)| {
+            for value in chunk {
+                *value = op(offset, *value);
+            }
+        });
+}
+
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
 mod page_aligned_allocator {
     use alloc::alloc::Global;
@@ -576,8 +1087,10 @@ impl<D: digest::Digest> CanonicalDeserialize for SerdeOutput<D> {
         compress: ark_serialize::Compress,
         validate: ark_serialize::Validate,
     ) -> Result<Self, ark_serialize::SerializationError> {
-        let bytes = Vec::deserialize_with_mode(reader, compress, validate)?;
-        Ok(Self(digest::Output::<D>::from_iter(bytes)))
+        let bytes = Vec::<u8>::deserialize_with_mode(reader, compress, validate)?;
+        let hash = digest::Output::<D>::from_exact_iter(bytes);
+        hash.map(Self)
+            .ok_or(ark_serialize::SerializationError::InvalidData)
     }
 }
 
@@ -595,6 +1108,41 @@ impl<D: digest::Digest> DerefMut for SerdeOutput<D> {
     }
 }
 
+#[cfg(any(feature = "service", feature = "ffi"))]
+pub(crate) use executor::block_on;
+
+#[cfg(any(feature = "service", feature = "ffi"))]
+mod executor {
+    use alloc::sync::Arc;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::Context;
+    use core::task::Poll;
+    use std::task::Wake;
+    use std::thread::Thread;
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Runs a future to completion on the current thread
+    pub fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Arc::new(ThreadWaker(std::thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+}
+
 pub mod tests {
     use super::GpuAllocator;
     use crate::Matrix;
@@ -652,4 +1200,201 @@ pub mod tests {
         col.resize_with(n, || if rng.gen() { v1 } else { v2 });
         Matrix::new(vec![col])
     }
+
+    #[test]
+    fn fft_columns_match_chunked_bit_reversed_evaluations() {
+        use super::fft_bit_reversed_columns;
+        use ark_ff::FftField;
+        use ark_ff::UniformRand;
+        use ark_poly::EvaluationDomain;
+        use ark_poly::Radix2EvaluationDomain;
+        use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+        use ministark_gpu::utils::bit_reverse;
+
+        let mut rng = ark_std::test_rng();
+        let domain = Radix2EvaluationDomain::new_coset(256, Fp::GENERATOR).unwrap();
+        // fewer coefficients than the domain size like a low degree codeword
+        let coeffs = (0..100).map(|_| Fp::rand(&mut rng)).collect::<Vec<_>>();
+        let mut evals = domain.fft(&coeffs);
+        bit_reverse(&mut evals);
+
+        for num_cols in [1, 2, 4, 8, 16] {
+            let columns = fft_bit_reversed_columns(domain, &coeffs, num_cols);
+
+            assert_eq!(num_cols, columns.len());
+            for (i, row) in evals.chunks(num_cols).enumerate() {
+                for (j, eval) in row.iter().enumerate() {
+                    assert_eq!(*eval, columns[j][i]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn four_step_fft_matches_radix2_fft() {
+        use super::four_step_fft_in_place;
+        use super::four_step_ifft_in_place;
+        use ark_ff::FftField;
+        use ark_ff::UniformRand;
+        use ark_poly::EvaluationDomain;
+        use ark_poly::Radix2EvaluationDomain;
+        use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+        let mut rng = ark_std::test_rng();
+        // odd and even log sizes split into square and non-square matrices
+        for log_n in [10, 11] {
+            let domain = Radix2EvaluationDomain::new_coset(1 << log_n, Fp::GENERATOR).unwrap();
+            let coeffs = (0..domain.size())
+                .map(|_| Fp::rand(&mut rng))
+                .collect::<Vec<_>>();
+
+            let mut evals = coeffs.clone();
+            four_step_fft_in_place(domain, &mut evals);
+            assert_eq!(domain.fft(&coeffs), evals);
+
+            four_step_ifft_in_place(domain, &mut evals);
+            assert_eq!(coeffs, evals);
+        }
+    }
+
+    #[test]
+    fn divide_by_vanishing_polynomial_matches_division() {
+        use super::divide_by_vanishing_polynomial;
+        use super::evaluate_vanishing_polynomial;
+        use ark_ff::FftField;
+        use ark_ff::UniformRand;
+        use ark_poly::EvaluationDomain;
+        use ark_poly::Radix2EvaluationDomain;
+        use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+        let mut rng = ark_std::test_rng();
+        let vanish_domain = Radix2EvaluationDomain::<Fp>::new(256).unwrap();
+        let eval_domain = Radix2EvaluationDomain::new_coset(1024, Fp::GENERATOR).unwrap();
+        let evals = (0..eval_domain.size())
+            .map(|_| Fp::rand(&mut rng))
+            .collect::<Vec<_>>();
+
+        let mut quotient = evals.clone();
+        divide_by_vanishing_polynomial(&mut quotient, &vanish_domain, &eval_domain);
+
+        for ((eval, quotient), x) in evals.iter().zip(quotient).zip(eval_domain.elements()) {
+            let vanishing = evaluate_vanishing_polynomial(&vanish_domain, x);
+            assert_eq!(*eval, quotient * vanishing);
+        }
+    }
+
+    #[test]
+    fn barycentric_evaluate_matches_horner() {
+        use super::barycentric_evaluate;
+        use super::horner_evaluate;
+        use ark_ff::FftField;
+        use ark_ff::UniformRand;
+        use ark_poly::EvaluationDomain;
+        use ark_poly::Radix2EvaluationDomain;
+        use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+        let mut rng = ark_std::test_rng();
+        let domain = Radix2EvaluationDomain::new_coset(64, Fp::GENERATOR).unwrap();
+        let coeffs = (0..domain.size())
+            .map(|_| Fp::rand(&mut rng))
+            .collect::<Vec<_>>();
+        let evals = domain.fft(&coeffs);
+        let point = Fp::rand(&mut rng);
+
+        assert_eq!(
+            horner_evaluate(&coeffs, &point),
+            barycentric_evaluate(&domain, &evals, point)
+        );
+        assert_eq!(
+            evals[5],
+            barycentric_evaluate(&domain, &evals, domain.element(5))
+        );
+    }
+}
+
+#[cfg(test)]
+mod interleave_tests {
+    use super::interleave;
+    use super::interleave_padded;
+
+    #[test]
+    fn interleave_pads_partial_chunks() {
+        let source = [0, 1, 2, 3, 4, 5, 6];
+
+        assert_eq!(
+            [[0, 4], [1, 5], [2, 6], [3, 0]],
+            *interleave::<_, 2>(&source)
+        );
+        assert_eq!(
+            [[0, 2, 4, 6], [1, 3, 5, 9]],
+            *interleave_padded::<_, 4>(&source, 9)
+        );
+        assert_eq!(
+            [[0, 2, 4, 6], [1, 3, 5, 7]],
+            *interleave::<_, 4>(&[0, 1, 2, 3, 4, 5, 6, 7])
+        );
+    }
+}
+
+#[cfg(test)]
+mod scan_tests {
+    use super::GpuAllocator;
+    use super::Scan;
+    use alloc::vec::Vec;
+    use ark_ff::UniformRand;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    #[test]
+    fn scans_match_sequential_scans() {
+        let mut rng = ark_std::test_rng();
+        // spans several chunks with a partial chunk at the end
+        let values = (0..10_000).map(|_| Fp::rand(&mut rng)).collect::<Vec<_>>();
+
+        let mut sums = values.to_vec_in(GpuAllocator);
+        sums.scan_sum();
+        let mut products = values.to_vec_in(GpuAllocator);
+        products.scan_product();
+
+        let (mut sum, mut product) = (Fp::from(0u8), Fp::from(1u8));
+        for (i, value) in values.into_iter().enumerate() {
+            sum += value;
+            product *= value;
+            assert_eq!(sum, sums[i]);
+            assert_eq!(product, products[i]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod column_ops_tests {
+    use super::ColumnOps;
+    use super::GpuAllocator;
+    use alloc::vec::Vec;
+    use ark_ff::Field;
+    use ark_ff::UniformRand;
+    use ark_ff::Zero;
+    use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+    #[test]
+    fn column_ops_match_scalar_ops() {
+        let mut rng = ark_std::test_rng();
+        let mut lhs = (0..2048).map(|_| Fp::rand(&mut rng)).collect::<Vec<_>>();
+        lhs[3] = Fp::zero();
+        let rhs = (0..2048).map(|_| Fp::rand(&mut rng)).collect::<Vec<_>>();
+        let (mul, add) = (Fp::rand(&mut rng), Fp::rand(&mut rng));
+
+        let mut column = lhs.to_vec_in(GpuAllocator);
+        column.add_in_place(&rhs.to_vec_in(GpuAllocator));
+        column.mul_in_place(&rhs.to_vec_in(GpuAllocator));
+        column.affine_in_place(mul, add);
+        column.pow_in_place(5);
+        let mut inverses = lhs.to_vec_in(GpuAllocator);
+        inverses.inverse_in_place();
+
+        for i in 0..lhs.len() {
+            let expected = ((lhs[i] + rhs[i]) * rhs[i] * mul + add).pow([5]);
+            assert_eq!(expected, column[i]);
+            assert_eq!(lhs[i].inverse().unwrap_or_default(), inverses[i]);
+        }
+    }
 }
@@ -4,33 +4,190 @@ use crate::channel::VerifierChannelArtifacts;
 use crate::composer::DeepCompositionCoeffs;
 use crate::constraints::AlgebraicItem;
 use crate::constraints::CompositionItem;
+use crate::domain::lde_domain_size;
 use crate::fri;
-use crate::fri::FriVerifier;
+use crate::fri::FriTailProof;
+use crate::fri::LayerProof;
 use crate::hints::Hints;
+use crate::ldt::LdtVerifier;
+use crate::ldt::LowDegreeTestVerifier;
+use crate::merkle;
 use crate::merkle::MatrixMerkleTree;
-use crate::random::draw_multiple;
+use crate::merkle::MerkleTree;
+use crate::merkle::MerkleTreeDomain;
+use crate::positions::fold_positions;
+use crate::positions::leaf_rows;
+use crate::random::DrawTag;
 use crate::random::PublicCoin;
+use crate::random::ReseedTag;
+use crate::setup::VerifyingKey;
 use crate::stark::Stark;
 use crate::utils::horner_evaluate;
 use crate::utils::FieldVariant;
 use crate::Air;
 use crate::Proof;
+use crate::ProofOptions;
+use crate::ProofView;
 use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 use ark_ff::Field;
 use ark_ff::Zero;
 use ark_poly::EvaluationDomain;
+use ark_serialize::Compress;
+use ark_serialize::SerializationError;
 use ministark_gpu::utils::bit_reverse_index;
 use snafu::Snafu;
 
-#[allow(clippy::too_many_lines)]
 pub fn default_verify<S: Stark>(
     this: &S,
     proof: Proof<S>,
     required_security_bits: u32,
+) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+    verify_with_fri_tail(this, proof, FriTailProof::default(), required_security_bits)
+}
+
+/// Verifies a proof whose FRI tail was split off with
+/// [`Proof::split_fri_tail`]
+pub fn verify_with_fri_tail<S: Stark>(
+    this: &S,
+    proof: Proof<S>,
+    fri_tail: FriTailProof<S::Fq, S::MerkleTree>,
+    required_security_bits: u32,
+) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+    verify_impl(this, proof, fri_tail.layers, None, required_security_bits)
+}
+
+/// Verifies a proof of an AIR with preprocessed columns (see
+/// [`AirConfig::PREPROCESSED_BASE_COLUMNS`]). Openings of the preprocessed
+/// columns are checked against the commitment in `key`.
+pub fn verify_with_key<S: Stark>(
+    this: &S,
+    proof: Proof<S>,
+    key: &VerifyingKey<S>,
+    required_security_bits: u32,
+) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+    let fri_tail = FriTailProof::<S::Fq, S::MerkleTree>::default();
+    verify_impl(
+        this,
+        proof,
+        fri_tail.layers,
+        Some(key),
+        required_security_bits,
+    )
+}
+
+/// Verifies a proof directly from its serialization. The queries of each FRI
+/// layer are only decoded when the layer is checked.
+pub fn verify_view<S: Stark>(
+    this: &S,
+    view: ProofView<'_, S>,
+    required_security_bits: u32,
+) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+    let (proof, fri_layers) = view.into_parts();
+    verify_impl(this, proof, fri_layers, None, required_security_bits)
+}
+
+/// Verifies a proof serialized with [`Proof::serialize_streaming`] while
+/// reading it. Only one FRI layer is held in memory at a time.
+pub fn verify_from_reader<S: Stark>(
+    this: &S,
+    reader: impl ark_serialize::Read,
+    compress: Compress,
+    required_security_bits: u32,
+) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+    let (proof, mut fri_layers) = Proof::<S>::deserialize_streaming(reader, compress)
+        .map_err(|error| VerificationError::InvalidEncoding { error })?;
+    let result = verify_impl(this, proof, &mut fri_layers, None, required_security_bits);
+    // a layer that couldn't be read ends verification early
+    fri_layers
+        .finish()
+        .map_err(|error| VerificationError::InvalidEncoding { error })?;
+    result
+}
+
+/// Verifies a proof if its FRI folding schedule is accepted by `policy`
+pub fn verify_with_policy<S: Stark>(
+    this: &S,
+    proof: Proof<S>,
+    policy: &FoldingPolicy,
+    required_security_bits: u32,
+) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+    if !policy.accepts(&proof.options) {
+        return Err(VerificationError::UnacceptedFoldingSchedule {
+            folding_factor: proof.options.fri_folding_factor,
+            folds_per_layer: proof.options.fri_folds_per_layer,
+        });
+    }
+    this.verify(proof, required_security_bits)
+}
+
+/// FRI folding schedules a verifier accepts.
+///
+/// The verifier reads the folding factor and the number of folds per layer
+/// from the [`ProofOptions`] of each proof so proofs folded with different
+/// factors can be checked by the same verifier. The policy restricts this to
+/// the schedules an application supports. Security is checked separately with
+/// the required number of security bits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FoldingPolicy {
+    /// Accepted pairs of folding factor and folds per layer
+    schedules: Vec<(u8, u8)>,
+}
+
+impl FoldingPolicy {
+    /// Returns a policy that doesn't accept any schedule
+    pub const fn new() -> Self {
+        Self {
+            schedules: Vec::new(),
+        }
+    }
+
+    /// Accepts proofs that fold by `folding_factor` `folds_per_layer` times
+    /// between FRI layer commitments
+    pub fn accept(mut self, folding_factor: u8, folds_per_layer: u8) -> Self {
+        if !self.schedules.contains(&(folding_factor, folds_per_layer)) {
+            self.schedules.push((folding_factor, folds_per_layer));
+        }
+        self
+    }
+
+    /// Accepts the folding schedule of `options`
+    pub fn accept_options(self, options: &ProofOptions) -> Self {
+        self.accept(options.fri_folding_factor, options.fri_folds_per_layer)
+    }
+
+    /// Returns true if the folding schedule of `options` is accepted
+    pub fn accepts(&self, options: &ProofOptions) -> bool {
+        let schedule = (options.fri_folding_factor, options.fri_folds_per_layer);
+        self.schedules.contains(&schedule)
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+fn verify_impl<S: Stark>(
+    this: &S,
+    proof: Proof<S>,
+    fri_tail: impl IntoIterator<Item = LayerProof<S::Fq, S::MerkleTree>>,
+    verifying_key: Option<&VerifyingKey<S>>,
+    required_security_bits: u32,
 ) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
     use VerificationError::*;
 
+    if !proof.options.is_valid() {
+        return Err(InvalidProofOptions);
+    }
+
+    let trace_len = proof.trace_len;
+    let lde_blowup_factor = proof.options.lde_blowup_factor.into();
+    if lde_domain_size::<S::Fp>(trace_len, lde_blowup_factor).is_none() {
+        return Err(InvalidTraceLength { trace_len });
+    }
+
+    let values_per_leaf = proof.options.trace_values_per_leaf;
+    if !values_per_leaf.is_power_of_two() {
+        return Err(InvalidTraceValuesPerLeaf { values_per_leaf });
+    }
+
     if proof.security_level_bits() < required_security_bits {
         return Err(InvalidProofSecurity);
     }
@@ -38,41 +195,130 @@ pub fn default_verify<S: Stark>(
     let Proof {
         options,
         base_trace_commitment,
+        base_column_commitments,
         extension_trace_commitment,
         composition_trace_commitment,
         execution_trace_ood_evals,
         composition_trace_ood_evals,
         trace_queries,
         trace_len,
+        nonce,
+        air_fingerprint,
         fri_proof,
         pow_nonce,
         ..
     } = proof;
 
-    let air = Air::new(trace_len, this.get_public_inputs(), options);
+    let air = Air::try_new(trace_len, this.get_public_inputs(), options).ok_or(
+        InsufficientBlowupFactor {
+            lde_blowup_factor: options.lde_blowup_factor,
+        },
+    )?;
+    // a proof for another version of the AIR or with other parameters would
+    // otherwise fail with an unrelated error further down
+    let expected_fingerprint = air.fingerprint();
+    if air_fingerprint != expected_fingerprint {
+        return Err(AirFingerprintMismatch);
+    }
+    if verifying_key.is_some_and(|key| key.air_fingerprint != expected_fingerprint) {
+        return Err(IncompatibleVerifyingKey);
+    }
+    let separate_columns = S::AirConfig::SEPARATELY_COMMITTED_BASE_COLUMNS;
+    for (item, expected, actual) in [
+        (
+            "base column commitments",
+            separate_columns.len(),
+            base_column_commitments.len(),
+        ),
+        (
+            "execution trace ood evaluations",
+            air.trace_arguments().len(),
+            execution_trace_ood_evals.len(),
+        ),
+        (
+            "composition trace ood evaluations",
+            air.ce_blowup_factor(),
+            composition_trace_ood_evals.len(),
+        ),
+    ] {
+        if expected != actual {
+            return Err(TranscriptMismatch {
+                item,
+                expected,
+                actual,
+            });
+        }
+    }
+
+    // preprocessed columns are committed to by the verifying key
+    let preprocessed_columns = S::AirConfig::PREPROCESSED_BASE_COLUMNS;
+    let preprocessed_commitment = if preprocessed_columns.is_empty() {
+        None
+    } else {
+        let key = verifying_key.ok_or(MissingVerifyingKey)?;
+        if !key.is_compatible(trace_len, &options) {
+            return Err(IncompatibleVerifyingKey);
+        }
+        Some(&key.preprocessed_commitment)
+    };
+
+    // trace commitments are merkle caps (see
+    // `ProofOptions::with_merkle_cap_height`)
+    let lde_domain_size = air.trace_len() * air.lde_blowup_factor();
+    let rows_per_leaf = options.trace_rows_per_leaf(trace_len);
+    let num_trace_leaves = lde_domain_size / rows_per_leaf;
+    let cap_height = options.merkle_cap_height.into();
+    let cap_size = S::MerkleTree::cap_size(num_trace_leaves, cap_height);
+    let trace_caps = [&base_trace_commitment, &composition_trace_commitment]
+        .into_iter()
+        .chain(&base_column_commitments)
+        .chain(preprocessed_commitment)
+        .chain(&extension_trace_commitment);
+    for cap in trace_caps {
+        if cap.len() != cap_size {
+            return Err(TranscriptMismatch {
+                item: "trace commitment cap nodes",
+                expected: cap_size,
+                actual: cap.len(),
+            });
+        }
+    }
+
     let mut public_coin = this.gen_public_coin(&air);
+    air.reseed_with_domain_parameters(&mut public_coin);
+    if let Some(nonce) = &nonce {
+        public_coin.reseed_with_nonce(nonce);
+    }
 
-    public_coin.reseed_with_digest(&base_trace_commitment);
+    if let Some(commitment) = preprocessed_commitment {
+        public_coin.reseed_with_cap(ReseedTag::Preprocessed, commitment);
+    }
+    public_coin.reseed_with_cap(ReseedTag::BaseTrace, &base_trace_commitment);
+    for commitment in &base_column_commitments {
+        public_coin.reseed_with_cap(ReseedTag::BaseColumn, commitment);
+    }
     let num_challenges = air.num_challenges();
-    let air_challenges = Challenges::new(draw_multiple(&mut public_coin, num_challenges));
+    let air_challenges =
+        Challenges::new(public_coin.draw_fqx(DrawTag::AirChallenge, num_challenges));
     let air_hints = air.gen_hints(&air_challenges);
 
     let extension_trace_commitment = extension_trace_commitment.map(|commitment| {
-        public_coin.reseed_with_digest(&commitment);
+        public_coin.reseed_with_cap(ReseedTag::ExtensionTrace, &commitment);
         commitment
     });
 
     let num_composition_coeffs = air.num_composition_constraint_coeffs();
-    let composition_coeffs = draw_multiple(&mut public_coin, num_composition_coeffs);
-    public_coin.reseed_with_digest(&composition_trace_commitment);
+    let composition_coeffs =
+        public_coin.draw_fqx(DrawTag::CompositionCoeff, num_composition_coeffs);
+    public_coin.reseed_with_cap(ReseedTag::CompositionTrace, &composition_trace_commitment);
 
-    let z = public_coin.draw();
+    let z = public_coin.draw_fq(DrawTag::OodPoint);
     let ood_evals = [
         execution_trace_ood_evals.clone(),
         composition_trace_ood_evals.clone(),
     ]
     .concat();
-    public_coin.reseed_with_field_elements(&ood_evals);
+    public_coin.reseed_with_field_elements(ReseedTag::OodEvals, &ood_evals);
     // execution trace ood evaluation map
     let trace_ood_eval_map = air
         .trace_arguments()
@@ -95,7 +341,7 @@ pub fn default_verify<S: Stark>(
     }
 
     let deep_coeffs = this.gen_deep_coeffs(&mut public_coin, &air);
-    let fri_verifier = FriVerifier::<S::Fq, S::Digest, S::MerkleTree>::new(
+    let mut fri_verifier = LdtVerifier::<S::Fq, S::Digest, S::MerkleTree>::new(
         &mut public_coin,
         options.into_fri_options(),
         fri_proof,
@@ -106,12 +352,59 @@ pub fn default_verify<S: Stark>(
         if !public_coin.verify_proof_of_work(options.grinding_factor, pow_nonce) {
             return Err(FriProofOfWork);
         }
-        public_coin.reseed_with_int(pow_nonce);
+        public_coin.reseed_with_int(ReseedTag::ProofOfWork, pow_nonce);
     }
 
-    let lde_domain_size = air.trace_len() * air.lde_blowup_factor();
     let query_positions =
         Vec::from_iter(public_coin.draw_queries(options.num_queries.into(), lde_domain_size));
+    fri_verifier.draw_layer_positions(&mut public_coin, options.num_queries.into());
+    #[cfg(debug_assertions)]
+    crate::transcript::assert_transcript("verifier", &public_coin);
+
+    // all rows of the leaves containing the query positions are opened
+    let leaf_positions = fold_positions(&query_positions, rows_per_leaf);
+    let opened_rows = leaf_rows(&query_positions, rows_per_leaf);
+    let num_rows = opened_rows.len();
+    for (item, expected, actual) in [
+        (
+            "base trace query values",
+            num_rows * S::AirConfig::NUM_BASE_COLUMNS,
+            trace_queries.base_trace_values.len(),
+        ),
+        (
+            "extension trace query values",
+            num_rows * S::AirConfig::NUM_EXTENSION_COLUMNS,
+            trace_queries.extension_trace_values.len(),
+        ),
+        (
+            "composition trace query values",
+            num_rows * air.ce_blowup_factor(),
+            trace_queries.composition_trace_values.len(),
+        ),
+        (
+            "base column query proofs",
+            separate_columns.len(),
+            trace_queries.base_column_proofs.len(),
+        ),
+        (
+            "preprocessed trace query proofs",
+            usize::from(preprocessed_commitment.is_some()),
+            usize::from(trace_queries.preprocessed_trace_proof.is_some()),
+        ),
+        (
+            "extension trace query proofs",
+            usize::from(extension_trace_commitment.is_some()),
+            usize::from(trace_queries.extension_trace_proof.is_some()),
+        ),
+    ] {
+        if expected != actual {
+            return Err(TranscriptMismatch {
+                item,
+                expected,
+                actual,
+            });
+        }
+    }
 
     let base_trace_rows = trace_queries
         .base_trace_values
@@ -132,32 +425,93 @@ pub fn default_verify<S: Stark>(
         .collect::<Vec<&[S::Fq]>>();
 
     // base trace positions
-    S::MerkleTree::verify_rows(
+    let matrix_columns = air.matrix_committed_base_columns();
+    let base_trace_matrix_rows = base_trace_rows
+        .iter()
+        .map(|row| matrix_columns.iter().map(|&i| row[i]).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    S::MerkleTree::verify_row_chunks(
         &base_trace_commitment,
-        &query_positions,
-        &base_trace_rows,
+        MerkleTreeDomain::BaseTrace,
+        &leaf_positions,
+        &base_trace_matrix_rows,
+        rows_per_leaf,
         trace_queries.base_trace_proof,
     )
-    .map_err(|_| BaseTraceQueryDoesNotMatchCommitment)?;
+    .map_err(|source| MerklePathInvalid {
+        domain: MerkleTreeDomain::BaseTrace,
+        source,
+    })?;
+
+    // separately committed base columns
+    for ((&column, commitment), proof) in separate_columns
+        .iter()
+        .zip(&base_column_commitments)
+        .zip(trace_queries.base_column_proofs)
+    {
+        let values = base_trace_rows.iter().map(|row| row[column]);
+        verify_base_column::<S>(
+            commitment,
+            column,
+            &leaf_positions,
+            values,
+            rows_per_leaf,
+            proof,
+        )?;
+    }
+
+    if let Some(commitment) = preprocessed_commitment {
+        let preprocessed_rows = base_trace_rows
+            .iter()
+            .map(|row| preprocessed_columns.iter().map(|&i| row[i]).collect())
+            .collect::<Vec<Vec<S::Fp>>>();
+        S::MerkleTree::verify_row_chunks(
+            commitment,
+            MerkleTreeDomain::Preprocessed,
+            &leaf_positions,
+            &preprocessed_rows,
+            rows_per_leaf,
+            trace_queries.preprocessed_trace_proof.unwrap(),
+        )
+        .map_err(|source| MerklePathInvalid {
+            domain: MerkleTreeDomain::Preprocessed,
+            source,
+        })?;
+    }
 
     if let Some(extension_trace_commitment) = extension_trace_commitment {
-        S::MerkleTree::verify_rows(
+        S::MerkleTree::verify_row_chunks(
             &extension_trace_commitment,
-            &query_positions,
+            MerkleTreeDomain::ExtensionTrace,
+            &leaf_positions,
             &extension_trace_rows,
+            rows_per_leaf,
             trace_queries.extension_trace_proof.unwrap(),
         )
-        .map_err(|_| ExtensionTraceQueryDoesNotMatchCommitment)?;
+        .map_err(|source| MerklePathInvalid {
+            domain: MerkleTreeDomain::ExtensionTrace,
+            source,
+        })?;
     }
 
     // composition trace positions
-    S::MerkleTree::verify_rows(
+    S::MerkleTree::verify_row_chunks(
         &composition_trace_commitment,
-        &query_positions,
+        MerkleTreeDomain::CompositionTrace,
+        &leaf_positions,
         &composition_trace_rows,
+        rows_per_leaf,
         trace_queries.composition_trace_proof,
     )
-    .map_err(|_| CompositionTraceQueryDoesNotMatchCommitment)?;
+    .map_err(|source| MerklePathInvalid {
+        domain: MerkleTreeDomain::CompositionTrace,
+        source,
+    })?;
+
+    // rows at the query positions
+    let base_trace_rows = rows_at(&base_trace_rows, &opened_rows, &query_positions);
+    let extension_trace_rows = rows_at(&extension_trace_rows, &opened_rows, &query_positions);
+    let composition_trace_rows = rows_at(&composition_trace_rows, &opened_rows, &query_positions);
 
     let deep_evaluations = deep_composition_evaluations(
         &air,
@@ -171,8 +525,9 @@ pub fn default_verify<S: Stark>(
         z,
     );
 
-    let fri_alphas = fri_verifier.layer_alphas.clone();
-    fri_verifier.verify(&query_positions, &deep_evaluations)?;
+    let fri_alphas = fri_verifier.layer_alphas().to_vec();
+    let fri_tail_claim = fri_verifier.verify_head(&query_positions, &deep_evaluations)?;
+    fri_verifier.verify_tail_layers(fri_tail_claim, fri_tail)?;
 
     Ok(VerifierChannelArtifacts {
         air_challenges,
@@ -182,24 +537,155 @@ pub fn default_verify<S: Stark>(
     })
 }
 
+/// Returns the rows at `positions` from the opened rows at `opened_rows`.
+/// Traces without any columns have no rows.
+fn rows_at<'a, T>(rows: &[&'a [T]], opened_rows: &[usize], positions: &[usize]) -> Vec<&'a [T]> {
+    if rows.is_empty() {
+        return Vec::new();
+    }
+    positions
+        .iter()
+        .map(|p| rows[opened_rows.binary_search(p).unwrap()])
+        .collect()
+}
+
 /// Errors that are returned during verification of a STARK proof
 #[derive(Debug, Snafu)]
 pub enum VerificationError {
     #[snafu(display("proof params do not satisfy security requirements"))]
     InvalidProofSecurity,
+    #[snafu(display("proof options are invalid"))]
+    InvalidProofOptions,
+    #[snafu(display(
+        "folding by {folding_factor} {folds_per_layer} times per FRI layer isn't accepted"
+    ))]
+    UnacceptedFoldingSchedule {
+        folding_factor: u8,
+        folds_per_layer: u8,
+    },
+    #[snafu(display("LDE blowup factor {lde_blowup_factor} is less than the constraint degree"))]
+    InsufficientBlowupFactor { lde_blowup_factor: u8 },
+    #[snafu(display(
+        "trace length {trace_len} isn't a power of two or its LDE domain exceeds the 2-adicity of the field"
+    ))]
+    InvalidTraceLength { trace_len: usize },
+    #[snafu(display("{values_per_leaf} trace values per leaf isn't a power of two"))]
+    InvalidTraceValuesPerLeaf { values_per_leaf: u8 },
     #[snafu(display("constraint evaluations at the out-of-domain point are inconsistent"))]
     InconsistentOodConstraintEvaluations,
     #[snafu(context(false))]
     #[snafu(display("fri verification failed: {source}"))]
     FriVerification { source: fri::VerificationError },
-    #[snafu(display("query does not resolve to the base trace commitment"))]
-    BaseTraceQueryDoesNotMatchCommitment,
-    #[snafu(display("query does not resolve to the extension trace commitment"))]
-    ExtensionTraceQueryDoesNotMatchCommitment,
-    #[snafu(display("query does not resolve to the composition trace commitment"))]
-    CompositionTraceQueryDoesNotMatchCommitment,
+    #[snafu(display("queries do not resolve to the {domain:?} commitment: {source}"))]
+    MerklePathInvalid {
+        domain: MerkleTreeDomain,
+        source: merkle::Error,
+    },
+    #[snafu(display("proof contains {actual} {item} but the verifier expects {expected}"))]
+    TranscriptMismatch {
+        item: &'static str,
+        expected: usize,
+        actual: usize,
+    },
     #[snafu(display("insufficient proof of work on fri commitments"))]
     FriProofOfWork,
+    #[snafu(display("proof was generated for a different AIR or proof options"))]
+    AirFingerprintMismatch,
+    #[snafu(display("the AIR has preprocessed columns but no verifying key was given"))]
+    MissingVerifyingKey,
+    #[snafu(display("verifying key was set up for a different AIR, trace length or commitment"))]
+    IncompatibleVerifyingKey,
+    #[snafu(display("base column {column} isn't committed to separately"))]
+    ColumnNotCommittedSeparately { column: usize },
+    // not a snafu source since the error type only implements `Error` with std
+    #[snafu(display("proof couldn't be read: {error}"))]
+    InvalidEncoding { error: SerializationError },
+}
+
+/// Verifies the opening of a separately committed base column (see
+/// [`AirConfig::SEPARATELY_COMMITTED_BASE_COLUMNS`]) at the query positions
+/// and returns the opened values. Only the column's commitment and opening are
+/// checked so this can be used by verifiers that only need a few columns of a
+/// proof. Query positions are returned by [`default_verify`] in
+/// [`VerifierChannelArtifacts::query_positions`].
+pub fn verify_base_column_opening<S: Stark>(
+    proof: &Proof<S>,
+    column: usize,
+    query_positions: &[usize],
+) -> Result<Vec<S::Fp>, VerificationError> {
+    use VerificationError::*;
+    let separate_columns = S::AirConfig::SEPARATELY_COMMITTED_BASE_COLUMNS;
+    let index = separate_columns
+        .iter()
+        .position(|&i| i == column)
+        .ok_or(ColumnNotCommittedSeparately { column })?;
+    let queries = &proof.trace_queries;
+    let commitment = proof.base_column_commitments.get(index);
+    let column_proof = queries.base_column_proofs.get(index);
+    let (Some(commitment), Some(column_proof)) = (commitment, column_proof) else {
+        return Err(TranscriptMismatch {
+            item: "base column commitments",
+            expected: separate_columns.len(),
+            actual: proof.base_column_commitments.len(),
+        });
+    };
+    let values_per_leaf = proof.options.trace_values_per_leaf;
+    if !values_per_leaf.is_power_of_two() {
+        return Err(InvalidTraceValuesPerLeaf { values_per_leaf });
+    }
+    let rows_per_leaf = proof.options.trace_rows_per_leaf(proof.trace_len);
+    let leaf_positions = fold_positions(query_positions, rows_per_leaf);
+    let opened_rows = leaf_rows(query_positions, rows_per_leaf);
+    let num_base_columns = S::AirConfig::NUM_BASE_COLUMNS;
+    let expected = opened_rows.len() * num_base_columns;
+    if queries.base_trace_values.len() != expected {
+        return Err(TranscriptMismatch {
+            item: "base trace query values",
+            expected,
+            actual: queries.base_trace_values.len(),
+        });
+    }
+
+    let values = queries
+        .base_trace_values
+        .chunks(num_base_columns)
+        .map(|row| row[column])
+        .collect::<Vec<S::Fp>>();
+    let proof = column_proof.clone();
+    let leaves = &leaf_positions;
+    verify_base_column::<S>(
+        commitment,
+        column,
+        leaves,
+        values.clone(),
+        rows_per_leaf,
+        proof,
+    )?;
+    Ok(query_positions
+        .iter()
+        .map(|p| values[opened_rows.binary_search(p).unwrap()])
+        .collect())
+}
+
+fn verify_base_column<S: Stark>(
+    commitment: &[S::Digest],
+    column: usize,
+    leaf_positions: &[usize],
+    values: impl IntoIterator<Item = S::Fp>,
+    rows_per_leaf: usize,
+    proof: <S::MerkleTree as MerkleTree>::Proof,
+) -> Result<(), VerificationError> {
+    let domain = MerkleTreeDomain::BaseTraceColumn(column);
+    let rows = values.into_iter().map(|v| [v]).collect::<Vec<_>>();
+    S::MerkleTree::verify_row_chunks(
+        commitment,
+        domain,
+        leaf_positions,
+        &rows,
+        rows_per_leaf,
+        proof,
+    )
+    .map_err(|source| VerificationError::MerklePathInvalid { domain, source })
 }
 
 pub fn ood_constraint_evaluation<A: AirConfig>(
@@ -218,16 +704,7 @@ pub fn ood_constraint_evaluation<A: AirConfig>(
             &Item(Constant(v)) => v,
             &Item(Challenge(i)) => FieldVariant::Fq(challenges[i]),
             &Item(Hint(i)) => FieldVariant::Fq(hints[i]),
-            &Item(Periodic(col)) => {
-                let trace_len = air.trace_len();
-                let point = x.pow([(trace_len / col.interval_size()) as u64]);
-                let coeffs = col
-                    .coeffs()
-                    .iter()
-                    .map(FieldVariant::as_fq)
-                    .collect::<Vec<_>>();
-                FieldVariant::Fq(horner_evaluate(&coeffs, &point))
-            }
+            &Item(Periodic(col)) => FieldVariant::Fq(col.evaluate_at(air.trace_len(), x)),
             &Item(Trace(i, j)) => FieldVariant::Fq(trace_ood_eval_map[&(i, j)]),
             &CompositionCoeff(i) => FieldVariant::Fq(composition_coefficients[i]),
         })
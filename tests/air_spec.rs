@@ -0,0 +1,52 @@
+#![feature(allocator_api)]
+
+mod common;
+
+use common::FibAirConfig;
+use common::Separate;
+use ministark::Air;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+#[test]
+fn fib_air_spec() {
+    let options = ProofOptions::new(24, 4, 2, 2, 8);
+    let air = Air::<FibAirConfig<Separate>>::new(256, Fp::from(5u8), options);
+
+    let spec = air.to_spec();
+
+    let fingerprint = air.fingerprint().map(|byte| format!("{byte:02x}")).concat();
+    let header = format!(
+        concat!(
+            r#"{{"version":1,"fingerprint":"{}","trace_len":256,"ce_blowup_factor":1,"#,
+            r#""composition_degree":255,"columns":{{"base":8,"extension":0,"#,
+            r#""separately_committed":[1, 5],"preprocessed":[]}},"num_challenges":0,"#,
+            r#""num_hints":1,"periodic_columns":[],"constraints":["#
+        ),
+        fingerprint
+    );
+    assert!(spec.starts_with(&header), "{spec}");
+    assert!(spec.ends_with("}]}"));
+    let constraints = &spec[header.len()..spec.len() - 2];
+    assert_eq!(constraints.matches(r#"{"numerator_degree""#).count(), 11);
+    // `(0.curr() - 1) / (x - 1)`
+    let one = r#"{"op":"constant","value":{"field":"fp","coeffs":["1"]}}"#;
+    let first_constraint = format!(
+        concat!(
+            r#"{{"numerator_degree":255,"denominator_degree":1,"expr":{{"op":"div","args":["#,
+            r#"{{"op":"add","args":[{{"op":"trace","column":0,"offset":0}},{{"op":"neg","args":[{one}]}}]}},"#,
+            r#"{{"op":"add","args":[{{"op":"x"}},{{"op":"neg","args":[{one}]}}]}}]}}}}"#
+        ),
+        one = one
+    );
+    assert!(constraints.starts_with(&first_constraint), "{constraints}");
+    // the terminal constraint binds the last cell to the claim
+    assert!(constraints.contains(
+        r#"{"op":"add","args":[{"op":"trace","column":7,"offset":0},{"op":"neg","args":[{"op":"hint","index":0}]}]}"#
+    ));
+    // transitions reference the next row and vanish on all rows but the last
+    assert!(constraints.contains(r#"{"op":"trace","column":0,"offset":1}"#));
+    assert!(constraints.contains(r#"{"op":"pow","args":[{"op":"x"}],"exponent":256}"#));
+    assert_eq!(spec.matches('{').count(), spec.matches('}').count());
+    assert_eq!(spec.matches('[').count(), spec.matches(']').count());
+}
@@ -0,0 +1,218 @@
+//! Fibonacci style AIR shared by the integration tests. Each row holds eight
+//! consecutive terms of `a_(n+2) = a_n * a_(n+1)` starting from `1, 2` and the
+//! claim is the last term of the trace.
+#![allow(dead_code)]
+
+use ark_ff::One;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::CanonicalSerialize;
+use core::marker::PhantomData;
+use ministark::air::AirConfig;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::hints::Hints;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::utils::GpuAllocator;
+use ministark::utils::SerdeOutput;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::Trace;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use num_traits::Pow;
+use sha2::Sha256;
+
+pub const NUM_COLUMNS: usize = 8;
+
+/// How the columns of the Fibonacci AIR are committed to
+pub trait Layout: Clone + Send + Sync + 'static {
+    const SEPARATELY_COMMITTED_BASE_COLUMNS: &'static [usize] = &[];
+    const PREPROCESSED_BASE_COLUMNS: &'static [usize] = &[];
+}
+
+/// All columns are part of the base trace commitment
+#[derive(Clone)]
+pub struct Combined;
+
+impl Layout for Combined {}
+
+/// Columns 1 and 5 are committed to separately
+#[derive(Clone)]
+pub struct Separate;
+
+impl Layout for Separate {
+    const SEPARATELY_COMMITTED_BASE_COLUMNS: &'static [usize] = &[1, 5];
+}
+
+/// Columns 3 and 6 are committed to in a verifying key
+#[derive(Clone)]
+pub struct Preprocessed;
+
+impl Layout for Preprocessed {
+    const PREPROCESSED_BASE_COLUMNS: &'static [usize] = &[3, 6];
+}
+
+pub struct FibTrace(pub Matrix<Fp>);
+
+impl FibTrace {
+    pub fn last_value(&self) -> Fp {
+        *self.0 .0[NUM_COLUMNS - 1].last().unwrap()
+    }
+
+    /// Returns the columns of `L` that are committed to in a verifying key
+    pub fn preprocessed_columns<L: Layout>(&self) -> Matrix<Fp> {
+        Matrix::new(
+            L::PREPROCESSED_BASE_COLUMNS
+                .iter()
+                .map(|&i| self.0 .0[i].to_vec_in(GpuAllocator))
+                .collect(),
+        )
+    }
+}
+
+impl Trace for FibTrace {
+    type Fp = Fp;
+    type Fq = Fp;
+
+    fn len(&self) -> usize {
+        self.0.num_rows()
+    }
+
+    fn base_columns(&self) -> &Matrix<Self::Fp> {
+        &self.0
+    }
+}
+
+pub fn gen_trace(num_rows: usize) -> FibTrace {
+    assert!(num_rows.is_power_of_two());
+    let mut columns = (0..NUM_COLUMNS)
+        .map(|_| Vec::with_capacity_in(num_rows, GpuAllocator))
+        .collect::<Vec<_>>();
+    let (mut a, mut b) = (Fp::one(), Fp::from(2u8));
+    for i in 0..num_rows * NUM_COLUMNS {
+        columns[i % NUM_COLUMNS].push(a);
+        (a, b) = (b, a * b);
+    }
+    FibTrace(Matrix::new(columns))
+}
+
+pub struct FibAirConfig<L: Layout = Combined>(PhantomData<L>);
+
+impl<L: Layout> AirConfig for FibAirConfig<L> {
+    const NUM_BASE_COLUMNS: usize = NUM_COLUMNS;
+    const SEPARATELY_COMMITTED_BASE_COLUMNS: &'static [usize] =
+        L::SEPARATELY_COMMITTED_BASE_COLUMNS;
+    const PREPROCESSED_BASE_COLUMNS: &'static [usize] = L::PREPROCESSED_BASE_COLUMNS;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = Fp;
+
+    fn gen_hints(
+        _trace_len: usize,
+        claimed_last_value: &Fp,
+        _: &Challenges<Self::Fq>,
+    ) -> Hints<Self::Fq> {
+        Hints::new(vec![(0, *claimed_last_value)])
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Self::Fp, Self::Fq>>> {
+        use AlgebraicItem::*;
+        let trace_xs = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+        let first_trace_x = Constant(FieldVariant::Fp(trace_xs.element(0)));
+        let last_trace_x = Constant(FieldVariant::Fp(trace_xs.element(trace_len - 1)));
+        let one = Constant(FieldVariant::Fp(Fp::one()));
+
+        // first row holds the first terms
+        let v0 = Constant(FieldVariant::Fp(Fp::one()));
+        let v1 = v0 + v0;
+        let boundary_constraints = [0.curr() - v0, 1.curr() - v1]
+            .into_iter()
+            .map(|constraint| constraint / (X - first_trace_x));
+
+        // last row holds the claimed term
+        let terminal_constraint = (7.curr() - Hint(0)) / (X - last_trace_x);
+
+        // every term is the product of the previous two
+        let row_constraints = (2..NUM_COLUMNS)
+            .map(|i| i.curr() - (i - 2).curr() * (i - 1).curr())
+            .map(|constraint| constraint / (X.pow(trace_len) - one));
+        let transition_constraints = [
+            0.next() - 6.curr() * 7.curr(),
+            1.next() - 7.curr() * 0.next(),
+        ]
+        .into_iter()
+        .map(|constraint| constraint * ((X - last_trace_x) / (X.pow(trace_len) - one)));
+
+        boundary_constraints
+            .chain([terminal_constraint])
+            .chain(row_constraints)
+            .chain(transition_constraints)
+            .map(Constraint::new)
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+pub struct FibClaim<L: Layout = Combined> {
+    pub last_value: Fp,
+    pub nonce: Option<[u8; 32]>,
+    _layout: PhantomData<L>,
+}
+
+impl<L: Layout> FibClaim<L> {
+    pub fn new(trace: &FibTrace) -> Self {
+        Self::from_last_value(trace.last_value())
+    }
+
+    pub fn from_last_value(last_value: Fp) -> Self {
+        Self {
+            last_value,
+            nonce: None,
+            _layout: PhantomData,
+        }
+    }
+
+    pub fn with_nonce(mut self, nonce: [u8; 32]) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+}
+
+impl<L: Layout> Stark for FibClaim<L> {
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = FibAirConfig<L>;
+    type Digest = SerdeOutput<Sha256>;
+    type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+    type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+    type Witness = FibTrace;
+    type Trace = FibTrace;
+
+    fn get_public_inputs(&self) -> Fp {
+        self.last_value
+    }
+
+    fn generate_trace(&self, witness: FibTrace) -> FibTrace {
+        witness
+    }
+
+    fn gen_proof_nonce(&self) -> Option<[u8; 32]> {
+        self.nonce
+    }
+
+    fn gen_public_coin(&self, air: &Air<Self::AirConfig>) -> Self::PublicCoin {
+        let mut seed = Vec::new();
+        air.public_inputs().serialize_compressed(&mut seed).unwrap();
+        air.trace_len().serialize_compressed(&mut seed).unwrap();
+        air.options().serialize_compressed(&mut seed).unwrap();
+        PublicCoinImpl::new(Sha256HashFn::hash_chunks([&*seed]))
+    }
+}
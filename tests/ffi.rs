@@ -0,0 +1,71 @@
+#![feature(allocator_api)]
+
+mod common;
+
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use ark_serialize::SerializationError;
+use common::gen_trace;
+use common::FibClaim;
+use common::FibTrace;
+use ministark::ffi::verify_bytes;
+use ministark::ffi::FfiStark;
+use ministark::stark::Stark;
+use ministark::verifier::FoldingPolicy;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+impl FfiStark for FibClaim {
+    fn from_public_inputs(bytes: &[u8]) -> Result<Self, SerializationError> {
+        Ok(Self::from_last_value(Fp::deserialize_compressed(bytes)?))
+    }
+
+    /// The witness is the number of rows of the trace
+    fn deserialize_witness(bytes: &[u8]) -> Result<FibTrace, SerializationError> {
+        Ok(gen_trace(u64::deserialize_compressed(bytes)? as usize))
+    }
+}
+
+#[test]
+fn verify_bytes_rejects_malformed_proofs() {
+    let options = ProofOptions::new(24, 4, 2, 2, 8);
+    let policy = FoldingPolicy::new().accept_options(&options);
+    let trace = gen_trace(256);
+    let claim: FibClaim = FibClaim::new(&trace);
+    let proof = pollster::block_on(claim.prove(options, trace)).unwrap();
+    let mut public_inputs = Vec::new();
+    claim
+        .last_value
+        .serialize_compressed(&mut public_inputs)
+        .unwrap();
+    let mut proof_bytes = Vec::new();
+    proof.serialize_compressed(&mut proof_bytes).unwrap();
+    let verify = |bytes: &[u8]| verify_bytes::<FibClaim>(bytes, &public_inputs, &policy, 1);
+    assert!(verify(&proof_bytes).is_ok());
+
+    // truncated proofs
+    let step = proof_bytes.len() / 64;
+    for len in (0..proof_bytes.len())
+        .step_by(step)
+        .chain([proof_bytes.len() - 1])
+    {
+        assert!(
+            verify(&proof_bytes[..len]).is_err(),
+            "truncated to {len} bytes"
+        );
+    }
+
+    // mutated proofs
+    let mut bytes = proof_bytes.clone();
+    for i in (0..bytes.len()).step_by(step / 4) {
+        bytes[i] ^= 0x55;
+        assert!(verify(&bytes).is_err(), "byte {i} flipped");
+        bytes[i] ^= 0x55;
+    }
+
+    // malformed public inputs
+    let verify =
+        |public_inputs: &[u8]| verify_bytes::<FibClaim>(&proof_bytes, public_inputs, &policy, 1);
+    assert!(verify(&[]).is_err());
+    assert!(verify(&[0xff; 8]).is_err());
+}
@@ -0,0 +1,81 @@
+#![feature(allocator_api)]
+
+mod common;
+
+use common::gen_trace;
+use common::FibClaim;
+use ministark::stark::Stark;
+use ministark::ProofOptions;
+
+#[test]
+fn security_report_of_fib_proof() {
+    // (options, conjectured bits, proven bits)
+    let cases = [
+        // FRI queries bound both levels: 2 * 24 + 2 and 0.78 * 24 + 2
+        (ProofOptions::new(24, 4, 2, 2, 8), 50, 20),
+        // the 64 bit field bounds both levels
+        (ProofOptions::new(32, 8, 16, 2, 8), 53, 26),
+    ];
+
+    for (options, conjectured_bits, proven_bits) in cases {
+        let trace = gen_trace(256);
+        let claim: FibClaim = FibClaim::new(&trace);
+
+        let (proof, report) = pollster::block_on(claim.prove_with_report(options, trace)).unwrap();
+
+        assert_eq!(report.conjectured_bits, conjectured_bits);
+        assert_eq!(report.proven_bits, proven_bits);
+        // constraints are quadratic but the transition constraints are divided
+        // by the trace domain
+        assert_eq!(report.max_constraint_degree, 1);
+        assert_eq!(report.lde_blowup_factor, options.lde_blowup_factor);
+        assert_eq!(report.num_queries, options.num_queries);
+        assert_eq!(report.grinding_factor, options.grinding_factor);
+        assert_eq!(report, proof.security_report());
+    }
+}
+
+#[test]
+fn describe_fib_proof() {
+    let options = ProofOptions::new(24, 4, 2, 2, 8);
+    let trace = gen_trace(256);
+    let claim: FibClaim = FibClaim::new(&trace);
+    let proof = pollster::block_on(claim.prove(options, trace)).unwrap();
+    let query_positions = claim.verify(proof.clone(), 1).unwrap().query_positions;
+
+    let description = proof.describe();
+    let queries = proof.describe_queries(&query_positions);
+
+    assert!(description.contains("trace length: 256\n"));
+    assert!(description.contains("composition trace ood evaluations: "));
+    assert!(description.ends_with(&format!("queries: {}\n", options.num_queries)));
+    for (i, position) in query_positions.iter().enumerate() {
+        assert!(queries.contains(&format!("query {i} (position {position}):\n")));
+    }
+    assert_eq!(
+        queries.matches("base trace row: [").count(),
+        query_positions.len()
+    );
+    assert_eq!(
+        queries.matches("composition trace row: [").count(),
+        query_positions.len()
+    );
+    assert!(!queries.contains("extension trace row"));
+    assert!(!queries.contains("missing"));
+}
+
+#[test]
+fn describe_queries_without_composition_columns() {
+    let trace = gen_trace(256);
+    let claim: FibClaim = FibClaim::new(&trace);
+    let mut proof =
+        pollster::block_on(claim.prove(ProofOptions::new(24, 4, 2, 2, 8), trace)).unwrap();
+    proof.composition_trace_ood_evals.clear();
+
+    let queries = proof.describe_queries(&[0, 1]);
+
+    assert_eq!(
+        queries.matches("composition trace row: missing\n").count(),
+        2
+    );
+}
@@ -0,0 +1,254 @@
+#![feature(allocator_api)]
+//! Proofs generated with each proof option and protocol variant round trip on
+//! the Fibonacci AIR. Checks specific to an option live next to the unit tests
+//! of the option.
+
+mod common;
+
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use common::gen_trace;
+use common::Combined;
+use common::FibAirConfig;
+use common::FibClaim;
+use common::Layout;
+use ministark::merkle;
+use ministark::merkle::MerkleTreeDomain;
+use ministark::stark::Stark;
+use ministark::verifier::VerificationError;
+use ministark::Air;
+use ministark::Proof;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+const NUM_ROWS: usize = 256;
+const OPTIONS: ProofOptions = ProofOptions::new(24, 4, 2, 2, 8);
+
+fn prove<L: Layout>(options: ProofOptions) -> (FibClaim<L>, Proof<FibClaim<L>>) {
+    let trace = gen_trace(NUM_ROWS);
+    let claim = FibClaim::new(&trace);
+    let proof = pollster::block_on(claim.prove(options, trace)).unwrap();
+    (claim, proof)
+}
+
+/// Claims the proof was generated with `options`. The AIR fingerprint is
+/// updated so the proof gets past the fingerprint check.
+fn with_options<L: Layout>(
+    claim: &FibClaim<L>,
+    mut proof: Proof<FibClaim<L>>,
+    options: ProofOptions,
+) -> Proof<FibClaim<L>> {
+    proof.options = options;
+    proof.air_fingerprint =
+        Air::<FibAirConfig<L>>::new(NUM_ROWS, claim.last_value, options).fingerprint();
+    proof
+}
+
+#[test]
+fn round_trip() {
+    let fri = |folding_factor, max_remainder_coeffs| {
+        ProofOptions::new(24, 4, 2, folding_factor, max_remainder_coeffs)
+    };
+    for options in [
+        OPTIONS,
+        fri(4, 8),
+        OPTIONS.with_fri_folds_per_layer(2),
+        OPTIONS.with_fri_folds_per_layer(4),
+        fri(4, 8).with_fri_folds_per_layer(2),
+        OPTIONS.with_fri_independent_queries(true),
+        OPTIONS
+            .with_fri_folds_per_layer(2)
+            .with_fri_independent_queries(true),
+        OPTIONS.with_fri_values_per_leaf(8),
+        OPTIONS.with_trace_values_per_leaf(4),
+        OPTIONS
+            .with_fri_values_per_leaf(16)
+            .with_trace_values_per_leaf(8),
+        OPTIONS.with_merkle_cap_height(2),
+        OPTIONS.with_merkle_cap_height(4),
+        OPTIONS.with_fri_stir(true),
+        fri(4, 4).with_fri_stir(true),
+        fri(8, 16).with_fri_stir(true),
+        OPTIONS.with_degree_adjusted_composition(false),
+    ] {
+        let (claim, proof) = prove::<Combined>(options);
+        let mut bad = proof.clone();
+        bad.trace_queries.base_trace_values[0] += Fp::from(1u8);
+
+        claim.verify(proof, 1).unwrap();
+        assert!(
+            matches!(
+                claim.verify(bad, 1),
+                Err(VerificationError::MerklePathInvalid {
+                    domain: MerkleTreeDomain::BaseTrace,
+                    source: merkle::Error::InvalidProof
+                })
+            ),
+            "{options:?}"
+        );
+    }
+}
+
+#[test]
+fn separately_committed_columns() {
+    use common::Separate;
+    use ministark::verifier::verify_base_column_opening;
+    let (claim, proof) = prove::<Separate>(OPTIONS);
+
+    let artifacts = claim.verify(proof.clone(), 1).unwrap();
+    let positions = &artifacts.query_positions;
+    assert_eq!(
+        verify_base_column_opening(&proof, 5, positions)
+            .unwrap()
+            .len(),
+        positions.len()
+    );
+    assert!(matches!(
+        verify_base_column_opening(&proof, 2, positions),
+        Err(VerificationError::ColumnNotCommittedSeparately { column: 2 })
+    ));
+
+    let mut bad = proof.clone();
+    bad.base_column_commitments[1][0] = Default::default();
+    assert!(matches!(
+        verify_base_column_opening(&bad, 5, positions),
+        Err(VerificationError::MerklePathInvalid {
+            domain: MerkleTreeDomain::BaseTraceColumn(5),
+            ..
+        })
+    ));
+    assert!(matches!(
+        claim.verify(bad, 1),
+        Err(VerificationError::InconsistentOodConstraintEvaluations)
+    ));
+
+    // an AIR that commits to all columns in the base trace matrix
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+    let proof = Proof::<FibClaim<Combined>>::deserialize_compressed(&*bytes).unwrap();
+    let claim = FibClaim::<Combined>::new(&gen_trace(NUM_ROWS));
+    assert!(matches!(
+        claim.verify(proof.clone(), 1),
+        Err(VerificationError::AirFingerprintMismatch)
+    ));
+    let options = proof.options;
+    assert!(matches!(
+        claim.verify(with_options(&claim, proof, options), 1),
+        Err(VerificationError::TranscriptMismatch {
+            item: "base column commitments",
+            expected: 0,
+            actual: 2
+        })
+    ));
+}
+
+#[test]
+fn proof_nonce() {
+    let trace = gen_trace(NUM_ROWS);
+    let claim: FibClaim = FibClaim::new(&trace).with_nonce([7; 32]);
+    let proof = pollster::block_on(claim.prove(OPTIONS, trace)).unwrap();
+    let (_, plain_proof) = prove::<Combined>(OPTIONS);
+
+    assert_eq!(proof.nonce, Some([7; 32]));
+    assert_ne!(
+        proof.composition_trace_commitment,
+        plain_proof.composition_trace_commitment
+    );
+    claim.verify(proof.clone(), 1).unwrap();
+
+    // the nonce seeds the public coin so every later challenge differs
+    let mut bad = proof.clone();
+    bad.nonce.as_mut().unwrap()[0] ^= 1;
+    assert!(matches!(
+        claim.verify(bad, 1),
+        Err(VerificationError::InconsistentOodConstraintEvaluations)
+    ));
+
+    let mut bad = proof;
+    bad.nonce = None;
+    assert!(matches!(
+        claim.verify(bad, 1),
+        Err(VerificationError::InconsistentOodConstraintEvaluations)
+    ));
+}
+
+#[test]
+fn merkle_cap_height_mismatch() {
+    let options = OPTIONS.with_merkle_cap_height(2);
+    let (claim, proof) = prove::<Combined>(options);
+
+    assert_eq!(proof.base_trace_commitment.len(), 4);
+    let bad = with_options(&claim, proof, options.with_merkle_cap_height(3));
+    assert!(matches!(
+        claim.verify(bad, 1),
+        Err(VerificationError::TranscriptMismatch {
+            item: "trace commitment cap nodes",
+            expected: 8,
+            actual: 4
+        })
+    ));
+}
+
+#[test]
+fn preprocessed_columns() {
+    use common::Preprocessed;
+    use ministark::prover::prove_with_key;
+    use ministark::setup::setup;
+    use ministark::verifier::verify_with_key;
+    let options = OPTIONS.with_merkle_cap_height(2);
+    let trace = gen_trace(NUM_ROWS);
+    let claim = FibClaim::<Preprocessed>::new(&trace);
+    let columns = trace.preprocessed_columns::<Preprocessed>();
+    let (pk, vk) = setup::<FibClaim<Preprocessed>>(NUM_ROWS, options, columns).unwrap();
+    let proof = prove_with_key(&claim, &pk, trace).unwrap();
+
+    verify_with_key(&claim, proof.clone(), &vk, 1).unwrap();
+    // openings of the preprocessed columns can only be checked with the key
+    assert!(matches!(
+        claim.verify(proof.clone(), 1),
+        Err(VerificationError::MissingVerifyingKey)
+    ));
+
+    let mut bad = proof.clone();
+    bad.trace_queries.base_trace_values[3] += Fp::from(1u8);
+    assert!(matches!(
+        verify_with_key(&claim, bad, &vk, 1),
+        Err(VerificationError::MerklePathInvalid {
+            domain: MerkleTreeDomain::Preprocessed,
+            source: merkle::Error::InvalidProof
+        })
+    ));
+
+    let mut bad_vk = vk.clone();
+    bad_vk.preprocessed_commitment[0] = Default::default();
+    assert!(matches!(
+        verify_with_key(&claim, proof.clone(), &bad_vk, 1),
+        Err(VerificationError::InconsistentOodConstraintEvaluations)
+    ));
+
+    let bad = with_options(&claim, proof, options.with_merkle_cap_height(0));
+    assert!(matches!(
+        verify_with_key(&claim, bad, &vk, 1),
+        Err(VerificationError::IncompatibleVerifyingKey)
+    ));
+
+    // a key for different options
+    let trace = gen_trace(NUM_ROWS);
+    let columns = trace.preprocessed_columns::<Preprocessed>();
+    let (_, other_vk) = setup::<FibClaim<Preprocessed>>(NUM_ROWS, OPTIONS, columns).unwrap();
+    let proof = prove_with_key(&claim, &pk, trace).unwrap();
+    assert!(matches!(
+        verify_with_key(&claim, proof, &other_vk, 1),
+        Err(VerificationError::IncompatibleVerifyingKey)
+    ));
+}
+
+#[test]
+fn single_coefficient_composition_security() {
+    let options = OPTIONS.with_degree_adjusted_composition(false);
+    let (_, proof) = prove::<Combined>(options);
+    let (_, adjusted_proof) = prove::<Combined>(OPTIONS);
+
+    // the security levels don't depend on how constraints are composed
+    assert_eq!(proof.security_report(), adjusted_proof.security_report());
+}
@@ -0,0 +1,65 @@
+#![feature(allocator_api)]
+
+mod common;
+
+use ark_serialize::CanonicalDeserialize;
+use common::gen_trace;
+use common::FibClaim;
+use common::FibTrace;
+use ministark::service::JobStatus;
+use ministark::service::ProverService;
+use ministark::stark::Stark;
+use ministark::Proof;
+use ministark::ProofOptions;
+use std::time::Duration;
+
+const OPTIONS: ProofOptions = ProofOptions::new(24, 4, 2, 2, 8);
+
+fn wait_until_finished(service: &ProverService<FibClaim>, id: u64) -> JobStatus {
+    loop {
+        match service.status(id).unwrap() {
+            JobStatus::Queued | JobStatus::Running => std::thread::sleep(Duration::from_millis(5)),
+            status => return status,
+        }
+    }
+}
+
+#[test]
+fn submitted_proofs_verify() {
+    let service = ProverService::<FibClaim>::new(2);
+    let jobs = [64, 128, 256].map(|num_rows| {
+        let trace = gen_trace(num_rows);
+        let claim = FibClaim::new(&trace);
+        (service.submit(claim.clone(), trace, OPTIONS), claim)
+    });
+
+    for (id, claim) in jobs {
+        assert_eq!(wait_until_finished(&service, id), JobStatus::Done);
+        let bytes = service.take_proof(id).unwrap();
+        let proof = Proof::<FibClaim>::deserialize_compressed(&*bytes).unwrap();
+        claim.verify(proof, 1).unwrap();
+        // proofs can only be taken once
+        assert_eq!(service.status(id), None);
+        assert_eq!(service.take_proof(id), None);
+    }
+}
+
+#[test]
+fn panicking_proof_is_reported_as_failed() {
+    let service = ProverService::<FibClaim>::new(1);
+    let trace = gen_trace(64);
+    let claim = FibClaim::new(&trace);
+    // columns of different lengths make the prover panic
+    let mut columns = trace.0;
+    columns.0[3].truncate(32);
+    let id = service.submit(claim, FibTrace(columns), OPTIONS);
+
+    let status = wait_until_finished(&service, id);
+
+    assert!(matches!(status, JobStatus::Failed(err) if err.starts_with("prover panicked")));
+    assert_eq!(service.take_proof(id), None);
+    // the worker keeps taking jobs
+    let trace = gen_trace(64);
+    let id = service.submit(FibClaim::new(&trace), trace, OPTIONS);
+    assert_eq!(wait_until_finished(&service, id), JobStatus::Done);
+}